@@ -8,6 +8,8 @@ use noli::net::SocketAddr;
 use noli::net::TcpStream;
 use saba_core::error::Error;
 use saba_core::http::HttpResponse;
+use saba_core::http::ResourceLoader;
+use saba_core::url::Url;
 
 pub struct HttpClient {}
 
@@ -86,3 +88,19 @@ impl HttpClient {
         }
     }
 }
+
+impl ResourceLoader for HttpClient {
+    fn fetch(&mut self, url: &str) -> Result<HttpResponse, Error> {
+        let url = Url::new(url.to_string()).parse().map_err(Error::Network)?;
+        let port = match url.port().parse::<u16>() {
+            Ok(port) => port,
+            Err(_) => return Err(Error::Network(format!("Invalid port: {}", url.port()))),
+        };
+        let path = if url.searchpart().is_empty() {
+            url.path()
+        } else {
+            format!("{}?{}", url.path(), url.searchpart())
+        };
+        self.get(url.host(), port, path)
+    }
+}
@@ -96,6 +96,13 @@ impl HttpResponse {
     }
 }
 
+/// Fetches subresources, such as style sheets, for the renderer. The host
+/// implements it on top of its network stack.
+pub trait ResourceLoader {
+    /// Sends a GET request for the absolute URL `url`.
+    fn fetch(&mut self, url: &str) -> Result<HttpResponse, Error>;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,1133 @@
+//! https://www.w3.org/TR/css-cascade-4/#cascading
+
+use crate::renderer::css::color::Color;
+use crate::renderer::css::computed_style::compute_value;
+use crate::renderer::css::computed_style::ComputeContext;
+use crate::renderer::css::computed_style::ComputedStyle;
+use crate::renderer::css::computed_style::ComputedValue;
+use crate::renderer::css::computed_style::Display;
+use crate::renderer::css::computed_style::MEDIUM_FONT_SIZE;
+use crate::renderer::css::invalidation::InvalidationSet;
+use crate::renderer::css::invalidation::Restyle;
+use crate::renderer::css::invalidation::SelectorIndex;
+use crate::renderer::css::length::LengthContext;
+use crate::renderer::css::media::MediaQueryList;
+use crate::renderer::css::media::Viewport;
+use crate::renderer::css::parser::CssParser;
+use crate::renderer::css::properties::find_shorthand;
+use crate::renderer::css::properties::Property;
+use crate::renderer::css::properties::LONGHANDS;
+use crate::renderer::css::shorthand::expand;
+use crate::renderer::css::shorthand::expand_declarations;
+use crate::renderer::css::stylesheet::ComponentValue;
+use crate::renderer::css::stylesheet::CssRule;
+use crate::renderer::css::stylesheet::Declaration;
+use crate::renderer::css::stylesheet::StyleSheet;
+use crate::renderer::css::token::CssToken;
+use crate::renderer::css::token::CssTokenizer;
+use crate::renderer::css::variables::compute_custom_properties;
+use crate::renderer::css::variables::contains_var;
+use crate::renderer::css::variables::substitute;
+use crate::renderer::css::variables::CustomProperties;
+use crate::renderer::dom::api::inclusive_descendants;
+use crate::renderer::dom::mutation::MutationRecord;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::selector::PseudoElement;
+use crate::renderer::dom::selector::SelectorList;
+use crate::renderer::dom::selector::Specificity;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// https://html.spec.whatwg.org/multipage/rendering.html#rendering
+const USER_AGENT_STYLE_SHEET: &str = r#"
+html, body, p, h1, h2 { display: block }
+head, title, base, link, meta, style, script { display: none }
+body { margin: 8px }
+p { margin-top: 1em; margin-bottom: 1em }
+h1 { font-size: 2em; font-weight: bold; margin-top: 0.67em; margin-bottom: 0.67em }
+h2 { font-size: 1.5em; font-weight: bold; margin-top: 0.83em; margin-bottom: 0.83em }
+a { color: #0000ee; text-decoration: underline }
+"#;
+
+/// https://www.w3.org/TR/css-cascade-4/#cascading-origins
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Origin {
+    UserAgent,
+    Author,
+}
+
+#[derive(Debug, Clone)]
+struct CascadedRule {
+    origin: Origin,
+    selectors: SelectorList,
+    declarations: Vec<Declaration>,
+    /// The media query lists of the enclosing `@media` rules. The rule
+    /// applies only if all of them match.
+    media: Vec<Rc<MediaQueryList>>,
+}
+
+/// A declaration that applies to an element, with what decides its
+/// precedence.
+struct MatchedDeclaration<'a> {
+    /// https://www.w3.org/TR/css-cascade-4/#cascade-origin
+    origin: Origin,
+    /// Origin and importance, from lowest to highest precedence.
+    level: u8,
+    /// https://www.w3.org/TR/css-cascade-4/#style-attr
+    from_style_attribute: bool,
+    specificity: Specificity,
+    /// The index of the rule and of the declaration in it.
+    order: (usize, usize),
+    declaration: &'a Declaration,
+}
+
+/// The style rules of the user agent and author style sheets, in the order
+/// they apply.
+#[derive(Debug, Clone)]
+pub struct Cascade {
+    rules: Vec<CascadedRule>,
+    /// The initial values of `LONGHANDS`, in the same order.
+    initial_values: Vec<Vec<ComponentValue>>,
+    /// What media queries are evaluated against. Its size is also the
+    /// basis of `vw` and `vh`.
+    viewport: Viewport,
+    /// The indices in `rules` by the subject of their selectors.
+    index: SelectorIndex,
+    invalidation: InvalidationSet,
+    /// Whether a rule has a selector with a pseudo-element.
+    has_pseudo_elements: bool,
+}
+
+impl Cascade {
+    /// Builds a cascade from the built-in user agent style sheet and
+    /// `author_sheets`, which must be in document order. Those are usually
+    /// the sheets of `<style>` and `<link rel=stylesheet>` elements, as
+    /// loaded by `StyleSheet::from_document`.
+    ///
+    /// `@supports` rules aren't evaluated, so the rules in them don't apply.
+    pub fn new(author_sheets: &[StyleSheet]) -> Self {
+        let mut rules = Vec::new();
+        let user_agent_sheet = StyleSheet::parse(USER_AGENT_STYLE_SHEET);
+        for (origin, sheet) in core::iter::once((Origin::UserAgent, &user_agent_sheet))
+            .chain(author_sheets.iter().map(|s| (Origin::Author, s)))
+        {
+            let media = with_media(&[], &sheet.media);
+            collect_rules(&mut rules, origin, &sheet.rules, &media);
+        }
+
+        let initial_values = LONGHANDS
+            .iter()
+            .map(|p| parse_component_values(p.initial))
+            .collect();
+
+        let mut index = SelectorIndex::default();
+        let mut invalidation = InvalidationSet::default();
+        for (i, rule) in rules.iter().enumerate() {
+            index.insert(i, &rule.selectors);
+            invalidation.insert(&rule.selectors);
+        }
+
+        let has_pseudo_elements = rules.iter().any(|r| {
+            r.selectors
+                .selectors()
+                .iter()
+                .any(|s| s.pseudo_element().is_some())
+        });
+
+        Self {
+            rules,
+            initial_values,
+            viewport: Viewport::default(),
+            index,
+            invalidation,
+            has_pseudo_elements,
+        }
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// Sets what media queries are evaluated against. Styles that were
+    /// already computed aren't updated.
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+
+    /// Sets the viewport, and computes the styles under `root` again if it
+    /// changed, e.g. when the window is resized.
+    pub fn update_viewport(&mut self, viewport: Viewport, root: &Rc<RefCell<Node>>) {
+        if self.viewport != viewport {
+            self.viewport = viewport;
+            self.apply(root);
+        }
+    }
+
+    /// Computes the style of `element`, whose parent element has
+    /// `parent_style`.
+    /// https://www.w3.org/TR/css-cascade-4/#value-stages
+    pub fn compute_style(
+        &self,
+        element: &Rc<RefCell<Node>>,
+        parent_style: Option<&ComputedStyle>,
+    ) -> ComputedStyle {
+        let style_attribute = element
+            .borrow()
+            .get_element()
+            .and_then(|e| e.get_attribute_ns(None, "style"))
+            .map(|style| CssParser::new(CssTokenizer::new(style)).parse_declarations())
+            .map(|declarations| expand_declarations(&declarations))
+            .unwrap_or_default();
+        let matched = self.matched_declarations(element, None, &style_attribute);
+        self.compute(element, None, &matched, parent_style)
+    }
+
+    /// Computes the style of the pseudo-element `pseudo_element` of
+    /// `element`, which inherits from `element_style`. Returns `None` if the
+    /// pseudo-element generates no box because its `content` is `normal` or
+    /// `none`, or its `display` is `none`.
+    /// https://www.w3.org/TR/css-pseudo-4/#generated-content
+    pub fn compute_pseudo_element_style(
+        &self,
+        element: &Rc<RefCell<Node>>,
+        pseudo_element: PseudoElement,
+        element_style: &ComputedStyle,
+    ) -> Option<ComputedStyle> {
+        if !self.has_pseudo_elements {
+            return None;
+        }
+        let matched = self.matched_declarations(element, Some(pseudo_element), &[]);
+        if matched.is_empty() {
+            return None;
+        }
+        let style = self.compute(element, Some(pseudo_element), &matched, Some(element_style));
+        // https://www.w3.org/TR/css-content-3/#valdef-content-normal
+        // `normal` computes to `none` for `::before` and `::after`.
+        match (style.keyword("content"), style.display()) {
+            (Some("normal") | Some("none"), _) | (_, Display::None) => None,
+            _ => Some(style),
+        }
+    }
+
+    /// Computes the style of `element`, or of its pseudo-element
+    /// `pseudo_element`, from the declarations that apply to it.
+    fn compute(
+        &self,
+        element: &Rc<RefCell<Node>>,
+        pseudo_element: Option<PseudoElement>,
+        matched: &[MatchedDeclaration],
+        parent_style: Option<&ComputedStyle>,
+    ) -> ComputedStyle {
+        let parent_font_size = parent_style.map_or(MEDIUM_FONT_SIZE, |p| p.font_size());
+        // https://www.w3.org/TR/css-values-4/#rem
+        // `rem` in the root element refers to the initial font size.
+        let root_font_size = root_element(element)
+            .filter(|root| pseudo_element.is_some() || !Rc::ptr_eq(root, element))
+            .and_then(|root| root.borrow().computed_style())
+            .map_or(MEDIUM_FONT_SIZE, |s| s.font_size());
+        let mut context = ComputeContext {
+            length: LengthContext {
+                font_size: parent_font_size,
+                root_font_size,
+                viewport_width: self.viewport.width,
+                viewport_height: self.viewport.height,
+            },
+            parent_font_size,
+            parent_font_weight: parent_style.map_or(400.0, |p| p.font_weight()),
+            current_color: parent_style
+                .and_then(|p| p.color("color"))
+                .unwrap_or(Color::BLACK),
+        };
+
+        // `em` and `currentcolor` in other properties depend on the
+        // computed `font-size` and `color`, so those are computed first.
+        let custom_properties = custom_properties(matched, parent_style);
+        let mut longhands: Vec<(&Property, &Vec<ComponentValue>)> =
+            LONGHANDS.iter().zip(self.initial_values.iter()).collect();
+        longhands.sort_by_key(|(p, _)| match p.name {
+            "font-size" => 0,
+            "color" => 1,
+            _ => 2,
+        });
+
+        let mut values = BTreeMap::new();
+        for (property, initial) in longhands {
+            let compute_initial = || {
+                compute_value(property, initial, &context)
+                    .expect("initial values are valid for their property")
+            };
+            let inherited = || match parent_style.and_then(|p| p.get(property.name)) {
+                Some(value) => value.clone(),
+                None => compute_initial(),
+            };
+            let unset = || match property.inherited {
+                true => inherited(),
+                false => compute_initial(),
+            };
+            let is_valid = |value: &[ComponentValue]| {
+                ["inherit", "initial", "unset", "revert"]
+                    .iter()
+                    .any(|k| is_keyword(value, k))
+                    || contains_var(value)
+                    || compute_value(property, value, &context).is_some()
+            };
+
+            // https://www.w3.org/TR/css-cascade-4/#defaulting-keywords
+            let value = match cascaded_value(matched, property.name, is_valid) {
+                // https://www.w3.org/TR/css-variables-1/#invalid-at-computed-value-time
+                // A value that is invalid after substitution acts like `unset`.
+                Some(declaration) if contains_var(&declaration.value) => {
+                    substitute_declaration(declaration, property.name, &custom_properties)
+                        .and_then(|value| compute_value(property, &value, &context))
+                        .unwrap_or_else(unset)
+                }
+                Some(declaration) if is_keyword(&declaration.value, "inherit") => inherited(),
+                Some(declaration) if is_keyword(&declaration.value, "initial") => compute_initial(),
+                Some(declaration) if !is_keyword(&declaration.value, "unset") => {
+                    compute_value(property, &declaration.value, &context)
+                        .unwrap_or_else(compute_initial)
+                }
+                _ => unset(),
+            };
+
+            match (property.name, &value) {
+                ("font-size", ComputedValue::Length(size)) => {
+                    context.length.font_size = *size;
+                    if parent_style.is_none() {
+                        context.length.root_font_size = *size;
+                    }
+                }
+                ("color", ComputedValue::Color(color)) => context.current_color = *color,
+                _ => {}
+            }
+            values.insert(property.name, value);
+        }
+
+        let mut style = ComputedStyle::new(values, custom_properties);
+        zero_border_widths(&mut style);
+        style
+    }
+
+    /// Computes the style of an anonymous box whose parent box has the style
+    /// `parent_style`. Inherited properties inherit and the others have
+    /// their initial value.
+    /// https://www.w3.org/TR/css-display-3/#anonymous
+    pub fn compute_anonymous_style(&self, parent_style: &ComputedStyle) -> ComputedStyle {
+        let font_size = parent_style.font_size();
+        let context = ComputeContext {
+            length: LengthContext {
+                font_size,
+                root_font_size: MEDIUM_FONT_SIZE,
+                viewport_width: self.viewport.width,
+                viewport_height: self.viewport.height,
+            },
+            parent_font_size: font_size,
+            parent_font_weight: parent_style.font_weight(),
+            current_color: parent_style.color("color").unwrap_or(Color::BLACK),
+        };
+        let values = LONGHANDS
+            .iter()
+            .zip(self.initial_values.iter())
+            .map(|(property, initial)| {
+                let value = match parent_style.get(property.name) {
+                    Some(value) if property.inherited => value.clone(),
+                    _ => compute_value(property, initial, &context)
+                        .expect("initial values are valid for their property"),
+                };
+                (property.name, value)
+            })
+            .collect();
+        let mut style = ComputedStyle::new(values, parent_style.custom_properties().clone());
+        zero_border_widths(&mut style);
+        style
+    }
+
+    /// Computes the style of every element under `root`, inclusive, and
+    /// stores it in the element's node.
+    pub fn apply(&self, root: &Rc<RefCell<Node>>) {
+        for node in inclusive_descendants(root) {
+            if node.borrow().get_element().is_none() {
+                continue;
+            }
+            let parent_style = node
+                .borrow()
+                .parent()
+                .upgrade()
+                .and_then(|p| p.borrow().computed_style());
+            let style = Rc::new(self.compute_style(&node, parent_style.as_deref()));
+            self.apply_pseudo_elements(&node, &style);
+            let mut node = node.borrow_mut();
+            node.set_computed_style(Some(style));
+            node.set_restyle(None);
+        }
+    }
+
+    /// Computes and stores the styles of the `::before` and `::after`
+    /// pseudo-elements of `element`, whose style is `style`.
+    fn apply_pseudo_elements(&self, element: &Rc<RefCell<Node>>, style: &ComputedStyle) {
+        for pseudo_element in [PseudoElement::Before, PseudoElement::After] {
+            let pseudo_style = self
+                .compute_pseudo_element_style(element, pseudo_element, style)
+                .map(Rc::new);
+            element
+                .borrow_mut()
+                .set_pseudo_element_style(pseudo_element, pseudo_style);
+        }
+    }
+
+    /// Marks the elements whose style may have changed because of the
+    /// mutations described by `records`, so that `restyle` computes their
+    /// style again. The records are usually taken from a `MutationObserver`
+    /// that observes the document with `child_list`, `attributes`,
+    /// `attribute_old_value`, `character_data` and `subtree`.
+    pub fn invalidate(&self, records: &[MutationRecord]) {
+        for record in records {
+            self.invalidation.invalidate(record);
+        }
+    }
+
+    /// Computes the style again for the elements under `root`, inclusive,
+    /// that `invalidate` marked or that have none yet. The children of an
+    /// element whose style changed are restyled too, since they may inherit
+    /// from it.
+    pub fn restyle(&self, root: &Rc<RefCell<Node>>) {
+        let parent_style = root
+            .borrow()
+            .parent()
+            .upgrade()
+            .and_then(|p| p.borrow().computed_style());
+        self.restyle_subtree(root, parent_style.as_ref(), None);
+    }
+
+    /// Restyles `node` and its descendants. `forced` is what the parent's
+    /// restyle requires of its children.
+    fn restyle_subtree(
+        &self,
+        node: &Rc<RefCell<Node>>,
+        parent_style: Option<&Rc<ComputedStyle>>,
+        forced: Option<Restyle>,
+    ) {
+        let mut style = parent_style.cloned();
+        let mut children = forced.filter(|r| *r == Restyle::Subtree);
+        if node.borrow().get_element().is_some() {
+            let old_style = node.borrow().computed_style();
+            let restyle = node.borrow().restyle().max(forced);
+            style = old_style.clone();
+            if restyle.is_some() || old_style.is_none() {
+                let new_style = Rc::new(self.compute_style(node, parent_style.map(|s| &**s)));
+                if restyle == Some(Restyle::Subtree) {
+                    children = Some(Restyle::Subtree);
+                } else if old_style.as_ref() != Some(&new_style) {
+                    // `rem` in every descendant depends on the font size of
+                    // the root element.
+                    let is_root_font_size_changed = parent_style.is_none()
+                        && old_style.map(|s| s.font_size()) != Some(new_style.font_size());
+                    children = children.max(Some(match is_root_font_size_changed {
+                        true => Restyle::Subtree,
+                        false => Restyle::Element,
+                    }));
+                }
+                self.apply_pseudo_elements(node, &new_style);
+                node.borrow_mut()
+                    .set_computed_style(Some(new_style.clone()));
+                style = Some(new_style);
+            }
+            node.borrow_mut().set_restyle(None);
+        }
+
+        let mut child = node.borrow().first_child();
+        while let Some(c) = child {
+            self.restyle_subtree(&c, style.as_ref(), children);
+            child = c.borrow().next_sibling();
+        }
+    }
+
+    /// Returns the declarations that apply to `element`, sorted from the
+    /// lowest to the highest precedence.
+    /// https://www.w3.org/TR/css-cascade-4/#cascade-sort
+    fn matched_declarations<'a>(
+        &'a self,
+        element: &Rc<RefCell<Node>>,
+        pseudo_element: Option<PseudoElement>,
+        style_attribute: &'a [Declaration],
+    ) -> Vec<MatchedDeclaration<'a>> {
+        let mut matched = Vec::new();
+        let candidates = element
+            .borrow()
+            .get_element()
+            .map(|e| self.index.candidates(&e))
+            .unwrap_or_default();
+        for i in candidates {
+            let rule = &self.rules[i];
+            if !rule.media.iter().all(|m| m.matches(&self.viewport)) {
+                continue;
+            }
+            // https://www.w3.org/TR/selectors-4/#specificity-rules
+            // A selector list has the specificity of its most specific
+            // selector that matches.
+            let specificity = rule
+                .selectors
+                .selectors()
+                .iter()
+                .filter(|s| match pseudo_element {
+                    Some(p) => s.matches_pseudo_element(element, p),
+                    None => s.matches(element),
+                })
+                .map(|s| s.specificity())
+                .max();
+            if let Some(specificity) = specificity {
+                for (j, declaration) in rule.declarations.iter().enumerate() {
+                    matched.push(MatchedDeclaration {
+                        origin: rule.origin,
+                        level: level(rule.origin, declaration.important),
+                        from_style_attribute: false,
+                        specificity,
+                        order: (i, j),
+                        declaration,
+                    });
+                }
+            }
+        }
+        for (j, declaration) in style_attribute.iter().enumerate() {
+            matched.push(MatchedDeclaration {
+                origin: Origin::Author,
+                level: level(Origin::Author, declaration.important),
+                from_style_attribute: true,
+                specificity: Specificity::default(),
+                order: (self.rules.len(), j),
+                declaration,
+            });
+        }
+
+        matched.sort_by_key(|m| (m.level, m.from_style_attribute, m.specificity, m.order));
+        matched
+    }
+}
+
+/// Appends the style rules in `sheet_rules` to `rules`, including those in
+/// `@media` rules and in imported style sheets. `media` is the media query
+/// lists of the enclosing `@media` rules.
+fn collect_rules(
+    rules: &mut Vec<CascadedRule>,
+    origin: Origin,
+    sheet_rules: &[CssRule],
+    media: &[Rc<MediaQueryList>],
+) {
+    for rule in sheet_rules {
+        match rule {
+            CssRule::Style(rule) => rules.push(CascadedRule {
+                origin,
+                selectors: rule.selectors.clone(),
+                declarations: expand_declarations(&rule.declarations),
+                media: media.to_vec(),
+            }),
+            CssRule::Media(rule) => {
+                collect_rules(rules, origin, &rule.rules, &with_media(media, &rule.media));
+            }
+            // The media of an `@import` rule is also that of the imported
+            // sheet.
+            CssRule::Import(rule) => {
+                if let Some(sheet) = &rule.style_sheet {
+                    let media = with_media(media, &sheet.media);
+                    collect_rules(rules, origin, &sheet.rules, &media);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns `media` with the media query list `query` appended, unless it is
+/// empty and so always matches.
+fn with_media(media: &[Rc<MediaQueryList>], query: &str) -> Vec<Rc<MediaQueryList>> {
+    let mut media = media.to_vec();
+    if !query.trim().is_empty() {
+        media.push(Rc::new(MediaQueryList::parse(query)));
+    }
+    media
+}
+
+/// https://www.w3.org/TR/css-cascade-4/#cascade-origin
+fn level(origin: Origin, important: bool) -> u8 {
+    match (origin, important) {
+        (Origin::UserAgent, false) => 0,
+        (Origin::Author, false) => 1,
+        (Origin::Author, true) => 2,
+        (Origin::UserAgent, true) => 3,
+    }
+}
+
+/// Returns the valid declaration of `property` with the highest precedence
+/// in `matched`, or `None` if there is none. It may be a declaration of a
+/// shorthand of `property` whose value contains `var()`, which is only
+/// expanded after substitution.
+/// https://www.w3.org/TR/css-cascade-4/#cascaded
+fn cascaded_value<'a>(
+    matched: &[MatchedDeclaration<'a>],
+    property: &str,
+    is_valid: impl Fn(&[ComponentValue]) -> bool,
+) -> Option<&'a Declaration> {
+    let declares = |declaration: &Declaration| {
+        declaration.name == property
+            || find_shorthand(&declaration.name).is_some_and(|s| s.longhands.contains(&property))
+    };
+    let mut candidates = matched
+        .iter()
+        .rev()
+        .filter(|m| declares(m.declaration) && is_valid(&m.declaration.value));
+    let winner = candidates.next()?;
+    if !is_keyword(&winner.declaration.value, "revert") {
+        return Some(winner.declaration);
+    }
+
+    // https://www.w3.org/TR/css-cascade-4/#valdef-all-revert
+    // `revert` in an author style sheet rolls back to the user agent origin.
+    // In the user agent origin, it acts like `unset`.
+    if winner.origin == Origin::UserAgent {
+        return None;
+    }
+    candidates
+        .find(|m| m.origin == Origin::UserAgent)
+        .map(|m| m.declaration)
+        .filter(|d| !is_keyword(&d.value, "revert"))
+}
+
+/// Computes the custom properties of an element from its matched
+/// declarations. Custom properties are inherited.
+/// https://www.w3.org/TR/css-variables-1/#defining-variables
+fn custom_properties(
+    matched: &[MatchedDeclaration],
+    parent_style: Option<&ComputedStyle>,
+) -> Rc<CustomProperties> {
+    let inherited = parent_style.map_or_else(Default::default, |p| p.custom_properties().clone());
+    let mut names: Vec<&str> = matched
+        .iter()
+        .map(|m| m.declaration.name.as_str())
+        .filter(|name| name.starts_with("--"))
+        .collect();
+    if names.is_empty() {
+        return inherited;
+    }
+    names.sort_unstable();
+    names.dedup();
+
+    let mut properties = (*inherited).clone();
+    let mut declared = BTreeMap::new();
+    for name in names {
+        match cascaded_value(matched, name, |_| true) {
+            // The initial value of a custom property is the guaranteed-invalid
+            // value.
+            Some(declaration) if is_keyword(&declaration.value, "initial") => {
+                properties.remove(name);
+            }
+            Some(declaration)
+                if !is_keyword(&declaration.value, "inherit")
+                    && !is_keyword(&declaration.value, "unset") =>
+            {
+                declared.insert(name, declaration.value.as_slice());
+            }
+            _ => {}
+        }
+    }
+    Rc::new(compute_custom_properties(&declared, properties))
+}
+
+/// Returns the value of the longhand `property` set by `declaration` after
+/// `var()` substitution, or `None` if it is invalid at computed-value time.
+/// https://www.w3.org/TR/css-variables-1/#variables-in-shorthands
+fn substitute_declaration(
+    declaration: &Declaration,
+    property: &str,
+    custom_properties: &CustomProperties,
+) -> Option<Vec<ComponentValue>> {
+    let value = substitute(&declaration.value, &mut |name| {
+        custom_properties.get(name).cloned()
+    })?;
+    if declaration.name == property {
+        return Some(value);
+    }
+    expand(find_shorthand(&declaration.name)?, &value)?
+        .into_iter()
+        .find(|(longhand, _)| *longhand == property)
+        .map(|(_, value)| value)
+}
+
+/// https://www.w3.org/TR/css-backgrounds-3/#border-width
+/// The width is 0 if the style is `none` or `hidden`.
+fn zero_border_widths(style: &mut ComputedStyle) {
+    for (border_style, border_width) in [
+        ("border-top-style", "border-top-width"),
+        ("border-right-style", "border-right-width"),
+        ("border-bottom-style", "border-bottom-width"),
+        ("border-left-style", "border-left-width"),
+    ] {
+        if matches!(style.keyword(border_style), Some("none") | Some("hidden")) {
+            style.set(border_width, ComputedValue::Length(0.0));
+        }
+    }
+}
+
+/// Returns the topmost inclusive ancestor of `node` that is an element.
+fn root_element(node: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    let mut root = None;
+    let mut current = Some(node.clone());
+    while let Some(n) = current {
+        if n.borrow().get_element().is_none() {
+            break;
+        }
+        current = n.borrow().parent().upgrade();
+        root = Some(n);
+    }
+    root
+}
+
+fn is_keyword(value: &[ComponentValue], keyword: &str) -> bool {
+    match value {
+        [ComponentValue::Token(CssToken::Ident(ident))] => ident.eq_ignore_ascii_case(keyword),
+        _ => false,
+    }
+}
+
+fn parse_component_values(css: &str) -> Vec<ComponentValue> {
+    CssParser::new(CssTokenizer::new(String::from(css))).parse_component_values()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::css::length::LengthPercentage;
+    use crate::renderer::css::media::MediaType;
+    use crate::renderer::dom::api::append_child;
+    use crate::renderer::dom::api::query_selector;
+    use crate::renderer::dom::api::remove_child;
+    use crate::renderer::dom::api::set_attribute;
+    use crate::renderer::dom::mutation::MutationObserver;
+    use crate::renderer::dom::mutation::MutationObserverInit;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+
+    /// Parses `html` and applies the cascade of its `<style>` elements.
+    fn styled_document(html: &str) -> Rc<RefCell<Node>> {
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let document = window.borrow().document();
+        Cascade::new(&StyleSheet::from_style_elements(&document)).apply(&document);
+        document
+    }
+
+    fn serialize(value: &[ComponentValue]) -> String {
+        value.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn style(document: &Rc<RefCell<Node>>, selector: &str) -> Rc<ComputedStyle> {
+        let node = query_selector(document, selector).unwrap().unwrap();
+        let style = node.borrow().computed_style().unwrap();
+        style
+    }
+
+    #[test]
+    fn test_user_agent_style_sheet() {
+        let document = styled_document("<p>a <a href=/>b</a></p>");
+        assert_eq!(Display::Block, style(&document, "html").display());
+        assert_eq!(Display::None, style(&document, "head").display());
+        assert_eq!(Display::Block, style(&document, "p").display());
+        assert_eq!(Display::Inline, style(&document, "a").display());
+        assert_eq!(Some(8.0), style(&document, "body").length("margin-left"));
+        assert_eq!(
+            Some(Color::rgb(0, 0, 0xee)),
+            style(&document, "a").color("color")
+        );
+        assert_eq!(Some(0.0), style(&document, "a").length("margin-left"));
+    }
+
+    #[test]
+    fn test_precedence() {
+        let document = styled_document(
+            r#"<style>
+  #x { color: red }
+  p.c { color: green; width: 1px }
+  p { color: blue !important; width: 2px }
+  p { width: 3px }
+  a { text-decoration: none }
+  h1 { display: inline !important }
+</style>
+<p id=x class=c style="color: black; width: 4px">t</p>
+<p class=c>t</p>
+<h1><a style="text-decoration: overline" href=/>h</a></h1>"#,
+        );
+        let p = style(&document, "#x");
+        // An important declaration beats the style attribute, which beats
+        // any selector.
+        assert_eq!(Some(Color::rgb(0, 0, 0xff)), p.color("color"));
+        assert_eq!(Some(4.0), p.length("width"));
+
+        // Higher specificity beats later source order.
+        let p = style(&document, "p:not(#x)");
+        assert_eq!(Some(1.0), p.length("width"));
+        // Author declarations beat user agent ones.
+        assert_eq!(
+            Some("overline"),
+            style(&document, "a").keyword("text-decoration")
+        );
+        assert_eq!(Display::Inline, style(&document, "h1").display());
+    }
+
+    #[test]
+    fn test_inheritance_and_defaulting() {
+        let document = styled_document(
+            r#"<style>
+  body { color: green; margin-top: 3px; display: inline }
+  p { margin-top: inherit; color: initial; display: unset }
+  h1 { color: unset; margin-top: unset; width: revert; display: revert }
+  h2 { margin-top: revert; font-weight: revert }
+</style>
+<p>p</p><h1>h1</h1><h2>h2</h2>"#,
+        );
+        let p = style(&document, "p");
+        assert_eq!(Some(3.0), p.length("margin-top"));
+        assert_eq!(Some(Color::BLACK), p.color("color"));
+        assert_eq!(Display::Inline, p.display());
+
+        let h1 = style(&document, "h1");
+        assert_eq!(Some(Color::rgb(0, 0x80, 0)), h1.color("color"));
+        assert_eq!(Some(0.0), h1.length("margin-top"));
+        assert_eq!(Some("auto"), h1.keyword("width"));
+        assert_eq!(Display::Block, h1.display());
+
+        let h2 = style(&document, "h2");
+        assert_eq!(Some(0.83 * 24.0), h2.length("margin-top"));
+        assert_eq!(Some(700.0), h2.number("font-weight"));
+        assert_eq!(Some(Color::rgb(0, 0x80, 0)), h2.color("color"));
+    }
+
+    #[test]
+    fn test_compute_style_without_parent() {
+        let document = styled_document("<p>t</p>");
+        let p = query_selector(&document, "p").unwrap().unwrap();
+        let cascade = Cascade::new(&[StyleSheet::parse("p { color: inherit; margin-top: 1px }")]);
+        let style = cascade.compute_style(&p, None);
+        assert_eq!(Some(Color::BLACK), style.color("color"));
+        assert_eq!(Some(1.0), style.length("margin-top"));
+        assert_eq!(Display::Block, style.display());
+        assert_eq!(Some("serif"), style.keyword("font-family"));
+    }
+
+    #[test]
+    fn test_typed_values() {
+        let document = styled_document(
+            r#"<style>
+  html { font-size: 20px }
+  body { font-size: larger; color: rgb(0 128 0 / 50%) }
+  p { font-size: 10px; width: calc(50% - 2em); height: 2rem; margin-left: auto; padding-top: 2vw; line-height: 150%; font-weight: bolder; border-top-style: solid; border-top-width: thick; border-top-color: currentColor; border-bottom-width: 4px; color: hsl(120, 100%, 25%) }
+  a { width: 1in; height: 12pt; margin-top: 1cm; padding-left: 1ex; padding-right: 2ch; line-height: 1.5; color: bad; font-size: -1px; display: flex; width: -1px; padding-left: -1%; max-width: -1px; line-height: -1; line-height: -10%; top: -2px }
+</style>
+<p><a href=/>a</a></p>"#,
+        );
+        let body = style(&document, "body");
+        assert_eq!(24.0, body.font_size());
+        assert_eq!(Some(Color::rgba(0, 128, 0, 128)), body.color("color"));
+
+        let p = style(&document, "p");
+        assert_eq!(10.0, p.font_size());
+        let width = p.length_percentage("width").unwrap();
+        assert!(matches!(width, LengthPercentage::Calc(_)));
+        assert_eq!(180.0, width.resolve(400.0));
+        assert_eq!(Some(40.0), p.length("height"));
+        assert_eq!(Some("auto"), p.keyword("margin-left"));
+        assert_eq!(Some(16.0), p.length("padding-top"));
+        assert_eq!(Some(15.0), p.length("line-height"));
+        assert_eq!(Some(700.0), p.number("font-weight"));
+        assert_eq!(Some(5.0), p.length("border-top-width"));
+        assert_eq!(Some(Color::rgb(0, 128, 0)), p.color("border-top-color"));
+        // The width of a border without a style is 0.
+        assert_eq!(Some(0.0), p.length("border-bottom-width"));
+
+        let a = style(&document, "a");
+        assert_eq!(Some(96.0), a.length("width"));
+        assert_eq!(Some(16.0), a.length("height"));
+        assert_eq!(Some(96.0 / 2.54), a.length("margin-top"));
+        assert_eq!(Some(5.0), a.length("padding-left"));
+        assert_eq!(Some(10.0), a.length("padding-right"));
+        assert_eq!(Some(1.5), a.number("line-height"));
+        assert_eq!(Some("none"), a.keyword("max-width"));
+        // Insets may be negative.
+        assert_eq!(Some(-2.0), a.length("top"));
+        // Invalid declarations are ignored.
+        assert_eq!(Some(Color::rgb(0, 0, 0xee)), a.color("color"));
+        assert_eq!(10.0, a.font_size());
+        assert_eq!(Display::Inline, a.display());
+    }
+
+    #[test]
+    fn test_shorthands() {
+        let document = styled_document(
+            r#"<style>
+  p { margin: 1px 2px; border: thin solid red; border-left: 3px dotted; font: italic 20px/2 monospace }
+  p { border-top-color: blue; margin: 1px 2px 3px 4px 5px }
+</style>
+<p style="padding: 0 auto; padding: 5px 6px; flex: 2">t</p>"#,
+        );
+        let p = style(&document, "p");
+        assert_eq!(Some(2.0), p.length("margin-right"));
+        assert_eq!(Some(1.0), p.length("margin-bottom"));
+        assert_eq!(Some(Color::rgb(0, 0, 0xff)), p.color("border-top-color"));
+        assert_eq!(Some(Color::rgb(0xff, 0, 0)), p.color("border-right-color"));
+        assert_eq!(Some(1.0), p.length("border-right-width"));
+        assert_eq!(Some(3.0), p.length("border-left-width"));
+        assert_eq!(Some("dotted"), p.keyword("border-left-style"));
+        assert_eq!(Some(Color::BLACK), p.color("border-left-color"));
+        assert_eq!(Some("italic"), p.keyword("font-style"));
+        assert_eq!(20.0, p.font_size());
+        assert_eq!(Some(2.0), p.number("line-height"));
+        assert_eq!(Some("monospace"), p.keyword("font-family"));
+        assert_eq!(Some(6.0), p.length("padding-left"));
+        assert_eq!(Some(2.0), p.number("flex-grow"));
+    }
+
+    #[test]
+    fn test_anonymous_style() {
+        let document = styled_document(
+            "<style>p { color: red; font-size: 20px; margin-left: 1em; border-left: 1px solid }</style><p>a</p>",
+        );
+        let cascade = Cascade::new(&[]);
+        let anonymous = cascade.compute_anonymous_style(&style(&document, "p"));
+        assert_eq!(Some(Color::rgb(0xff, 0, 0)), anonymous.color("color"));
+        assert_eq!(20.0, anonymous.font_size());
+        assert_eq!(Some(0.0), anonymous.length("margin-left"));
+        assert_eq!(Some(0.0), anonymous.length("border-left-width"));
+        assert_eq!(Display::Inline, anonymous.display());
+    }
+
+    #[test]
+    fn test_media_rules() {
+        let window = HtmlParser::new(HtmlTokenizer::new(
+            r#"<style>
+  p { width: 1px }
+  @media (min-width: 600px) {
+    p { width: 50vw }
+    @media print { p { width: 3px } }
+  }
+  @media print, (max-width: 100px) { p { color: red } }
+</style>
+<p>t</p>"#
+                .to_string(),
+        ))
+        .construct_tree();
+        let document = window.borrow().document();
+        let mut cascade = Cascade::new(&StyleSheet::from_style_elements(&document));
+        cascade.apply(&document);
+        assert_eq!(Some(400.0), style(&document, "p").length("width"));
+        assert_eq!(Some(Color::BLACK), style(&document, "p").color("color"));
+
+        let viewport = Viewport {
+            width: 100.0,
+            ..cascade.viewport()
+        };
+        cascade.update_viewport(viewport, &document);
+        assert_eq!(Some(1.0), style(&document, "p").length("width"));
+        assert_eq!(
+            Some(Color::rgb(0xff, 0, 0)),
+            style(&document, "p").color("color")
+        );
+
+        let viewport = Viewport {
+            width: 1000.0,
+            media_type: MediaType::Print,
+            ..cascade.viewport()
+        };
+        cascade.update_viewport(viewport, &document);
+        assert_eq!(Some(3.0), style(&document, "p").length("width"));
+        assert_eq!(
+            Some(Color::rgb(0xff, 0, 0)),
+            style(&document, "p").color("color")
+        );
+    }
+
+    #[test]
+    fn test_imported_style_sheets() {
+        let window = HtmlParser::new(HtmlTokenizer::new("<p>t</p>".to_string())).construct_tree();
+        let document = window.borrow().document();
+        let mut sheet = StyleSheet::parse(
+            "@import 'a.css'; @import 'b.css' print; p { width: 2px; height: 2px }",
+        );
+        let mut imported = StyleSheet::parse("p { width: 1px; height: 1px; color: red }");
+        imported.media = "(max-width: 100px)".to_string();
+        for (rule, css) in sheet
+            .rules
+            .iter_mut()
+            .zip(["p { height: 3px }", "p { color: blue }"])
+        {
+            if let CssRule::Import(import) = rule {
+                let mut nested = imported.clone();
+                nested.rules.extend(StyleSheet::parse(css).rules);
+                import.style_sheet = Some(Box::new(nested));
+            }
+        }
+
+        let mut cascade = Cascade::new(&[sheet]);
+        cascade.apply(&document);
+        assert_eq!(Some(2.0), style(&document, "p").length("width"));
+        assert_eq!(Some(2.0), style(&document, "p").length("height"));
+        assert_eq!(Some(Color::BLACK), style(&document, "p").color("color"));
+
+        let viewport = Viewport {
+            width: 100.0,
+            media_type: MediaType::Print,
+            ..cascade.viewport()
+        };
+        cascade.update_viewport(viewport, &document);
+        assert_eq!(Some(2.0), style(&document, "p").length("width"));
+        assert_eq!(
+            Some(Color::rgb(0, 0, 0xff)),
+            style(&document, "p").color("color")
+        );
+    }
+
+    #[test]
+    fn test_custom_properties() {
+        let document = styled_document(
+            r#"<style>
+  html { --color: #00f; --size: 10px; --space: var(--size) 0 }
+  body { --size: 20px; color: var(--color); margin: var(--space); width: calc(var(--size) * 2) }
+  p { --color: initial; --a: var(--b); --b: var(--a); --c: var(--a, 1px) }
+  p { color: var(--color); margin: var(--size) var(--missing); width: var(--size, 5px) var(--a) }
+  p { height: var(--color, 3px); padding-left: var(--c); font-size: var(--size) }
+</style>
+<p>t</p>"#,
+        );
+        let html = style(&document, "html");
+        let body = style(&document, "body");
+        let p = style(&document, "p");
+        assert_eq!(
+            Some("#00f".to_string()),
+            html.custom_property("--color").map(serialize)
+        );
+        assert_eq!(None, html.custom_property("--missing"));
+        // Elements that declare no custom properties share their parent's.
+        assert!(Rc::ptr_eq(
+            html.custom_properties(),
+            style(&document, "head").custom_properties()
+        ));
+
+        // `--space` is substituted in `html`, with the `--size` of `html`.
+        assert_eq!(
+            Some("10px 0".to_string()),
+            body.custom_property("--space").map(serialize)
+        );
+        assert_eq!(Some(Color::rgb(0, 0, 0xff)), body.color("color"));
+        assert_eq!(Some(10.0), body.length("margin-top"));
+        assert_eq!(Some(0.0), body.length("margin-left"));
+        assert_eq!(Some(40.0), body.length("width"));
+
+        assert_eq!(None, p.custom_property("--color"));
+        assert_eq!(None, p.custom_property("--a"));
+        assert_eq!(None, p.custom_property("--b"));
+        assert_eq!(
+            Some("1px".to_string()),
+            p.custom_property("--c").map(serialize)
+        );
+        assert_eq!(
+            Some("20px".to_string()),
+            p.custom_property("--size").map(serialize)
+        );
+        // Values that are invalid at computed-value time act like `unset`.
+        assert_eq!(Some(Color::rgb(0, 0, 0xff)), p.color("color"));
+        assert_eq!(Some(0.0), p.length("margin-top"));
+        assert_eq!(
+            Some(&ComputedValue::Keyword("auto".to_string())),
+            p.get("width")
+        );
+        assert_eq!(Some(3.0), p.length("height"));
+        assert_eq!(Some(1.0), p.length("padding-left"));
+        assert_eq!(20.0, p.font_size());
+    }
+
+    #[test]
+    fn test_restyle() {
+        let document = styled_document(
+            r#"<style>
+  .big { font-size: 20px }
+  .big a { color: blue }
+  p + p { margin-left: 1em }
+</style>
+<p id=a>t<a id=x>l</a></p><p id=b>t<a id=y>l</a></p><p id=c></p>"#,
+        );
+        let cascade = Cascade::new(&StyleSheet::from_style_elements(&document));
+        let observer = MutationObserver::new();
+        let options = MutationObserverInit {
+            child_list: true,
+            attributes: true,
+            attribute_old_value: true,
+            character_data: true,
+            subtree: true,
+            ..Default::default()
+        };
+        observer.observe(&document, options).unwrap();
+        let a = style(&document, "#a");
+        let x = style(&document, "#x");
+        let c = style(&document, "#c");
+
+        let b = query_selector(&document, "#b").unwrap().unwrap();
+        set_attribute(&b, "class", "big");
+        cascade.invalidate(&observer.take_records());
+        cascade.restyle(&document);
+        assert_eq!(20.0, style(&document, "#b").font_size());
+        assert_eq!(Some(20.0), style(&document, "#b").length("margin-left"));
+        assert_eq!(20.0, style(&document, "#y").font_size());
+        assert_eq!(
+            Some(Color::rgb(0, 0, 0xff)),
+            style(&document, "#y").color("color")
+        );
+        // The other elements aren't restyled.
+        assert!(Rc::ptr_eq(&a, &style(&document, "#a")));
+        assert!(Rc::ptr_eq(&x, &style(&document, "#x")));
+        assert!(Rc::ptr_eq(&c, &style(&document, "#c")));
+        assert!(inclusive_descendants(&document).all(|n| n.borrow().restyle().is_none()));
+
+        let body = query_selector(&document, "body").unwrap().unwrap();
+        let y = query_selector(&document, "#y").unwrap().unwrap();
+        append_child(&body, y).unwrap();
+        let a = query_selector(&document, "#a").unwrap().unwrap();
+        remove_child(&body, &a).unwrap();
+        cascade.invalidate(&observer.take_records());
+        cascade.restyle(&document);
+        assert_eq!(Some(0.0), style(&document, "#b").length("margin-left"));
+        assert_eq!(
+            Some(Color::rgb(0, 0, 0xee)),
+            style(&document, "#y").color("color")
+        );
+        assert_eq!(16.0, style(&document, "#y").font_size());
+        assert_eq!(Some(16.0), style(&document, "#c").length("margin-left"));
+    }
+
+    #[test]
+    fn test_pseudo_element_styles() {
+        let document = styled_document(
+            r#"<style>
+  p { color: red; font-size: 20px }
+  p::before { content: "a"; margin-left: 1em }
+  p::after { content: normal }
+  #b::before { display: none }
+  #c::after { content: "c" }
+</style>
+<p id=a>a</p><p id=b>b</p><p id=c>c</p>"#,
+        );
+        let pseudo_style = |selector: &str, pseudo_element| {
+            let node = query_selector(&document, selector).unwrap().unwrap();
+            let style = node.borrow().pseudo_element_style(pseudo_element);
+            style
+        };
+        let before = pseudo_style("#a", PseudoElement::Before).unwrap();
+        assert_eq!(Some(Color::rgb(0xff, 0, 0)), before.color("color"));
+        assert_eq!(Some(20.0), before.length("margin-left"));
+        assert_eq!(Display::Inline, before.display());
+        assert!(pseudo_style("#a", PseudoElement::After).is_none());
+        assert!(pseudo_style("#b", PseudoElement::Before).is_none());
+        assert!(pseudo_style("#c", PseudoElement::After).is_some());
+        assert!(pseudo_style("body", PseudoElement::Before).is_none());
+    }
+}
@@ -0,0 +1,452 @@
+//! https://www.w3.org/TR/css-color-4/
+
+use crate::renderer::css::stylesheet::ComponentValue;
+use crate::renderer::css::token::CssToken;
+use alloc::vec::Vec;
+
+/// An sRGB color with 8 bits per channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    /// https://www.w3.org/TR/css-color-4/#transparent-color
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    const fn from_hex(rgb: u32) -> Self {
+        Self::rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    }
+}
+
+/// A `<color>` value before `currentcolor` is resolved.
+/// https://www.w3.org/TR/css-color-4/#typedef-color
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpecifiedColor {
+    Rgba(Color),
+    /// https://www.w3.org/TR/css-color-4/#currentcolor-color
+    CurrentColor,
+}
+
+impl SpecifiedColor {
+    /// Parses a `<color>` from a declaration value.
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        let values: Vec<&ComponentValue> = values.iter().filter(|v| !is_whitespace(v)).collect();
+        match values.as_slice() {
+            [ComponentValue::Token(CssToken::Ident(name))] => {
+                let name = name.to_ascii_lowercase();
+                match name.as_str() {
+                    "currentcolor" => Some(SpecifiedColor::CurrentColor),
+                    "transparent" => Some(SpecifiedColor::Rgba(Color::TRANSPARENT)),
+                    _ => NAMED_COLORS
+                        .iter()
+                        .find(|(n, _)| *n == name)
+                        .map(|(_, c)| SpecifiedColor::Rgba(*c)),
+                }
+            }
+            [ComponentValue::Token(CssToken::Hash { value, .. })] => {
+                parse_hex(value).map(SpecifiedColor::Rgba)
+            }
+            [ComponentValue::Function { name, value }] => {
+                let name = name.to_ascii_lowercase();
+                let args = function_arguments(value)?;
+                let color = match name.as_str() {
+                    "rgb" | "rgba" => parse_rgb(&args)?,
+                    "hsl" | "hsla" => parse_hsl(&args)?,
+                    _ => return None,
+                };
+                Some(SpecifiedColor::Rgba(color))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the color, taking `current_color` for `currentcolor`.
+    pub fn resolve(&self, current_color: Color) -> Color {
+        match self {
+            SpecifiedColor::Rgba(color) => *color,
+            SpecifiedColor::CurrentColor => current_color,
+        }
+    }
+}
+
+fn is_whitespace(value: &ComponentValue) -> bool {
+    *value == ComponentValue::Token(CssToken::Whitespace)
+}
+
+/// https://www.w3.org/TR/css-color-4/#hex-notation
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    match hex.len() {
+        3 | 4 => {
+            let alpha = if hex.len() == 4 { digit(3)? * 17 } else { 255 };
+            Some(Color::rgba(
+                digit(0)? * 17,
+                digit(1)? * 17,
+                digit(2)? * 17,
+                alpha,
+            ))
+        }
+        6 | 8 => {
+            let alpha = if hex.len() == 8 { byte(6)? } else { 255 };
+            Some(Color::rgba(byte(0)?, byte(2)?, byte(4)?, alpha))
+        }
+        _ => None,
+    }
+}
+
+/// Splits the arguments of a color function, in either the legacy comma
+/// syntax, `rgb(1, 2, 3, 0.5)`, or the modern syntax, `rgb(1 2 3 / 0.5)`.
+/// The alpha, if any, is the fourth argument.
+fn function_arguments(values: &[ComponentValue]) -> Option<Vec<CssToken>> {
+    let mut tokens = Vec::new();
+    for value in values.iter().filter(|v| !is_whitespace(v)) {
+        match value {
+            ComponentValue::Token(token) => tokens.push(token),
+            _ => return None,
+        }
+    }
+    let is_numeric = |t: &CssToken| {
+        matches!(
+            t,
+            CssToken::Number { .. } | CssToken::Percentage(_) | CssToken::Dimension { .. }
+        )
+    };
+
+    let mut args = Vec::new();
+    if tokens.contains(&&CssToken::Comma) {
+        // Arguments and commas alternate, without a trailing comma.
+        if tokens.len() % 2 == 0 {
+            return None;
+        }
+        for (i, token) in tokens.iter().enumerate() {
+            match (i % 2, token) {
+                (0, t) if is_numeric(t) => args.push((*t).clone()),
+                (1, CssToken::Comma) => {}
+                _ => return None,
+            }
+        }
+    } else {
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                // `/` comes between the third argument and the alpha.
+                CssToken::Delim('/') if i == 3 && tokens.len() == 5 => {}
+                t if is_numeric(t) => args.push((*t).clone()),
+                _ => return None,
+            }
+        }
+        if args.len() == 4 && tokens.len() != 5 {
+            return None;
+        }
+    }
+    Some(args)
+}
+
+fn number(token: &CssToken) -> Option<f64> {
+    match token {
+        CssToken::Number { value, .. } => Some(*value),
+        _ => None,
+    }
+}
+
+/// Returns the alpha channel in `[0, 255]`.
+fn alpha(args: &[CssToken]) -> Option<u8> {
+    match args.get(3) {
+        None => Some(255),
+        Some(CssToken::Number { value, .. }) => Some(to_u8(value * 255.0)),
+        Some(CssToken::Percentage(p)) => Some(to_u8(p * 255.0 / 100.0)),
+        Some(_) => None,
+    }
+}
+
+/// Rounds `x` to the nearest integer in `[0, 255]`.
+fn to_u8(x: f64) -> u8 {
+    (x.clamp(0.0, 255.0) + 0.5) as u8
+}
+
+/// https://www.w3.org/TR/css-color-4/#rgb-functions
+fn parse_rgb(args: &[CssToken]) -> Option<Color> {
+    if args.len() != 3 && args.len() != 4 {
+        return None;
+    }
+    let mut channels = [0u8; 3];
+    for (channel, arg) in channels.iter_mut().zip(args.iter()) {
+        *channel = match arg {
+            CssToken::Number { value, .. } => to_u8(*value),
+            CssToken::Percentage(p) => to_u8(p * 255.0 / 100.0),
+            _ => return None,
+        };
+    }
+    Some(Color::rgba(
+        channels[0],
+        channels[1],
+        channels[2],
+        alpha(args)?,
+    ))
+}
+
+/// https://www.w3.org/TR/css-color-4/#the-hsl-notation
+fn parse_hsl(args: &[CssToken]) -> Option<Color> {
+    if args.len() != 3 && args.len() != 4 {
+        return None;
+    }
+    let hue = match &args[0] {
+        CssToken::Number { value, .. } => *value,
+        CssToken::Dimension { value, unit, .. } => match unit.to_ascii_lowercase().as_str() {
+            "deg" => *value,
+            "grad" => value * 0.9,
+            "rad" => value * 180.0 / core::f64::consts::PI,
+            "turn" => value * 360.0,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let percentage = |token: &CssToken| match token {
+        CssToken::Percentage(p) => Some(p / 100.0),
+        token => number(token).map(|n| n / 100.0),
+    };
+    let saturation = percentage(&args[1])?.clamp(0.0, 1.0);
+    let lightness = percentage(&args[2])?.clamp(0.0, 1.0);
+
+    // https://www.w3.org/TR/css-color-4/#hsl-to-rgb
+    let mut hue = hue % 360.0;
+    if hue < 0.0 {
+        hue += 360.0;
+    }
+    let f = |n: f64| {
+        let k = (n + hue / 30.0) % 12.0;
+        let a = saturation * lightness.min(1.0 - lightness);
+        lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    Some(Color::rgba(
+        to_u8(f(0.0) * 255.0),
+        to_u8(f(8.0) * 255.0),
+        to_u8(f(4.0) * 255.0),
+        alpha(args)?,
+    ))
+}
+
+/// https://www.w3.org/TR/css-color-4/#named-colors
+const NAMED_COLORS: &[(&str, Color)] = &[
+    ("aliceblue", Color::from_hex(0xf0f8ff)),
+    ("antiquewhite", Color::from_hex(0xfaebd7)),
+    ("aqua", Color::from_hex(0x00ffff)),
+    ("aquamarine", Color::from_hex(0x7fffd4)),
+    ("azure", Color::from_hex(0xf0ffff)),
+    ("beige", Color::from_hex(0xf5f5dc)),
+    ("bisque", Color::from_hex(0xffe4c4)),
+    ("black", Color::from_hex(0x000000)),
+    ("blanchedalmond", Color::from_hex(0xffebcd)),
+    ("blue", Color::from_hex(0x0000ff)),
+    ("blueviolet", Color::from_hex(0x8a2be2)),
+    ("brown", Color::from_hex(0xa52a2a)),
+    ("burlywood", Color::from_hex(0xdeb887)),
+    ("cadetblue", Color::from_hex(0x5f9ea0)),
+    ("chartreuse", Color::from_hex(0x7fff00)),
+    ("chocolate", Color::from_hex(0xd2691e)),
+    ("coral", Color::from_hex(0xff7f50)),
+    ("cornflowerblue", Color::from_hex(0x6495ed)),
+    ("cornsilk", Color::from_hex(0xfff8dc)),
+    ("crimson", Color::from_hex(0xdc143c)),
+    ("cyan", Color::from_hex(0x00ffff)),
+    ("darkblue", Color::from_hex(0x00008b)),
+    ("darkcyan", Color::from_hex(0x008b8b)),
+    ("darkgoldenrod", Color::from_hex(0xb8860b)),
+    ("darkgray", Color::from_hex(0xa9a9a9)),
+    ("darkgreen", Color::from_hex(0x006400)),
+    ("darkgrey", Color::from_hex(0xa9a9a9)),
+    ("darkkhaki", Color::from_hex(0xbdb76b)),
+    ("darkmagenta", Color::from_hex(0x8b008b)),
+    ("darkolivegreen", Color::from_hex(0x556b2f)),
+    ("darkorange", Color::from_hex(0xff8c00)),
+    ("darkorchid", Color::from_hex(0x9932cc)),
+    ("darkred", Color::from_hex(0x8b0000)),
+    ("darksalmon", Color::from_hex(0xe9967a)),
+    ("darkseagreen", Color::from_hex(0x8fbc8f)),
+    ("darkslateblue", Color::from_hex(0x483d8b)),
+    ("darkslategray", Color::from_hex(0x2f4f4f)),
+    ("darkslategrey", Color::from_hex(0x2f4f4f)),
+    ("darkturquoise", Color::from_hex(0x00ced1)),
+    ("darkviolet", Color::from_hex(0x9400d3)),
+    ("deeppink", Color::from_hex(0xff1493)),
+    ("deepskyblue", Color::from_hex(0x00bfff)),
+    ("dimgray", Color::from_hex(0x696969)),
+    ("dimgrey", Color::from_hex(0x696969)),
+    ("dodgerblue", Color::from_hex(0x1e90ff)),
+    ("firebrick", Color::from_hex(0xb22222)),
+    ("floralwhite", Color::from_hex(0xfffaf0)),
+    ("forestgreen", Color::from_hex(0x228b22)),
+    ("fuchsia", Color::from_hex(0xff00ff)),
+    ("gainsboro", Color::from_hex(0xdcdcdc)),
+    ("ghostwhite", Color::from_hex(0xf8f8ff)),
+    ("gold", Color::from_hex(0xffd700)),
+    ("goldenrod", Color::from_hex(0xdaa520)),
+    ("gray", Color::from_hex(0x808080)),
+    ("green", Color::from_hex(0x008000)),
+    ("greenyellow", Color::from_hex(0xadff2f)),
+    ("grey", Color::from_hex(0x808080)),
+    ("honeydew", Color::from_hex(0xf0fff0)),
+    ("hotpink", Color::from_hex(0xff69b4)),
+    ("indianred", Color::from_hex(0xcd5c5c)),
+    ("indigo", Color::from_hex(0x4b0082)),
+    ("ivory", Color::from_hex(0xfffff0)),
+    ("khaki", Color::from_hex(0xf0e68c)),
+    ("lavender", Color::from_hex(0xe6e6fa)),
+    ("lavenderblush", Color::from_hex(0xfff0f5)),
+    ("lawngreen", Color::from_hex(0x7cfc00)),
+    ("lemonchiffon", Color::from_hex(0xfffacd)),
+    ("lightblue", Color::from_hex(0xadd8e6)),
+    ("lightcoral", Color::from_hex(0xf08080)),
+    ("lightcyan", Color::from_hex(0xe0ffff)),
+    ("lightgoldenrodyellow", Color::from_hex(0xfafad2)),
+    ("lightgray", Color::from_hex(0xd3d3d3)),
+    ("lightgreen", Color::from_hex(0x90ee90)),
+    ("lightgrey", Color::from_hex(0xd3d3d3)),
+    ("lightpink", Color::from_hex(0xffb6c1)),
+    ("lightsalmon", Color::from_hex(0xffa07a)),
+    ("lightseagreen", Color::from_hex(0x20b2aa)),
+    ("lightskyblue", Color::from_hex(0x87cefa)),
+    ("lightslategray", Color::from_hex(0x778899)),
+    ("lightslategrey", Color::from_hex(0x778899)),
+    ("lightsteelblue", Color::from_hex(0xb0c4de)),
+    ("lightyellow", Color::from_hex(0xffffe0)),
+    ("lime", Color::from_hex(0x00ff00)),
+    ("limegreen", Color::from_hex(0x32cd32)),
+    ("linen", Color::from_hex(0xfaf0e6)),
+    ("magenta", Color::from_hex(0xff00ff)),
+    ("maroon", Color::from_hex(0x800000)),
+    ("mediumaquamarine", Color::from_hex(0x66cdaa)),
+    ("mediumblue", Color::from_hex(0x0000cd)),
+    ("mediumorchid", Color::from_hex(0xba55d3)),
+    ("mediumpurple", Color::from_hex(0x9370db)),
+    ("mediumseagreen", Color::from_hex(0x3cb371)),
+    ("mediumslateblue", Color::from_hex(0x7b68ee)),
+    ("mediumspringgreen", Color::from_hex(0x00fa9a)),
+    ("mediumturquoise", Color::from_hex(0x48d1cc)),
+    ("mediumvioletred", Color::from_hex(0xc71585)),
+    ("midnightblue", Color::from_hex(0x191970)),
+    ("mintcream", Color::from_hex(0xf5fffa)),
+    ("mistyrose", Color::from_hex(0xffe4e1)),
+    ("moccasin", Color::from_hex(0xffe4b5)),
+    ("navajowhite", Color::from_hex(0xffdead)),
+    ("navy", Color::from_hex(0x000080)),
+    ("oldlace", Color::from_hex(0xfdf5e6)),
+    ("olive", Color::from_hex(0x808000)),
+    ("olivedrab", Color::from_hex(0x6b8e23)),
+    ("orange", Color::from_hex(0xffa500)),
+    ("orangered", Color::from_hex(0xff4500)),
+    ("orchid", Color::from_hex(0xda70d6)),
+    ("palegoldenrod", Color::from_hex(0xeee8aa)),
+    ("palegreen", Color::from_hex(0x98fb98)),
+    ("paleturquoise", Color::from_hex(0xafeeee)),
+    ("palevioletred", Color::from_hex(0xdb7093)),
+    ("papayawhip", Color::from_hex(0xffefd5)),
+    ("peachpuff", Color::from_hex(0xffdab9)),
+    ("peru", Color::from_hex(0xcd853f)),
+    ("pink", Color::from_hex(0xffc0cb)),
+    ("plum", Color::from_hex(0xdda0dd)),
+    ("powderblue", Color::from_hex(0xb0e0e6)),
+    ("purple", Color::from_hex(0x800080)),
+    ("rebeccapurple", Color::from_hex(0x663399)),
+    ("red", Color::from_hex(0xff0000)),
+    ("rosybrown", Color::from_hex(0xbc8f8f)),
+    ("royalblue", Color::from_hex(0x4169e1)),
+    ("saddlebrown", Color::from_hex(0x8b4513)),
+    ("salmon", Color::from_hex(0xfa8072)),
+    ("sandybrown", Color::from_hex(0xf4a460)),
+    ("seagreen", Color::from_hex(0x2e8b57)),
+    ("seashell", Color::from_hex(0xfff5ee)),
+    ("sienna", Color::from_hex(0xa0522d)),
+    ("silver", Color::from_hex(0xc0c0c0)),
+    ("skyblue", Color::from_hex(0x87ceeb)),
+    ("slateblue", Color::from_hex(0x6a5acd)),
+    ("slategray", Color::from_hex(0x708090)),
+    ("slategrey", Color::from_hex(0x708090)),
+    ("snow", Color::from_hex(0xfffafa)),
+    ("springgreen", Color::from_hex(0x00ff7f)),
+    ("steelblue", Color::from_hex(0x4682b4)),
+    ("tan", Color::from_hex(0xd2b48c)),
+    ("teal", Color::from_hex(0x008080)),
+    ("thistle", Color::from_hex(0xd8bfd8)),
+    ("tomato", Color::from_hex(0xff6347)),
+    ("turquoise", Color::from_hex(0x40e0d0)),
+    ("violet", Color::from_hex(0xee82ee)),
+    ("wheat", Color::from_hex(0xf5deb3)),
+    ("white", Color::from_hex(0xffffff)),
+    ("whitesmoke", Color::from_hex(0xf5f5f5)),
+    ("yellow", Color::from_hex(0xffff00)),
+    ("yellowgreen", Color::from_hex(0x9acd32)),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::css::parser::CssParser;
+    use crate::renderer::css::token::CssTokenizer;
+    use alloc::string::String;
+
+    fn parse(css: &str) -> Option<SpecifiedColor> {
+        SpecifiedColor::parse(
+            &CssParser::new(CssTokenizer::new(String::from(css))).parse_component_values(),
+        )
+    }
+
+    fn color(css: &str) -> Option<Color> {
+        parse(css).map(|c| c.resolve(Color::WHITE))
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(Some(Color::rgb(255, 0, 0)), color("red"));
+        assert_eq!(Some(Color::rgb(0x66, 0x33, 0x99)), color("RebeccaPurple"));
+        assert_eq!(Some(Color::TRANSPARENT), color("transparent"));
+        assert_eq!(Some(SpecifiedColor::CurrentColor), parse("currentColor"));
+        assert_eq!(Some(Color::WHITE), color("currentcolor"));
+
+        assert_eq!(Some(Color::rgb(0xaa, 0xbb, 0xcc)), color("#abc"));
+        assert_eq!(Some(Color::rgba(0xaa, 0xbb, 0xcc, 0xdd)), color("#abcd"));
+        assert_eq!(Some(Color::rgb(0x12, 0x34, 0x56)), color("#123456"));
+        assert_eq!(
+            Some(Color::rgba(0x12, 0x34, 0x56, 0x78)),
+            color("#12345678")
+        );
+        assert_eq!(None, color("#12345"));
+        assert_eq!(None, color("#ggg"));
+
+        assert_eq!(Some(Color::rgb(1, 2, 3)), color("rgb(1, 2, 3)"));
+        assert_eq!(
+            Some(Color::rgba(255, 0, 0, 128)),
+            color("rgba(100%, 0%, -5, 0.5)")
+        );
+        assert_eq!(Some(Color::rgba(1, 2, 3, 51)), color("rgb(1 2 3 / 20%)"));
+        assert_eq!(None, color("rgb(1, 2 3)"));
+        assert_eq!(None, color("rgb(1, 2, 3,)"));
+        assert_eq!(None, color("rgb(1 2 3 /)"));
+
+        assert_eq!(Some(Color::rgb(255, 0, 0)), color("hsl(0, 100%, 50%)"));
+        assert_eq!(Some(Color::rgb(0, 0, 255)), color("hsl(240deg 100% 50%)"));
+        assert_eq!(
+            Some(Color::rgba(0, 255, 0, 128)),
+            color("hsla(0.3333turn, 100%, 50%, 0.5)")
+        );
+        assert_eq!(None, color("hsl(0, 100%, 50px)"));
+        assert_eq!(None, color("red blue"));
+    }
+}
@@ -0,0 +1,459 @@
+//! https://www.w3.org/TR/css-cascade-4/#computed
+
+use crate::renderer::css::color::Color;
+use crate::renderer::css::color::SpecifiedColor;
+use crate::renderer::css::content::parse_content;
+use crate::renderer::css::content::parse_counters;
+use crate::renderer::css::content::parse_quotes;
+use crate::renderer::css::length::Calc;
+use crate::renderer::css::length::LengthContext;
+use crate::renderer::css::length::LengthPercentage;
+use crate::renderer::css::properties::Property;
+use crate::renderer::css::properties::ValueKind;
+use crate::renderer::css::stylesheet::ComponentValue;
+use crate::renderer::css::token::CssToken;
+use crate::renderer::css::variables::CustomProperties;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// https://www.w3.org/TR/css-fonts-4/#valdef-font-size-medium
+pub const MEDIUM_FONT_SIZE: f64 = 16.0;
+
+/// https://www.w3.org/TR/css-cascade-4/#computed-value
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComputedValue {
+    /// A keyword, lowercased.
+    Keyword(String),
+    Color(Color),
+    /// A length in px.
+    Length(f64),
+    LengthPercentage(LengthPercentage),
+    Number(f64),
+    /// The value of a property whose value isn't interpreted yet.
+    ComponentValues(Vec<ComponentValue>),
+}
+
+/// The computed values of all the longhands in
+/// `renderer::css::properties::LONGHANDS` and of the custom properties for
+/// an element.
+/// https://www.w3.org/TR/css-cascade-4/#computed-value
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputedStyle {
+    values: BTreeMap<&'static str, ComputedValue>,
+    /// Shared with the parent's style when the element declares no custom
+    /// properties.
+    custom_properties: Rc<CustomProperties>,
+}
+
+impl ComputedStyle {
+    pub(crate) fn new(
+        values: BTreeMap<&'static str, ComputedValue>,
+        custom_properties: Rc<CustomProperties>,
+    ) -> Self {
+        Self {
+            values,
+            custom_properties,
+        }
+    }
+
+    /// Returns the computed value of the longhand `property`.
+    pub fn get(&self, property: &str) -> Option<&ComputedValue> {
+        self.values.get(property)
+    }
+
+    pub(crate) fn set(&mut self, property: &'static str, value: ComputedValue) {
+        self.values.insert(property, value);
+    }
+
+    /// Returns the computed value of the custom property `name`, e.g.
+    /// `--main-color`, or `None` if it is the guaranteed-invalid value.
+    /// https://www.w3.org/TR/css-variables-1/#custom-property
+    pub fn custom_property(&self, name: &str) -> Option<&[ComponentValue]> {
+        self.custom_properties.get(name).map(|v| v.as_slice())
+    }
+
+    pub(crate) fn custom_properties(&self) -> &Rc<CustomProperties> {
+        &self.custom_properties
+    }
+
+    /// Returns the value of `property` if it is a single keyword.
+    pub fn keyword(&self, property: &str) -> Option<&str> {
+        match self.get(property)? {
+            ComputedValue::Keyword(keyword) => Some(keyword),
+            ComputedValue::ComponentValues(values) => match values.as_slice() {
+                [ComponentValue::Token(CssToken::Ident(ident))] => Some(ident),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn color(&self, property: &str) -> Option<Color> {
+        match self.get(property)? {
+            ComputedValue::Color(color) => Some(*color),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of `property` in px if it is a length.
+    pub fn length(&self, property: &str) -> Option<f64> {
+        match self.get(property)? {
+            ComputedValue::Length(px)
+            | ComputedValue::LengthPercentage(LengthPercentage::Length(px)) => Some(*px),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of `property` if it is a `<length-percentage>`.
+    pub fn length_percentage(&self, property: &str) -> Option<&LengthPercentage> {
+        match self.get(property)? {
+            ComputedValue::LengthPercentage(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self, property: &str) -> Option<f64> {
+        match self.get(property)? {
+            ComputedValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Returns the font size in px.
+    pub fn font_size(&self) -> f64 {
+        self.length("font-size").unwrap_or(MEDIUM_FONT_SIZE)
+    }
+
+    /// https://www.w3.org/TR/css-fonts-4/#font-weight-numeric-values
+    pub fn font_weight(&self) -> f64 {
+        self.number("font-weight").unwrap_or(400.0)
+    }
+
+    /// https://www.w3.org/TR/css-display-3/#the-display-properties
+    pub fn display(&self) -> Display {
+        match self.keyword("display") {
+            Some(k) if k.eq_ignore_ascii_case("block") => Display::Block,
+            Some(k) if k.eq_ignore_ascii_case("inline-block") => Display::InlineBlock,
+            Some(k) if k.eq_ignore_ascii_case("list-item") => Display::ListItem,
+            Some(k) if k.eq_ignore_ascii_case("none") => Display::None,
+            _ => Display::Inline,
+        }
+    }
+
+    /// https://www.w3.org/TR/CSS2/visudet.html#propdef-vertical-align
+    pub fn vertical_align(&self) -> VerticalAlign {
+        match self.keyword("vertical-align") {
+            Some("middle") => VerticalAlign::Middle,
+            Some("top") => VerticalAlign::Top,
+            Some("bottom") => VerticalAlign::Bottom,
+            _ => VerticalAlign::Baseline,
+        }
+    }
+
+    /// https://www.w3.org/TR/css-text-3/#white-space-property
+    pub fn white_space(&self) -> WhiteSpace {
+        match self.keyword("white-space") {
+            Some("pre") => WhiteSpace::Pre,
+            Some("nowrap") => WhiteSpace::Nowrap,
+            Some("pre-wrap") => WhiteSpace::PreWrap,
+            Some("pre-line") => WhiteSpace::PreLine,
+            _ => WhiteSpace::Normal,
+        }
+    }
+}
+
+/// https://www.w3.org/TR/css-display-3/#the-display-properties
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Display {
+    Block,
+    Inline,
+    InlineBlock,
+    ListItem,
+    None,
+}
+
+/// https://www.w3.org/TR/CSS2/visudet.html#propdef-vertical-align
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VerticalAlign {
+    Baseline,
+    Middle,
+    Top,
+    Bottom,
+}
+
+/// https://www.w3.org/TR/css-text-3/#white-space-property
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WhiteSpace {
+    Normal,
+    Pre,
+    Nowrap,
+    PreWrap,
+    PreLine,
+}
+
+impl WhiteSpace {
+    /// Whether spaces and tabs are collapsible.
+    /// https://www.w3.org/TR/css-text-3/#white-space-phase-1
+    pub fn collapses_spaces(&self) -> bool {
+        matches!(
+            self,
+            WhiteSpace::Normal | WhiteSpace::Nowrap | WhiteSpace::PreLine
+        )
+    }
+
+    /// Whether segment breaks are preserved as forced line breaks.
+    /// https://www.w3.org/TR/css-text-3/#line-break-transform
+    pub fn preserves_segment_breaks(&self) -> bool {
+        !matches!(self, WhiteSpace::Normal | WhiteSpace::Nowrap)
+    }
+
+    /// Whether lines may wrap at soft wrap opportunities.
+    pub fn wraps(&self) -> bool {
+        !matches!(self, WhiteSpace::Pre | WhiteSpace::Nowrap)
+    }
+}
+
+/// What computing a value depends on.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub(crate) struct ComputeContext {
+    /// `font_size` is the parent's font size while `font-size` is computed,
+    /// and the element's own after that.
+    pub(crate) length: LengthContext,
+    pub(crate) parent_font_size: f64,
+    pub(crate) parent_font_weight: f64,
+    /// The value of `currentcolor`: the parent's color while `color` is
+    /// computed, and the element's own after that.
+    pub(crate) current_color: Color,
+}
+
+/// Computes the specified value `value` of `property`. Returns `None` if the
+/// value is invalid for the property.
+/// https://www.w3.org/TR/css-cascade-4/#computed
+pub(crate) fn compute_value(
+    property: &Property,
+    value: &[ComponentValue],
+    context: &ComputeContext,
+) -> Option<ComputedValue> {
+    let keyword = match value {
+        [ComponentValue::Token(CssToken::Ident(ident))] => Some(ident.to_ascii_lowercase()),
+        _ => None,
+    };
+    let keyword = keyword.as_deref();
+
+    let computed = match property.kind {
+        ValueKind::Keyword(keywords) => ComputedValue::Keyword(
+            keywords
+                .iter()
+                .find(|k| Some(**k) == keyword)
+                .map(|k| String::from(*k))?,
+        ),
+        ValueKind::Color => {
+            ComputedValue::Color(SpecifiedColor::parse(value)?.resolve(context.current_color))
+        }
+        ValueKind::LengthPercentage(keywords) => match keyword {
+            Some(k) if keywords.contains(&k) => ComputedValue::Keyword(String::from(k)),
+            _ => ComputedValue::LengthPercentage(
+                Calc::parse_length_percentage(value)?.compute(&context.length),
+            ),
+        },
+        ValueKind::NonNegativeLengthPercentage(keywords) => match keyword {
+            Some(k) if keywords.contains(&k) => ComputedValue::Keyword(String::from(k)),
+            _ => match Calc::parse_length_percentage(value)?.compute(&context.length) {
+                LengthPercentage::Length(v) | LengthPercentage::Percentage(v) if v < 0.0 => {
+                    return None
+                }
+                v => ComputedValue::LengthPercentage(v),
+            },
+        },
+        // https://www.w3.org/TR/css-backgrounds-3/#border-width
+        ValueKind::LineWidth => ComputedValue::Length(match keyword {
+            Some("thin") => 1.0,
+            Some("medium") => 3.0,
+            Some("thick") => 5.0,
+            _ => non_negative(Calc::parse_length_percentage(value)?.compute(&context.length))?,
+        }),
+        ValueKind::FontSize => ComputedValue::Length(compute_font_size(value, keyword, context)?),
+        // https://www.w3.org/TR/css-fonts-4/#relative-weights
+        ValueKind::FontWeight => ComputedValue::Number(match keyword {
+            Some("normal") => 400.0,
+            Some("bold") => 700.0,
+            Some("bolder") => match context.parent_font_weight {
+                w if w < 350.0 => 400.0,
+                w if w < 550.0 => 700.0,
+                w if w < 750.0 => 900.0,
+                w => w,
+            },
+            Some("lighter") => match context.parent_font_weight {
+                w if w < 100.0 => w,
+                w if w < 550.0 => 100.0,
+                w if w < 750.0 => 400.0,
+                _ => 700.0,
+            },
+            _ => match Calc::parse_number(value)?.evaluate(&context.length, 0.0) {
+                w if (1.0..=1000.0).contains(&w) => w,
+                _ => return None,
+            },
+        }),
+        // https://www.w3.org/TR/CSS2/visudet.html#propdef-line-height
+        // A number is inherited as is, while a percentage is computed to a
+        // length.
+        ValueKind::LineHeight => match keyword {
+            Some("normal") => ComputedValue::Keyword(String::from("normal")),
+            _ => match Calc::parse_number(value) {
+                Some(n) => match n.evaluate(&context.length, 0.0) {
+                    n if n >= 0.0 => ComputedValue::Number(n),
+                    _ => return None,
+                },
+                None => match Calc::parse_length_percentage(value)?
+                    .compute(&context.length)
+                    .resolve(context.length.font_size)
+                {
+                    l if l >= 0.0 => ComputedValue::Length(l),
+                    _ => return None,
+                },
+            },
+        },
+        ValueKind::FontFamily if is_font_family(value) => {
+            ComputedValue::ComponentValues(value.to_vec())
+        }
+        ValueKind::Image => match (keyword, value) {
+            (Some("none"), _) => ComputedValue::Keyword(String::from("none")),
+            (_, [ComponentValue::Token(CssToken::Url(_))]) => {
+                ComputedValue::ComponentValues(value.to_vec())
+            }
+            (_, [ComponentValue::Function { name, .. }]) if name.eq_ignore_ascii_case("url") => {
+                ComputedValue::ComponentValues(value.to_vec())
+            }
+            _ => return None,
+        },
+        ValueKind::Position if is_position(value) => ComputedValue::ComponentValues(value.to_vec()),
+        ValueKind::NonNegativeNumber => {
+            match Calc::parse_number(value)?.evaluate(&context.length, 0.0) {
+                n if n >= 0.0 => ComputedValue::Number(n),
+                _ => return None,
+            }
+        }
+        ValueKind::Content => match keyword {
+            Some(k @ ("normal" | "none")) => ComputedValue::Keyword(String::from(k)),
+            _ => {
+                parse_content(value)?;
+                ComputedValue::ComponentValues(value.to_vec())
+            }
+        },
+        ValueKind::Quotes => match keyword {
+            Some(k @ ("auto" | "none")) => ComputedValue::Keyword(String::from(k)),
+            _ => {
+                parse_quotes(value)?;
+                ComputedValue::ComponentValues(value.to_vec())
+            }
+        },
+        ValueKind::Counters(default) => match keyword {
+            Some("none") => ComputedValue::Keyword(String::from("none")),
+            _ => {
+                parse_counters(value, default)?;
+                ComputedValue::ComponentValues(value.to_vec())
+            }
+        },
+        ValueKind::Any => ComputedValue::ComponentValues(value.to_vec()),
+        ValueKind::FontFamily | ValueKind::Position => return None,
+    };
+    Some(computed)
+}
+
+/// Returns true if `value` is valid for `property`. CSS-wide keywords aren't
+/// valid.
+pub(crate) fn is_valid_value(property: &Property, value: &[ComponentValue]) -> bool {
+    compute_value(property, value, &ComputeContext::default()).is_some()
+}
+
+/// Splits `value` at commas and drops whitespace.
+pub(crate) fn comma_separated(value: &[ComponentValue]) -> Vec<Vec<&ComponentValue>> {
+    value
+        .split(|v| *v == ComponentValue::Token(CssToken::Comma))
+        .map(|part| {
+            part.iter()
+                .filter(|v| **v != ComponentValue::Token(CssToken::Whitespace))
+                .collect()
+        })
+        .collect()
+}
+
+/// https://www.w3.org/TR/css-fonts-4/#font-family-prop
+/// A comma-separated list of strings or sequences of identifiers.
+fn is_font_family(value: &[ComponentValue]) -> bool {
+    comma_separated(value)
+        .iter()
+        .all(|family| match family.as_slice() {
+            [ComponentValue::Token(CssToken::String(_))] => true,
+            [] => false,
+            idents => idents
+                .iter()
+                .all(|v| matches!(v, ComponentValue::Token(CssToken::Ident(_)))),
+        })
+}
+
+/// https://www.w3.org/TR/css-backgrounds-3/#typedef-bg-position
+fn is_position(value: &[ComponentValue]) -> bool {
+    let parts = match comma_separated(value).as_slice() {
+        [parts] => parts.clone(),
+        _ => return false,
+    };
+    let horizontal = |v: &ComponentValue| match v {
+        ComponentValue::Token(CssToken::Ident(k)) => ["left", "center", "right"]
+            .iter()
+            .any(|h| k.eq_ignore_ascii_case(h)),
+        v => Calc::parse_length_percentage(core::slice::from_ref(v)).is_some(),
+    };
+    let vertical = |v: &ComponentValue| match v {
+        ComponentValue::Token(CssToken::Ident(k)) => ["top", "center", "bottom"]
+            .iter()
+            .any(|h| k.eq_ignore_ascii_case(h)),
+        v => horizontal(v),
+    };
+    match parts.as_slice() {
+        [v] => horizontal(v) || vertical(v),
+        [h, v] => (horizontal(h) && vertical(v)) || (vertical(h) && horizontal(v)),
+        _ => false,
+    }
+}
+
+/// https://www.w3.org/TR/css-fonts-4/#font-size-prop
+fn compute_font_size(
+    value: &[ComponentValue],
+    keyword: Option<&str>,
+    context: &ComputeContext,
+) -> Option<f64> {
+    // https://www.w3.org/TR/css-fonts-4/#absolute-size-mapping
+    let size = match keyword {
+        Some("xx-small") => 9.0,
+        Some("x-small") => 10.0,
+        Some("small") => 13.0,
+        Some("medium") => MEDIUM_FONT_SIZE,
+        Some("large") => 18.0,
+        Some("x-large") => 24.0,
+        Some("xx-large") => 32.0,
+        Some("xxx-large") => 48.0,
+        // https://www.w3.org/TR/css-fonts-4/#valdef-font-size-relative-size
+        Some("larger") => context.parent_font_size * 1.2,
+        Some("smaller") => context.parent_font_size / 1.2,
+        _ => match Calc::parse_length_percentage(value)?
+            .compute(&context.length)
+            .resolve(context.parent_font_size)
+        {
+            size if size >= 0.0 => size,
+            _ => return None,
+        },
+    };
+    Some(size)
+}
+
+/// Returns the value in px, or `None` if it is negative or depends on a
+/// percentage.
+fn non_negative(value: LengthPercentage) -> Option<f64> {
+    match value {
+        LengthPercentage::Length(px) if px >= 0.0 => Some(px),
+        _ => None,
+    }
+}
@@ -0,0 +1,682 @@
+//! https://www.w3.org/TR/css-content-3/
+//! https://www.w3.org/TR/css-lists-3/#auto-numbering
+
+use crate::renderer::css::computed_style::comma_separated;
+use crate::renderer::css::computed_style::ComputedStyle;
+use crate::renderer::css::computed_style::ComputedValue;
+use crate::renderer::css::computed_style::Display;
+use crate::renderer::css::stylesheet::ComponentValue;
+use crate::renderer::css::token::CssToken;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::selector::PseudoElement;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// An item of the value of the `content` property.
+/// https://www.w3.org/TR/css-content-3/#content-property
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentItem {
+    String(String),
+    /// An image.
+    Url(String),
+    /// The value of an attribute of the element.
+    /// https://www.w3.org/TR/CSS2/generate.html#propdef-content
+    Attr(String),
+    /// https://www.w3.org/TR/css-lists-3/#funcdef-counter
+    Counter {
+        name: String,
+        style: CounterStyle,
+    },
+    /// https://www.w3.org/TR/css-lists-3/#funcdef-counters
+    Counters {
+        name: String,
+        separator: String,
+        style: CounterStyle,
+    },
+    /// https://www.w3.org/TR/css-content-3/#quote-values
+    OpenQuote,
+    CloseQuote,
+    NoOpenQuote,
+    NoCloseQuote,
+}
+
+/// https://www.w3.org/TR/css-counter-styles-3/#predefined-counters
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CounterStyle {
+    Decimal,
+    DecimalLeadingZero,
+    LowerRoman,
+    UpperRoman,
+    LowerAlpha,
+    UpperAlpha,
+    LowerGreek,
+    Disc,
+    Circle,
+    Square,
+    None,
+}
+
+impl CounterStyle {
+    /// Returns the counter style named `name`, e.g. a value of
+    /// `list-style-type`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let style = match name.to_ascii_lowercase().as_str() {
+            "decimal" => CounterStyle::Decimal,
+            "decimal-leading-zero" => CounterStyle::DecimalLeadingZero,
+            "lower-roman" => CounterStyle::LowerRoman,
+            "upper-roman" => CounterStyle::UpperRoman,
+            "lower-alpha" | "lower-latin" => CounterStyle::LowerAlpha,
+            "upper-alpha" | "upper-latin" => CounterStyle::UpperAlpha,
+            "lower-greek" => CounterStyle::LowerGreek,
+            "disc" => CounterStyle::Disc,
+            "circle" => CounterStyle::Circle,
+            "square" => CounterStyle::Square,
+            "none" => CounterStyle::None,
+            _ => return None,
+        };
+        Some(style)
+    }
+
+    /// Returns the representation of the counter value `value`. Values out
+    /// of the range of the style are represented in decimal.
+    /// https://www.w3.org/TR/css-counter-styles-3/#generate-a-counter
+    pub fn format(&self, value: i32) -> String {
+        let formatted = match self {
+            CounterStyle::Decimal => None,
+            CounterStyle::DecimalLeadingZero => match value {
+                0..=9 => Some(alloc::format!("0{}", value)),
+                -9..=-1 => Some(alloc::format!("-0{}", -value)),
+                _ => None,
+            },
+            CounterStyle::LowerRoman => roman(value).map(|r| r.to_ascii_lowercase()),
+            CounterStyle::UpperRoman => roman(value),
+            CounterStyle::LowerAlpha => alphabetic(value, &LATIN),
+            CounterStyle::UpperAlpha => alphabetic(value, &LATIN).map(|a| a.to_ascii_uppercase()),
+            CounterStyle::LowerGreek => alphabetic(value, &GREEK),
+            CounterStyle::Disc => Some("\u{2022}".to_string()),
+            CounterStyle::Circle => Some("\u{25e6}".to_string()),
+            CounterStyle::Square => Some("\u{25aa}".to_string()),
+            CounterStyle::None => Some(String::new()),
+        };
+        formatted.unwrap_or_else(|| value.to_string())
+    }
+}
+
+const LATIN: [char; 26] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
+    't', 'u', 'v', 'w', 'x', 'y', 'z',
+];
+const GREEK: [char; 24] = [
+    'α', 'β', 'γ', 'δ', 'ε', 'ζ', 'η', 'θ', 'ι', 'κ', 'λ', 'μ', 'ν', 'ξ', 'ο', 'π', 'ρ', 'σ', 'τ',
+    'υ', 'φ', 'χ', 'ψ', 'ω',
+];
+
+/// https://www.w3.org/TR/css-counter-styles-3/#alphabetic-system
+fn alphabetic(value: i32, symbols: &[char]) -> Option<String> {
+    if value < 1 {
+        return None;
+    }
+    let n = symbols.len() as i32;
+    let mut value = value;
+    let mut result = Vec::new();
+    while value > 0 {
+        value -= 1;
+        result.push(symbols[(value % n) as usize]);
+        value /= n;
+    }
+    Some(result.iter().rev().collect())
+}
+
+/// https://www.w3.org/TR/css-counter-styles-3/#upper-roman
+fn roman(value: i32) -> Option<String> {
+    if !(1..=3999).contains(&value) {
+        return None;
+    }
+    let symbols = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut value = value;
+    let mut result = String::new();
+    for (weight, symbol) in symbols {
+        while value >= weight {
+            result.push_str(symbol);
+            value -= weight;
+        }
+    }
+    Some(result)
+}
+
+/// Parses a value of `content` other than `normal` and `none`. Returns
+/// `None` if it is invalid.
+pub fn parse_content(value: &[ComponentValue]) -> Option<Vec<ContentItem>> {
+    let mut items = Vec::new();
+    for v in value {
+        let item = match v {
+            ComponentValue::Token(CssToken::Whitespace) => continue,
+            ComponentValue::Token(CssToken::String(s)) => ContentItem::String(s.clone()),
+            ComponentValue::Token(CssToken::Url(url)) => ContentItem::Url(url.clone()),
+            ComponentValue::Token(CssToken::Ident(ident)) => {
+                match ident.to_ascii_lowercase().as_str() {
+                    "open-quote" => ContentItem::OpenQuote,
+                    "close-quote" => ContentItem::CloseQuote,
+                    "no-open-quote" => ContentItem::NoOpenQuote,
+                    "no-close-quote" => ContentItem::NoCloseQuote,
+                    _ => return None,
+                }
+            }
+            ComponentValue::Function { name, value } => parse_function(name, value)?,
+            _ => return None,
+        };
+        items.push(item);
+    }
+    if items.is_empty() {
+        return None;
+    }
+    Some(items)
+}
+
+fn parse_function(name: &str, value: &[ComponentValue]) -> Option<ContentItem> {
+    let arguments = comma_separated(value);
+    let arguments: Vec<&[&ComponentValue]> = arguments.iter().map(|a| a.as_slice()).collect();
+    let ident = |v: &ComponentValue| match v {
+        ComponentValue::Token(CssToken::Ident(ident)) => Some(ident.clone()),
+        _ => None,
+    };
+    let counter_name = |v: &ComponentValue| ident(v).filter(|n| !n.eq_ignore_ascii_case("none"));
+    // https://www.w3.org/TR/css-counter-styles-3/#counter-style-name
+    // An unknown counter style is treated as `decimal`.
+    let style = |argument: Option<&&[&ComponentValue]>| match argument.copied() {
+        None => Some(CounterStyle::Decimal),
+        Some([v]) => ident(v).map(|s| CounterStyle::from_name(&s).unwrap_or(CounterStyle::Decimal)),
+        Some(_) => None,
+    };
+
+    let item = match (name.to_ascii_lowercase().as_str(), arguments.as_slice()) {
+        ("url", [[ComponentValue::Token(CssToken::String(url))]]) => ContentItem::Url(url.clone()),
+        ("attr", [[name]]) => ContentItem::Attr(ident(name)?.to_ascii_lowercase()),
+        ("counter", [[name], rest @ ..]) if rest.len() <= 1 => ContentItem::Counter {
+            name: counter_name(name)?,
+            style: style(rest.first())?,
+        },
+        ("counters", [[name], [ComponentValue::Token(CssToken::String(separator))], rest @ ..])
+            if rest.len() <= 1 =>
+        {
+            ContentItem::Counters {
+                name: counter_name(name)?,
+                separator: separator.clone(),
+                style: style(rest.first())?,
+            }
+        }
+        _ => return None,
+    };
+    Some(item)
+}
+
+/// Parses a value of `quotes` into pairs of open and close quotes. Returns
+/// `None` if it is invalid.
+/// https://www.w3.org/TR/css-content-3/#quotes-property
+pub fn parse_quotes(value: &[ComponentValue]) -> Option<Vec<(String, String)>> {
+    let strings: Vec<&ComponentValue> = value
+        .iter()
+        .filter(|v| **v != ComponentValue::Token(CssToken::Whitespace))
+        .collect();
+    match strings.as_slice() {
+        [ComponentValue::Token(CssToken::Ident(k))] if k.eq_ignore_ascii_case("none") => {
+            return Some(Vec::new())
+        }
+        // https://www.w3.org/TR/css-content-3/#valdef-quotes-auto
+        [ComponentValue::Token(CssToken::Ident(k))] if k.eq_ignore_ascii_case("auto") => {
+            return Some(vec![
+                ("\u{201c}".to_string(), "\u{201d}".to_string()),
+                ("\u{2018}".to_string(), "\u{2019}".to_string()),
+            ])
+        }
+        [] => return None,
+        _ => {}
+    }
+    if strings.len() % 2 != 0 {
+        return None;
+    }
+    strings
+        .chunks(2)
+        .map(|pair| match pair {
+            [ComponentValue::Token(CssToken::String(open)), ComponentValue::Token(CssToken::String(close))] => {
+                Some((open.clone(), close.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Parses a value of `counter-reset`, `counter-set` or `counter-increment`
+/// into counter names and values, which are `default` when omitted.
+/// Returns `None` if it is invalid.
+/// https://www.w3.org/TR/css-lists-3/#counter-properties
+pub fn parse_counters(value: &[ComponentValue], default: i32) -> Option<Vec<(String, i32)>> {
+    let mut counters: Vec<(String, i32)> = Vec::new();
+    // Whether the last counter already has a value.
+    let mut has_value = true;
+    for v in value {
+        match v {
+            ComponentValue::Token(CssToken::Whitespace) => {}
+            ComponentValue::Token(CssToken::Ident(name)) => {
+                if ["none", "inherit", "initial", "unset", "revert"]
+                    .iter()
+                    .any(|k| name.eq_ignore_ascii_case(k))
+                {
+                    return if value.len() == 1 && name.eq_ignore_ascii_case("none") {
+                        Some(counters)
+                    } else {
+                        None
+                    };
+                }
+                counters.push((name.clone(), default));
+                has_value = false;
+            }
+            ComponentValue::Token(CssToken::Number {
+                value,
+                is_integer: true,
+            }) => match counters.last_mut() {
+                Some(counter) if !has_value => {
+                    counter.1 = value.clamp(i32::MIN as f64, i32::MAX as f64) as i32;
+                    has_value = true;
+                }
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+    if counters.is_empty() {
+        return None;
+    }
+    Some(counters)
+}
+
+/// The counters in scope at an element, in tree order.
+/// https://www.w3.org/TR/css-lists-3/#nested-counters
+#[derive(Debug, Clone, Default)]
+pub struct Counters {
+    /// The name and value of each counter instance, from the outermost to
+    /// the innermost, with the depth of the element that created it. An
+    /// instance is in scope for that element, its following siblings and
+    /// their descendants.
+    instances: Vec<(String, i32, usize)>,
+}
+
+impl Counters {
+    /// Applies the counter properties of `style`, of an element at `depth`
+    /// in the tree. Elements must be visited in tree order.
+    /// https://www.w3.org/TR/css-lists-3/#counters-without-boxes
+    pub fn apply(&mut self, style: &ComputedStyle, depth: usize) {
+        // The instances created by the descendants of the previous siblings
+        // are out of scope.
+        self.instances.retain(|(_, _, d)| *d <= depth);
+
+        for (name, value) in counter_property(style, "counter-reset", 0) {
+            // https://www.w3.org/TR/css-lists-3/#instantiate-counter
+            // A counter created by a previous sibling is replaced.
+            if let Some(i) = self.instances.iter().rposition(|(n, _, _)| *n == name) {
+                if self.instances[i].2 == depth {
+                    self.instances.remove(i);
+                }
+            }
+            self.instances.push((name, value, depth));
+        }
+        let mut increments = counter_property(style, "counter-increment", 1);
+        // https://www.w3.org/TR/css-lists-3/#list-item-counter
+        // List items increment `list-item` unless it is incremented
+        // explicitly.
+        if style.display() == Display::ListItem && !increments.iter().any(|(n, _)| n == "list-item")
+        {
+            increments.push((String::from("list-item"), 1));
+        }
+        for (name, value) in increments {
+            match self.innermost_mut(&name) {
+                Some(v) => *v = v.saturating_add(value),
+                None => self.instances.push((name, value, depth)),
+            }
+        }
+        for (name, value) in counter_property(style, "counter-set", 0) {
+            match self.innermost_mut(&name) {
+                Some(v) => *v = value,
+                None => self.instances.push((name, value, depth)),
+            }
+        }
+    }
+
+    fn innermost_mut(&mut self, name: &str) -> Option<&mut i32> {
+        self.instances
+            .iter_mut()
+            .rev()
+            .find(|(n, _, _)| n == name)
+            .map(|(_, v, _)| v)
+    }
+
+    /// Returns the value of the innermost counter `name`, or 0 if there is
+    /// none.
+    pub fn value(&self, name: &str) -> i32 {
+        self.values(name).last().copied().unwrap_or(0)
+    }
+
+    /// Returns the values of the counters `name` in scope, from the
+    /// outermost to the innermost.
+    pub fn values(&self, name: &str) -> Vec<i32> {
+        self.instances
+            .iter()
+            .filter(|(n, _, _)| n == name)
+            .map(|(_, v, _)| *v)
+            .collect()
+    }
+}
+
+fn counter_property(style: &ComputedStyle, property: &str, default: i32) -> Vec<(String, i32)> {
+    match style.get(property) {
+        Some(ComputedValue::ComponentValues(value)) => {
+            parse_counters(value, default).unwrap_or_default()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// A part of the content of a `::before` or `::after` pseudo-element.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeneratedContent {
+    Text(String),
+    /// The URL of an image.
+    Image(String),
+}
+
+/// Generates the content of the `::before` and `::after` pseudo-elements,
+/// whose styles are computed by `Cascade`, with the counters and the quote
+/// depth at each of them.
+#[derive(Debug, Clone, Default)]
+pub struct ContentGenerator {
+    counters: Counters,
+    /// https://www.w3.org/TR/css-content-3/#quote-nesting-depth
+    quote_depth: usize,
+}
+
+impl ContentGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    /// Applies the counter properties of `element`. It must be called for
+    /// each element in tree order, before its `::before` pseudo-element and
+    /// its children.
+    pub fn enter(&mut self, element: &Rc<RefCell<Node>>) {
+        if let Some(style) = element.borrow().computed_style() {
+            self.counters.apply(&style, depth(element));
+        }
+    }
+
+    /// Returns the content of the pseudo-element `pseudo_element` of
+    /// `element`, or `None` if it generates no box. `::before` must be
+    /// generated right after `enter(element)`, and `::after` after the
+    /// children of `element`.
+    pub fn generate(
+        &mut self,
+        element: &Rc<RefCell<Node>>,
+        pseudo_element: PseudoElement,
+    ) -> Option<Vec<GeneratedContent>> {
+        let style = element.borrow().pseudo_element_style(pseudo_element)?;
+        // A pseudo-element is the first or the last child of its element.
+        self.counters.apply(&style, depth(element) + 1);
+
+        let items = match style.get("content") {
+            Some(ComputedValue::ComponentValues(value)) => parse_content(value)?,
+            _ => return None,
+        };
+        let quotes = match style.get("quotes") {
+            Some(ComputedValue::ComponentValues(value)) => parse_quotes(value),
+            Some(ComputedValue::Keyword(keyword)) => {
+                parse_quotes(&[ComponentValue::Token(CssToken::Ident(keyword.clone()))])
+            }
+            _ => None,
+        }
+        .unwrap_or_default();
+        // https://www.w3.org/TR/css-content-3/#quotes-property
+        // The last pair is used for deeper nesting levels.
+        let quote = |depth: usize, close: bool| {
+            let pair = quotes.get(depth.min(quotes.len().saturating_sub(1)));
+            match pair {
+                Some((open, _)) if !close => open.clone(),
+                Some((_, close)) => close.clone(),
+                None => String::new(),
+            }
+        };
+
+        let mut content = Vec::new();
+        let mut text = String::new();
+        for item in items {
+            match item {
+                ContentItem::String(s) => text.push_str(&s),
+                ContentItem::Url(url) => {
+                    if !text.is_empty() {
+                        content.push(GeneratedContent::Text(core::mem::take(&mut text)));
+                    }
+                    content.push(GeneratedContent::Image(url));
+                }
+                ContentItem::Attr(name) => {
+                    let element = element.borrow().get_element();
+                    text.push_str(
+                        &element
+                            .and_then(|e| e.get_attribute(&name))
+                            .unwrap_or_default(),
+                    );
+                }
+                ContentItem::Counter { name, style } => {
+                    text.push_str(&style.format(self.counters.value(&name)))
+                }
+                ContentItem::Counters {
+                    name,
+                    separator,
+                    style,
+                } => {
+                    let mut values = self.counters.values(&name);
+                    if values.is_empty() {
+                        values.push(0);
+                    }
+                    let formatted: Vec<String> = values.iter().map(|v| style.format(*v)).collect();
+                    text.push_str(&formatted.join(&separator));
+                }
+                ContentItem::OpenQuote => {
+                    text.push_str(&quote(self.quote_depth, false));
+                    self.quote_depth += 1;
+                }
+                ContentItem::NoOpenQuote => self.quote_depth += 1,
+                // A close quote at depth 0 is not rendered.
+                ContentItem::CloseQuote => {
+                    if self.quote_depth > 0 {
+                        self.quote_depth -= 1;
+                        text.push_str(&quote(self.quote_depth, true));
+                    }
+                }
+                ContentItem::NoCloseQuote => self.quote_depth = self.quote_depth.saturating_sub(1),
+            }
+        }
+        if !text.is_empty() {
+            content.push(GeneratedContent::Text(text));
+        }
+        Some(content)
+    }
+}
+
+/// Returns the number of ancestors of `node`.
+fn depth(node: &Rc<RefCell<Node>>) -> usize {
+    node.borrow().ancestors().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::css::cascade::Cascade;
+    use crate::renderer::css::parser::CssParser;
+    use crate::renderer::css::stylesheet::StyleSheet;
+    use crate::renderer::css::token::CssTokenizer;
+    use crate::renderer::dom::api::inclusive_descendants;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+
+    fn parse(css: &str) -> Vec<ComponentValue> {
+        CssParser::new(CssTokenizer::new(css.to_string())).parse_component_values()
+    }
+
+    #[test]
+    fn test_counter_styles() {
+        let format =
+            |style: &str, value: i32| CounterStyle::from_name(style).unwrap().format(value);
+        assert_eq!("-3", format("decimal", -3));
+        assert_eq!("07", format("decimal-leading-zero", 7));
+        assert_eq!("12", format("decimal-leading-zero", 12));
+        assert_eq!("xiv", format("lower-roman", 14));
+        assert_eq!("MCMXCIX", format("upper-roman", 1999));
+        assert_eq!("4000", format("upper-roman", 4000));
+        assert_eq!("z", format("lower-alpha", 26));
+        assert_eq!("AB", format("upper-latin", 28));
+        assert_eq!("0", format("lower-alpha", 0));
+        assert_eq!("γ", format("lower-greek", 3));
+        assert_eq!("\u{2022}", format("disc", 5));
+        assert_eq!("", format("none", 5));
+        assert_eq!(None, CounterStyle::from_name("unknown"));
+    }
+
+    #[test]
+    fn test_parse_content() {
+        assert_eq!(
+            Some(vec![
+                ContentItem::String("a".to_string()),
+                ContentItem::Attr("title".to_string()),
+                ContentItem::Counter {
+                    name: "c".to_string(),
+                    style: CounterStyle::Decimal,
+                },
+                ContentItem::Counters {
+                    name: "c".to_string(),
+                    separator: ".".to_string(),
+                    style: CounterStyle::UpperRoman,
+                },
+                ContentItem::Counter {
+                    name: "c".to_string(),
+                    style: CounterStyle::Decimal,
+                },
+                ContentItem::OpenQuote,
+                ContentItem::NoCloseQuote,
+                ContentItem::Url("i.png".to_string()),
+            ]),
+            parse_content(&parse(
+                r#""a" attr(TITLE) counter(c) counters(c, ".", upper-roman) counter(c, foo) open-quote no-close-quote url("i.png")"#
+            ))
+        );
+        assert_eq!(None, parse_content(&parse("")));
+        assert_eq!(None, parse_content(&parse("foo")));
+        assert_eq!(None, parse_content(&parse("attr(a, b)")));
+        assert_eq!(None, parse_content(&parse("counter(none)")));
+        assert_eq!(None, parse_content(&parse("counters(c)")));
+        assert_eq!(None, parse_content(&parse("counter(c, decimal, x)")));
+    }
+
+    #[test]
+    fn test_parse_quotes_and_counters() {
+        assert_eq!(Some(Vec::new()), parse_quotes(&parse("none")));
+        assert_eq!(2, parse_quotes(&parse("auto")).unwrap().len());
+        assert_eq!(
+            Some(vec![("<".to_string(), ">".to_string())]),
+            parse_quotes(&parse(r#""<" ">""#))
+        );
+        assert_eq!(None, parse_quotes(&parse(r#""<""#)));
+        assert_eq!(None, parse_quotes(&parse(r#""<" x"#)));
+
+        assert_eq!(
+            Some(vec![
+                ("a".to_string(), 1),
+                ("b".to_string(), -2),
+                ("c".to_string(), 1)
+            ]),
+            parse_counters(&parse("a b -2 c"), 1)
+        );
+        assert_eq!(Some(Vec::new()), parse_counters(&parse("none"), 0));
+        assert_eq!(None, parse_counters(&parse("a 1 2"), 0));
+        assert_eq!(None, parse_counters(&parse("a none"), 0));
+        assert_eq!(None, parse_counters(&parse("a 1.5"), 0));
+    }
+
+    #[test]
+    fn test_generate_content() {
+        let html = r#"<style>
+  body { counter-reset: section }
+  h1 { counter-increment: section; counter-reset: item }
+  h1::before { content: counter(section, upper-roman) ". " }
+  p { counter-increment: item }
+  p::before { content: counters(item, ".") " " attr(title) }
+  p::after { content: url(i.png) "!" }
+  a { quotes: "<" ">" "[" "]" }
+  a::before { content: open-quote open-quote }
+  a::after { content: close-quote close-quote close-quote }
+  h2::before { counter-reset: item 5; content: counter(item) }
+  h2::after { content: none }
+</style>
+<h1>A</h1><p title=x>1</p><p>2</p><h1>B</h1><p>3 <a>q</a></p><h2>C</h2>"#;
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let document = window.borrow().document();
+        Cascade::new(&StyleSheet::from_style_elements(&document)).apply(&document);
+
+        let mut generator = ContentGenerator::new();
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        // `::after` pseudo-elements here have no counter properties, so
+        // they can be generated before the children.
+        for node in inclusive_descendants(&document) {
+            if node.borrow().get_element().is_none() {
+                continue;
+            }
+            generator.enter(&node);
+            before.extend(generator.generate(&node, PseudoElement::Before));
+            after.extend(generator.generate(&node, PseudoElement::After));
+        }
+        let text = |s: &str| GeneratedContent::Text(s.to_string());
+        assert_eq!(
+            vec![
+                vec![text("I. ")],
+                vec![text("1 x")],
+                vec![text("2 ")],
+                vec![text("II. ")],
+                vec![text("1 ")],
+                vec![text("<[")],
+                vec![text("5")],
+            ],
+            before
+        );
+        assert_eq!(
+            vec![
+                vec![GeneratedContent::Image("i.png".to_string()), text("!")],
+                vec![GeneratedContent::Image("i.png".to_string()), text("!")],
+                vec![GeneratedContent::Image("i.png".to_string()), text("!")],
+                vec![text("]>")],
+            ],
+            after
+        );
+        assert_eq!(5, generator.counters().value("item"));
+        assert_eq!(vec![2], generator.counters().values("section"));
+    }
+}
@@ -0,0 +1,567 @@
+//! Incremental style invalidation.
+//!
+//! `Cascade::invalidate` reads the mutation records of a `MutationObserver`
+//! and marks the elements whose style may have changed, based on the
+//! features that the selectors of the style sheets depend on. Only those
+//! elements are restyled by `Cascade::restyle`.
+
+use crate::renderer::dom::mutation::MutationRecord;
+use crate::renderer::dom::mutation::MutationRecordType;
+use crate::renderer::dom::node::Element;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use crate::renderer::dom::selector::Combinator;
+use crate::renderer::dom::selector::ComplexSelector;
+use crate::renderer::dom::selector::PseudoClass;
+use crate::renderer::dom::selector::SelectorList;
+use crate::renderer::dom::selector::SimpleSelector;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// What must be restyled for an element.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Restyle {
+    /// The element itself. Its children are also restyled if its style
+    /// changes.
+    Element,
+    /// The element and all its descendants.
+    Subtree,
+}
+
+/// The elements whose style may depend on a feature of an element, e.g. a
+/// class.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+struct Dependents {
+    element: bool,
+    descendants: bool,
+    /// The following siblings and their descendants.
+    following_siblings: bool,
+    /// All the siblings and their descendants.
+    siblings: bool,
+}
+
+impl Dependents {
+    const ELEMENT: Self = Self {
+        element: true,
+        descendants: false,
+        following_siblings: false,
+        siblings: false,
+    };
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn union(self, other: Self) -> Self {
+        Self {
+            element: self.element || other.element,
+            descendants: self.descendants || other.descendants,
+            following_siblings: self.following_siblings || other.following_siblings,
+            siblings: self.siblings || other.siblings,
+        }
+    }
+
+    /// Returns the dependents of a feature that has the dependents `inner`
+    /// in a selector argument of a pseudo-class with the dependents `self`.
+    fn then(self, inner: Self) -> Self {
+        if inner == Self::ELEMENT {
+            self
+        } else if self == Self::ELEMENT {
+            inner
+        } else {
+            self.union(inner)
+        }
+    }
+
+    /// Marks the elements that depend on a feature of `node`.
+    fn invalidate(&self, node: &Rc<RefCell<Node>>) {
+        if self.element {
+            mark(node, Restyle::Element);
+        }
+        if self.descendants {
+            mark(node, Restyle::Subtree);
+        }
+        if self.siblings {
+            let parent = node.borrow().parent().upgrade();
+            for sibling in parent.iter().flat_map(|p| p.borrow().children()) {
+                if !Rc::ptr_eq(&sibling, node) {
+                    mark(&sibling, Restyle::Subtree);
+                }
+            }
+        } else if self.following_siblings {
+            for sibling in following_siblings(node) {
+                mark(&sibling, Restyle::Subtree);
+            }
+        }
+    }
+}
+
+/// Marks `node`, if it is an element, unless it is already marked for at
+/// least `restyle`.
+fn mark(node: &Rc<RefCell<Node>>, restyle: Restyle) {
+    let mut node = node.borrow_mut();
+    if matches!(node.kind, NodeKind::Element(_)) && node.restyle() < Some(restyle) {
+        node.set_restyle(Some(restyle));
+    }
+}
+
+fn following_siblings(node: &Rc<RefCell<Node>>) -> impl Iterator<Item = Rc<RefCell<Node>>> {
+    let first = node.borrow().next_sibling();
+    core::iter::successors(first, |n| n.borrow().next_sibling())
+}
+
+/// The style rules keyed by the most specific ID, class or type selector of
+/// the subject compound selector of each of their selectors, so that only
+/// the rules that may match an element are tried.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SelectorIndex {
+    ids: BTreeMap<String, Vec<usize>>,
+    classes: BTreeMap<String, Vec<usize>>,
+    types: BTreeMap<String, Vec<usize>>,
+    /// The rules with a selector that has none of those.
+    others: Vec<usize>,
+}
+
+impl SelectorIndex {
+    /// Adds the rule at `index`, which must be greater than the indices
+    /// already added.
+    pub(crate) fn insert(&mut self, index: usize, selectors: &SelectorList) {
+        for selector in selectors.selectors() {
+            let subject = selector
+                .compounds()
+                .last()
+                .map_or(&[][..], |c| c.selectors());
+            let find = |f: fn(&SimpleSelector) -> Option<&String>| subject.iter().find_map(f);
+            let bucket = if let Some(id) = find(|s| match s {
+                SimpleSelector::Id(id) => Some(id),
+                _ => None,
+            }) {
+                self.ids.entry(id.clone()).or_default()
+            } else if let Some(class) = find(|s| match s {
+                SimpleSelector::Class(class) => Some(class),
+                _ => None,
+            }) {
+                self.classes.entry(class.clone()).or_default()
+            } else if let Some(name) = find(|s| match s {
+                SimpleSelector::Type(name) => Some(name),
+                _ => None,
+            }) {
+                self.types.entry(name.clone()).or_default()
+            } else {
+                &mut self.others
+            };
+            if bucket.last() != Some(&index) {
+                bucket.push(index);
+            }
+        }
+    }
+
+    /// Returns the indices of the rules that may match `element`, in
+    /// ascending order.
+    pub(crate) fn candidates(&self, element: &Element) -> Vec<usize> {
+        let mut candidates = self.others.clone();
+        if let Some(id) = element.id() {
+            candidates.extend(self.ids.get(&id).into_iter().flatten());
+        }
+        for class in element.class_list() {
+            candidates.extend(self.classes.get(&class).into_iter().flatten());
+        }
+        let name = element.kind().to_string();
+        candidates.extend(self.types.get(&name).into_iter().flatten());
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+}
+
+/// The features of elements that the selectors of the style rules depend
+/// on and that DOM mutations can change, with the elements that depend on
+/// them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InvalidationSet {
+    ids: BTreeMap<String, Dependents>,
+    classes: BTreeMap<String, Dependents>,
+    /// Keyed by lowercase attribute names.
+    attributes: BTreeMap<String, Dependents>,
+    /// Pseudo-classes that depend on the position of an element among its
+    /// siblings, e.g. `:first-child`.
+    structural: Dependents,
+    /// https://www.w3.org/TR/selectors-4/#the-empty-pseudo
+    empty: Dependents,
+    /// https://www.w3.org/TR/selectors-4/#adjacent-sibling-combinators
+    /// https://www.w3.org/TR/selectors-4/#general-sibling-combinators
+    sibling_combinators: bool,
+}
+
+impl InvalidationSet {
+    pub(crate) fn insert(&mut self, selectors: &SelectorList) {
+        self.insert_list(selectors, Dependents::ELEMENT);
+    }
+
+    fn insert_list(&mut self, selectors: &SelectorList, dependents: Dependents) {
+        for selector in selectors.selectors() {
+            self.insert_complex(selector, dependents);
+        }
+    }
+
+    fn insert_complex(&mut self, selector: &ComplexSelector, dependents: Dependents) {
+        let compounds = selector.compounds();
+        for (i, compound) in compounds.iter().enumerate() {
+            // The combinator on the right of a compound selector tells which
+            // elements the features it tests affect.
+            let inner = match selector.combinators().get(i) {
+                None => Dependents::ELEMENT,
+                Some(Combinator::Descendant) | Some(Combinator::Child) => Dependents {
+                    descendants: true,
+                    ..Default::default()
+                },
+                Some(Combinator::NextSibling) | Some(Combinator::SubsequentSibling) => {
+                    self.sibling_combinators = true;
+                    Dependents {
+                        following_siblings: true,
+                        ..Default::default()
+                    }
+                }
+            };
+            for simple in compound.selectors() {
+                self.insert_simple(simple, dependents.then(inner));
+            }
+        }
+    }
+
+    fn insert_simple(&mut self, selector: &SimpleSelector, dependents: Dependents) {
+        let merge = |map: &mut BTreeMap<String, Dependents>, key: &str| {
+            let entry = map.entry(key.to_string()).or_default();
+            *entry = entry.union(dependents);
+        };
+        match selector {
+            SimpleSelector::Universal | SimpleSelector::Type(_) => {}
+            SimpleSelector::Id(id) => merge(&mut self.ids, id),
+            SimpleSelector::Class(class) => merge(&mut self.classes, class),
+            SimpleSelector::Attribute { name, .. } => {
+                merge(&mut self.attributes, &name.to_ascii_lowercase())
+            }
+            SimpleSelector::PseudoClass(pseudo_class) => match pseudo_class {
+                PseudoClass::Not(list) | PseudoClass::Is(list) | PseudoClass::Where(list) => {
+                    self.insert_list(list, dependents)
+                }
+                PseudoClass::NthChild(_, of) | PseudoClass::NthLastChild(_, of) => {
+                    self.structural = self.structural.union(dependents);
+                    // The features of the siblings decide the index.
+                    if let Some(of) = of {
+                        let siblings = Dependents {
+                            siblings: true,
+                            ..Dependents::ELEMENT
+                        };
+                        self.insert_list(of, dependents.then(siblings));
+                    }
+                }
+                PseudoClass::NthOfType(_)
+                | PseudoClass::NthLastOfType(_)
+                | PseudoClass::FirstChild
+                | PseudoClass::LastChild
+                | PseudoClass::OnlyChild
+                | PseudoClass::FirstOfType
+                | PseudoClass::LastOfType
+                | PseudoClass::OnlyOfType => {
+                    self.structural = self.structural.union(dependents);
+                }
+                PseudoClass::Empty => self.empty = self.empty.union(dependents),
+                PseudoClass::Root => {}
+                PseudoClass::Link => merge(&mut self.attributes, "href"),
+            },
+        }
+    }
+
+    /// Marks the elements whose style may have changed because of the
+    /// mutation described by `record`. Attribute records must have their
+    /// old value.
+    pub(crate) fn invalidate(&self, record: &MutationRecord) {
+        match record.kind {
+            MutationRecordType::Attributes => self.invalidate_attribute(record),
+            MutationRecordType::CharacterData => {
+                if let Some(parent) = record.target.borrow().parent().upgrade() {
+                    self.empty.invalidate(&parent);
+                }
+            }
+            MutationRecordType::ChildList => self.invalidate_children(record),
+        }
+    }
+
+    fn invalidate_attribute(&self, record: &MutationRecord) {
+        let target = &record.target;
+        let name = match &record.attribute_name {
+            Some(name) => name.to_ascii_lowercase(),
+            None => return,
+        };
+        let value = target
+            .borrow()
+            .get_element()
+            .and_then(|e| e.get_attribute(&name));
+        let old_value = record.old_value.as_deref();
+        if let Some(dependents) = self.attributes.get(&name) {
+            dependents.invalidate(target);
+        }
+
+        match name.as_str() {
+            // https://www.w3.org/TR/css-cascade-4/#style-attr
+            "style" => mark(target, Restyle::Element),
+            "id" => {
+                for id in [old_value, value.as_deref()].into_iter().flatten() {
+                    if let Some(dependents) = self.ids.get(id) {
+                        dependents.invalidate(target);
+                    }
+                }
+            }
+            "class" => {
+                let old: Vec<&str> = old_value.unwrap_or("").split_ascii_whitespace().collect();
+                let new: Vec<&str> = value
+                    .as_deref()
+                    .unwrap_or("")
+                    .split_ascii_whitespace()
+                    .collect();
+                // Only the classes that were added or removed matter.
+                let changed = old
+                    .iter()
+                    .filter(|c| !new.contains(c))
+                    .chain(new.iter().filter(|c| !old.contains(c)));
+                for class in changed {
+                    if let Some(dependents) = self.classes.get(*class) {
+                        dependents.invalidate(target);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn invalidate_children(&self, record: &MutationRecord) {
+        let parent = &record.target;
+        for node in &record.added_nodes {
+            mark(node, Restyle::Subtree);
+        }
+        if !self.empty.is_empty() {
+            self.empty.invalidate(parent);
+        }
+
+        // Insertions and removals change the position of the siblings. Both
+        // kinds of dependents are marked, since `mark` keeps the larger
+        // restyle.
+        if !self.structural.is_empty() {
+            let restyle = match self.structural == Dependents::ELEMENT {
+                true => Restyle::Element,
+                false => Restyle::Subtree,
+            };
+            for child in parent.borrow().children() {
+                mark(&child, restyle);
+            }
+        }
+        if self.sibling_combinators {
+            let next = record.next_sibling.iter();
+            for sibling in next
+                .clone()
+                .cloned()
+                .chain(next.flat_map(following_siblings))
+            {
+                mark(&sibling, Restyle::Subtree);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::append_child;
+    use crate::renderer::dom::api::query_selector;
+    use crate::renderer::dom::api::remove_child;
+    use crate::renderer::dom::api::set_attribute;
+    use crate::renderer::dom::mutation::MutationObserver;
+    use crate::renderer::dom::mutation::MutationObserverInit;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+    use alloc::vec;
+
+    fn index(selectors: &[&str]) -> SelectorIndex {
+        let mut index = SelectorIndex::default();
+        for (i, s) in selectors.iter().enumerate() {
+            index.insert(i, &SelectorList::parse(s).unwrap());
+        }
+        index
+    }
+
+    #[test]
+    fn test_selector_index() {
+        let index = index(&[
+            "p.a#b",
+            ".a",
+            "p",
+            "*",
+            ".c, .a, .b",
+            "div p.c",
+            ":not(p)",
+            "p > .b",
+        ]);
+        let element = |html: &str| {
+            let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+            let document = window.borrow().document();
+            let p = query_selector(&document, "p").unwrap().unwrap();
+            let element = p.borrow().get_element().unwrap();
+            element
+        };
+        assert_eq!(
+            vec![0, 1, 2, 3, 4, 6, 7],
+            index.candidates(&element("<p id=b class='b a'>"))
+        );
+        assert_eq!(
+            vec![2, 3, 4, 5, 6],
+            index.candidates(&element("<p class=c>"))
+        );
+        assert_eq!(vec![2, 3, 6], index.candidates(&element("<p>")));
+    }
+
+    /// Parses `html`, applies the mutation `mutate` to the document, and
+    /// returns the IDs of the elements it marks with `selectors`.
+    fn invalidated(
+        selectors: &[&str],
+        html: &str,
+        mutate: impl FnOnce(&Rc<RefCell<Node>>),
+    ) -> Vec<(String, Restyle)> {
+        let mut set = InvalidationSet::default();
+        for s in selectors {
+            set.insert(&SelectorList::parse(s).unwrap());
+        }
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let document = window.borrow().document();
+        let observer = MutationObserver::new();
+        let options = MutationObserverInit {
+            child_list: true,
+            attributes: true,
+            attribute_old_value: true,
+            character_data: true,
+            subtree: true,
+            ..Default::default()
+        };
+        observer.observe(&document, options).unwrap();
+
+        mutate(&document);
+        for record in observer.take_records() {
+            set.invalidate(&record);
+        }
+        let restyles = document.borrow().descendants().filter_map(|n| {
+            let restyle = n.borrow().restyle()?;
+            let id = n.borrow().get_element()?.id().unwrap_or_default();
+            Some((id, restyle))
+        });
+        restyles.collect()
+    }
+
+    fn get(document: &Rc<RefCell<Node>>, selector: &str) -> Rc<RefCell<Node>> {
+        query_selector(document, selector).unwrap().unwrap()
+    }
+
+    fn marks(marks: &[(&str, Restyle)]) -> Vec<(String, Restyle)> {
+        marks.iter().map(|(id, r)| (id.to_string(), *r)).collect()
+    }
+
+    #[test]
+    fn test_invalidate_attributes() {
+        use Restyle::*;
+        let html = "<p id=a class='x y'><a id=b></a></p><p id=c></p><p id=d></p>";
+        let selectors = [".x", ".z .w", ".y + p", "[title]", "#e"];
+
+        let set_class = |value: &'static str| {
+            move |d: &Rc<RefCell<Node>>| set_attribute(&get(d, "#a"), "class", value)
+        };
+        assert_eq!(marks(&[]), invalidated(&selectors, html, set_class("y x")));
+        assert_eq!(
+            marks(&[("a", Element)]),
+            invalidated(&selectors, html, set_class("y"))
+        );
+        assert_eq!(
+            marks(&[("a", Subtree)]),
+            invalidated(&selectors, html, set_class("x y z"))
+        );
+        assert_eq!(
+            marks(&[("a", Element), ("c", Subtree), ("d", Subtree)]),
+            invalidated(&selectors, html, set_class(""))
+        );
+        assert_eq!(
+            marks(&[("b", Element)]),
+            invalidated(&selectors, html, |d| set_attribute(
+                &get(d, "#b"),
+                "TITLE",
+                "t"
+            ))
+        );
+        assert_eq!(
+            marks(&[]),
+            invalidated(&selectors, html, |d| set_attribute(
+                &get(d, "#b"),
+                "lang",
+                "en"
+            ))
+        );
+        assert_eq!(
+            marks(&[("b", Element)]),
+            invalidated(&selectors, html, |d| set_attribute(
+                &get(d, "#b"),
+                "style",
+                "color: red"
+            ))
+        );
+        assert_eq!(
+            marks(&[("e", Element)]),
+            invalidated(&selectors, html, |d| set_attribute(
+                &get(d, "#b"),
+                "id",
+                "e"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_invalidate_children() {
+        use Restyle::*;
+        let html = "<p id=a><a id=b></a><a id=c></a><a id=d></a></p>";
+        let remove = |d: &Rc<RefCell<Node>>| {
+            remove_child(&get(d, "#a"), &get(d, "#c")).unwrap();
+        };
+        let append = |d: &Rc<RefCell<Node>>| {
+            let p = get(d, "#c");
+            append_child(&get(d, "#d"), p).unwrap();
+        };
+
+        assert_eq!(marks(&[]), invalidated(&["a"], html, remove));
+        assert_eq!(
+            marks(&[("d", Subtree)]),
+            invalidated(&["a", "a + a"], html, remove)
+        );
+        assert_eq!(
+            marks(&[("b", Element), ("d", Subtree)]),
+            invalidated(&[":last-child", "a ~ a"], html, remove)
+        );
+        assert_eq!(
+            marks(&[("f", Subtree)]),
+            invalidated(
+                &["h2:last-child", "h1 + p a"],
+                "<h1 id=e></h1><p id=f><a id=g></a></p>",
+                |d| {
+                    remove_child(&get(d, "body"), &get(d, "#e")).unwrap();
+                }
+            )
+        );
+        assert_eq!(
+            marks(&[("a", Element), ("b", Subtree), ("d", Subtree)]),
+            invalidated(&[":empty", ":first-child a"], html, remove)
+        );
+        assert_eq!(
+            marks(&[("a", Element), ("d", Element), ("c", Subtree)]),
+            invalidated(&[":empty"], html, append)
+        );
+    }
+}
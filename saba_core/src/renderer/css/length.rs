@@ -0,0 +1,477 @@
+//! https://www.w3.org/TR/css-values-4/#lengths
+
+use crate::renderer::css::stylesheet::ComponentValue;
+use crate::renderer::css::token::CssToken;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// https://www.w3.org/TR/css-values-4/#lengths
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LengthUnit {
+    Px,
+    Em,
+    Rem,
+    Ex,
+    Ch,
+    Vw,
+    Vh,
+    Pt,
+    Cm,
+    Mm,
+    In,
+}
+
+impl LengthUnit {
+    pub fn from_unit(unit: &str) -> Option<Self> {
+        let unit = match unit.to_ascii_lowercase().as_str() {
+            "px" => LengthUnit::Px,
+            "em" => LengthUnit::Em,
+            "rem" => LengthUnit::Rem,
+            "ex" => LengthUnit::Ex,
+            "ch" => LengthUnit::Ch,
+            "vw" => LengthUnit::Vw,
+            "vh" => LengthUnit::Vh,
+            "pt" => LengthUnit::Pt,
+            "cm" => LengthUnit::Cm,
+            "mm" => LengthUnit::Mm,
+            "in" => LengthUnit::In,
+            _ => return None,
+        };
+        Some(unit)
+    }
+
+    /// Converts `value` in this unit to px.
+    pub fn to_px(self, value: f64, context: &LengthContext) -> f64 {
+        match self {
+            LengthUnit::Px => value,
+            LengthUnit::Em => value * context.font_size,
+            LengthUnit::Rem => value * context.root_font_size,
+            // Without font metrics, the x-height and the advance of `0` are
+            // taken to be half the font size.
+            LengthUnit::Ex | LengthUnit::Ch => value * context.font_size / 2.0,
+            LengthUnit::Vw => value * context.viewport_width / 100.0,
+            LengthUnit::Vh => value * context.viewport_height / 100.0,
+            // https://www.w3.org/TR/css-values-4/#absolute-lengths
+            LengthUnit::Pt => value * 96.0 / 72.0,
+            LengthUnit::Cm => value * 96.0 / 2.54,
+            LengthUnit::Mm => value * 96.0 / 25.4,
+            LengthUnit::In => value * 96.0,
+        }
+    }
+}
+
+/// What relative lengths are relative to, in px.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LengthContext {
+    /// The font size of the element, or of its parent for `font-size`.
+    pub font_size: f64,
+    /// The font size of the root element.
+    pub root_font_size: f64,
+    pub viewport_width: f64,
+    pub viewport_height: f64,
+}
+
+impl Default for LengthContext {
+    fn default() -> Self {
+        Self {
+            font_size: 16.0,
+            root_font_size: 16.0,
+            viewport_width: 0.0,
+            viewport_height: 0.0,
+        }
+    }
+}
+
+/// A math expression of `calc()`, `min()`, `max()` or `clamp()`. Plain
+/// numbers, lengths and percentages are leaves.
+/// https://www.w3.org/TR/css-values-4/#calc-func
+#[derive(Debug, Clone, PartialEq)]
+pub enum Calc {
+    Number(f64),
+    Length(f64, LengthUnit),
+    Percentage(f64),
+    Sum(Box<Calc>, Box<Calc>),
+    Difference(Box<Calc>, Box<Calc>),
+    Product(Box<Calc>, Box<Calc>),
+    Quotient(Box<Calc>, Box<Calc>),
+    Min(Vec<Calc>),
+    Max(Vec<Calc>),
+    /// `clamp(min, value, max)`
+    Clamp(Box<Calc>, Box<Calc>, Box<Calc>),
+}
+
+/// https://www.w3.org/TR/css-values-4/#calc-type-checking
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum CalcType {
+    Number,
+    Length,
+    Percentage,
+    /// A mix of lengths and percentages.
+    LengthPercentage,
+}
+
+impl Calc {
+    /// Parses a `<length-percentage>`: a length, a percentage, a unitless
+    /// zero or a math function that resolves to a length.
+    pub fn parse_length_percentage(values: &[ComponentValue]) -> Option<Self> {
+        let values = trim_whitespace(values);
+        let calc = match values {
+            [ComponentValue::Token(CssToken::Number { value, .. })] if *value == 0.0 => {
+                Calc::Length(0.0, LengthUnit::Px)
+            }
+            [value @ ComponentValue::Token(CssToken::Dimension { .. })]
+            | [value @ ComponentValue::Token(CssToken::Percentage(_))]
+            | [value @ ComponentValue::Function { .. }] => Calc::parse_value(value)?,
+            _ => return None,
+        };
+        match calc.calc_type()? {
+            CalcType::Number => None,
+            _ => Some(calc),
+        }
+    }
+
+    /// Parses a `<number>`, or a math function that resolves to a number.
+    pub fn parse_number(values: &[ComponentValue]) -> Option<Self> {
+        let calc = match trim_whitespace(values) {
+            [value @ ComponentValue::Token(CssToken::Number { .. })]
+            | [value @ ComponentValue::Function { .. }] => Calc::parse_value(value)?,
+            _ => return None,
+        };
+        match calc.calc_type()? {
+            CalcType::Number => Some(calc),
+            _ => None,
+        }
+    }
+
+    /// https://www.w3.org/TR/css-values-4/#calc-syntax
+    fn parse_value(value: &ComponentValue) -> Option<Self> {
+        match value {
+            ComponentValue::Token(CssToken::Number { value, .. }) => Some(Calc::Number(*value)),
+            ComponentValue::Token(CssToken::Percentage(value)) => Some(Calc::Percentage(*value)),
+            ComponentValue::Token(CssToken::Dimension { value, unit, .. }) => {
+                Some(Calc::Length(*value, LengthUnit::from_unit(unit)?))
+            }
+            ComponentValue::Block { open: '(', value } => Calc::parse_sum(value),
+            ComponentValue::Function { name, value } => {
+                let mut args = value
+                    .split(|v| *v == ComponentValue::Token(CssToken::Comma))
+                    .map(Calc::parse_sum);
+                let name = name.to_ascii_lowercase();
+                let calc = match name.as_str() {
+                    "calc" => Calc::parse_sum(value)?,
+                    "min" => Calc::Min(args.collect::<Option<Vec<Calc>>>()?),
+                    "max" => Calc::Max(args.collect::<Option<Vec<Calc>>>()?),
+                    "clamp" => {
+                        let min = args.next()??;
+                        let value = args.next()??;
+                        let max = args.next()??;
+                        if args.next().is_some() {
+                            return None;
+                        }
+                        Calc::Clamp(Box::new(min), Box::new(value), Box::new(max))
+                    }
+                    _ => return None,
+                };
+                Some(calc)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses `<calc-sum>`. `+` and `-` must have whitespace on both sides.
+    /// https://www.w3.org/TR/css-values-4/#typedef-calc-sum
+    fn parse_sum(values: &[ComponentValue]) -> Option<Self> {
+        let values = trim_whitespace(values);
+        let is_operator = |i: usize| {
+            matches!(
+                values[i],
+                ComponentValue::Token(CssToken::Delim('+'))
+                    | ComponentValue::Token(CssToken::Delim('-'))
+            ) && i > 0
+                && is_whitespace(&values[i - 1])
+                && values.get(i + 1).is_some_and(is_whitespace)
+        };
+
+        let mut sum: Option<Calc> = None;
+        let mut start = 0;
+        let mut operator = '+';
+        for i in 0..=values.len() {
+            if i < values.len() && !is_operator(i) {
+                continue;
+            }
+            let term = Calc::parse_product(&values[start..i])?;
+            sum = Some(match sum {
+                None => term,
+                Some(s) if operator == '+' => Calc::Sum(Box::new(s), Box::new(term)),
+                Some(s) => Calc::Difference(Box::new(s), Box::new(term)),
+            });
+            if let Some(ComponentValue::Token(CssToken::Delim(c))) = values.get(i) {
+                operator = *c;
+            }
+            start = i + 1;
+        }
+        sum
+    }
+
+    /// https://www.w3.org/TR/css-values-4/#typedef-calc-product
+    fn parse_product(values: &[ComponentValue]) -> Option<Self> {
+        let mut operands = values.iter().filter(|v| !is_whitespace(v));
+        let mut product = Calc::parse_value(operands.next()?)?;
+        while let Some(operator) = operands.next() {
+            let operand = Calc::parse_value(operands.next()?)?;
+            product = match operator {
+                ComponentValue::Token(CssToken::Delim('*')) => {
+                    Calc::Product(Box::new(product), Box::new(operand))
+                }
+                ComponentValue::Token(CssToken::Delim('/')) => {
+                    Calc::Quotient(Box::new(product), Box::new(operand))
+                }
+                _ => return None,
+            };
+        }
+        Some(product)
+    }
+
+    /// Returns the type of the expression, or `None` if it mixes types that
+    /// can't be combined, like a number and a length in a sum.
+    fn calc_type(&self) -> Option<CalcType> {
+        let combine = |a: CalcType, b: CalcType| match (a, b) {
+            (a, b) if a == b => Some(a),
+            (CalcType::Number, _) | (_, CalcType::Number) => None,
+            _ => Some(CalcType::LengthPercentage),
+        };
+        match self {
+            Calc::Number(_) => Some(CalcType::Number),
+            Calc::Length(_, _) => Some(CalcType::Length),
+            Calc::Percentage(_) => Some(CalcType::Percentage),
+            Calc::Sum(a, b) | Calc::Difference(a, b) => combine(a.calc_type()?, b.calc_type()?),
+            Calc::Product(a, b) => match (a.calc_type()?, b.calc_type()?) {
+                (CalcType::Number, t) | (t, CalcType::Number) => Some(t),
+                _ => None,
+            },
+            Calc::Quotient(a, b) => match b.calc_type()? {
+                CalcType::Number => a.calc_type(),
+                _ => None,
+            },
+            Calc::Min(args) | Calc::Max(args) => {
+                let mut types = args.iter().map(|a| a.calc_type());
+                let first = types.next()??;
+                types.try_fold(first, |acc, t| combine(acc, t?))
+            }
+            Calc::Clamp(min, value, max) => combine(
+                combine(min.calc_type()?, value.calc_type()?)?,
+                max.calc_type()?,
+            ),
+        }
+    }
+
+    fn has_percentage(&self) -> bool {
+        match self {
+            Calc::Number(_) | Calc::Length(_, _) => false,
+            Calc::Percentage(_) => true,
+            Calc::Sum(a, b)
+            | Calc::Difference(a, b)
+            | Calc::Product(a, b)
+            | Calc::Quotient(a, b) => a.has_percentage() || b.has_percentage(),
+            Calc::Min(args) | Calc::Max(args) => args.iter().any(|a| a.has_percentage()),
+            Calc::Clamp(min, value, max) => {
+                min.has_percentage() || value.has_percentage() || max.has_percentage()
+            }
+        }
+    }
+
+    /// Evaluates the expression. Lengths are in px, and percentages are
+    /// relative to `percentage_basis`.
+    pub fn evaluate(&self, context: &LengthContext, percentage_basis: f64) -> f64 {
+        let eval = |c: &Calc| c.evaluate(context, percentage_basis);
+        match self {
+            Calc::Number(n) => *n,
+            Calc::Length(value, unit) => unit.to_px(*value, context),
+            Calc::Percentage(p) => p * percentage_basis / 100.0,
+            Calc::Sum(a, b) => eval(a) + eval(b),
+            Calc::Difference(a, b) => eval(a) - eval(b),
+            Calc::Product(a, b) => eval(a) * eval(b),
+            Calc::Quotient(a, b) => eval(a) / eval(b),
+            Calc::Min(args) => args.iter().map(eval).fold(f64::INFINITY, f64::min),
+            Calc::Max(args) => args.iter().map(eval).fold(f64::NEG_INFINITY, f64::max),
+            // https://www.w3.org/TR/css-values-4/#funcdef-clamp
+            // The minimum wins over the maximum.
+            Calc::Clamp(min, value, max) => eval(value).min(eval(max)).max(eval(min)),
+        }
+    }
+
+    /// Replaces relative lengths with px.
+    fn absolutize(&self, context: &LengthContext) -> Calc {
+        let abs = |c: &Calc| Box::new(c.absolutize(context));
+        match self {
+            Calc::Length(value, unit) => Calc::Length(unit.to_px(*value, context), LengthUnit::Px),
+            Calc::Number(_) | Calc::Percentage(_) => self.clone(),
+            Calc::Sum(a, b) => Calc::Sum(abs(a), abs(b)),
+            Calc::Difference(a, b) => Calc::Difference(abs(a), abs(b)),
+            Calc::Product(a, b) => Calc::Product(abs(a), abs(b)),
+            Calc::Quotient(a, b) => Calc::Quotient(abs(a), abs(b)),
+            Calc::Min(args) => Calc::Min(args.iter().map(|a| a.absolutize(context)).collect()),
+            Calc::Max(args) => Calc::Max(args.iter().map(|a| a.absolutize(context)).collect()),
+            Calc::Clamp(min, value, max) => Calc::Clamp(abs(min), abs(value), abs(max)),
+        }
+    }
+
+    /// Computes the value of a `<length-percentage>`. Lengths are resolved
+    /// to px, while percentages are kept until their basis is known.
+    /// https://www.w3.org/TR/css-values-4/#calc-computed-value
+    pub fn compute(&self, context: &LengthContext) -> LengthPercentage {
+        match self {
+            Calc::Percentage(p) => LengthPercentage::Percentage(*p),
+            c if !c.has_percentage() => LengthPercentage::Length(finite(c.evaluate(context, 0.0))),
+            c => LengthPercentage::Calc(Box::new(c.absolutize(context))),
+        }
+    }
+}
+
+/// The computed value of a `<length-percentage>`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LengthPercentage {
+    /// A length in px.
+    Length(f64),
+    Percentage(f64),
+    /// An expression whose lengths are in px.
+    Calc(Box<Calc>),
+}
+
+impl LengthPercentage {
+    /// Returns the used value in px, with percentages relative to `basis`.
+    /// https://www.w3.org/TR/css-cascade-4/#used
+    pub fn resolve(&self, basis: f64) -> f64 {
+        match self {
+            LengthPercentage::Length(px) => *px,
+            LengthPercentage::Percentage(p) => p * basis / 100.0,
+            LengthPercentage::Calc(calc) => finite(calc.evaluate(&LengthContext::default(), basis)),
+        }
+    }
+}
+
+/// https://www.w3.org/TR/css-values-4/#calc-ieee
+/// NaN becomes zero, and infinities are clamped to the largest finite
+/// values.
+fn finite(value: f64) -> f64 {
+    if value.is_nan() {
+        0.0
+    } else {
+        value.clamp(f64::MIN, f64::MAX)
+    }
+}
+
+fn is_whitespace(value: &ComponentValue) -> bool {
+    *value == ComponentValue::Token(CssToken::Whitespace)
+}
+
+fn trim_whitespace(values: &[ComponentValue]) -> &[ComponentValue] {
+    let start = values
+        .iter()
+        .position(|v| !is_whitespace(v))
+        .unwrap_or(values.len());
+    let end = values
+        .iter()
+        .rposition(|v| !is_whitespace(v))
+        .map_or(start, |i| i + 1);
+    &values[start..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::css::parser::CssParser;
+    use crate::renderer::css::token::CssTokenizer;
+    use alloc::string::String;
+
+    fn values(css: &str) -> Vec<ComponentValue> {
+        CssParser::new(CssTokenizer::new(String::from(css))).parse_component_values()
+    }
+
+    fn context() -> LengthContext {
+        LengthContext {
+            font_size: 10.0,
+            root_font_size: 20.0,
+            viewport_width: 800.0,
+            viewport_height: 600.0,
+        }
+    }
+
+    /// Parses and computes a `<length-percentage>`.
+    fn compute(css: &str) -> Option<LengthPercentage> {
+        Calc::parse_length_percentage(&values(css)).map(|c| c.compute(&context()))
+    }
+
+    #[test]
+    fn test_units() {
+        assert_eq!(Some(LengthPercentage::Length(3.0)), compute("3px"));
+        assert_eq!(Some(LengthPercentage::Length(15.0)), compute("1.5em"));
+        assert_eq!(Some(LengthPercentage::Length(40.0)), compute("2REM"));
+        assert_eq!(Some(LengthPercentage::Length(5.0)), compute("1ex"));
+        assert_eq!(Some(LengthPercentage::Length(5.0)), compute("1ch"));
+        assert_eq!(Some(LengthPercentage::Length(80.0)), compute("10vw"));
+        assert_eq!(Some(LengthPercentage::Length(60.0)), compute("10vh"));
+        assert_eq!(Some(LengthPercentage::Length(4.0)), compute("3pt"));
+        assert_eq!(Some(LengthPercentage::Length(96.0)), compute("2.54cm"));
+        assert_eq!(Some(LengthPercentage::Length(0.0)), compute("0"));
+        assert_eq!(Some(LengthPercentage::Percentage(50.0)), compute("50%"));
+        assert_eq!(None, compute("1"));
+        assert_eq!(None, compute("1deg"));
+        assert_eq!(None, compute("1px 2px"));
+    }
+
+    #[test]
+    fn test_calc() {
+        assert_eq!(
+            Some(LengthPercentage::Length(25.0)),
+            compute("calc(1em + 3px * 5)")
+        );
+        assert_eq!(
+            Some(LengthPercentage::Length(5.0)),
+            compute("calc((1em + 10px) / 4)")
+        );
+        assert_eq!(
+            Some(LengthPercentage::Length(4.0)),
+            compute("calc(10px - 2px - 4px)")
+        );
+        assert_eq!(
+            Some(LengthPercentage::Length(3.0)),
+            compute("min(1em, 3px, 2rem)")
+        );
+        assert_eq!(
+            Some(LengthPercentage::Length(40.0)),
+            compute("max(1em, 3px, 2rem)")
+        );
+        assert_eq!(
+            Some(LengthPercentage::Length(10.0)),
+            compute("clamp(1px, 1em, 2rem)")
+        );
+        // The minimum wins over the maximum.
+        assert_eq!(
+            Some(LengthPercentage::Length(5.0)),
+            compute("clamp(5px, 1px, 2px)")
+        );
+
+        // A percentage is only resolved at used-value time.
+        let value = compute("calc(50% - 1em)").unwrap();
+        assert!(matches!(value, LengthPercentage::Calc(_)));
+        assert_eq!(190.0, value.resolve(400.0));
+        assert_eq!(100.0, compute("max(10%, 100px)").unwrap().resolve(500.0));
+        assert_eq!(200.0, compute("max(10%, 100px)").unwrap().resolve(2000.0));
+
+        // `+` and `-` need whitespace around them.
+        assert_eq!(None, compute("calc(1px +2px)"));
+        assert_eq!(None, compute("calc(1px -2px)"));
+        // Types must match.
+        assert_eq!(None, compute("calc(1px + 2)"));
+        assert_eq!(None, compute("calc(1px * 2px)"));
+        assert_eq!(None, compute("calc(2 / 1px)"));
+        assert_eq!(None, compute("calc(2 * 3)"));
+        assert_eq!(None, compute("clamp(1px, 2px)"));
+        assert_eq!(None, compute("foo(1px)"));
+
+        let number = Calc::parse_number(&values("calc(2 * 3)")).unwrap();
+        assert_eq!(6.0, number.evaluate(&context(), 0.0));
+        assert_eq!(None, Calc::parse_number(&values("1px")));
+    }
+}
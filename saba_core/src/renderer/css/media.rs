@@ -0,0 +1,629 @@
+//! https://www.w3.org/TR/mediaqueries-4/
+
+use crate::renderer::css::length::Calc;
+use crate::renderer::css::length::LengthContext;
+use crate::renderer::css::length::LengthPercentage;
+use crate::renderer::css::parser::CssParser;
+use crate::renderer::css::stylesheet::ComponentValue;
+use crate::renderer::css::token::CssToken;
+use crate::renderer::css::token::CssTokenizer;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// https://www.w3.org/TR/mediaqueries-4/#media-types
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MediaType {
+    All,
+    Screen,
+    Print,
+    /// A media type that is recognized but never matches, like `tv`.
+    Unknown,
+}
+
+/// https://www.w3.org/TR/mediaqueries-5/#prefers-color-scheme
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+/// What media queries are evaluated against. The host supplies it, e.g. from
+/// the size of the window.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Viewport {
+    /// The width of the viewport in px.
+    pub width: f64,
+    /// The height of the viewport in px.
+    pub height: f64,
+    /// `Screen` or `Print`.
+    pub media_type: MediaType,
+    pub color_scheme: ColorScheme,
+    /// Device pixels per px.
+    /// https://www.w3.org/TR/mediaqueries-4/#resolution
+    pub resolution: f64,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            width: 800.0,
+            height: 600.0,
+            media_type: MediaType::Screen,
+            color_scheme: ColorScheme::Light,
+            resolution: 1.0,
+        }
+    }
+}
+
+/// https://www.w3.org/TR/mediaqueries-4/#typedef-media-query-list
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQueryList {
+    queries: Vec<MediaQuery>,
+}
+
+/// https://www.w3.org/TR/mediaqueries-4/#typedef-media-query
+#[derive(Debug, Clone, PartialEq)]
+struct MediaQuery {
+    not: bool,
+    media_type: MediaType,
+    condition: Option<MediaCondition>,
+}
+
+/// https://www.w3.org/TR/mediaqueries-4/#typedef-media-condition
+#[derive(Debug, Clone, PartialEq)]
+enum MediaCondition {
+    Not(Box<MediaCondition>),
+    And(Vec<MediaCondition>),
+    Or(Vec<MediaCondition>),
+    Feature(MediaFeature),
+    /// https://www.w3.org/TR/mediaqueries-4/#typedef-general-enclosed
+    /// Anything in parentheses that isn't understood. It is neither true nor
+    /// false.
+    Unknown,
+}
+
+/// https://www.w3.org/TR/mediaqueries-4/#mq-features
+#[derive(Debug, Clone, PartialEq)]
+enum MediaFeature {
+    Width(Vec<(Comparison, Calc)>),
+    Height(Vec<(Comparison, Calc)>),
+    /// In dppx.
+    Resolution(Vec<(Comparison, f64)>),
+    /// `None` in a boolean context.
+    Orientation(Option<Orientation>),
+    PrefersColorScheme(Option<ColorScheme>),
+}
+
+/// https://www.w3.org/TR/mediaqueries-4/#orientation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+/// How the value of a feature compares to a value in a range context, as in
+/// `feature < value`.
+/// https://www.w3.org/TR/mediaqueries-4/#mq-range-context
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+impl Comparison {
+    /// Returns the comparison with the operands swapped, for `value < feature`.
+    fn flip(self) -> Self {
+        match self {
+            Comparison::Lt => Comparison::Gt,
+            Comparison::Le => Comparison::Ge,
+            Comparison::Eq => Comparison::Eq,
+            Comparison::Ge => Comparison::Le,
+            Comparison::Gt => Comparison::Lt,
+        }
+    }
+
+    fn holds(self, feature: f64, value: f64) -> bool {
+        match self {
+            Comparison::Lt => feature < value,
+            Comparison::Le => feature <= value,
+            Comparison::Eq => feature == value,
+            Comparison::Ge => feature >= value,
+            Comparison::Gt => feature > value,
+        }
+    }
+}
+
+impl MediaQueryList {
+    /// Parses a media query list. Invalid media queries in it never match.
+    /// https://www.w3.org/TR/mediaqueries-4/#mq-syntax
+    pub fn parse(media: &str) -> Self {
+        let values =
+            CssParser::new(CssTokenizer::new(String::from(media))).parse_component_values();
+        if values.iter().all(is_whitespace) {
+            return Self {
+                queries: Vec::new(),
+            };
+        }
+        let queries = values
+            .split(|v| *v == ComponentValue::Token(CssToken::Comma))
+            .map(|query| {
+                let components: Vec<&ComponentValue> =
+                    query.iter().filter(|v| !is_whitespace(v)).collect();
+                // https://www.w3.org/TR/mediaqueries-4/#error-handling
+                // An invalid media query is replaced with `not all`.
+                parse_media_query(&components).unwrap_or(MediaQuery {
+                    not: true,
+                    media_type: MediaType::All,
+                    condition: None,
+                })
+            })
+            .collect();
+        Self { queries }
+    }
+
+    /// Returns true if any media query in the list matches `viewport`. An
+    /// empty list always matches.
+    /// https://www.w3.org/TR/mediaqueries-4/#mq-list
+    pub fn matches(&self, viewport: &Viewport) -> bool {
+        self.queries.is_empty() || self.queries.iter().any(|q| q.matches(viewport))
+    }
+}
+
+impl MediaQuery {
+    /// https://www.w3.org/TR/mediaqueries-4/#evaluating
+    /// A result that is unknown is false.
+    fn matches(&self, viewport: &Viewport) -> bool {
+        let media_type = match self.media_type {
+            MediaType::All => true,
+            MediaType::Unknown => false,
+            media_type => media_type == viewport.media_type,
+        };
+        let result = match &self.condition {
+            Some(condition) => and(Some(media_type), condition.evaluate(viewport)),
+            None => Some(media_type),
+        };
+        let result = if self.not { result.map(|r| !r) } else { result };
+        result.unwrap_or(false)
+    }
+}
+
+/// Three-valued `and`, where `None` is unknown.
+fn and(a: Option<bool>, b: Option<bool>) -> Option<bool> {
+    match (a, b) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
+
+impl MediaCondition {
+    /// Returns `None` if the result is unknown.
+    fn evaluate(&self, viewport: &Viewport) -> Option<bool> {
+        match self {
+            MediaCondition::Not(c) => c.evaluate(viewport).map(|r| !r),
+            MediaCondition::And(cs) => {
+                let results: Vec<Option<bool>> = cs.iter().map(|c| c.evaluate(viewport)).collect();
+                match results {
+                    r if r.contains(&Some(false)) => Some(false),
+                    r if r.contains(&None) => None,
+                    _ => Some(true),
+                }
+            }
+            MediaCondition::Or(cs) => {
+                let results: Vec<Option<bool>> = cs.iter().map(|c| c.evaluate(viewport)).collect();
+                match results {
+                    r if r.contains(&Some(true)) => Some(true),
+                    r if r.contains(&None) => None,
+                    _ => Some(false),
+                }
+            }
+            MediaCondition::Feature(f) => Some(f.evaluate(viewport)),
+            MediaCondition::Unknown => None,
+        }
+    }
+}
+
+impl MediaFeature {
+    fn evaluate(&self, viewport: &Viewport) -> bool {
+        // https://www.w3.org/TR/mediaqueries-4/#units
+        // Relative lengths are based on the initial font size.
+        let context = LengthContext {
+            viewport_width: viewport.width,
+            viewport_height: viewport.height,
+            ..LengthContext::default()
+        };
+        let lengths = |value: f64, comparisons: &[(Comparison, Calc)]| {
+            // https://www.w3.org/TR/mediaqueries-4/#mq-boolean-context
+            // A feature alone is true unless it is zero.
+            if comparisons.is_empty() {
+                return value != 0.0;
+            }
+            comparisons
+                .iter()
+                .all(|(c, calc)| c.holds(value, calc.evaluate(&context, 0.0)))
+        };
+        match self {
+            MediaFeature::Width(comparisons) => lengths(viewport.width, comparisons),
+            MediaFeature::Height(comparisons) => lengths(viewport.height, comparisons),
+            MediaFeature::Resolution(comparisons) => {
+                comparisons.is_empty()
+                    || comparisons
+                        .iter()
+                        .all(|(c, dppx)| c.holds(viewport.resolution, *dppx))
+            }
+            MediaFeature::Orientation(orientation) => {
+                // https://www.w3.org/TR/mediaqueries-4/#orientation
+                // A square viewport is in portrait orientation.
+                let actual = if viewport.height >= viewport.width {
+                    Orientation::Portrait
+                } else {
+                    Orientation::Landscape
+                };
+                orientation.map_or(true, |o| o == actual)
+            }
+            MediaFeature::PrefersColorScheme(scheme) => {
+                scheme.map_or(true, |s| s == viewport.color_scheme)
+            }
+        }
+    }
+}
+
+fn is_whitespace(value: &ComponentValue) -> bool {
+    *value == ComponentValue::Token(CssToken::Whitespace)
+}
+
+fn ident(value: &ComponentValue) -> Option<String> {
+    match value {
+        ComponentValue::Token(CssToken::Ident(ident)) => Some(ident.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+/// `<media-query> = <media-condition> | [ not | only ]? <media-type>
+/// [ and <media-condition-without-or> ]?`
+fn parse_media_query(components: &[&ComponentValue]) -> Option<MediaQuery> {
+    let first = ident(components.first()?);
+    let is_type_query = match first.as_deref() {
+        // `not (...)` is a media condition.
+        Some("not") => components.get(1).and_then(|c| ident(c)).is_some(),
+        Some(_) => true,
+        None => false,
+    };
+    if !is_type_query {
+        return Some(MediaQuery {
+            not: false,
+            media_type: MediaType::All,
+            condition: Some(parse_media_condition(components, true)?),
+        });
+    }
+
+    let (not, rest) = match first.as_deref() {
+        Some("not") => (true, &components[1..]),
+        Some("only") => (false, &components[1..]),
+        _ => (false, components),
+    };
+    let media_type = match ident(rest.first()?)?.as_str() {
+        "all" => MediaType::All,
+        "screen" => MediaType::Screen,
+        "print" => MediaType::Print,
+        "only" | "not" | "and" | "or" | "layer" => return None,
+        _ => MediaType::Unknown,
+    };
+    let condition = match rest.get(1) {
+        None => None,
+        Some(c) if ident(c).as_deref() == Some("and") => {
+            Some(parse_media_condition(&rest[2..], false)?)
+        }
+        Some(_) => return None,
+    };
+    Some(MediaQuery {
+        not,
+        media_type,
+        condition,
+    })
+}
+
+/// https://www.w3.org/TR/mediaqueries-4/#typedef-media-condition
+fn parse_media_condition(components: &[&ComponentValue], allow_or: bool) -> Option<MediaCondition> {
+    let first = components.first()?;
+    if ident(first).as_deref() == Some("not") {
+        return match components {
+            [_, in_parens] => Some(MediaCondition::Not(Box::new(parse_media_in_parens(
+                in_parens,
+            )?))),
+            _ => None,
+        };
+    }
+
+    // `and` and `or` can't be mixed without parentheses.
+    let mut conditions = alloc::vec![parse_media_in_parens(first)?];
+    let mut operator: Option<String> = None;
+    for chunk in components[1..].chunks(2) {
+        let [keyword, in_parens] = chunk else {
+            return None;
+        };
+        let keyword = ident(keyword)?;
+        let is_valid = keyword == "and" || (keyword == "or" && allow_or);
+        if !is_valid || operator.as_ref().is_some_and(|o| *o != keyword) {
+            return None;
+        }
+        operator = Some(keyword);
+        conditions.push(parse_media_in_parens(in_parens)?);
+    }
+    Some(match operator.as_deref() {
+        None => conditions.remove(0),
+        Some("and") => MediaCondition::And(conditions),
+        _ => MediaCondition::Or(conditions),
+    })
+}
+
+/// `<media-in-parens> = ( <media-condition> ) | <media-feature> |
+/// <general-enclosed>`
+fn parse_media_in_parens(value: &ComponentValue) -> Option<MediaCondition> {
+    let inner = match value {
+        ComponentValue::Block { open: '(', value } => value,
+        ComponentValue::Function { .. } => return Some(MediaCondition::Unknown),
+        _ => return None,
+    };
+    let components: Vec<&ComponentValue> = inner.iter().filter(|v| !is_whitespace(v)).collect();
+    if let Some(condition) = parse_media_condition(&components, true) {
+        return Some(condition);
+    }
+    Some(parse_media_feature(&components).map_or(MediaCondition::Unknown, MediaCondition::Feature))
+}
+
+/// Parses what's in the parentheses of a media feature. Returns `None` if
+/// the feature or its value isn't supported.
+/// https://www.w3.org/TR/mediaqueries-4/#typedef-media-feature
+fn parse_media_feature(components: &[&ComponentValue]) -> Option<MediaFeature> {
+    match components {
+        // https://www.w3.org/TR/mediaqueries-4/#mq-boolean-context
+        [name] => feature(&ident(name)?, Vec::new()),
+        // https://www.w3.org/TR/mediaqueries-4/#mq-min-max
+        [name, ComponentValue::Token(CssToken::Colon), value @ ..] => {
+            let name = ident(name)?;
+            let (name, comparison) = match name.strip_prefix("min-") {
+                Some(name) => (name, Comparison::Ge),
+                None => match name.strip_prefix("max-") {
+                    Some(name) => (name, Comparison::Le),
+                    None => (name.as_str(), Comparison::Eq),
+                },
+            };
+            if comparison != Comparison::Eq && !is_range_feature(name) {
+                return None;
+            }
+            feature(name, alloc::vec![(comparison, *value.first()?)]).filter(|_| value.len() == 1)
+        }
+        _ => parse_range(components),
+    }
+}
+
+fn is_range_feature(name: &str) -> bool {
+    matches!(name, "width" | "height" | "resolution")
+}
+
+/// Parses `name op value`, `value op name` or `value op name op value`.
+/// https://www.w3.org/TR/mediaqueries-4/#mq-range-context
+fn parse_range(components: &[&ComponentValue]) -> Option<MediaFeature> {
+    // Splits the components into operands and operators.
+    let mut operands: Vec<&ComponentValue> = Vec::new();
+    let mut operators = Vec::new();
+    let mut i = 0;
+    while i < components.len() {
+        let delim = |j: usize| match components.get(j) {
+            Some(ComponentValue::Token(CssToken::Delim(c))) => Some(*c),
+            _ => None,
+        };
+        let operator = match (delim(i), delim(i + 1)) {
+            (Some('<'), Some('=')) => Some(Comparison::Le),
+            (Some('>'), Some('=')) => Some(Comparison::Ge),
+            (Some('<'), _) => Some(Comparison::Lt),
+            (Some('>'), _) => Some(Comparison::Gt),
+            (Some('='), _) => Some(Comparison::Eq),
+            _ => None,
+        };
+        match operator {
+            Some(operator) => {
+                if operators.len() != operands.len().checked_sub(1)? {
+                    return None;
+                }
+                operators.push(operator);
+                i += if matches!(operator, Comparison::Le | Comparison::Ge) {
+                    2
+                } else {
+                    1
+                };
+            }
+            None => {
+                if operands.len() != operators.len() {
+                    return None;
+                }
+                operands.push(components[i]);
+                i += 1;
+            }
+        }
+    }
+
+    match (operands.as_slice(), operators.as_slice()) {
+        ([a, b], [op]) => match ident(a) {
+            Some(name) if is_range_feature(&name) => feature(&name, alloc::vec![(*op, *b)]),
+            _ => feature(&ident(b)?, alloc::vec![(op.flip(), *a)]),
+        },
+        // https://www.w3.org/TR/mediaqueries-4/#mq-range-context
+        // Both operators must point the same way.
+        ([a, name, b], [op1, op2]) => {
+            let is_less = |op: &Comparison| matches!(op, Comparison::Lt | Comparison::Le);
+            let is_greater = |op: &Comparison| matches!(op, Comparison::Gt | Comparison::Ge);
+            if !(is_less(op1) && is_less(op2) || is_greater(op1) && is_greater(op2)) {
+                return None;
+            }
+            feature(&ident(name)?, alloc::vec![(op1.flip(), *a), (*op2, *b)])
+        }
+        _ => None,
+    }
+}
+
+/// Builds the feature `name` compared to each value in `comparisons`. An
+/// empty list is for a boolean context.
+fn feature(name: &str, comparisons: Vec<(Comparison, &ComponentValue)>) -> Option<MediaFeature> {
+    let length = |value: &ComponentValue| {
+        let calc = Calc::parse_length_percentage(core::slice::from_ref(value))?;
+        // Percentages aren't allowed.
+        match calc.compute(&LengthContext::default()) {
+            LengthPercentage::Length(_) => Some(calc),
+            _ => None,
+        }
+    };
+    let keyword = |comparisons: &[(Comparison, &ComponentValue)]| match comparisons {
+        [] => Some(None),
+        [(Comparison::Eq, value)] => ident(value).map(Some),
+        _ => None,
+    };
+
+    let feature = match name {
+        "width" | "height" => {
+            let comparisons = comparisons
+                .into_iter()
+                .map(|(c, v)| Some((c, length(v)?)))
+                .collect::<Option<Vec<_>>>()?;
+            if name == "width" {
+                MediaFeature::Width(comparisons)
+            } else {
+                MediaFeature::Height(comparisons)
+            }
+        }
+        // https://www.w3.org/TR/css-values-4/#resolution
+        "resolution" => MediaFeature::Resolution(
+            comparisons
+                .into_iter()
+                .map(|(c, v)| match v {
+                    ComponentValue::Token(CssToken::Dimension { value, unit, .. }) => {
+                        let dppx = match unit.to_ascii_lowercase().as_str() {
+                            "dppx" | "x" => *value,
+                            "dpi" => value / 96.0,
+                            "dpcm" => value * 2.54 / 96.0,
+                            _ => return None,
+                        };
+                        Some((c, dppx))
+                    }
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?,
+        ),
+        "orientation" => MediaFeature::Orientation(match keyword(&comparisons)? {
+            None => None,
+            Some(k) if k == "portrait" => Some(Orientation::Portrait),
+            Some(k) if k == "landscape" => Some(Orientation::Landscape),
+            Some(_) => return None,
+        }),
+        "prefers-color-scheme" => MediaFeature::PrefersColorScheme(match keyword(&comparisons)? {
+            None => None,
+            Some(k) if k == "light" => Some(ColorScheme::Light),
+            Some(k) if k == "dark" => Some(ColorScheme::Dark),
+            Some(_) => return None,
+        }),
+        _ => return None,
+    };
+    Some(feature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(media: &str, viewport: &Viewport) -> bool {
+        MediaQueryList::parse(media).matches(viewport)
+    }
+
+    #[test]
+    fn test_media_types() {
+        let screen = Viewport::default();
+        let print = Viewport {
+            media_type: MediaType::Print,
+            ..Viewport::default()
+        };
+        assert!(matches("", &screen));
+        assert!(matches("all", &screen));
+        assert!(matches("SCREEN", &screen));
+        assert!(!matches("print", &screen));
+        assert!(matches("print", &print));
+        assert!(matches("only screen", &screen));
+        assert!(matches("not print", &screen));
+        assert!(!matches("not screen", &screen));
+        assert!(!matches("tv", &screen));
+        assert!(matches("print, screen", &screen));
+        // Invalid media queries are `not all`, but don't affect the others.
+        assert!(!matches("screen print", &screen));
+        assert!(!matches("only", &screen));
+        assert!(matches("screen print, screen", &screen));
+    }
+
+    #[test]
+    fn test_media_features() {
+        let viewport = Viewport {
+            width: 600.0,
+            height: 400.0,
+            color_scheme: ColorScheme::Dark,
+            resolution: 2.0,
+            ..Viewport::default()
+        };
+        assert!(matches("(min-width: 600px)", &viewport));
+        assert!(!matches("(min-width: 601px)", &viewport));
+        assert!(matches("(max-width: 37.5em)", &viewport));
+        assert!(matches("(width: 600px)", &viewport));
+        assert!(matches("(width)", &viewport));
+        assert!(matches("screen and (max-height: 400px)", &viewport));
+        assert!(!matches("print and (max-height: 400px)", &viewport));
+        assert!(matches("not print and (max-height: 400px)", &viewport));
+        assert!(matches("(orientation: landscape)", &viewport));
+        assert!(!matches("(orientation: portrait)", &viewport));
+        assert!(matches("(prefers-color-scheme: dark)", &viewport));
+        assert!(!matches("(prefers-color-scheme: light)", &viewport));
+        assert!(matches("(min-resolution: 2dppx)", &viewport));
+        assert!(matches("(resolution: 192dpi)", &viewport));
+        assert!(!matches("(min-resolution: 3x)", &viewport));
+
+        // Range syntax.
+        assert!(matches("(width >= 600px)", &viewport));
+        assert!(!matches("(width > 600px)", &viewport));
+        assert!(matches("(500px < width <= 600px)", &viewport));
+        assert!(!matches("(500px < width < 600px)", &viewport));
+        assert!(matches("(700px > width)", &viewport));
+        assert!(matches("(height < calc(300px + 101px))", &viewport));
+        assert!(!matches("(500px < width > 400px)", &viewport));
+
+        // Conditions.
+        assert!(matches(
+            "(min-width: 500px) and (max-width: 700px)",
+            &viewport
+        ));
+        assert!(matches(
+            "(max-width: 500px) or (orientation: landscape)",
+            &viewport
+        ));
+        assert!(matches("not (max-width: 500px)", &viewport));
+        assert!(matches(
+            "((width > 1px) and (not (width > 1000px)))",
+            &viewport
+        ));
+        assert!(!matches(
+            "(width > 1px) and (height > 1px) or (color)",
+            &viewport
+        ));
+        assert!(!matches(
+            "screen and (width > 1px) or (height > 1px)",
+            &viewport
+        ));
+
+        // Unknown features are neither true nor false, even under `not`.
+        assert!(!matches("(hover: hover)", &viewport));
+        assert!(!matches("not (hover: hover)", &viewport));
+        assert!(!matches("(min-width: 50%)", &viewport));
+        assert!(matches("(hover: hover) or (width)", &viewport));
+        assert!(!matches("(min-orientation: portrait)", &viewport));
+    }
+}
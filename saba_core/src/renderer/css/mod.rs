@@ -0,0 +1,13 @@
+pub mod cascade;
+pub mod color;
+pub mod computed_style;
+pub mod content;
+pub mod invalidation;
+pub mod length;
+pub mod media;
+pub mod parser;
+pub mod properties;
+pub mod shorthand;
+pub mod stylesheet;
+pub mod token;
+pub mod variables;
@@ -0,0 +1,604 @@
+//! https://www.w3.org/TR/css-syntax-3/#parsing
+
+use crate::renderer::css::properties::is_supported_property;
+use crate::renderer::css::stylesheet::ComponentValue;
+use crate::renderer::css::stylesheet::CssRule;
+use crate::renderer::css::stylesheet::Declaration;
+use crate::renderer::css::stylesheet::FontFaceRule;
+use crate::renderer::css::stylesheet::ImportRule;
+use crate::renderer::css::stylesheet::MediaRule;
+use crate::renderer::css::stylesheet::StyleRule;
+use crate::renderer::css::stylesheet::StyleSheet;
+use crate::renderer::css::stylesheet::SupportsRule;
+use crate::renderer::css::token::CssToken;
+use crate::renderer::css::token::CssTokenizer;
+use crate::renderer::dom::selector::SelectorList;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+/// Builds a `StyleSheet` from the tokens of a `CssTokenizer`.
+///
+/// Invalid rules and declarations are dropped following the error handling
+/// of the spec, so parsing never fails.
+pub struct CssParser {
+    tokens: Vec<CssToken>,
+    /// The offsets in `input` where each token starts and ends.
+    spans: Vec<(usize, usize)>,
+    input: Vec<char>,
+    pos: usize,
+}
+
+impl CssParser {
+    pub fn new(mut t: CssTokenizer) -> Self {
+        let mut tokens = Vec::new();
+        let mut spans = Vec::new();
+        while let Some(token) = t.next() {
+            tokens.push(token);
+            spans.push(t.span());
+        }
+        Self {
+            tokens,
+            spans,
+            input: t.input().to_vec(),
+            pos: 0,
+        }
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#parse-a-stylesheet
+    pub fn parse_stylesheet(&mut self) -> StyleSheet {
+        let mut rules = self.consume_rules(true);
+
+        // https://www.w3.org/TR/css-cascade-4/#at-import
+        // `@import` rules after any other rule are invalid.
+        let first_other = rules
+            .iter()
+            .position(|r| !matches!(r, CssRule::Import(_)))
+            .unwrap_or(rules.len());
+        let mut i = first_other;
+        while i < rules.len() {
+            if matches!(rules[i], CssRule::Import(_)) {
+                rules.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+
+        StyleSheet {
+            rules,
+            href: None,
+            media: String::new(),
+        }
+    }
+
+    /// Parses the contents of a `style` attribute.
+    /// https://www.w3.org/TR/css-syntax-3/#parse-a-list-of-declarations
+    pub fn parse_declarations(&mut self) -> Vec<Declaration> {
+        self.consume_declarations(true)
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#parse-a-list-of-component-values
+    pub fn parse_component_values(&mut self) -> Vec<ComponentValue> {
+        let mut values = Vec::new();
+        while self.peek().is_some() {
+            values.push(self.consume_component_value());
+        }
+        values
+    }
+
+    fn peek(&self) -> Option<&CssToken> {
+        self.tokens.get(self.pos)
+    }
+
+    fn consume(&mut self) -> Option<CssToken> {
+        let token = self.tokens.get(self.pos).cloned();
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    /// Returns the source text of `tokens[start..end]` without comments and
+    /// surrounding whitespace.
+    fn source_text(&self, start: usize, end: usize) -> String {
+        let text: String = self.spans[start..end]
+            .iter()
+            .flat_map(|(s, e)| self.input[*s..*e].iter())
+            .collect();
+        text.trim_matches(|c: char| c.is_ascii_whitespace())
+            .to_string()
+    }
+
+    /// Consumes rules until EOF, or until the `}` that closes the block of
+    /// an enclosing rule if `top_level` is false.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-list-of-rules
+    fn consume_rules(&mut self, top_level: bool) -> Vec<CssRule> {
+        let mut rules = Vec::new();
+        loop {
+            match self.peek() {
+                None => return rules,
+                Some(CssToken::Whitespace) => {
+                    self.pos += 1;
+                }
+                Some(CssToken::Cdo) | Some(CssToken::Cdc) if top_level => {
+                    self.pos += 1;
+                }
+                Some(CssToken::CloseCurlyBracket) if !top_level => {
+                    self.pos += 1;
+                    return rules;
+                }
+                Some(CssToken::AtKeyword(_)) => {
+                    if let Some(rule) = self.consume_at_rule(top_level) {
+                        rules.push(rule);
+                    }
+                }
+                Some(_) => {
+                    if let Some(rule) = self.consume_qualified_rule(top_level) {
+                        rules.push(rule);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Consumes a prelude up to the `{` of a block, a `;` if `at_rule` is
+    /// true, or the `}` of an enclosing block if `top_level` is false. None
+    /// of those is consumed. Returns the range of the prelude's tokens.
+    fn consume_prelude(&mut self, at_rule: bool, top_level: bool) -> (usize, usize) {
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                None | Some(CssToken::OpenCurlyBracket) => break,
+                Some(CssToken::Semicolon) if at_rule => break,
+                Some(CssToken::CloseCurlyBracket) if !top_level => break,
+                _ => {
+                    self.consume_component_value();
+                }
+            }
+        }
+        (start, self.pos)
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#consume-an-at-rule
+    fn consume_at_rule(&mut self, top_level: bool) -> Option<CssRule> {
+        let name = match self.consume() {
+            Some(CssToken::AtKeyword(name)) => name.to_ascii_lowercase(),
+            _ => return None,
+        };
+        let (start, end) = self.consume_prelude(true, top_level);
+        let prelude = self.source_text(start, end);
+
+        // Consume the `{` or `;` that ends the prelude.
+        let has_block = self.peek() == Some(&CssToken::OpenCurlyBracket);
+        if matches!(
+            self.peek(),
+            Some(CssToken::OpenCurlyBracket) | Some(CssToken::Semicolon)
+        ) {
+            self.pos += 1;
+        }
+
+        match (name.as_str(), has_block) {
+            ("media", true) => Some(CssRule::Media(MediaRule {
+                media: prelude,
+                rules: self.consume_rules(false),
+            })),
+            ("supports", true) => Some(CssRule::Supports(SupportsRule {
+                condition: prelude,
+                rules: self.consume_rules(false),
+            })),
+            ("font-face", true) => Some(CssRule::FontFace(FontFaceRule {
+                declarations: self.consume_declarations(false),
+            })),
+            ("import", false) if top_level => self.import_rule(start, end),
+            (_, true) => {
+                // Unknown at-rules are dropped along with their blocks.
+                self.consume_block_contents(CssToken::CloseCurlyBracket);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Interprets the prelude `tokens[start..end]` of an `@import` rule.
+    /// https://www.w3.org/TR/css-cascade-4/#at-import
+    fn import_rule(&self, start: usize, end: usize) -> Option<CssRule> {
+        let mut i = start;
+        while i < end && self.tokens[i] == CssToken::Whitespace {
+            i += 1;
+        }
+        let url = match self.tokens.get(i).filter(|_| i < end)? {
+            CssToken::String(url) | CssToken::Url(url) => {
+                i += 1;
+                url.clone()
+            }
+            CssToken::Function(name) if name.eq_ignore_ascii_case("url") => {
+                // `url("...")` is a function whose argument is a string.
+                let mut j = i + 1;
+                while j < end && self.tokens[j] == CssToken::Whitespace {
+                    j += 1;
+                }
+                let url = match self.tokens.get(j).filter(|_| j < end)? {
+                    CssToken::String(url) => url.clone(),
+                    _ => return None,
+                };
+                j += 1;
+                while j < end && self.tokens[j] == CssToken::Whitespace {
+                    j += 1;
+                }
+                if j >= end || self.tokens[j] != CssToken::CloseParenthesis {
+                    return None;
+                }
+                i = j + 1;
+                url
+            }
+            _ => return None,
+        };
+
+        Some(CssRule::Import(ImportRule {
+            url,
+            media: self.source_text(i, end),
+            style_sheet: None,
+        }))
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#consume-a-qualified-rule
+    fn consume_qualified_rule(&mut self, top_level: bool) -> Option<CssRule> {
+        let (start, end) = self.consume_prelude(false, top_level);
+        // Without a block, the prelude is dropped.
+        if self.peek() != Some(&CssToken::OpenCurlyBracket) {
+            return None;
+        }
+        self.pos += 1;
+
+        let declarations = self.consume_declarations(true);
+        // A rule with an invalid selector is dropped as a whole.
+        let selectors = SelectorList::parse_tokens(&self.tokens[start..end]).ok()?;
+        Some(CssRule::Style(StyleRule {
+            selectors,
+            declarations,
+        }))
+    }
+
+    /// Consumes declarations up to and including the `}` that closes the
+    /// block. If `properties_only` is true, declarations of unsupported
+    /// properties are dropped.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-a-list-of-declarations
+    fn consume_declarations(&mut self, properties_only: bool) -> Vec<Declaration> {
+        let mut declarations = Vec::new();
+        loop {
+            match self.peek() {
+                None => return declarations,
+                Some(CssToken::CloseCurlyBracket) => {
+                    self.pos += 1;
+                    return declarations;
+                }
+                Some(CssToken::Whitespace) | Some(CssToken::Semicolon) => {
+                    self.pos += 1;
+                }
+                Some(CssToken::AtKeyword(_)) => {
+                    // At-rules aren't allowed in declaration lists.
+                    self.consume_at_rule(false);
+                }
+                Some(CssToken::Ident(_)) => {
+                    let value = self.consume_declaration_value();
+                    if let Some(declaration) = consume_declaration(value) {
+                        if !properties_only || is_supported_property(&declaration.name) {
+                            declarations.push(declaration);
+                        }
+                    }
+                }
+                Some(_) => {
+                    // Anything else is a parse error, and is skipped up to
+                    // the next declaration.
+                    self.consume_declaration_value();
+                }
+            }
+        }
+    }
+
+    /// Consumes component values up to a `;` or the `}` that closes the
+    /// block, neither of which is consumed.
+    fn consume_declaration_value(&mut self) -> Vec<ComponentValue> {
+        let mut values = Vec::new();
+        while !matches!(
+            self.peek(),
+            None | Some(CssToken::Semicolon) | Some(CssToken::CloseCurlyBracket)
+        ) {
+            values.push(self.consume_component_value());
+        }
+        values
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#consume-a-component-value
+    fn consume_component_value(&mut self) -> ComponentValue {
+        let token = match self.consume() {
+            Some(token) => token,
+            None => return ComponentValue::Token(CssToken::Whitespace),
+        };
+        let (open, close) = match token {
+            CssToken::OpenCurlyBracket => ('{', CssToken::CloseCurlyBracket),
+            CssToken::OpenSquareBracket => ('[', CssToken::CloseSquareBracket),
+            CssToken::OpenParenthesis => ('(', CssToken::CloseParenthesis),
+            CssToken::Function(name) => {
+                return ComponentValue::Function {
+                    name,
+                    value: self.consume_block_contents(CssToken::CloseParenthesis),
+                };
+            }
+            token => return ComponentValue::Token(token),
+        };
+        ComponentValue::Block {
+            open,
+            value: self.consume_block_contents(close),
+        }
+    }
+
+    /// Consumes component values up to and including `close`, or EOF.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-a-simple-block
+    fn consume_block_contents(&mut self, close: CssToken) -> Vec<ComponentValue> {
+        let mut values = Vec::new();
+        loop {
+            match self.peek() {
+                None => return values,
+                Some(token) if *token == close => {
+                    self.pos += 1;
+                    return values;
+                }
+                _ => values.push(self.consume_component_value()),
+            }
+        }
+    }
+}
+
+/// Builds a declaration from the component values between two `;`s, which
+/// start with an ident.
+/// https://www.w3.org/TR/css-syntax-3/#consume-a-declaration
+fn consume_declaration(mut values: Vec<ComponentValue>) -> Option<Declaration> {
+    let is_whitespace = |v: &ComponentValue| *v == ComponentValue::Token(CssToken::Whitespace);
+
+    let mut rest = values.drain(..);
+    let name = match rest.next() {
+        Some(ComponentValue::Token(CssToken::Ident(name))) => name,
+        _ => return None,
+    };
+    let mut rest = rest.skip_while(is_whitespace);
+    if rest.next() != Some(ComponentValue::Token(CssToken::Colon)) {
+        return None;
+    }
+    let mut value: Vec<ComponentValue> = rest.skip_while(is_whitespace).collect();
+
+    while value.last().is_some_and(is_whitespace) {
+        value.pop();
+    }
+    let mut important = false;
+    if let Some(ComponentValue::Token(CssToken::Ident(ident))) = value.last() {
+        if ident.eq_ignore_ascii_case("important") {
+            let bang = value[..value.len() - 1]
+                .iter()
+                .rposition(|v| !is_whitespace(v))
+                .filter(|i| value[*i] == ComponentValue::Token(CssToken::Delim('!')));
+            if let Some(bang) = bang {
+                value.truncate(bang);
+                important = true;
+                while value.last().is_some_and(is_whitespace) {
+                    value.pop();
+                }
+            }
+        }
+    }
+
+    // Custom property names are case-sensitive.
+    let name = if name.starts_with("--") {
+        name
+    } else {
+        name.to_ascii_lowercase()
+    };
+    Some(Declaration {
+        name,
+        value,
+        important,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn parse(css: &str) -> StyleSheet {
+        StyleSheet::parse(css)
+    }
+
+    fn ident(s: &str) -> ComponentValue {
+        ComponentValue::Token(CssToken::Ident(s.to_string()))
+    }
+
+    fn style_rule(rule: &CssRule) -> &StyleRule {
+        match rule {
+            CssRule::Style(rule) => rule,
+            r => panic!("unexpected rule {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        assert!(parse("").rules.is_empty());
+        assert!(parse(" <!-- /* comment */ --> ").rules.is_empty());
+    }
+
+    #[test]
+    fn test_style_rules() {
+        let sheet = parse("h1, .title > a { color: red; font-weight : BOLD !IMPORTANT ; }\np{}");
+        assert_eq!(2, sheet.rules.len());
+
+        let rule = style_rule(&sheet.rules[0]);
+        assert_eq!(
+            SelectorList::parse("h1, .title > a").unwrap(),
+            rule.selectors
+        );
+        assert_eq!(
+            vec![
+                Declaration {
+                    name: "color".to_string(),
+                    value: vec![ident("red")],
+                    important: false,
+                },
+                Declaration {
+                    name: "font-weight".to_string(),
+                    value: vec![ident("BOLD")],
+                    important: true,
+                },
+            ],
+            rule.declarations
+        );
+        assert!(style_rule(&sheet.rules[1]).declarations.is_empty());
+    }
+
+    #[test]
+    fn test_declaration_values() {
+        let sheet = parse("p { margin: 0 calc(1px + 2%) ; --Main-Color: { a b } ; width:}");
+        let declarations = &style_rule(&sheet.rules[0]).declarations;
+        assert_eq!(3, declarations.len());
+        assert_eq!(
+            vec![
+                ComponentValue::Token(CssToken::Number {
+                    value: 0.0,
+                    is_integer: true,
+                }),
+                ComponentValue::Token(CssToken::Whitespace),
+                ComponentValue::Function {
+                    name: "calc".to_string(),
+                    value: vec![
+                        ComponentValue::Token(CssToken::Dimension {
+                            value: 1.0,
+                            is_integer: true,
+                            unit: "px".to_string(),
+                        }),
+                        ComponentValue::Token(CssToken::Whitespace),
+                        ComponentValue::Token(CssToken::Delim('+')),
+                        ComponentValue::Token(CssToken::Whitespace),
+                        ComponentValue::Token(CssToken::Percentage(2.0)),
+                    ],
+                },
+            ],
+            declarations[0].value
+        );
+        assert_eq!("--Main-Color", declarations[1].name);
+        assert_eq!(
+            vec![ComponentValue::Block {
+                open: '{',
+                value: vec![
+                    ComponentValue::Token(CssToken::Whitespace),
+                    ident("a"),
+                    ComponentValue::Token(CssToken::Whitespace),
+                    ident("b"),
+                    ComponentValue::Token(CssToken::Whitespace),
+                ],
+            }],
+            declarations[1].value
+        );
+        assert_eq!("width", declarations[2].name);
+        assert!(declarations[2].value.is_empty());
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let sheet = parse(
+            "p { colour: red; color red; 12: x; color: blue; @nested { a: b } ; display: block }
+             h1 } { color: red }
+             ##bad { color: red }
+             a { color: green; [ ; } ] ; width: 1px }
+             @unknown foo { p { color: red } }
+             b { color: black",
+        );
+        let rules: Vec<&StyleRule> = sheet.rules.iter().map(style_rule).collect();
+        assert_eq!(3, rules.len());
+
+        let names = |rule: &StyleRule| -> Vec<String> {
+            rule.declarations.iter().map(|d| d.name.clone()).collect()
+        };
+        assert_eq!(vec!["color", "display"], names(rules[0]));
+        assert_eq!(vec![ident("blue")], rules[0].declarations[0].value);
+        // The `}` inside `[]` doesn't close the block.
+        assert_eq!(vec!["color", "width"], names(rules[1]));
+        // An unclosed block ends at EOF.
+        assert_eq!(SelectorList::parse("b").unwrap(), rules[2].selectors);
+        assert_eq!(vec!["color"], names(rules[2]));
+    }
+
+    #[test]
+    fn test_selectors_from_tokens() {
+        // A comment separates tokens, so the two identifiers aren't joined.
+        let sheet = parse(".x/**/y { color: red } .a\\:b, p/**/> a { color: blue }");
+        assert_eq!(1, sheet.rules.len());
+        assert_eq!(
+            SelectorList::parse(".a\\:b, p > a").unwrap(),
+            style_rule(&sheet.rules[0]).selectors
+        );
+    }
+
+    #[test]
+    fn test_at_rules() {
+        let sheet = parse(
+            "@charset \"utf-8\";
+             @import url(a.css);
+             @import \"b.css\" screen and (color);
+             @import url( 'c.css' ) print;
+             @media screen and (min-width: 600px) { p { color: red } @media print { a {} } }
+             @font-face { font-family: Foo; src: url(foo.woff) }
+             @supports (display: grid) { p { display: block } }
+             @media print;
+             @import \"late.css\";",
+        );
+        assert_eq!(
+            vec![
+                CssRule::Import(ImportRule {
+                    url: "a.css".to_string(),
+                    media: String::new(),
+                    style_sheet: None,
+                }),
+                CssRule::Import(ImportRule {
+                    url: "b.css".to_string(),
+                    media: "screen and (color)".to_string(),
+                    style_sheet: None,
+                }),
+                CssRule::Import(ImportRule {
+                    url: "c.css".to_string(),
+                    media: "print".to_string(),
+                    style_sheet: None,
+                }),
+            ],
+            sheet.rules[..3]
+        );
+
+        match &sheet.rules[3] {
+            CssRule::Media(media) => {
+                assert_eq!("screen and (min-width: 600px)", media.media);
+                assert_eq!(2, media.rules.len());
+                assert!(matches!(&media.rules[1], CssRule::Media(m) if m.media == "print"));
+            }
+            r => panic!("unexpected rule {:?}", r),
+        }
+        match &sheet.rules[4] {
+            CssRule::FontFace(font_face) => {
+                // Descriptors aren't properties, so they are all kept.
+                let names: Vec<&str> = font_face
+                    .declarations
+                    .iter()
+                    .map(|d| d.name.as_str())
+                    .collect();
+                assert_eq!(vec!["font-family", "src"], names);
+            }
+            r => panic!("unexpected rule {:?}", r),
+        }
+        match &sheet.rules[5] {
+            CssRule::Supports(supports) => {
+                assert_eq!("(display: grid)", supports.condition);
+                assert_eq!(1, supports.rules.len());
+            }
+            r => panic!("unexpected rule {:?}", r),
+        }
+        // `@media` without a block and a late `@import` are dropped.
+        assert_eq!(6, sheet.rules.len());
+    }
+}
@@ -0,0 +1,375 @@
+//! The CSS properties supported by the renderer.
+//! https://www.w3.org/TR/CSS2/propidx.html
+
+/// A longhand property.
+/// https://www.w3.org/TR/css-cascade-4/#longhand
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Property {
+    pub name: &'static str,
+    /// https://www.w3.org/TR/css-cascade-4/#inherited-property
+    pub inherited: bool,
+    /// https://www.w3.org/TR/css-cascade-4/#initial-value
+    pub initial: &'static str,
+    /// What values the property accepts.
+    pub kind: ValueKind,
+}
+
+/// The grammar of a property's value.
+/// https://www.w3.org/TR/css-values-4/#value-defs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ValueKind {
+    /// One of the keywords.
+    Keyword(&'static [&'static str]),
+    /// https://www.w3.org/TR/css-color-4/#typedef-color
+    Color,
+    /// A `<length-percentage>` or one of the keywords.
+    LengthPercentage(&'static [&'static str]),
+    /// A non-negative `<length-percentage>` or one of the keywords.
+    NonNegativeLengthPercentage(&'static [&'static str]),
+    /// https://www.w3.org/TR/css-backgrounds-3/#typedef-line-width
+    LineWidth,
+    /// https://www.w3.org/TR/css-fonts-4/#font-size-prop
+    FontSize,
+    /// https://www.w3.org/TR/css-fonts-4/#font-weight-prop
+    FontWeight,
+    /// https://www.w3.org/TR/CSS2/visudet.html#propdef-line-height
+    LineHeight,
+    /// https://www.w3.org/TR/css-fonts-4/#font-family-prop
+    FontFamily,
+    /// `none` or a URL.
+    /// https://www.w3.org/TR/css-images-3/#typedef-image
+    Image,
+    /// https://www.w3.org/TR/css-backgrounds-3/#typedef-bg-position
+    /// Only the one and two value forms are supported.
+    Position,
+    /// https://www.w3.org/TR/css-values-4/#number-value
+    NonNegativeNumber,
+    /// https://www.w3.org/TR/css-content-3/#content-property
+    Content,
+    /// https://www.w3.org/TR/css-content-3/#quotes-property
+    Quotes,
+    /// `none` or counter names, each followed by an optional integer whose
+    /// default is the value.
+    /// https://www.w3.org/TR/css-lists-3/#counter-properties
+    Counters(i32),
+    /// Any value. It is kept as component values.
+    Any,
+}
+
+const COLOR: ValueKind = ValueKind::Color;
+const BACKGROUND_ATTACHMENT: ValueKind = ValueKind::Keyword(&["scroll", "fixed", "local"]);
+const BACKGROUND_REPEAT: ValueKind = ValueKind::Keyword(&[
+    "repeat",
+    "repeat-x",
+    "repeat-y",
+    "no-repeat",
+    "space",
+    "round",
+]);
+const BOX_SIZING: ValueKind = ValueKind::Keyword(&["content-box", "border-box"]);
+const DISPLAY: ValueKind =
+    ValueKind::Keyword(&["block", "inline", "inline-block", "list-item", "none"]);
+const FONT_VARIANT: ValueKind = ValueKind::Keyword(&["normal", "small-caps"]);
+const FONT_STYLE: ValueKind = ValueKind::Keyword(&["normal", "italic", "oblique"]);
+const LIST_STYLE_POSITION: ValueKind = ValueKind::Keyword(&["inside", "outside"]);
+const LIST_STYLE_TYPE: ValueKind = ValueKind::Keyword(&[
+    "disc",
+    "circle",
+    "square",
+    "decimal",
+    "lower-alpha",
+    "upper-alpha",
+    "lower-roman",
+    "upper-roman",
+    "none",
+]);
+const TEXT_ALIGN: ValueKind =
+    ValueKind::Keyword(&["start", "end", "left", "right", "center", "justify"]);
+const TEXT_DECORATION: ValueKind =
+    ValueKind::Keyword(&["none", "underline", "overline", "line-through"]);
+const VERTICAL_ALIGN: ValueKind = ValueKind::Keyword(&["baseline", "middle", "top", "bottom"]);
+const VISIBILITY: ValueKind = ValueKind::Keyword(&["visible", "hidden", "collapse"]);
+const WHITE_SPACE: ValueKind =
+    ValueKind::Keyword(&["normal", "pre", "nowrap", "pre-wrap", "pre-line"]);
+const BORDER_STYLE: ValueKind = ValueKind::Keyword(&[
+    "none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset", "outset",
+]);
+const SIZE: ValueKind = ValueKind::NonNegativeLengthPercentage(&["auto"]);
+const MAX_SIZE: ValueKind = ValueKind::NonNegativeLengthPercentage(&["none"]);
+const FLEX_BASIS: ValueKind = ValueKind::NonNegativeLengthPercentage(&["auto", "content"]);
+const INSET: ValueKind = ValueKind::LengthPercentage(&["auto"]);
+const MARGIN: ValueKind = ValueKind::LengthPercentage(&["auto"]);
+const PADDING: ValueKind = ValueKind::NonNegativeLengthPercentage(&[]);
+
+const fn longhand(
+    name: &'static str,
+    inherited: bool,
+    initial: &'static str,
+    kind: ValueKind,
+) -> Property {
+    Property {
+        name,
+        inherited,
+        initial,
+        kind,
+    }
+}
+
+pub const LONGHANDS: &[Property] = &[
+    longhand(
+        "background-attachment",
+        false,
+        "scroll",
+        BACKGROUND_ATTACHMENT,
+    ),
+    longhand("background-color", false, "transparent", COLOR),
+    longhand("background-image", false, "none", ValueKind::Image),
+    longhand("background-position", false, "0% 0%", ValueKind::Position),
+    longhand("background-repeat", false, "repeat", BACKGROUND_REPEAT),
+    longhand("border-bottom-color", false, "currentcolor", COLOR),
+    longhand("border-bottom-style", false, "none", BORDER_STYLE),
+    longhand("border-bottom-width", false, "medium", ValueKind::LineWidth),
+    longhand("border-left-color", false, "currentcolor", COLOR),
+    longhand("border-left-style", false, "none", BORDER_STYLE),
+    longhand("border-left-width", false, "medium", ValueKind::LineWidth),
+    longhand("border-right-color", false, "currentcolor", COLOR),
+    longhand("border-right-style", false, "none", BORDER_STYLE),
+    longhand("border-right-width", false, "medium", ValueKind::LineWidth),
+    longhand("border-top-color", false, "currentcolor", COLOR),
+    longhand("border-top-style", false, "none", BORDER_STYLE),
+    longhand("border-top-width", false, "medium", ValueKind::LineWidth),
+    longhand("bottom", false, "auto", INSET),
+    longhand("box-sizing", false, "content-box", BOX_SIZING),
+    longhand("color", true, "black", COLOR),
+    longhand("content", false, "normal", ValueKind::Content),
+    longhand("counter-increment", false, "none", ValueKind::Counters(1)),
+    longhand("counter-reset", false, "none", ValueKind::Counters(0)),
+    longhand("counter-set", false, "none", ValueKind::Counters(0)),
+    longhand("display", false, "inline", DISPLAY),
+    longhand("flex-basis", false, "auto", FLEX_BASIS),
+    longhand("flex-grow", false, "0", ValueKind::NonNegativeNumber),
+    longhand("flex-shrink", false, "1", ValueKind::NonNegativeNumber),
+    longhand("font-family", true, "serif", ValueKind::FontFamily),
+    longhand("font-size", true, "medium", ValueKind::FontSize),
+    longhand("font-style", true, "normal", FONT_STYLE),
+    longhand("font-variant", true, "normal", FONT_VARIANT),
+    longhand("font-weight", true, "normal", ValueKind::FontWeight),
+    longhand("height", false, "auto", SIZE),
+    longhand("left", false, "auto", INSET),
+    longhand("line-height", true, "normal", ValueKind::LineHeight),
+    longhand("list-style-image", true, "none", ValueKind::Image),
+    longhand("list-style-position", true, "outside", LIST_STYLE_POSITION),
+    longhand("list-style-type", true, "disc", LIST_STYLE_TYPE),
+    longhand("margin-bottom", false, "0", MARGIN),
+    longhand("margin-left", false, "0", MARGIN),
+    longhand("margin-right", false, "0", MARGIN),
+    longhand("margin-top", false, "0", MARGIN),
+    longhand("max-height", false, "none", MAX_SIZE),
+    longhand("max-width", false, "none", MAX_SIZE),
+    longhand("min-height", false, "0", SIZE),
+    longhand("min-width", false, "0", SIZE),
+    longhand("padding-bottom", false, "0", PADDING),
+    longhand("padding-left", false, "0", PADDING),
+    longhand("padding-right", false, "0", PADDING),
+    longhand("padding-top", false, "0", PADDING),
+    longhand("quotes", true, "auto", ValueKind::Quotes),
+    longhand("right", false, "auto", INSET),
+    longhand("text-align", true, "start", TEXT_ALIGN),
+    longhand("text-decoration", false, "none", TEXT_DECORATION),
+    longhand("top", false, "auto", INSET),
+    longhand("vertical-align", false, "baseline", VERTICAL_ALIGN),
+    longhand("visibility", true, "visible", VISIBILITY),
+    longhand("white-space", true, "normal", WHITE_SPACE),
+    longhand("width", false, "auto", SIZE),
+];
+
+/// https://www.w3.org/TR/css-cascade-4/#shorthand-property
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Shorthand {
+    pub name: &'static str,
+    /// The longhands it sets, in the order `kind` refers to them.
+    pub longhands: &'static [&'static str],
+    pub kind: ShorthandKind,
+}
+
+/// The grammar of a shorthand's value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShorthandKind {
+    /// One to four values for the top, right, bottom and left longhands.
+    /// https://www.w3.org/TR/css-box-4/#margin-shorthand
+    Sides,
+    /// Values in any order, each for a different longhand.
+    AnyOrder,
+    /// A value for the first three longhands in any order, which is also
+    /// used for each following group of three.
+    /// https://www.w3.org/TR/css-backgrounds-3/#border-shorthands
+    Border,
+    /// https://www.w3.org/TR/css-fonts-4/#font-prop
+    Font,
+    /// https://www.w3.org/TR/css-flexbox-1/#flex-property
+    Flex,
+}
+
+const fn shorthand(
+    name: &'static str,
+    longhands: &'static [&'static str],
+    kind: ShorthandKind,
+) -> Shorthand {
+    Shorthand {
+        name,
+        longhands,
+        kind,
+    }
+}
+
+pub const SHORTHANDS: &[Shorthand] = &[
+    shorthand(
+        "background",
+        &[
+            "background-color",
+            "background-image",
+            "background-repeat",
+            "background-attachment",
+            "background-position",
+        ],
+        ShorthandKind::AnyOrder,
+    ),
+    shorthand(
+        "border",
+        &[
+            "border-top-width",
+            "border-top-style",
+            "border-top-color",
+            "border-right-width",
+            "border-right-style",
+            "border-right-color",
+            "border-bottom-width",
+            "border-bottom-style",
+            "border-bottom-color",
+            "border-left-width",
+            "border-left-style",
+            "border-left-color",
+        ],
+        ShorthandKind::Border,
+    ),
+    shorthand(
+        "border-bottom",
+        &[
+            "border-bottom-width",
+            "border-bottom-style",
+            "border-bottom-color",
+        ],
+        ShorthandKind::AnyOrder,
+    ),
+    shorthand(
+        "border-color",
+        &[
+            "border-top-color",
+            "border-right-color",
+            "border-bottom-color",
+            "border-left-color",
+        ],
+        ShorthandKind::Sides,
+    ),
+    shorthand(
+        "border-left",
+        &[
+            "border-left-width",
+            "border-left-style",
+            "border-left-color",
+        ],
+        ShorthandKind::AnyOrder,
+    ),
+    shorthand(
+        "border-right",
+        &[
+            "border-right-width",
+            "border-right-style",
+            "border-right-color",
+        ],
+        ShorthandKind::AnyOrder,
+    ),
+    shorthand(
+        "border-style",
+        &[
+            "border-top-style",
+            "border-right-style",
+            "border-bottom-style",
+            "border-left-style",
+        ],
+        ShorthandKind::Sides,
+    ),
+    shorthand(
+        "border-top",
+        &["border-top-width", "border-top-style", "border-top-color"],
+        ShorthandKind::AnyOrder,
+    ),
+    shorthand(
+        "border-width",
+        &[
+            "border-top-width",
+            "border-right-width",
+            "border-bottom-width",
+            "border-left-width",
+        ],
+        ShorthandKind::Sides,
+    ),
+    shorthand(
+        "flex",
+        &["flex-grow", "flex-shrink", "flex-basis"],
+        ShorthandKind::Flex,
+    ),
+    shorthand(
+        "font",
+        &[
+            "font-style",
+            "font-variant",
+            "font-weight",
+            "font-size",
+            "line-height",
+            "font-family",
+        ],
+        ShorthandKind::Font,
+    ),
+    shorthand(
+        "inset",
+        &["top", "right", "bottom", "left"],
+        ShorthandKind::Sides,
+    ),
+    // `list-style-type` comes first so that `none` sets it rather than
+    // `list-style-image`, whose initial value is already `none`.
+    shorthand(
+        "list-style",
+        &["list-style-type", "list-style-position", "list-style-image"],
+        ShorthandKind::AnyOrder,
+    ),
+    shorthand(
+        "margin",
+        &["margin-top", "margin-right", "margin-bottom", "margin-left"],
+        ShorthandKind::Sides,
+    ),
+    shorthand(
+        "padding",
+        &[
+            "padding-top",
+            "padding-right",
+            "padding-bottom",
+            "padding-left",
+        ],
+        ShorthandKind::Sides,
+    ),
+];
+
+pub fn find_longhand(name: &str) -> Option<&'static Property> {
+    LONGHANDS.iter().find(|p| p.name == name)
+}
+
+pub fn find_shorthand(name: &str) -> Option<&'static Shorthand> {
+    SHORTHANDS.iter().find(|s| s.name == name)
+}
+
+/// Returns true if declarations of `name` are kept by the parser. Custom
+/// properties are always supported.
+pub fn is_supported_property(name: &str) -> bool {
+    name.starts_with("--") || find_longhand(name).is_some() || find_shorthand(name).is_some()
+}
@@ -0,0 +1,714 @@
+//! https://www.w3.org/TR/css-cascade-4/#shorthand-property
+
+use crate::renderer::css::computed_style::is_valid_value;
+use crate::renderer::css::parser::CssParser;
+use crate::renderer::css::properties::find_longhand;
+use crate::renderer::css::properties::find_shorthand;
+use crate::renderer::css::properties::Shorthand;
+use crate::renderer::css::properties::ShorthandKind;
+use crate::renderer::css::properties::SHORTHANDS;
+use crate::renderer::css::stylesheet::ComponentValue;
+use crate::renderer::css::stylesheet::Declaration;
+use crate::renderer::css::token::CssToken;
+use crate::renderer::css::token::CssTokenizer;
+use crate::renderer::css::variables::contains_var;
+use alloc::format;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// https://www.w3.org/TR/css-cascade-4/#defaulting-keywords
+const CSS_WIDE_KEYWORDS: &[&str] = &["inherit", "initial", "unset", "revert"];
+
+/// Replaces the declarations of shorthands with declarations of their
+/// longhands. Declarations of shorthands with an invalid value are dropped.
+///
+/// Declarations of shorthands whose value contains `var()` are kept as is,
+/// since their value can only be expanded after substitution.
+/// https://www.w3.org/TR/css-variables-1/#variables-in-shorthands
+pub fn expand_declarations(declarations: &[Declaration]) -> Vec<Declaration> {
+    let mut expanded = Vec::new();
+    for declaration in declarations {
+        let shorthand = match find_shorthand(&declaration.name) {
+            Some(shorthand) if !contains_var(&declaration.value) => shorthand,
+            _ => {
+                expanded.push(declaration.clone());
+                continue;
+            }
+        };
+        if let Some(longhands) = expand(shorthand, &declaration.value) {
+            for (name, value) in longhands {
+                expanded.push(Declaration {
+                    name: name.to_string(),
+                    value,
+                    important: declaration.important,
+                });
+            }
+        }
+    }
+    expanded
+}
+
+/// Returns the value of each longhand of `shorthand` set by `value`, or
+/// `None` if `value` is invalid. Longhands that `value` omits are set to
+/// their initial value.
+pub fn expand(
+    shorthand: &Shorthand,
+    value: &[ComponentValue],
+) -> Option<Vec<(&'static str, Vec<ComponentValue>)>> {
+    if let Some(keyword) = css_wide_keyword(value) {
+        return Some(
+            shorthand
+                .longhands
+                .iter()
+                .map(|l| {
+                    (
+                        *l,
+                        vec![ComponentValue::Token(CssToken::Ident(keyword.clone()))],
+                    )
+                })
+                .collect(),
+        );
+    }
+
+    let components = Components::new(value);
+    if components.len() == 0 {
+        return None;
+    }
+    let values = match shorthand.kind {
+        ShorthandKind::Sides => expand_sides(shorthand.longhands, &components)?,
+        ShorthandKind::AnyOrder => expand_any_order(shorthand.longhands, &components)?,
+        ShorthandKind::Border => {
+            let side = expand_any_order(&shorthand.longhands[..3], &components)?;
+            (0..shorthand.longhands.len())
+                .map(|i| side[i % 3].clone())
+                .collect()
+        }
+        ShorthandKind::Font => expand_font(shorthand.longhands, &components)?,
+        ShorthandKind::Flex => expand_flex(shorthand.longhands, &components)?,
+    };
+    Some(shorthand.longhands.iter().copied().zip(values).collect())
+}
+
+/// The component values of a declaration value, without whitespace.
+struct Components<'a> {
+    value: &'a [ComponentValue],
+    /// The index in `value` of each component.
+    indices: Vec<usize>,
+}
+
+impl<'a> Components<'a> {
+    fn new(value: &'a [ComponentValue]) -> Self {
+        let indices = value
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| **v != ComponentValue::Token(CssToken::Whitespace))
+            .map(|(i, _)| i)
+            .collect();
+        Self { value, indices }
+    }
+
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    fn get(&self, i: usize) -> Option<&'a ComponentValue> {
+        self.indices.get(i).map(|j| &self.value[*j])
+    }
+
+    /// Returns the components `start..end` with the whitespace between them.
+    fn slice(&self, start: usize, end: usize) -> &'a [ComponentValue] {
+        &self.value[self.indices[start]..=self.indices[end - 1]]
+    }
+
+    fn slice_all(&self) -> &'a [ComponentValue] {
+        match self.len() {
+            0 => &[],
+            n => self.slice(0, n),
+        }
+    }
+}
+
+/// Returns true if `value` is valid for the longhand `name`.
+fn is_valid(name: &str, value: &[ComponentValue]) -> bool {
+    find_longhand(name).is_some_and(|p| is_valid_value(p, value))
+}
+
+fn initial_value(name: &str) -> Vec<ComponentValue> {
+    let initial = find_longhand(name).map_or("", |p| p.initial);
+    CssParser::new(CssTokenizer::new(String::from(initial))).parse_component_values()
+}
+
+fn css_wide_keyword(value: &[ComponentValue]) -> Option<String> {
+    match Components::new(value).slice_all() {
+        [ComponentValue::Token(CssToken::Ident(ident))] => {
+            let ident = ident.to_ascii_lowercase();
+            CSS_WIDE_KEYWORDS.contains(&ident.as_str()).then_some(ident)
+        }
+        _ => None,
+    }
+}
+
+/// https://www.w3.org/TR/css-box-4/#margin-shorthand
+fn expand_sides(longhands: &[&str], components: &Components) -> Option<Vec<Vec<ComponentValue>>> {
+    let sides: [usize; 4] = match components.len() {
+        1 => [0, 0, 0, 0],
+        2 => [0, 1, 0, 1],
+        3 => [0, 1, 2, 1],
+        4 => [0, 1, 2, 3],
+        _ => return None,
+    };
+    longhands
+        .iter()
+        .zip(sides)
+        .map(|(name, i)| {
+            let value = components.slice(i, i + 1);
+            is_valid(name, value).then(|| value.to_vec())
+        })
+        .collect()
+}
+
+/// Assigns each run of components to the first longhand it is valid for
+/// that isn't set yet. Longer runs are tried first, so that e.g. a
+/// `background-position` can take two components.
+fn expand_any_order(
+    longhands: &[&str],
+    components: &Components,
+) -> Option<Vec<Vec<ComponentValue>>> {
+    let mut values: Vec<Option<Vec<ComponentValue>>> = vec![None; longhands.len()];
+    let mut start = 0;
+    'components: while start < components.len() {
+        for end in (start + 1..=components.len()).rev() {
+            let value = components.slice(start, end);
+            let longhand = longhands
+                .iter()
+                .zip(values.iter())
+                .position(|(name, v)| v.is_none() && is_valid(name, value));
+            if let Some(i) = longhand {
+                values[i] = Some(value.to_vec());
+                start = end;
+                continue 'components;
+            }
+        }
+        return None;
+    }
+    Some(
+        values
+            .into_iter()
+            .zip(longhands)
+            .map(|(v, name)| v.unwrap_or_else(|| initial_value(name)))
+            .collect(),
+    )
+}
+
+/// https://www.w3.org/TR/css-fonts-4/#font-prop
+/// `[ <font-style> || <font-variant> || <font-weight> ]? <font-size>
+/// [ / <line-height> ]? <font-family>`
+fn expand_font(longhands: &[&str], components: &Components) -> Option<Vec<Vec<ComponentValue>>> {
+    // The longhands are style, variant, weight, size, line-height and
+    // family, in that order.
+    let mut values: Vec<Option<Vec<ComponentValue>>> = vec![None; longhands.len()];
+    let mut i = 0;
+    while i < 3 {
+        let value = components.slice(i, i + 1);
+        let is_normal = matches!(
+            value,
+            [ComponentValue::Token(CssToken::Ident(k))] if k.eq_ignore_ascii_case("normal")
+        );
+        // `normal` leaves the value to be reset to its initial value.
+        let longhand = (0..3).find(|j| values[*j].is_none() && is_valid(longhands[*j], value));
+        match longhand {
+            _ if is_normal => {}
+            Some(j) => values[j] = Some(value.to_vec()),
+            None => break,
+        }
+        i += 1;
+        if i >= components.len() {
+            return None;
+        }
+    }
+
+    let size = components.slice(i, i + 1);
+    if !is_valid(longhands[3], size) {
+        return None;
+    }
+    values[3] = Some(size.to_vec());
+    i += 1;
+
+    if components.get(i) == Some(&ComponentValue::Token(CssToken::Delim('/'))) {
+        if i + 1 >= components.len() {
+            return None;
+        }
+        let line_height = components.slice(i + 1, i + 2);
+        if !is_valid(longhands[4], line_height) {
+            return None;
+        }
+        values[4] = Some(line_height.to_vec());
+        i += 2;
+    }
+
+    if i >= components.len() {
+        return None;
+    }
+    let family = components.slice(i, components.len());
+    if !is_valid(longhands[5], family) {
+        return None;
+    }
+    values[5] = Some(family.to_vec());
+
+    Some(
+        values
+            .into_iter()
+            .zip(longhands)
+            .map(|(v, name)| v.unwrap_or_else(|| initial_value(name)))
+            .collect(),
+    )
+}
+
+/// https://www.w3.org/TR/css-flexbox-1/#flex-property
+/// `none | [ <flex-grow> <flex-shrink>? || <flex-basis> ]`
+fn expand_flex(longhands: &[&str], components: &Components) -> Option<Vec<Vec<ComponentValue>>> {
+    let parse =
+        |css: &str| CssParser::new(CssTokenizer::new(String::from(css))).parse_component_values();
+    if let [ComponentValue::Token(CssToken::Ident(keyword))] = components.slice_all() {
+        let keyword = keyword.to_ascii_lowercase();
+        if keyword == "none" {
+            return Some(vec![parse("0"), parse("0"), parse("auto")]);
+        }
+        if keyword == "auto" {
+            return Some(vec![parse("1"), parse("1"), parse("auto")]);
+        }
+    }
+
+    let value = |i: usize| components.slice(i, i + 1);
+    let is_number = |i: usize| i < components.len() && is_valid(longhands[0], value(i));
+    let is_basis = |i: usize| i < components.len() && is_valid(longhands[2], value(i));
+
+    let mut i = 0;
+    let mut basis = None;
+    if !is_number(0) && is_basis(0) {
+        basis = Some(value(0).to_vec());
+        i = 1;
+    }
+    let mut grow = None;
+    let mut shrink = None;
+    if is_number(i) {
+        grow = Some(value(i).to_vec());
+        i += 1;
+        if is_number(i) {
+            shrink = Some(value(i).to_vec());
+            i += 1;
+        }
+    }
+    if basis.is_none() && is_basis(i) {
+        basis = Some(value(i).to_vec());
+        i += 1;
+    }
+    if i != components.len() {
+        return None;
+    }
+
+    // https://www.w3.org/TR/css-flexbox-1/#flex-common
+    // An omitted `<flex-grow>` and `<flex-shrink>` are 1, and an omitted
+    // `<flex-basis>` is 0.
+    Some(vec![
+        grow.unwrap_or_else(|| parse("1")),
+        shrink.unwrap_or_else(|| parse("1")),
+        basis.unwrap_or_else(|| parse("0")),
+    ])
+}
+
+fn serialize_value(value: &[ComponentValue]) -> String {
+    value.iter().map(|v| v.to_string()).collect()
+}
+
+/// Serializes the values of the longhands of `shorthand`, given in the same
+/// order, as the value of `shorthand`. Returns `None` if the shorthand can't
+/// represent them.
+/// https://www.w3.org/TR/cssom-1/#serialize-a-css-value
+pub fn serialize(shorthand: &Shorthand, values: &[&[ComponentValue]]) -> Option<String> {
+    if values.len() != shorthand.longhands.len() {
+        return None;
+    }
+    let keywords: Vec<Option<String>> = values.iter().map(|v| css_wide_keyword(v)).collect();
+    if keywords.iter().any(|k| k.is_some()) {
+        return match keywords.first()? {
+            Some(k) if keywords.iter().all(|other| other.as_ref() == Some(k)) => Some(k.clone()),
+            _ => None,
+        };
+    }
+
+    let strings: Vec<String> = values.iter().map(|v| serialize_value(v)).collect();
+    let is_initial =
+        |i: usize| Components::new(values[i]).slice_all() == initial_value(shorthand.longhands[i]);
+    let serialized = match shorthand.kind {
+        ShorthandKind::Sides => {
+            let (top, right, bottom, left) = (&strings[0], &strings[1], &strings[2], &strings[3]);
+            if left != right {
+                format!("{} {} {} {}", top, right, bottom, left)
+            } else if bottom != top {
+                format!("{} {} {}", top, right, bottom)
+            } else if right != top {
+                format!("{} {}", top, right)
+            } else {
+                top.clone()
+            }
+        }
+        ShorthandKind::AnyOrder => serialize_any_order(&strings, is_initial),
+        ShorthandKind::Border => {
+            if strings.chunks(3).any(|side| side != &strings[..3]) {
+                return None;
+            }
+            serialize_any_order(&strings[..3], is_initial)
+        }
+        ShorthandKind::Font => {
+            let mut parts = Vec::new();
+            for s in strings.iter().take(3) {
+                if !s.eq_ignore_ascii_case("normal") {
+                    parts.push(s.clone());
+                }
+            }
+            if strings[4].eq_ignore_ascii_case("normal") {
+                parts.push(strings[3].clone());
+            } else {
+                parts.push(format!("{}/{}", strings[3], strings[4]));
+            }
+            parts.push(strings[5].clone());
+            parts.join(" ")
+        }
+        ShorthandKind::Flex => strings.join(" "),
+    };
+    Some(serialized)
+}
+
+/// Joins the values that aren't initial. If all are, the first one is used.
+fn serialize_any_order(strings: &[String], is_initial: impl Fn(usize) -> bool) -> String {
+    let parts: Vec<&str> = strings
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !is_initial(*i))
+        .map(|(_, s)| s.as_str())
+        .collect();
+    if parts.is_empty() {
+        strings[0].clone()
+    } else {
+        parts.join(" ")
+    }
+}
+
+/// Returns the value of the last declaration of each property, in the
+/// order of those declarations.
+fn last_declarations(declarations: &[Declaration]) -> Vec<&Declaration> {
+    let mut result: Vec<&Declaration> = Vec::new();
+    for declaration in declarations {
+        result.retain(|d| d.name != declaration.name);
+        result.push(declaration);
+    }
+    result
+}
+
+/// Returns the value of `property` in `declarations`, which may be a
+/// shorthand, or the empty string if it isn't set.
+/// https://www.w3.org/TR/cssom-1/#dom-cssstyledeclaration-getpropertyvalue
+pub fn get_property_value(declarations: &[Declaration], property: &str) -> String {
+    let declarations = expand_declarations(declarations);
+    let declarations = last_declarations(&declarations);
+    let find = |name: &str| declarations.iter().find(|d| d.name == name).copied();
+
+    // A shorthand whose value contains `var()` is also found as is.
+    if let Some(declaration) = find(property) {
+        return serialize_value(&declaration.value);
+    }
+    let shorthand = match find_shorthand(property) {
+        Some(shorthand) => shorthand,
+        None => return String::new(),
+    };
+    let longhands: Option<Vec<&Declaration>> =
+        shorthand.longhands.iter().map(|l| find(l)).collect();
+    match longhands {
+        // https://www.w3.org/TR/cssom-1/#dom-cssstyledeclaration-getpropertyvalue
+        // The longhands must all be set with the same importance.
+        Some(longhands)
+            if longhands
+                .iter()
+                .all(|d| d.important == longhands[0].important) =>
+        {
+            let values: Vec<&[ComponentValue]> =
+                longhands.iter().map(|d| d.value.as_slice()).collect();
+            serialize(shorthand, &values).unwrap_or_default()
+        }
+        _ => String::new(),
+    }
+}
+
+/// Serializes `declarations`, using shorthands where they can represent a
+/// set of longhands.
+/// https://www.w3.org/TR/cssom-1/#serialize-a-css-declaration-block
+pub fn serialize_declarations(declarations: &[Declaration]) -> String {
+    let declarations = expand_declarations(declarations);
+    let declarations = last_declarations(&declarations);
+    let find = |name: &str| declarations.iter().find(|d| d.name == name).copied();
+
+    // Shorthands that set more longhands are preferred.
+    let mut shorthands: Vec<&Shorthand> = SHORTHANDS.iter().collect();
+    shorthands.sort_by_key(|s| core::cmp::Reverse(s.longhands.len()));
+
+    let mut serialized: Vec<&str> = Vec::new();
+    let mut list = Vec::new();
+    for declaration in &declarations {
+        if serialized.contains(&declaration.name.as_str()) {
+            continue;
+        }
+        let important = if declaration.important {
+            " !important"
+        } else {
+            ""
+        };
+
+        let shorthand = shorthands.iter().find_map(|shorthand| {
+            if !shorthand.longhands.contains(&declaration.name.as_str())
+                || shorthand.longhands.iter().any(|l| serialized.contains(l))
+            {
+                return None;
+            }
+            let longhands: Vec<&Declaration> = shorthand
+                .longhands
+                .iter()
+                .map(|l| find(l))
+                .collect::<Option<_>>()?;
+            if longhands
+                .iter()
+                .any(|d| d.important != declaration.important)
+            {
+                return None;
+            }
+            let values: Vec<&[ComponentValue]> =
+                longhands.iter().map(|d| d.value.as_slice()).collect();
+            serialize(shorthand, &values).map(|value| (shorthand, value))
+        });
+
+        match shorthand {
+            Some((shorthand, value)) => {
+                list.push(format!("{}: {}{};", shorthand.name, value, important));
+                serialized.extend(shorthand.longhands.iter());
+            }
+            None => {
+                list.push(format!(
+                    "{}: {}{};",
+                    declaration.name,
+                    serialize_value(&declaration.value),
+                    important
+                ));
+                serialized.push(&declaration.name);
+            }
+        }
+    }
+    list.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declarations(css: &str) -> Vec<Declaration> {
+        CssParser::new(CssTokenizer::new(String::from(css))).parse_declarations()
+    }
+
+    /// Expands `css` and returns each longhand with its serialized value.
+    fn expanded(css: &str) -> Vec<(String, String)> {
+        expand_declarations(&declarations(css))
+            .iter()
+            .map(|d| (d.name.clone(), serialize_value(&d.value)))
+            .collect()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_sides() {
+        assert_eq!(
+            pairs(&[
+                ("margin-top", "1px"),
+                ("margin-right", "auto"),
+                ("margin-bottom", "1px"),
+                ("margin-left", "auto"),
+            ]),
+            expanded("margin: 1px auto")
+        );
+        assert_eq!(
+            pairs(&[
+                ("padding-top", "1px"),
+                ("padding-right", "2%"),
+                ("padding-bottom", "calc(1em + 2px)"),
+                ("padding-left", "2%"),
+            ]),
+            expanded("padding: 1px 2% calc(1em + 2px)")
+        );
+        assert_eq!(
+            pairs(&[
+                ("top", "0"),
+                ("right", "1px"),
+                ("bottom", "2px"),
+                ("left", "3px"),
+            ]),
+            expanded("inset: 0 1px 2px 3px")
+        );
+        assert_eq!(
+            pairs(&[
+                ("border-top-color", "inherit"),
+                ("border-right-color", "inherit"),
+                ("border-bottom-color", "inherit"),
+                ("border-left-color", "inherit"),
+            ]),
+            expanded("border-color: INHERIT")
+        );
+        // Invalid values drop the whole declaration.
+        assert!(expanded("margin: 1px 2px 3px 4px 5px").is_empty());
+        assert!(expanded("padding: auto").is_empty());
+        assert!(expanded("margin: 1px inherit").is_empty());
+        assert!(expanded("margin:").is_empty());
+    }
+
+    #[test]
+    fn test_expand_any_order() {
+        assert_eq!(
+            pairs(&[
+                ("border-top-width", "medium"),
+                ("border-top-style", "solid"),
+                ("border-top-color", "red"),
+            ]),
+            expanded("border-top: red solid")
+        );
+        let border = expanded("border: 2px dashed #fff");
+        assert_eq!(12, border.len());
+        assert_eq!(
+            pairs(&[
+                ("border-left-width", "2px"),
+                ("border-left-style", "dashed"),
+                ("border-left-color", "#fff"),
+            ]),
+            border[9..]
+        );
+        assert_eq!(
+            pairs(&[
+                ("background-color", "blue"),
+                ("background-image", "url(a.png)"),
+                ("background-repeat", "no-repeat"),
+                ("background-attachment", "scroll"),
+                ("background-position", "center top"),
+            ]),
+            expanded("background: url(a.png) center top no-repeat blue")
+        );
+        assert_eq!(
+            pairs(&[
+                ("list-style-type", "none"),
+                ("list-style-position", "outside"),
+                ("list-style-image", "none"),
+            ]),
+            expanded("list-style: none")
+        );
+        assert_eq!(
+            pairs(&[
+                ("list-style-type", "square"),
+                ("list-style-position", "inside"),
+                ("list-style-image", "none"),
+            ]),
+            expanded("list-style: inside square")
+        );
+        assert!(expanded("border: solid solid").is_empty());
+        assert!(expanded("background: red blue").is_empty());
+    }
+
+    #[test]
+    fn test_expand_font_and_flex() {
+        assert_eq!(
+            pairs(&[
+                ("font-style", "italic"),
+                ("font-variant", "normal"),
+                ("font-weight", "bold"),
+                ("font-size", "12px"),
+                ("line-height", "1.5"),
+                ("font-family", "\"Helvetica Neue\", sans-serif"),
+            ]),
+            expanded("font: italic normal bold 12px/1.5 \"Helvetica Neue\", sans-serif")
+        );
+        assert_eq!(
+            pairs(&[
+                ("font-style", "normal"),
+                ("font-variant", "normal"),
+                ("font-weight", "normal"),
+                ("font-size", "2em"),
+                ("line-height", "normal"),
+                ("font-family", "serif"),
+            ]),
+            expanded("font: 2em serif")
+        );
+        assert!(expanded("font: bold serif").is_empty());
+        assert!(expanded("font: 12px").is_empty());
+        assert!(expanded("font: 12px/ serif").is_empty());
+
+        let flex =
+            |css: &str| -> Vec<String> { expanded(css).into_iter().map(|(_, v)| v).collect() };
+        assert_eq!(vec!["0", "0", "auto"], flex("flex: none"));
+        assert_eq!(vec!["1", "1", "auto"], flex("flex: auto"));
+        assert_eq!(vec!["2", "1", "0"], flex("flex: 2"));
+        assert_eq!(vec!["2", "3", "10%"], flex("flex: 2 3 10%"));
+        assert_eq!(vec!["1", "1", "10px"], flex("flex: 10px"));
+        assert_eq!(vec!["2", "1", "10px"], flex("flex: 10px 2"));
+        assert_eq!(vec!["0", "0", "0"], flex("flex: 0 0 0"));
+        assert!(flex("flex: 1 2 3").is_empty());
+        assert!(flex("flex: -1").is_empty());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let value = |css: &str, property: &str| get_property_value(&declarations(css), property);
+        assert_eq!("1px 2px", value("margin: 1px 2px 1px", "margin"));
+        assert_eq!(
+            "1px 2px 3px",
+            value("margin: 1px 2px 3px; margin-left: 2px", "margin")
+        );
+        assert_eq!("0", value("padding: 0; margin: 1px", "padding-left"));
+        assert_eq!("1px", value("margin: 1px", "margin-top"));
+        assert_eq!("", value("margin-top: 1px", "margin"));
+        assert_eq!(
+            "",
+            value("margin: 1px; margin-top: 2px !important", "margin")
+        );
+        assert_eq!("inherit", value("margin: inherit", "margin"));
+        assert_eq!("", value("margin: 1px; margin-top: inherit", "margin"));
+        assert_eq!("solid red", value("border: red solid", "border"));
+        assert_eq!("", value("border: red; border-left-width: 1px", "border"));
+        assert_eq!("solid red", value("border: red solid", "border-top"));
+        assert_eq!("medium", value("border: medium", "border"));
+        assert_eq!("none", value("list-style: none", "list-style"));
+        assert_eq!(
+            "bold 12px/1.5 serif",
+            value("font: bold 12px/1.5 serif", "font")
+        );
+        assert_eq!("1 1 auto", value("flex: auto", "flex"));
+        assert_eq!("var(--m) 1px", value("margin: var(--m) 1px", "margin"));
+        assert_eq!("", value("margin: var(--m) 1px", "margin-top"));
+
+        assert_eq!(
+            "margin: 1px 2px; border-width: 1px; border-style: solid; border-color: red blue; color: red !important;",
+            serialize_declarations(&declarations(
+                "margin: 1px 2px; border: 1px solid red; border-right-color: blue; border-left-color: blue; color: red !important"
+            ))
+        );
+        assert_eq!(
+            "border: 1px solid red; --x: { a };",
+            serialize_declarations(&declarations("border: 1px solid red; --x: { a }"))
+        );
+        assert_eq!(
+            "margin: var(--m) 1px; padding: 0;",
+            serialize_declarations(&declarations("margin: var(--m) 1px; padding: 0"))
+        );
+    }
+}
@@ -0,0 +1,403 @@
+//! https://www.w3.org/TR/cssom-1/#css-object-model
+
+use crate::http::ResourceLoader;
+use crate::renderer::css::parser::CssParser;
+use crate::renderer::css::token::CssToken;
+use crate::renderer::css::token::CssTokenizer;
+use crate::renderer::dom::api::inclusive_descendants;
+use crate::renderer::dom::api::text_content;
+use crate::renderer::dom::node::Element;
+use crate::renderer::dom::node::ElementKind;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::HTML_NAMESPACE;
+use crate::renderer::dom::selector::SelectorList;
+use crate::renderer::html::metadata::has_link_type;
+use crate::renderer::html::metadata::DocumentMetadata;
+use crate::url::Url;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+
+/// https://www.w3.org/TR/cssom-1/#cssstylesheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleSheet {
+    pub rules: Vec<CssRule>,
+    /// The URL the style sheet was loaded from, or `None` for the contents
+    /// of a `style` element.
+    /// https://www.w3.org/TR/cssom-1/#concept-css-style-sheet-location
+    pub href: Option<String>,
+    /// The media query list the style sheet applies to. It is empty if it
+    /// always applies.
+    /// https://www.w3.org/TR/cssom-1/#concept-css-style-sheet-media
+    pub media: String,
+}
+
+impl StyleSheet {
+    /// https://www.w3.org/TR/css-syntax-3/#parse-a-css-stylesheet
+    pub fn parse(css: &str) -> Self {
+        CssParser::new(CssTokenizer::new(String::from(css))).parse_stylesheet()
+    }
+
+    /// Parses the contents of every `style` element in `document`, in tree
+    /// order. `@import` rules aren't loaded.
+    /// https://html.spec.whatwg.org/multipage/semantics.html#update-a-style-block
+    pub fn from_style_elements(document: &Rc<RefCell<Node>>) -> Vec<Self> {
+        inclusive_descendants(document)
+            .filter(|n| {
+                n.borrow()
+                    .get_element()
+                    .is_some_and(|e| e.kind() == ElementKind::Style && is_css_element(&e))
+            })
+            .map(|n| Self::from_style_element(&n))
+            .collect()
+    }
+
+    /// Loads the style sheets of `document`, which was loaded from
+    /// `document_url`, in tree order. Those are the sheets of `style`
+    /// elements and of `link rel=stylesheet` elements, with the sheets they
+    /// `@import`. Sheets that fail to load are skipped.
+    /// https://html.spec.whatwg.org/multipage/links.html#link-type-stylesheet
+    pub fn from_document(
+        document: &Rc<RefCell<Node>>,
+        document_url: &str,
+        loader: &mut dyn ResourceLoader,
+    ) -> Vec<Self> {
+        let base_url = DocumentMetadata::extract(document, document_url).base_url;
+        let mut sheets = Vec::new();
+        for node in inclusive_descendants(document) {
+            let element = match node.borrow().get_element() {
+                Some(element) if is_css_element(&element) => element,
+                _ => continue,
+            };
+            let mut sheet = match element.kind() {
+                ElementKind::Style => Self::from_style_element(&node),
+                // https://html.spec.whatwg.org/multipage/links.html#the-link-is-an-alternative-stylesheet
+                ElementKind::Link
+                    if has_link_type(&element, "stylesheet")
+                        && !has_link_type(&element, "alternate") =>
+                {
+                    // A link without an `href`, or with an empty one, is
+                    // skipped rather than fetching the document itself.
+                    let href = match element.get_attribute("href") {
+                        Some(href) if !href.trim().is_empty() => href,
+                        _ => continue,
+                    };
+                    let url = Url::new(base_url.clone()).join(&href);
+                    match fetch(&url, loader) {
+                        Some(mut sheet) => {
+                            sheet.media = element.get_attribute("media").unwrap_or_default();
+                            sheet
+                        }
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            };
+            sheet.load_imports(&base_url, loader, &mut Vec::new());
+            sheets.push(sheet);
+        }
+        sheets
+    }
+
+    fn from_style_element(node: &Rc<RefCell<Node>>) -> Self {
+        let mut sheet = Self::parse(&text_content(node).unwrap_or_default());
+        sheet.media = node
+            .borrow()
+            .get_element()
+            .and_then(|e| e.get_attribute("media"))
+            .unwrap_or_default();
+        sheet
+    }
+
+    /// Loads the style sheet of each `@import` rule, recursively. URLs are
+    /// resolved against the URL of the sheet, or `base_url` if it has none.
+    /// `ancestors` is the URLs of the sheets that import this one, so that
+    /// import cycles are skipped.
+    /// https://www.w3.org/TR/css-cascade-4/#at-import
+    fn load_imports(
+        &mut self,
+        base_url: &str,
+        loader: &mut dyn ResourceLoader,
+        ancestors: &mut Vec<String>,
+    ) {
+        let base = Url::new(self.href.clone().unwrap_or_else(|| base_url.to_string()));
+        if let Some(href) = &self.href {
+            ancestors.push(href.clone());
+        }
+        for rule in &mut self.rules {
+            if let CssRule::Import(import) = rule {
+                let url = base.join(&import.url);
+                if ancestors.contains(&url) {
+                    continue;
+                }
+                if let Some(mut sheet) = fetch(&url, loader) {
+                    sheet.media = import.media.clone();
+                    sheet.load_imports(base_url, loader, ancestors);
+                    import.style_sheet = Some(Box::new(sheet));
+                }
+            }
+        }
+        if self.href.is_some() {
+            ancestors.pop();
+        }
+    }
+}
+
+/// Returns true if `element` is an HTML element whose `type`, if any, is
+/// CSS.
+/// https://html.spec.whatwg.org/multipage/semantics.html#update-a-style-block
+fn is_css_element(element: &Element) -> bool {
+    element.namespace_uri().as_deref() == Some(HTML_NAMESPACE)
+        && element
+            .get_attribute("type")
+            .map_or(true, |t| t.is_empty() || t.eq_ignore_ascii_case("text/css"))
+}
+
+/// Fetches and parses the style sheet at `url`. Returns `None` if the
+/// request fails.
+fn fetch(url: &str, loader: &mut dyn ResourceLoader) -> Option<StyleSheet> {
+    let response = loader.fetch(url).ok()?;
+    if !(200..300).contains(&response.status_code()) {
+        return None;
+    }
+    let mut sheet = StyleSheet::parse(&response.body());
+    sheet.href = Some(url.to_string());
+    Some(sheet)
+}
+
+/// https://www.w3.org/TR/cssom-1/#cssrule
+#[derive(Debug, Clone, PartialEq)]
+pub enum CssRule {
+    Style(StyleRule),
+    Media(MediaRule),
+    Import(ImportRule),
+    FontFace(FontFaceRule),
+    Supports(SupportsRule),
+}
+
+/// https://www.w3.org/TR/cssom-1/#the-cssstylerule-interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleRule {
+    pub selectors: SelectorList,
+    pub declarations: Vec<Declaration>,
+}
+
+/// https://www.w3.org/TR/css-conditional-3/#the-cssmediarule-interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaRule {
+    /// The media query list, e.g. `screen and (min-width: 600px)`.
+    pub media: String,
+    pub rules: Vec<CssRule>,
+}
+
+/// https://www.w3.org/TR/cssom-1/#the-cssimportrule-interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRule {
+    /// The URL as written, not resolved against the style sheet's URL.
+    pub url: String,
+    pub media: String,
+    /// The imported style sheet, once it is loaded.
+    pub style_sheet: Option<Box<StyleSheet>>,
+}
+
+/// https://www.w3.org/TR/css-fonts-4/#cssfontfacerule
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFaceRule {
+    pub declarations: Vec<Declaration>,
+}
+
+/// https://www.w3.org/TR/css-conditional-3/#the-csssupportsrule-interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct SupportsRule {
+    pub condition: String,
+    pub rules: Vec<CssRule>,
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#declaration
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    /// The property name. It is lowercased unless it is a custom property.
+    pub name: String,
+    /// The value without surrounding whitespace and `!important`.
+    pub value: Vec<ComponentValue>,
+    pub important: bool,
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#component-value
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComponentValue {
+    /// https://www.w3.org/TR/css-syntax-3/#preserved-tokens
+    Token(CssToken),
+    /// https://www.w3.org/TR/css-syntax-3/#function
+    Function {
+        name: String,
+        value: Vec<ComponentValue>,
+    },
+    /// A `{}`, `[]` or `()` block. `open` is the opening bracket.
+    /// https://www.w3.org/TR/css-syntax-3/#simple-block
+    Block {
+        open: char,
+        value: Vec<ComponentValue>,
+    },
+}
+
+impl fmt::Display for ComponentValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ComponentValue::Token(token) => write!(f, "{}", token),
+            ComponentValue::Function { name, value } => {
+                write!(f, "{}(", name)?;
+                for v in value {
+                    write!(f, "{}", v)?;
+                }
+                write!(f, ")")
+            }
+            ComponentValue::Block { open, value } => {
+                write!(f, "{}", open)?;
+                for v in value {
+                    write!(f, "{}", v)?;
+                }
+                let close = match open {
+                    '{' => '}',
+                    '[' => ']',
+                    _ => ')',
+                };
+                write!(f, "{}", close)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::http::HttpResponse;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+    use alloc::collections::BTreeMap;
+    use alloc::format;
+    use alloc::vec;
+
+    /// Serves style sheets from memory, and records the requested URLs.
+    #[derive(Default)]
+    struct TestLoader {
+        resources: BTreeMap<String, String>,
+        requests: Vec<String>,
+    }
+
+    impl ResourceLoader for TestLoader {
+        fn fetch(&mut self, url: &str) -> Result<HttpResponse, Error> {
+            self.requests.push(url.to_string());
+            let response = match self.resources.get(url) {
+                Some(body) => format!("HTTP/1.1 200 OK\n\n{}", body),
+                None => "HTTP/1.1 404 Not Found\n\n".to_string(),
+            };
+            HttpResponse::new(response)
+        }
+    }
+
+    #[test]
+    fn test_from_style_elements() {
+        let window = HtmlParser::new(HtmlTokenizer::new(
+            "<html><head><style>p { color: red }</style></head><body><svg><style>a {}</style></svg><style>h1 > a { display: block } h2 {}</style></body></html>"
+                .to_string(),
+        ))
+        .construct_tree();
+        let document = window.borrow().document();
+
+        let sheets = StyleSheet::from_style_elements(&document);
+        assert_eq!(
+            vec![
+                StyleSheet::parse("p { color: red }"),
+                StyleSheet::parse("h1 > a { display: block } h2 {}")
+            ],
+            sheets
+        );
+        assert_eq!(1, sheets[0].rules.len());
+        assert_eq!(2, sheets[1].rules.len());
+    }
+
+    #[test]
+    fn test_from_document() {
+        let window = HtmlParser::new(HtmlTokenizer::new(
+            r#"<html><head><base href="/css/"><link rel=stylesheet href=a.css media=print><style>@import "b.css" screen; p {}</style><link rel="alternate stylesheet" href=alt.css><link rel=stylesheet href=missing.css><style type=text/plain>a {}</style></head><body><link rel=StyleSheet href=/c.css></body></html>"#
+                .to_string(),
+        ))
+        .construct_tree();
+        let document = window.borrow().document();
+        let mut loader = TestLoader::default();
+        for (url, css) in [
+            ("http://example.com/css/a.css", "a {}"),
+            ("http://example.com/css/b.css", "@import url(d/e.css); b {}"),
+            (
+                "http://example.com/css/d/e.css",
+                "@import '../b.css'; @import 'e.css'; e {}",
+            ),
+            ("http://example.com/css/alt.css", "alt {}"),
+            ("http://example.com/c.css", "c {}"),
+        ] {
+            loader.resources.insert(url.to_string(), css.to_string());
+        }
+
+        let sheets =
+            StyleSheet::from_document(&document, "http://example.com/index.html", &mut loader);
+        assert_eq!(3, sheets.len());
+        assert_eq!(
+            Some("http://example.com/css/a.css".to_string()),
+            sheets[0].href
+        );
+        assert_eq!("print", sheets[0].media);
+        assert_eq!(None, sheets[1].href);
+        assert_eq!(Some("http://example.com/c.css".to_string()), sheets[2].href);
+
+        let import = |rule: &CssRule| match rule {
+            CssRule::Import(import) => import.clone(),
+            _ => panic!("not an @import rule: {:?}", rule),
+        };
+        let b = import(&sheets[1].rules[0]).style_sheet.unwrap();
+        assert_eq!(Some("http://example.com/css/b.css".to_string()), b.href);
+        assert_eq!("screen", b.media);
+        let e = import(&b.rules[0]).style_sheet.unwrap();
+        assert_eq!(Some("http://example.com/css/d/e.css".to_string()), e.href);
+        // Import cycles aren't loaded.
+        assert_eq!(None, import(&e.rules[0]).style_sheet);
+        assert_eq!(None, import(&e.rules[1]).style_sheet);
+        assert_eq!(3, e.rules.len());
+
+        assert_eq!(
+            vec![
+                "http://example.com/css/a.css",
+                "http://example.com/css/b.css",
+                "http://example.com/css/d/e.css",
+                "http://example.com/css/missing.css",
+                "http://example.com/c.css",
+            ],
+            loader.requests
+        );
+    }
+
+    #[test]
+    fn test_from_document_empty_href() {
+        let window = HtmlParser::new(HtmlTokenizer::new(
+            r#"<link rel=stylesheet href=""><link rel=stylesheet href=" "><link rel=stylesheet>"#
+                .to_string(),
+        ))
+        .construct_tree();
+        let document = window.borrow().document();
+        let mut loader = TestLoader::default();
+        loader.resources.insert(
+            "http://example.com/index.html".to_string(),
+            "p {}".to_string(),
+        );
+
+        let sheets =
+            StyleSheet::from_document(&document, "http://example.com/index.html", &mut loader);
+        assert!(sheets.is_empty());
+        assert!(loader.requests.is_empty());
+    }
+}
@@ -0,0 +1,819 @@
+//! https://www.w3.org/TR/css-syntax-3/#tokenization
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// The line and column of a character in a style sheet. Both start at 1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct SourcePosition {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#tokenization
+#[derive(Debug, Clone, PartialEq)]
+pub enum CssToken {
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-ident-token
+    Ident(String),
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-function-token
+    Function(String),
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-at-keyword-token
+    AtKeyword(String),
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-hash-token
+    ///
+    /// `is_id` is true if the value would be a valid identifier, so that the
+    /// hash can be used as an ID selector.
+    Hash {
+        value: String,
+        is_id: bool,
+    },
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-string-token
+    String(String),
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-bad-string-token
+    BadString,
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-url-token
+    Url(String),
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-bad-url-token
+    BadUrl,
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-delim-token
+    Delim(char),
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-number-token
+    Number {
+        value: f64,
+        is_integer: bool,
+    },
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-percentage-token
+    Percentage(f64),
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-dimension-token
+    Dimension {
+        value: f64,
+        is_integer: bool,
+        unit: String,
+    },
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-whitespace-token
+    Whitespace,
+    /// `<!--`
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-cdo-token
+    Cdo,
+    /// `-->`
+    /// https://www.w3.org/TR/css-syntax-3/#typedef-cdc-token
+    Cdc,
+    Colon,
+    Semicolon,
+    Comma,
+    OpenSquareBracket,
+    CloseSquareBracket,
+    OpenParenthesis,
+    CloseParenthesis,
+    OpenCurlyBracket,
+    CloseCurlyBracket,
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#serialization
+/// Identifiers and URLs aren't escaped.
+impl fmt::Display for CssToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CssToken::Ident(name) => write!(f, "{}", name),
+            CssToken::Function(name) => write!(f, "{}(", name),
+            CssToken::AtKeyword(name) => write!(f, "@{}", name),
+            CssToken::Hash { value, .. } => write!(f, "#{}", value),
+            // https://www.w3.org/TR/cssom-1/#serialize-a-string
+            CssToken::String(value) => {
+                write!(f, "\"")?;
+                for c in value.chars() {
+                    match c {
+                        '"' | '\\' => write!(f, "\\{}", c)?,
+                        c if c.is_ascii_control() => write!(f, "\\{:x} ", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            CssToken::Url(url) => write!(f, "url({})", url),
+            CssToken::BadString | CssToken::BadUrl => Ok(()),
+            CssToken::Delim(c) => write!(f, "{}", c),
+            CssToken::Number { value, .. } => write!(f, "{}", value),
+            CssToken::Percentage(value) => write!(f, "{}%", value),
+            CssToken::Dimension { value, unit, .. } => write!(f, "{}{}", value, unit),
+            CssToken::Whitespace => write!(f, " "),
+            CssToken::Cdo => write!(f, "<!--"),
+            CssToken::Cdc => write!(f, "-->"),
+            CssToken::Colon => write!(f, ":"),
+            CssToken::Semicolon => write!(f, ";"),
+            CssToken::Comma => write!(f, ","),
+            CssToken::OpenSquareBracket => write!(f, "["),
+            CssToken::CloseSquareBracket => write!(f, "]"),
+            CssToken::OpenParenthesis => write!(f, "("),
+            CssToken::CloseParenthesis => write!(f, ")"),
+            CssToken::OpenCurlyBracket => write!(f, "{{"),
+            CssToken::CloseCurlyBracket => write!(f, "}}"),
+        }
+    }
+}
+
+/// Splits a style sheet into `CssToken`s. Comments are dropped.
+pub struct CssTokenizer {
+    pos: usize,
+    input: Vec<char>,
+    /// The offsets at which each line starts, for `position`.
+    line_starts: Vec<usize>,
+    /// The offset of the first character of the last returned token.
+    token_start: usize,
+}
+
+impl CssTokenizer {
+    pub fn new(css: String) -> Self {
+        let input = preprocess(&css);
+        let line_starts = core::iter::once(0)
+            .chain(
+                input
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| **c == '\n')
+                    .map(|(i, _)| i + 1),
+            )
+            .collect();
+        Self {
+            pos: 0,
+            input,
+            line_starts,
+            token_start: 0,
+        }
+    }
+
+    /// Returns where the token last returned by `next` starts.
+    pub fn position(&self) -> SourcePosition {
+        let line = match self.line_starts.binary_search(&self.token_start) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        SourcePosition {
+            line: line + 1,
+            column: self.token_start - self.line_starts[line] + 1,
+        }
+    }
+
+    /// Returns the offsets in `input` where the token last returned by `next`
+    /// starts and ends.
+    pub(crate) fn span(&self) -> (usize, usize) {
+        (self.token_start, self.pos)
+    }
+
+    /// Returns the preprocessed input.
+    pub(crate) fn input(&self) -> &[char] {
+        &self.input
+    }
+
+    /// Returns the `n`th input character after the current position without
+    /// consuming it. `peek(0)` is the next input character.
+    fn peek(&self, n: usize) -> Option<char> {
+        self.input.get(self.pos + n).copied()
+    }
+
+    fn consume(&mut self) -> Option<char> {
+        let c = self.peek(0);
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+    fn reconsume(&mut self) {
+        self.pos -= 1;
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#consume-comment
+    fn consume_comments(&mut self) {
+        while self.peek(0) == Some('/') && self.peek(1) == Some('*') {
+            self.pos += 2;
+            loop {
+                match self.consume() {
+                    Some('*') if self.peek(0) == Some('/') => {
+                        self.pos += 1;
+                        break;
+                    }
+                    // Unterminated comments end at EOF.
+                    None => return,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn consume_whitespace(&mut self) {
+        while self.peek(0).is_some_and(is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes an escaped code point after a `\` that has just been
+    /// consumed.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-escaped-code-point
+    fn consume_escaped_code_point(&mut self) -> char {
+        let c = match self.consume() {
+            Some(c) => c,
+            None => return char::REPLACEMENT_CHARACTER,
+        };
+        if !c.is_ascii_hexdigit() {
+            return c;
+        }
+
+        let mut digits = String::from(c);
+        while digits.len() < 6 && self.peek(0).is_some_and(|c| c.is_ascii_hexdigit()) {
+            digits.push(self.input[self.pos]);
+            self.pos += 1;
+        }
+        if self.peek(0).is_some_and(is_whitespace) {
+            self.pos += 1;
+        }
+        match u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+        {
+            Some('\0') | None => char::REPLACEMENT_CHARACTER,
+            Some(c) => c,
+        }
+    }
+
+    /// Returns true if the next input characters, starting `n` characters
+    /// ahead, are a valid escape.
+    /// https://www.w3.org/TR/css-syntax-3/#starts-with-a-valid-escape
+    fn starts_valid_escape(&self, n: usize) -> bool {
+        self.peek(n) == Some('\\') && self.peek(n + 1) != Some('\n')
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#would-start-an-identifier
+    fn starts_ident_sequence(&self, n: usize) -> bool {
+        match self.peek(n) {
+            Some('-') => {
+                self.peek(n + 1)
+                    .is_some_and(|c| is_ident_start(c) || c == '-')
+                    || self.starts_valid_escape(n + 1)
+            }
+            Some('\\') => self.starts_valid_escape(n),
+            Some(c) => is_ident_start(c),
+            None => false,
+        }
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#starts-with-a-number
+    fn starts_number(&self, n: usize) -> bool {
+        let is_digit = |i: usize| self.peek(i).is_some_and(|c| c.is_ascii_digit());
+        match self.peek(n) {
+            Some('+') | Some('-') => {
+                is_digit(n + 1) || (self.peek(n + 1) == Some('.') && is_digit(n + 2))
+            }
+            Some('.') => is_digit(n + 1),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        }
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#consume-name
+    fn consume_ident_sequence(&mut self) -> String {
+        let mut s = String::new();
+        loop {
+            if self.starts_valid_escape(0) {
+                self.pos += 1;
+                s.push(self.consume_escaped_code_point());
+                continue;
+            }
+            match self.peek(0) {
+                Some(c) if is_ident(c) => {
+                    s.push(c);
+                    self.pos += 1;
+                }
+                _ => return s,
+            }
+        }
+    }
+
+    /// Returns the value of the number and whether it is an integer.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-number
+    fn consume_number(&mut self) -> (f64, bool) {
+        let mut repr = String::new();
+        let mut is_integer = true;
+        let is_digit = |c: Option<char>| c.is_some_and(|c| c.is_ascii_digit());
+
+        if let Some(sign @ ('+' | '-')) = self.peek(0) {
+            repr.push(sign);
+            self.pos += 1;
+        }
+        self.consume_digits(&mut repr);
+        if self.peek(0) == Some('.') && is_digit(self.peek(1)) {
+            repr.push('.');
+            self.pos += 1;
+            self.consume_digits(&mut repr);
+            is_integer = false;
+        }
+        if matches!(self.peek(0), Some('e') | Some('E'))
+            && (is_digit(self.peek(1))
+                || (matches!(self.peek(1), Some('+') | Some('-')) && is_digit(self.peek(2))))
+        {
+            repr.push('e');
+            repr.push(self.input[self.pos + 1]);
+            self.pos += 2;
+            self.consume_digits(&mut repr);
+            is_integer = false;
+        }
+
+        (repr.parse().unwrap_or(0.0), is_integer)
+    }
+
+    fn consume_digits(&mut self, repr: &mut String) {
+        while let Some(c) = self.peek(0).filter(|c| c.is_ascii_digit()) {
+            repr.push(c);
+            self.pos += 1;
+        }
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#consume-numeric-token
+    fn consume_numeric(&mut self) -> CssToken {
+        let (value, is_integer) = self.consume_number();
+        if self.starts_ident_sequence(0) {
+            return CssToken::Dimension {
+                value,
+                is_integer,
+                unit: self.consume_ident_sequence(),
+            };
+        }
+        if self.peek(0) == Some('%') {
+            self.pos += 1;
+            return CssToken::Percentage(value);
+        }
+        CssToken::Number { value, is_integer }
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#consume-ident-like-token
+    fn consume_ident_like(&mut self) -> CssToken {
+        let name = self.consume_ident_sequence();
+        if self.peek(0) != Some('(') {
+            return CssToken::Ident(name);
+        }
+        self.pos += 1;
+
+        if name.eq_ignore_ascii_case("url") {
+            while self.peek(0).is_some_and(is_whitespace) && self.peek(1).is_some_and(is_whitespace)
+            {
+                self.pos += 1;
+            }
+            let is_quote = |c: Option<char>| matches!(c, Some('"') | Some('\''));
+            let quoted = is_quote(self.peek(0))
+                || (self.peek(0).is_some_and(is_whitespace) && is_quote(self.peek(1)));
+            if !quoted {
+                return self.consume_url();
+            }
+        }
+        CssToken::Function(name)
+    }
+
+    /// Consumes a string after its opening quote, `ending`.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-string-token
+    fn consume_string(&mut self, ending: char) -> CssToken {
+        let mut s = String::new();
+        loop {
+            match self.consume() {
+                Some(c) if c == ending => return CssToken::String(s),
+                // Unterminated strings end at EOF.
+                None => return CssToken::String(s),
+                Some('\n') => {
+                    self.reconsume();
+                    return CssToken::BadString;
+                }
+                Some('\\') => match self.peek(0) {
+                    None => {}
+                    // An escaped newline continues the string on the next line.
+                    Some('\n') => self.pos += 1,
+                    Some(_) => s.push(self.consume_escaped_code_point()),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// Consumes an unquoted URL after `url(`.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-url-token
+    fn consume_url(&mut self) -> CssToken {
+        let mut url = String::new();
+        self.consume_whitespace();
+        loop {
+            match self.consume() {
+                Some(')') | None => return CssToken::Url(url),
+                Some(c) if is_whitespace(c) => {
+                    self.consume_whitespace();
+                    match self.peek(0) {
+                        Some(')') | None => {
+                            self.consume();
+                            return CssToken::Url(url);
+                        }
+                        _ => {
+                            self.consume_bad_url_remnants();
+                            return CssToken::BadUrl;
+                        }
+                    }
+                }
+                Some('"') | Some('\'') | Some('(') => {
+                    self.consume_bad_url_remnants();
+                    return CssToken::BadUrl;
+                }
+                Some(c) if is_non_printable(c) => {
+                    self.consume_bad_url_remnants();
+                    return CssToken::BadUrl;
+                }
+                Some('\\') => {
+                    self.reconsume();
+                    if !self.starts_valid_escape(0) {
+                        self.consume_bad_url_remnants();
+                        return CssToken::BadUrl;
+                    }
+                    self.pos += 1;
+                    url.push(self.consume_escaped_code_point());
+                }
+                Some(c) => url.push(c),
+            }
+        }
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#consume-remnants-of-bad-url
+    fn consume_bad_url_remnants(&mut self) {
+        loop {
+            if self.starts_valid_escape(0) {
+                self.pos += 1;
+                self.consume_escaped_code_point();
+                continue;
+            }
+            match self.consume() {
+                Some(')') | None => return,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Iterator for CssTokenizer {
+    type Item = CssToken;
+
+    /// https://www.w3.org/TR/css-syntax-3/#consume-token
+    fn next(&mut self) -> Option<Self::Item> {
+        self.consume_comments();
+        self.token_start = self.pos;
+
+        let c = self.consume()?;
+        let token = match c {
+            c if is_whitespace(c) => {
+                self.consume_whitespace();
+                CssToken::Whitespace
+            }
+            '"' | '\'' => self.consume_string(c),
+            '#' => {
+                if self.peek(0).is_some_and(is_ident) || self.starts_valid_escape(0) {
+                    let is_id = self.starts_ident_sequence(0);
+                    CssToken::Hash {
+                        value: self.consume_ident_sequence(),
+                        is_id,
+                    }
+                } else {
+                    CssToken::Delim(c)
+                }
+            }
+            '(' => CssToken::OpenParenthesis,
+            ')' => CssToken::CloseParenthesis,
+            '+' | '.' => {
+                self.reconsume();
+                if self.starts_number(0) {
+                    self.consume_numeric()
+                } else {
+                    self.pos += 1;
+                    CssToken::Delim(c)
+                }
+            }
+            ',' => CssToken::Comma,
+            '-' => {
+                self.reconsume();
+                if self.starts_number(0) {
+                    self.consume_numeric()
+                } else if self.peek(1) == Some('-') && self.peek(2) == Some('>') {
+                    self.pos += 3;
+                    CssToken::Cdc
+                } else if self.starts_ident_sequence(0) {
+                    self.consume_ident_like()
+                } else {
+                    self.pos += 1;
+                    CssToken::Delim(c)
+                }
+            }
+            ':' => CssToken::Colon,
+            ';' => CssToken::Semicolon,
+            '<' => {
+                if self.peek(0) == Some('!')
+                    && self.peek(1) == Some('-')
+                    && self.peek(2) == Some('-')
+                {
+                    self.pos += 3;
+                    CssToken::Cdo
+                } else {
+                    CssToken::Delim(c)
+                }
+            }
+            '@' => {
+                if self.starts_ident_sequence(0) {
+                    CssToken::AtKeyword(self.consume_ident_sequence())
+                } else {
+                    CssToken::Delim(c)
+                }
+            }
+            '[' => CssToken::OpenSquareBracket,
+            ']' => CssToken::CloseSquareBracket,
+            '{' => CssToken::OpenCurlyBracket,
+            '}' => CssToken::CloseCurlyBracket,
+            '\\' => {
+                self.reconsume();
+                if self.starts_valid_escape(0) {
+                    self.consume_ident_like()
+                } else {
+                    // An escaped newline is a parse error.
+                    self.pos += 1;
+                    CssToken::Delim(c)
+                }
+            }
+            c if c.is_ascii_digit() => {
+                self.reconsume();
+                self.consume_numeric()
+            }
+            c if is_ident_start(c) => {
+                self.reconsume();
+                self.consume_ident_like()
+            }
+            c => CssToken::Delim(c),
+        };
+        Some(token)
+    }
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#input-preprocessing
+fn preprocess(css: &str) -> Vec<char> {
+    let mut input = Vec::with_capacity(css.len());
+    let mut chars = css.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => {
+                chars.next_if_eq(&'\n');
+                input.push('\n');
+            }
+            '\x0c' => input.push('\n'),
+            '\0' => input.push(char::REPLACEMENT_CHARACTER),
+            c => input.push(c),
+        }
+    }
+    input
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#whitespace
+fn is_whitespace(c: char) -> bool {
+    c == '\n' || c == '\t' || c == ' '
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#ident-start-code-point
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || !c.is_ascii() || c == '_'
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#ident-code-point
+fn is_ident(c: char) -> bool {
+    is_ident_start(c) || c.is_ascii_digit() || c == '-'
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#non-printable-code-point
+fn is_non_printable(c: char) -> bool {
+    matches!(c, '\0'..='\x08' | '\x0b' | '\x0e'..='\x1f' | '\x7f')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+    use alloc::vec;
+
+    fn tokenize(css: &str) -> Vec<CssToken> {
+        CssTokenizer::new(css.to_string()).collect()
+    }
+
+    fn ident(s: &str) -> CssToken {
+        CssToken::Ident(s.to_string())
+    }
+
+    #[test]
+    fn test_empty() {
+        assert!(tokenize("").is_empty());
+        assert!(tokenize("/* only a comment */").is_empty());
+    }
+
+    #[test]
+    fn test_rule() {
+        assert_eq!(
+            vec![
+                CssToken::Hash {
+                    value: "main".to_string(),
+                    is_id: true,
+                },
+                CssToken::Whitespace,
+                CssToken::Delim('>'),
+                CssToken::Whitespace,
+                CssToken::Delim('.'),
+                ident("item"),
+                CssToken::OpenSquareBracket,
+                ident("lang"),
+                CssToken::Delim('|'),
+                CssToken::Delim('='),
+                CssToken::String("en".to_string()),
+                CssToken::CloseSquareBracket,
+                CssToken::Colon,
+                CssToken::Function("nth-child".to_string()),
+                CssToken::Dimension {
+                    value: 2.0,
+                    is_integer: true,
+                    unit: "n".to_string(),
+                },
+                CssToken::Number {
+                    value: 1.0,
+                    is_integer: true,
+                },
+                CssToken::CloseParenthesis,
+                CssToken::OpenCurlyBracket,
+                ident("color"),
+                CssToken::Colon,
+                CssToken::Hash {
+                    value: "0f0".to_string(),
+                    is_id: false,
+                },
+                CssToken::Semicolon,
+                CssToken::CloseCurlyBracket,
+            ],
+            tokenize("#main > .item[lang|='en']:nth-child(2n+1){color:/* c */#0f0;}")
+        );
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(
+            vec![
+                CssToken::Number {
+                    value: 12.0,
+                    is_integer: true,
+                },
+                CssToken::Whitespace,
+                CssToken::Number {
+                    value: -0.5,
+                    is_integer: false,
+                },
+                CssToken::Whitespace,
+                CssToken::Percentage(50.0),
+                CssToken::Whitespace,
+                CssToken::Dimension {
+                    value: 1.5e2,
+                    is_integer: false,
+                    unit: "px".to_string(),
+                },
+                CssToken::Whitespace,
+                CssToken::Number {
+                    value: 0.25,
+                    is_integer: false,
+                },
+                CssToken::Whitespace,
+                CssToken::Delim('+'),
+                ident("a"),
+                CssToken::Whitespace,
+                CssToken::Dimension {
+                    value: 1.0,
+                    is_integer: true,
+                    unit: "e".to_string(),
+                },
+                CssToken::Whitespace,
+                CssToken::Dimension {
+                    value: 3.0,
+                    is_integer: true,
+                    unit: "-x".to_string(),
+                },
+            ],
+            tokenize("12 -.5 50% 1.5E+2px +.25 +a 1e 3-x")
+        );
+    }
+
+    #[test]
+    fn test_ident_like() {
+        assert_eq!(
+            vec![
+                ident("-webkit-box"),
+                CssToken::Whitespace,
+                ident("--custom"),
+                CssToken::Whitespace,
+                CssToken::AtKeyword("media".to_string()),
+                CssToken::Whitespace,
+                CssToken::Delim('@'),
+                CssToken::Whitespace,
+                CssToken::Delim('-'),
+                CssToken::Whitespace,
+                CssToken::Cdo,
+                CssToken::Whitespace,
+                CssToken::Cdc,
+                CssToken::Whitespace,
+                CssToken::Delim('<'),
+            ],
+            tokenize("-webkit-box --custom @media @ - <!-- --> <")
+        );
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert_eq!(
+            vec![
+                ident("a.b"),
+                CssToken::Whitespace,
+                // The whitespace after a hex escape belongs to the escape.
+                ident("\u{e9}t\u{e9}\u{fffd}"),
+                CssToken::Hash {
+                    value: "1x".to_string(),
+                    is_id: true,
+                },
+                CssToken::Whitespace,
+                CssToken::Delim('\\'),
+                CssToken::Whitespace,
+            ],
+            tokenize("a\\.b \\e9 t\\0000e9 \\0 #\\31 x \\\n")
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(
+            vec![
+                CssToken::String("it's".to_string()),
+                CssToken::Whitespace,
+                CssToken::String("ab\"".to_string()),
+                CssToken::Whitespace,
+                CssToken::BadString,
+                CssToken::Whitespace,
+                ident("x"),
+                CssToken::Whitespace,
+                CssToken::String("eof".to_string()),
+            ],
+            tokenize("\"it's\" 'a\\\nb\\\"' 'broken\nx \"eof")
+        );
+    }
+
+    #[test]
+    fn test_urls() {
+        assert_eq!(
+            vec![
+                CssToken::Url("a.png".to_string()),
+                CssToken::Whitespace,
+                CssToken::Url("b c)".to_string()),
+                CssToken::Whitespace,
+                CssToken::Function("url".to_string()),
+                CssToken::Whitespace,
+                CssToken::String("d.png".to_string()),
+                CssToken::CloseParenthesis,
+                CssToken::Whitespace,
+                CssToken::BadUrl,
+                CssToken::Whitespace,
+                CssToken::BadUrl,
+                CssToken::Whitespace,
+                ident("ok"),
+            ],
+            tokenize("url(a.png) URL(  b\\ c\\) ) url( \"d.png\") url(e f) url(g\"h\\)) ok")
+        );
+    }
+
+    #[test]
+    fn test_positions() {
+        let mut tokenizer = CssTokenizer::new("a {\r\n  b: 1;\n}".to_string());
+        let mut positions = Vec::new();
+        while let Some(token) = tokenizer.next() {
+            if token != CssToken::Whitespace {
+                let p = tokenizer.position();
+                positions.push((token, p.line, p.column));
+            }
+        }
+        assert_eq!(
+            vec![
+                (ident("a"), 1, 1),
+                (CssToken::OpenCurlyBracket, 1, 3),
+                (ident("b"), 2, 3),
+                (CssToken::Colon, 2, 4),
+                (
+                    CssToken::Number {
+                        value: 1.0,
+                        is_integer: true
+                    },
+                    2,
+                    6
+                ),
+                (CssToken::Semicolon, 2, 7),
+                (CssToken::CloseCurlyBracket, 3, 1),
+            ],
+            positions
+        );
+    }
+}
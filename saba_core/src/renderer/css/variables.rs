@@ -0,0 +1,276 @@
+//! https://www.w3.org/TR/css-variables-1/
+
+use crate::renderer::css::stylesheet::ComponentValue;
+use crate::renderer::css::token::CssToken;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+/// The computed values of the custom properties of an element, by name.
+/// Custom properties whose value is the guaranteed-invalid value are absent.
+/// https://www.w3.org/TR/css-variables-1/#guaranteed-invalid
+pub type CustomProperties = BTreeMap<String, Vec<ComponentValue>>;
+
+/// A `var()` function.
+/// https://www.w3.org/TR/css-variables-1/#funcdef-var
+struct VarReference<'a> {
+    name: &'a str,
+    /// The value after the comma, which may be empty, or `None` if there is
+    /// no comma.
+    fallback: Option<&'a [ComponentValue]>,
+}
+
+impl<'a> VarReference<'a> {
+    /// Parses the arguments of a `var()` function.
+    fn parse(arguments: &'a [ComponentValue]) -> Option<Self> {
+        let arguments = trim_whitespace(arguments);
+        let name = match arguments.first()? {
+            ComponentValue::Token(CssToken::Ident(name)) if name.starts_with("--") => name,
+            _ => return None,
+        };
+        match trim_whitespace(&arguments[1..]) {
+            [] => Some(Self {
+                name,
+                fallback: None,
+            }),
+            [ComponentValue::Token(CssToken::Comma), fallback @ ..] => Some(Self {
+                name,
+                fallback: Some(trim_whitespace(fallback)),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Returns true if `value` contains a `var()` function, which makes it
+/// valid at parse time for any property.
+/// https://www.w3.org/TR/css-variables-1/#using-variables
+pub fn contains_var(value: &[ComponentValue]) -> bool {
+    value.iter().any(|v| match v {
+        ComponentValue::Function { name, value } => {
+            name.eq_ignore_ascii_case("var") || contains_var(value)
+        }
+        ComponentValue::Block { value, .. } => contains_var(value),
+        ComponentValue::Token(_) => false,
+    })
+}
+
+/// Returns the names of the custom properties that `value` references,
+/// including in fallbacks.
+fn references(value: &[ComponentValue]) -> Vec<&str> {
+    let mut names = Vec::new();
+    for v in value {
+        match v {
+            ComponentValue::Function { name, value } if name.eq_ignore_ascii_case("var") => {
+                if let Some(reference) = VarReference::parse(value) {
+                    names.push(reference.name);
+                    names.extend(reference.fallback.map(references).unwrap_or_default());
+                }
+            }
+            ComponentValue::Function { value, .. } | ComponentValue::Block { value, .. } => {
+                names.extend(references(value));
+            }
+            ComponentValue::Token(_) => {}
+        }
+    }
+    names
+}
+
+/// Replaces the `var()` functions in `value` with the value of the custom
+/// property they reference, as returned by `lookup`, or with their fallback.
+/// Returns `None` if a function can't be replaced, in which case `value` is
+/// invalid at computed-value time.
+/// https://www.w3.org/TR/css-variables-1/#substitute-a-var
+pub fn substitute(
+    value: &[ComponentValue],
+    lookup: &mut dyn FnMut(&str) -> Option<Vec<ComponentValue>>,
+) -> Option<Vec<ComponentValue>> {
+    let mut result = Vec::new();
+    for v in value {
+        match v {
+            ComponentValue::Function { name, value } if name.eq_ignore_ascii_case("var") => {
+                let reference = VarReference::parse(value)?;
+                match (lookup(reference.name), reference.fallback) {
+                    (Some(value), _) => result.extend(value),
+                    (None, Some(fallback)) => result.extend(substitute(fallback, lookup)?),
+                    (None, None) => return None,
+                }
+            }
+            ComponentValue::Function { name, value } => result.push(ComponentValue::Function {
+                name: name.clone(),
+                value: substitute(value, lookup)?,
+            }),
+            ComponentValue::Block { open, value } => result.push(ComponentValue::Block {
+                open: *open,
+                value: substitute(value, lookup)?,
+            }),
+            ComponentValue::Token(_) => result.push(v.clone()),
+        }
+    }
+    Some(result)
+}
+
+/// Computes the custom properties of an element from `declared`, the
+/// cascaded values of the custom properties declared for it that aren't
+/// CSS-wide keywords, and `inherited`, those of its parent.
+/// https://www.w3.org/TR/css-variables-1/#defining-variables
+pub fn compute_custom_properties(
+    declared: &BTreeMap<&str, &[ComponentValue]>,
+    mut inherited: CustomProperties,
+) -> CustomProperties {
+    let mut computed = BTreeMap::new();
+    for name in declared.keys() {
+        resolve(name, declared, &inherited, &mut computed);
+    }
+    for (name, value) in computed {
+        match value {
+            Some(value) => inherited.insert(name, value),
+            None => inherited.remove(&name),
+        };
+    }
+    inherited
+}
+
+/// Returns the computed value of the custom property `name`, or `None` if it
+/// is the guaranteed-invalid value. `computed` holds the values of the
+/// declared custom properties computed so far.
+fn resolve(
+    name: &str,
+    declared: &BTreeMap<&str, &[ComponentValue]>,
+    inherited: &CustomProperties,
+    computed: &mut BTreeMap<String, Option<Vec<ComponentValue>>>,
+) -> Option<Vec<ComponentValue>> {
+    if let Some(value) = computed.get(name) {
+        return value.clone();
+    }
+    let value = match declared.get(name) {
+        None => return inherited.get(name).cloned(),
+        // https://www.w3.org/TR/css-variables-1/#cycles
+        // Custom properties in a dependency cycle are invalid at
+        // computed-value time.
+        Some(_) if is_in_cycle(name, declared) => None,
+        Some(value) => substitute(value, &mut |n| resolve(n, declared, inherited, computed)),
+    };
+    computed.insert(name.to_string(), value.clone());
+    value
+}
+
+/// Returns true if the custom property `name` depends on itself through the
+/// `var()` functions in `declared`.
+fn is_in_cycle(name: &str, declared: &BTreeMap<&str, &[ComponentValue]>) -> bool {
+    let mut pending = declared
+        .get(name)
+        .map(|v| references(v))
+        .unwrap_or_default();
+    let mut visited = Vec::new();
+    while let Some(n) = pending.pop() {
+        if n == name {
+            return true;
+        }
+        if visited.contains(&n) {
+            continue;
+        }
+        visited.push(n);
+        if let Some(value) = declared.get(n) {
+            pending.extend(references(value));
+        }
+    }
+    false
+}
+
+fn trim_whitespace(value: &[ComponentValue]) -> &[ComponentValue] {
+    let is_content = |v: &ComponentValue| *v != ComponentValue::Token(CssToken::Whitespace);
+    match value.iter().position(is_content) {
+        Some(start) => {
+            let end = value.iter().rposition(is_content).unwrap_or(start);
+            &value[start..=end]
+        }
+        None => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::css::parser::CssParser;
+    use crate::renderer::css::token::CssTokenizer;
+
+    fn parse(css: &str) -> Vec<ComponentValue> {
+        CssParser::new(CssTokenizer::new(css.to_string())).parse_component_values()
+    }
+
+    fn serialize(value: &[ComponentValue]) -> String {
+        value.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_substitute() {
+        let mut properties = CustomProperties::new();
+        properties.insert("--a".to_string(), parse("1px"));
+        properties.insert("--b".to_string(), parse("2px 3px"));
+        properties.insert("--empty".to_string(), Vec::new());
+        let mut lookup = |name: &str| properties.get(name).cloned();
+
+        let mut substituted =
+            |css: &str| substitute(&parse(css), &mut lookup).map(|v| serialize(&v));
+        assert!(contains_var(&parse("calc(1px + (VAR(--a)))")));
+        assert!(!contains_var(&parse("calc(1px + 2px)")));
+        assert_eq!(Some("1px".to_string()), substituted("var(--a)"));
+        assert_eq!(Some("0 2px 3px".to_string()), substituted("0 var( --b )"));
+        assert_eq!(
+            Some("calc(1px + (1px))".to_string()),
+            substituted("calc(var(--a) + (var(--a)))")
+        );
+        assert_eq!(Some("a  b".to_string()), substituted("a var(--empty) b"));
+        assert_eq!(
+            Some("red, blue".to_string()),
+            substituted("var(--c, red, blue)")
+        );
+        assert_eq!(Some("1px".to_string()), substituted("var(--c, var(--a))"));
+        assert_eq!(Some("".to_string()), substituted("var(--c,)"));
+        assert_eq!(None, substituted("var(--c)"));
+        assert_eq!(None, substituted("var(--c, var(--d))"));
+        assert_eq!(None, substituted("var(a)"));
+        assert_eq!(None, substituted("var(--a --b)"));
+    }
+
+    #[test]
+    fn test_compute_custom_properties() {
+        let values: Vec<(&str, Vec<ComponentValue>)> = [
+            ("--a", "var(--b) var(--c)"),
+            ("--b", "var(--inherited)"),
+            ("--c", "var(--b, x)"),
+            ("--d", "var(--e)"),
+            ("--e", "var(--f, 1) var(--d, 2)"),
+            ("--f", "var(--e)"),
+            ("--g", "var(--d, fallback)"),
+            ("--h", "var(--missing)"),
+            ("--i", "var(--missing, var(--b))"),
+        ]
+        .iter()
+        .map(|(name, css)| (*name, parse(css)))
+        .collect();
+        let declared = values
+            .iter()
+            .map(|(name, value)| (*name, value.as_slice()))
+            .collect();
+        let mut inherited = CustomProperties::new();
+        inherited.insert("--inherited".to_string(), parse("i"));
+        inherited.insert("--d".to_string(), parse("inherited"));
+        inherited.insert("--h".to_string(), parse("inherited"));
+
+        let computed = compute_custom_properties(&declared, inherited);
+        let get = |name: &str| computed.get(name).map(|v| serialize(v));
+        assert_eq!(Some("i i".to_string()), get("--a"));
+        assert_eq!(Some("i".to_string()), get("--b"));
+        assert_eq!(Some("i".to_string()), get("--c"));
+        assert_eq!(None, get("--d"));
+        assert_eq!(None, get("--e"));
+        assert_eq!(None, get("--f"));
+        assert_eq!(Some("fallback".to_string()), get("--g"));
+        assert_eq!(None, get("--h"));
+        assert_eq!(Some("i".to_string()), get("--i"));
+        assert_eq!(Some("i".to_string()), get("--inherited"));
+    }
+}
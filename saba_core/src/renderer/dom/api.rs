@@ -0,0 +1,1673 @@
+//! Operations on node handles.
+//!
+//! Trees should be modified through these functions rather than the raw
+//! `Node` setters so that document-level state, such as the id index, stays
+//! in sync with the tree.
+
+use crate::error::Error;
+use crate::renderer::css::computed_style::Display;
+use crate::renderer::dom::mutation::queue_attribute_mutation_record;
+use crate::renderer::dom::mutation::queue_character_data_mutation_record;
+use crate::renderer::dom::mutation::queue_tree_mutation_record;
+use crate::renderer::dom::node::validate_and_extract;
+use crate::renderer::dom::node::Element;
+use crate::renderer::dom::node::ElementKind;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use crate::renderer::dom::range::update_live_ranges_for_insert;
+use crate::renderer::dom::range::update_live_ranges_for_normalize;
+use crate::renderer::dom::range::update_live_ranges_for_remove;
+use crate::renderer::dom::range::update_live_ranges_for_replace_data;
+use crate::renderer::dom::selector::SelectorList;
+use crate::renderer::dom::traversal::update_node_iterators_for_remove;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::iter;
+use core::str::FromStr;
+
+/// Returns the root of the tree that `node` belongs to.
+/// https://dom.spec.whatwg.org/#concept-tree-root
+pub fn tree_root(node: &Rc<RefCell<Node>>) -> Rc<RefCell<Node>> {
+    let root = node.borrow().ancestors().last();
+    root.unwrap_or_else(|| node.clone())
+}
+
+/// Returns the document `node` is connected to, if any.
+/// https://dom.spec.whatwg.org/#connected
+pub fn owner_document(node: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    let root = tree_root(node);
+    let is_document = root.borrow().kind() == NodeKind::Document;
+    if is_document {
+        Some(root)
+    } else {
+        None
+    }
+}
+
+/// Returns `node` followed by its descendants in tree order.
+/// https://dom.spec.whatwg.org/#concept-tree-inclusive-descendant
+pub fn inclusive_descendants(node: &Rc<RefCell<Node>>) -> impl Iterator<Item = Rc<RefCell<Node>>> {
+    iter::once(node.clone()).chain(node.borrow().descendants())
+}
+
+/// Returns true if `ancestor` is `node` or one of its ancestors.
+/// https://dom.spec.whatwg.org/#concept-tree-inclusive-ancestor
+pub fn is_inclusive_ancestor(ancestor: &Rc<RefCell<Node>>, node: &Rc<RefCell<Node>>) -> bool {
+    Rc::ptr_eq(ancestor, node) || node.borrow().ancestors().any(|a| Rc::ptr_eq(&a, ancestor))
+}
+
+/// Returns the number of preceding siblings of `node`.
+/// https://dom.spec.whatwg.org/#concept-tree-index
+pub fn index(node: &Rc<RefCell<Node>>) -> usize {
+    let mut index = 0;
+    let mut previous = node.borrow().previous_sibling().upgrade();
+    while let Some(p) = previous {
+        index += 1;
+        previous = p.borrow().previous_sibling().upgrade();
+    }
+    index
+}
+
+/// Returns the number of characters of a character data node, 0 for a
+/// doctype, and the number of children otherwise. Offsets into character
+/// data count `char`s rather than UTF-16 code units.
+/// https://dom.spec.whatwg.org/#concept-node-length
+pub fn node_length(node: &Rc<RefCell<Node>>) -> usize {
+    match node.borrow().kind {
+        NodeKind::Text(ref s)
+        | NodeKind::Comment(ref s)
+        | NodeKind::ProcessingInstruction { data: ref s, .. } => s.chars().count(),
+        NodeKind::DocumentType { .. } => 0,
+        _ => node.borrow().children().count(),
+    }
+}
+
+/// Compares the positions of two nodes of the same tree in tree order.
+/// An ancestor comes before its descendants.
+/// https://dom.spec.whatwg.org/#concept-tree-order
+pub(crate) fn tree_order(a: &Rc<RefCell<Node>>, b: &Rc<RefCell<Node>>) -> Ordering {
+    let inclusive_ancestors = |node: &Rc<RefCell<Node>>| {
+        let mut nodes: Vec<Rc<RefCell<Node>>> = iter::once(node.clone())
+            .chain(node.borrow().ancestors())
+            .collect();
+        nodes.reverse();
+        nodes
+    };
+    let a_ancestors = inclusive_ancestors(a);
+    let b_ancestors = inclusive_ancestors(b);
+
+    let common = a_ancestors
+        .iter()
+        .zip(b_ancestors.iter())
+        .take_while(|(x, y)| Rc::ptr_eq(x, y))
+        .count();
+    match (a_ancestors.get(common), b_ancestors.get(common)) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Less,
+        (Some(_), None) => Ordering::Greater,
+        (Some(x), Some(y)) => index(x).cmp(&index(y)),
+    }
+}
+
+/// https://dom.spec.whatwg.org/#dom-node-document_position_disconnected
+pub const DOCUMENT_POSITION_DISCONNECTED: u16 = 0x01;
+/// https://dom.spec.whatwg.org/#dom-node-document_position_preceding
+pub const DOCUMENT_POSITION_PRECEDING: u16 = 0x02;
+/// https://dom.spec.whatwg.org/#dom-node-document_position_following
+pub const DOCUMENT_POSITION_FOLLOWING: u16 = 0x04;
+/// https://dom.spec.whatwg.org/#dom-node-document_position_contains
+pub const DOCUMENT_POSITION_CONTAINS: u16 = 0x08;
+/// https://dom.spec.whatwg.org/#dom-node-document_position_contained_by
+pub const DOCUMENT_POSITION_CONTAINED_BY: u16 = 0x10;
+/// https://dom.spec.whatwg.org/#dom-node-document_position_implementation_specific
+pub const DOCUMENT_POSITION_IMPLEMENTATION_SPECIFIC: u16 = 0x20;
+
+/// Returns the position of `other` relative to `reference` as a bitmask of
+/// the `DOCUMENT_POSITION_*` constants.
+/// https://dom.spec.whatwg.org/#dom-node-comparedocumentposition
+pub fn compare_document_position(reference: &Rc<RefCell<Node>>, other: &Rc<RefCell<Node>>) -> u16 {
+    if Rc::ptr_eq(reference, other) {
+        return 0;
+    }
+
+    if !Rc::ptr_eq(&tree_root(reference), &tree_root(other)) {
+        // Disconnected nodes are ordered consistently by their addresses.
+        let direction = if Rc::as_ptr(other) < Rc::as_ptr(reference) {
+            DOCUMENT_POSITION_PRECEDING
+        } else {
+            DOCUMENT_POSITION_FOLLOWING
+        };
+        return DOCUMENT_POSITION_DISCONNECTED
+            | DOCUMENT_POSITION_IMPLEMENTATION_SPECIFIC
+            | direction;
+    }
+
+    if is_inclusive_ancestor(other, reference) {
+        return DOCUMENT_POSITION_CONTAINS | DOCUMENT_POSITION_PRECEDING;
+    }
+    if is_inclusive_ancestor(reference, other) {
+        return DOCUMENT_POSITION_CONTAINED_BY | DOCUMENT_POSITION_FOLLOWING;
+    }
+    match tree_order(other, reference) {
+        Ordering::Less => DOCUMENT_POSITION_PRECEDING,
+        _ => DOCUMENT_POSITION_FOLLOWING,
+    }
+}
+
+/// Returns true if `a` and `b` are the same node.
+/// https://dom.spec.whatwg.org/#dom-node-issamenode
+pub fn is_same_node(a: &Rc<RefCell<Node>>, b: &Rc<RefCell<Node>>) -> bool {
+    Rc::ptr_eq(a, b)
+}
+
+/// Returns true if `a` and `b` have the same kind, data, attributes and
+/// children, compared recursively. Attribute order does not matter.
+/// https://dom.spec.whatwg.org/#concept-node-equals
+pub fn is_equal_node(a: &Rc<RefCell<Node>>, b: &Rc<RefCell<Node>>) -> bool {
+    if Rc::ptr_eq(a, b) {
+        return true;
+    }
+
+    let equal_kinds = match (&a.borrow().kind, &b.borrow().kind) {
+        (NodeKind::Document, NodeKind::Document) => true,
+        (NodeKind::DocumentFragment, NodeKind::DocumentFragment) => true,
+        (
+            NodeKind::DocumentType {
+                name: n1,
+                public_id: p1,
+                system_id: s1,
+            },
+            NodeKind::DocumentType {
+                name: n2,
+                public_id: p2,
+                system_id: s2,
+            },
+        ) => n1 == n2 && p1 == p2 && s1 == s2,
+        (NodeKind::Element(e1), NodeKind::Element(e2)) => {
+            let (attributes1, attributes2) = (e1.attributes(), e2.attributes());
+            e1.kind() == e2.kind()
+                && e1.namespace_uri() == e2.namespace_uri()
+                && e1.prefix() == e2.prefix()
+                && attributes1.len() == attributes2.len()
+                && attributes1.iter().all(|attr| {
+                    e2.get_attribute_ns(attr.namespace().as_deref(), &attr.local_name())
+                        == Some(attr.value())
+                })
+        }
+        (
+            NodeKind::ProcessingInstruction {
+                target: t1,
+                data: d1,
+            },
+            NodeKind::ProcessingInstruction {
+                target: t2,
+                data: d2,
+            },
+        ) => t1 == t2 && d1 == d2,
+        (NodeKind::Text(d1), NodeKind::Text(d2)) => d1 == d2,
+        (NodeKind::Comment(d1), NodeKind::Comment(d2)) => d1 == d2,
+        _ => false,
+    };
+    if !equal_kinds {
+        return false;
+    }
+
+    let children1: Vec<Rc<RefCell<Node>>> = a.borrow().children().collect();
+    let children2: Vec<Rc<RefCell<Node>>> = b.borrow().children().collect();
+    children1.len() == children2.len()
+        && children1
+            .iter()
+            .zip(children2.iter())
+            .all(|(c1, c2)| is_equal_node(c1, c2))
+}
+
+fn is_child_of(child: &Rc<RefCell<Node>>, parent: &Rc<RefCell<Node>>) -> bool {
+    match child.borrow().parent().upgrade() {
+        Some(p) => Rc::ptr_eq(&p, parent),
+        None => false,
+    }
+}
+
+/// https://dom.spec.whatwg.org/#dom-node-appendchild
+pub fn append_child(parent: &Rc<RefCell<Node>>, node: Rc<RefCell<Node>>) -> Result<(), Error> {
+    insert_before(parent, node, None)
+}
+
+fn hierarchy_request_error(message: &str) -> Error {
+    Error::UnexpectedInput(format!("HierarchyRequestError: {}", message))
+}
+
+fn is_element(node: &Rc<RefCell<Node>>) -> bool {
+    matches!(node.borrow().kind, NodeKind::Element(_))
+}
+
+fn is_doctype(node: &Rc<RefCell<Node>>) -> bool {
+    matches!(node.borrow().kind, NodeKind::DocumentType { .. })
+}
+
+/// https://dom.spec.whatwg.org/#concept-node-ensure-pre-insertion-validity
+fn ensure_pre_insertion_validity(
+    parent: &Rc<RefCell<Node>>,
+    node: &Rc<RefCell<Node>>,
+    child: Option<&Rc<RefCell<Node>>>,
+) -> Result<(), Error> {
+    let parent_kind = parent.borrow().kind();
+    if !matches!(
+        parent_kind,
+        NodeKind::Document | NodeKind::DocumentFragment | NodeKind::Element(_)
+    ) {
+        return Err(hierarchy_request_error("the parent cannot have children"));
+    }
+
+    let is_inclusive_ancestor =
+        Rc::ptr_eq(node, parent) || parent.borrow().ancestors().any(|a| Rc::ptr_eq(&a, node));
+    if is_inclusive_ancestor {
+        return Err(hierarchy_request_error(
+            "a node cannot be inserted into itself",
+        ));
+    }
+
+    if let Some(c) = child {
+        if !is_child_of(c, parent) {
+            return Err(Error::UnexpectedInput(
+                "NotFoundError: the reference node is not a child of the parent".to_string(),
+            ));
+        }
+    }
+
+    let node_kind = node.borrow().kind();
+    match node_kind {
+        NodeKind::Document => {
+            return Err(hierarchy_request_error("a document cannot be inserted"));
+        }
+        NodeKind::Text(_) if parent_kind == NodeKind::Document => {
+            return Err(hierarchy_request_error(
+                "a text node cannot be a child of a document",
+            ));
+        }
+        NodeKind::DocumentType { .. } if parent_kind != NodeKind::Document => {
+            return Err(hierarchy_request_error(
+                "a doctype can only be a child of a document",
+            ));
+        }
+        _ => {}
+    }
+
+    if parent_kind != NodeKind::Document {
+        return Ok(());
+    }
+
+    let parent_has_element_child = parent
+        .borrow()
+        .children()
+        .any(|c| is_element(&c) && !Rc::ptr_eq(&c, node));
+    let doctype_follows_child = match child {
+        Some(c) => iter::once(c.clone())
+            .chain(c.borrow().following())
+            .any(|n| is_doctype(&n)),
+        None => false,
+    };
+    let element_precedes_child = match child {
+        Some(c) => c
+            .borrow()
+            .preceding()
+            .any(|n| is_element(&n) && !Rc::ptr_eq(&n, node)),
+        None => false,
+    };
+
+    match node_kind {
+        NodeKind::DocumentFragment => {
+            let element_children = node.borrow().children().filter(is_element).count();
+            let has_text_child = node
+                .borrow()
+                .children()
+                .any(|c| matches!(c.borrow().kind, NodeKind::Text(_)));
+            if element_children > 1 || has_text_child {
+                return Err(hierarchy_request_error(
+                    "a document can have only one element child",
+                ));
+            }
+            if element_children == 1 && (parent_has_element_child || doctype_follows_child) {
+                return Err(hierarchy_request_error(
+                    "a document can have only one element child",
+                ));
+            }
+        }
+        NodeKind::Element(_) => {
+            if parent_has_element_child || doctype_follows_child {
+                return Err(hierarchy_request_error(
+                    "a document can have only one element child",
+                ));
+            }
+        }
+        NodeKind::DocumentType { .. } => {
+            let parent_has_doctype_child = parent
+                .borrow()
+                .children()
+                .any(|c| is_doctype(&c) && !Rc::ptr_eq(&c, node));
+            let element_child_before = match child {
+                Some(_) => element_precedes_child,
+                None => parent_has_element_child,
+            };
+            if parent_has_doctype_child || element_child_before {
+                return Err(hierarchy_request_error(
+                    "a doctype must come first and only once in a document",
+                ));
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Inserts `node` into `parent` before `child`, or at the end when `child`
+/// is `None`. `node` is removed from its current parent first. When `node`
+/// is a `DocumentFragment`, its children are inserted instead.
+/// https://dom.spec.whatwg.org/#dom-node-insertbefore
+pub fn insert_before(
+    parent: &Rc<RefCell<Node>>,
+    node: Rc<RefCell<Node>>,
+    child: Option<Rc<RefCell<Node>>>,
+) -> Result<(), Error> {
+    ensure_pre_insertion_validity(parent, &node, child.as_ref())?;
+
+    // https://dom.spec.whatwg.org/#concept-node-pre-insert
+    // If `child` is `node`, insert before the next sibling instead.
+    let child = match child {
+        Some(c) if Rc::ptr_eq(&c, &node) => c.borrow().next_sibling(),
+        c => c,
+    };
+
+    let is_fragment = node.borrow().kind() == NodeKind::DocumentFragment;
+    let nodes: Vec<Rc<RefCell<Node>>> = if is_fragment {
+        let children: Vec<Rc<RefCell<Node>>> = node.borrow().children().collect();
+        for c in &children {
+            remove(&node, c, true);
+        }
+        queue_tree_mutation_record(&node, Vec::new(), children.clone(), None, None);
+        children
+    } else {
+        let old_parent = node.borrow().parent().upgrade();
+        if let Some(old_parent) = old_parent {
+            remove(&old_parent, &node, false);
+        }
+        vec![node]
+    };
+
+    let previous_sibling = match child {
+        Some(ref c) => c.borrow().previous_sibling().upgrade(),
+        None => parent.borrow().last_child().upgrade(),
+    };
+    for n in &nodes {
+        insert(parent, n.clone(), child.clone());
+    }
+    queue_tree_mutation_record(parent, nodes, Vec::new(), previous_sibling, child);
+
+    Ok(())
+}
+
+/// Links `node` into `parent` before `child` without any validation.
+/// https://dom.spec.whatwg.org/#concept-node-insert
+fn insert(parent: &Rc<RefCell<Node>>, node: Rc<RefCell<Node>>, child: Option<Rc<RefCell<Node>>>) {
+    if let Some(ref c) = child {
+        update_live_ranges_for_insert(parent, index(c));
+    }
+
+    node.borrow_mut().set_parent(Rc::downgrade(parent));
+    match child {
+        Some(c) => {
+            let previous_sibling = c.borrow().previous_sibling().upgrade();
+            match previous_sibling {
+                Some(ref p) => {
+                    p.borrow_mut().set_next_sibling(Some(node.clone()));
+                    node.borrow_mut().set_previous_sibling(Rc::downgrade(p));
+                }
+                None => parent.borrow_mut().set_first_child(Some(node.clone())),
+            }
+            c.borrow_mut().set_previous_sibling(Rc::downgrade(&node));
+            node.borrow_mut().set_next_sibling(Some(c));
+        }
+        None => {
+            let last_child = parent.borrow().last_child().upgrade();
+            match last_child {
+                Some(ref l) => {
+                    l.borrow_mut().set_next_sibling(Some(node.clone()));
+                    node.borrow_mut().set_previous_sibling(Rc::downgrade(l));
+                }
+                None => parent.borrow_mut().set_first_child(Some(node.clone())),
+            }
+            parent.borrow_mut().set_last_child(Rc::downgrade(&node));
+        }
+    }
+
+    if let Some(document) = owner_document(parent) {
+        register_ids(&document, &node);
+    }
+}
+
+/// https://dom.spec.whatwg.org/#dom-node-removechild
+pub fn remove_child(
+    parent: &Rc<RefCell<Node>>,
+    child: &Rc<RefCell<Node>>,
+) -> Result<Rc<RefCell<Node>>, Error> {
+    if !is_child_of(child, parent) {
+        return Err(Error::UnexpectedInput(
+            "NotFoundError: the node is not a child of the parent".to_string(),
+        ));
+    }
+
+    remove(parent, child, false);
+
+    Ok(child.clone())
+}
+
+/// Unlinks `child` from `parent`, queueing a mutation record unless
+/// `suppress_observers` is set.
+/// https://dom.spec.whatwg.org/#concept-node-remove
+fn remove(parent: &Rc<RefCell<Node>>, child: &Rc<RefCell<Node>>, suppress_observers: bool) {
+    update_live_ranges_for_remove(parent, child, index(child));
+    update_node_iterators_for_remove(child);
+
+    if let Some(document) = owner_document(parent) {
+        unregister_ids(&document, child);
+    }
+
+    let previous_sibling = child.borrow().previous_sibling().upgrade();
+    let next_sibling = child.borrow().next_sibling();
+    match previous_sibling {
+        Some(ref p) => p.borrow_mut().set_next_sibling(next_sibling.clone()),
+        None => parent.borrow_mut().set_first_child(next_sibling.clone()),
+    }
+    match next_sibling {
+        Some(ref n) => n.borrow_mut().set_previous_sibling(
+            previous_sibling
+                .as_ref()
+                .map(Rc::downgrade)
+                .unwrap_or_default(),
+        ),
+        None => parent.borrow_mut().set_last_child(
+            previous_sibling
+                .as_ref()
+                .map(Rc::downgrade)
+                .unwrap_or_default(),
+        ),
+    }
+
+    {
+        let mut c = child.borrow_mut();
+        c.set_parent(Default::default());
+        c.set_previous_sibling(Default::default());
+        c.set_next_sibling(None);
+    }
+
+    if !suppress_observers {
+        queue_tree_mutation_record(
+            parent,
+            Vec::new(),
+            vec![child.clone()],
+            previous_sibling,
+            next_sibling,
+        );
+    }
+}
+
+/// Sets an attribute on the element `node`.
+/// https://dom.spec.whatwg.org/#dom-element-setattribute
+pub fn set_attribute(node: &Rc<RefCell<Node>>, name: &str, value: &str) {
+    let attribute = match node.borrow().get_element() {
+        Some(element) => element.get_attribute_node(name),
+        None => return,
+    };
+
+    let (namespace, local_name) = match attribute {
+        Some(ref attr) => (attr.namespace(), attr.local_name()),
+        None => (None, name.to_string()),
+    };
+    change_attribute(
+        node,
+        namespace.as_deref(),
+        &local_name,
+        attribute.map(|attr| attr.value()),
+        |element| element.set_attribute(name, value),
+    );
+}
+
+/// Sets an attribute in `namespace` on the element `node`. `qualified_name`
+/// may have a prefix, e.g. `xlink:href`.
+/// https://dom.spec.whatwg.org/#dom-element-setattributens
+pub fn set_attribute_ns(
+    node: &Rc<RefCell<Node>>,
+    namespace: Option<&str>,
+    qualified_name: &str,
+    value: &str,
+) -> Result<(), Error> {
+    let (namespace, _, local_name) = validate_and_extract(namespace, qualified_name)?;
+    let old_value = match node.borrow().get_element() {
+        Some(element) => element.get_attribute_ns(namespace.as_deref(), &local_name),
+        None => return Ok(()),
+    };
+
+    let mut result = Ok(());
+    change_attribute(
+        node,
+        namespace.as_deref(),
+        &local_name,
+        old_value,
+        |element| result = element.set_attribute_ns(namespace.as_deref(), qualified_name, value),
+    );
+    result
+}
+
+/// Removes an attribute from the element `node`.
+/// https://dom.spec.whatwg.org/#dom-element-removeattribute
+pub fn remove_attribute(node: &Rc<RefCell<Node>>, name: &str) {
+    let attribute = match node.borrow().get_element() {
+        Some(element) => element.get_attribute_node(name),
+        None => return,
+    };
+
+    if let Some(attr) = attribute {
+        change_attribute(
+            node,
+            attr.namespace().as_deref(),
+            &attr.local_name(),
+            Some(attr.value()),
+            |element| element.remove_attribute(name),
+        );
+    }
+}
+
+/// Removes the attribute in `namespace` named `local_name` from the element
+/// `node`.
+/// https://dom.spec.whatwg.org/#dom-element-removeattributens
+pub fn remove_attribute_ns(node: &Rc<RefCell<Node>>, namespace: Option<&str>, local_name: &str) {
+    let old_value = match node.borrow().get_element() {
+        Some(element) => element.get_attribute_ns(namespace, local_name),
+        None => return,
+    };
+
+    if old_value.is_some() {
+        change_attribute(node, namespace, local_name, old_value, |element| {
+            element.remove_attribute_ns(namespace, local_name)
+        });
+    }
+}
+
+/// Applies `change` to the element `node`, keeping the id index and mutation
+/// observers informed about the attribute it changes.
+/// https://dom.spec.whatwg.org/#concept-element-attributes-change
+fn change_attribute<F: FnOnce(&mut Element)>(
+    node: &Rc<RefCell<Node>>,
+    namespace: Option<&str>,
+    local_name: &str,
+    old_value: Option<String>,
+    change: F,
+) {
+    let namespace = namespace.filter(|ns| !ns.is_empty());
+    let document = match (namespace, local_name) {
+        (None, "id") => owner_document(node),
+        _ => None,
+    };
+    if let Some(ref document) = document {
+        unregister_id(document, node);
+    }
+
+    queue_attribute_mutation_record(node, namespace, local_name, old_value);
+    if let Some(element) = node.borrow_mut().get_element_mut() {
+        change(element);
+    }
+
+    if let Some(ref document) = document {
+        register_id(document, node);
+    }
+}
+
+/// Replaces the data of a `Text`, `Comment` or `ProcessingInstruction` node.
+/// Other nodes are left untouched.
+/// https://dom.spec.whatwg.org/#dom-characterdata-data
+pub fn set_character_data(node: &Rc<RefCell<Node>>, data: &str) {
+    let length = node_length(node);
+    // Only fails for nodes that are not character data.
+    let _ = replace_data(node, 0, length, data);
+}
+
+/// Replaces `count` characters starting at `offset` in the data of a
+/// `Text`, `Comment` or `ProcessingInstruction` node with `data`.
+/// https://dom.spec.whatwg.org/#concept-cd-replace
+pub fn replace_data(
+    node: &Rc<RefCell<Node>>,
+    offset: usize,
+    count: usize,
+    data: &str,
+) -> Result<(), Error> {
+    let length = node_length(node);
+    if offset > length {
+        return Err(Error::UnexpectedInput(
+            "IndexSizeError: the offset is larger than the length of the node".to_string(),
+        ));
+    }
+    let count = count.min(length - offset);
+
+    let old_value = match node.borrow_mut().kind {
+        NodeKind::Text(ref mut s)
+        | NodeKind::Comment(ref mut s)
+        | NodeKind::ProcessingInstruction {
+            data: ref mut s, ..
+        } => {
+            let old_value = s.clone();
+            let byte_offset =
+                |chars: usize| s.char_indices().nth(chars).map_or(s.len(), |(i, _)| i);
+            let range = byte_offset(offset)..byte_offset(offset + count);
+            s.replace_range(range, data);
+            old_value
+        }
+        _ => {
+            return Err(Error::UnexpectedInput(
+                "InvalidNodeTypeError: the node is not character data".to_string(),
+            ))
+        }
+    };
+    queue_character_data_mutation_record(node, old_value);
+    update_live_ranges_for_replace_data(node, offset, count, data.chars().count());
+
+    Ok(())
+}
+
+/// Returns a copy of `node` that is not in any tree. Its descendants are
+/// copied as well if `deep` is set.
+/// https://dom.spec.whatwg.org/#concept-node-clone
+pub fn clone_node(node: &Rc<RefCell<Node>>, deep: bool) -> Rc<RefCell<Node>> {
+    let copy = Rc::new(RefCell::new(Node::new(node.borrow().kind())));
+    if deep {
+        let children: Vec<Rc<RefCell<Node>>> = node.borrow().children().collect();
+        for child in children {
+            insert(&copy, clone_node(&child, true), None);
+        }
+    }
+    copy
+}
+
+/// https://dom.spec.whatwg.org/#dom-node-textcontent
+pub fn text_content(node: &Rc<RefCell<Node>>) -> Option<String> {
+    match node.borrow().kind {
+        NodeKind::Element(_) | NodeKind::DocumentFragment => Some(descendant_text_content(node)),
+        NodeKind::Text(ref s)
+        | NodeKind::Comment(ref s)
+        | NodeKind::ProcessingInstruction { data: ref s, .. } => Some(s.clone()),
+        NodeKind::Document | NodeKind::DocumentType { .. } => None,
+    }
+}
+
+/// https://dom.spec.whatwg.org/#concept-descendant-text-content
+fn descendant_text_content(node: &Rc<RefCell<Node>>) -> String {
+    let mut s = String::new();
+    for descendant in node.borrow().descendants() {
+        if let NodeKind::Text(ref text) = descendant.borrow().kind {
+            s.push_str(text);
+        }
+    }
+    s
+}
+
+/// Replaces the children of an element or fragment with a single text node,
+/// or sets the data of a character data node.
+/// https://dom.spec.whatwg.org/#set-text-content
+pub fn set_text_content(node: &Rc<RefCell<Node>>, value: &str) {
+    let kind = node.borrow().kind();
+    match kind {
+        NodeKind::Element(_) | NodeKind::DocumentFragment => {
+            let text = if value.is_empty() {
+                None
+            } else {
+                Some(Rc::new(RefCell::new(Node::new(NodeKind::Text(
+                    value.to_string(),
+                )))))
+            };
+            replace_all(node, text);
+        }
+        NodeKind::Text(_) | NodeKind::Comment(_) | NodeKind::ProcessingInstruction { .. } => {
+            set_character_data(node, value)
+        }
+        NodeKind::Document | NodeKind::DocumentType { .. } => {}
+    }
+}
+
+/// Removes all children of `parent` and inserts `node`, queueing a single
+/// mutation record for the whole change.
+/// https://dom.spec.whatwg.org/#concept-node-replace-all
+fn replace_all(parent: &Rc<RefCell<Node>>, node: Option<Rc<RefCell<Node>>>) {
+    let removed_nodes: Vec<Rc<RefCell<Node>>> = parent.borrow().children().collect();
+    for child in &removed_nodes {
+        remove(parent, child, true);
+    }
+    let added_nodes: Vec<Rc<RefCell<Node>>> = node.into_iter().collect();
+    for n in &added_nodes {
+        insert(parent, n.clone(), None);
+    }
+    queue_tree_mutation_record(parent, added_nodes, removed_nodes, None, None);
+}
+
+/// Removes empty text nodes and merges adjacent text nodes in the subtree of
+/// `node`.
+/// https://dom.spec.whatwg.org/#dom-node-normalize
+pub fn normalize(node: &Rc<RefCell<Node>>) {
+    let texts: Vec<Rc<RefCell<Node>>> = node
+        .borrow()
+        .descendants()
+        .filter(|n| matches!(n.borrow().kind, NodeKind::Text(_)))
+        .collect();
+
+    for text in texts {
+        // Nodes already merged into a preceding text node have no parent.
+        let parent = match text.borrow().parent().upgrade() {
+            Some(parent) => parent,
+            None => continue,
+        };
+
+        if node_length(&text) == 0 {
+            remove(&parent, &text, false);
+            continue;
+        }
+
+        let mut data = String::new();
+        let mut contiguous = Vec::new();
+        let mut next = text.borrow().next_sibling();
+        while let Some(n) = next {
+            match n.borrow().kind {
+                NodeKind::Text(ref s) => data.push_str(s),
+                _ => break,
+            }
+            next = n.borrow().next_sibling();
+            contiguous.push(n);
+        }
+        if contiguous.is_empty() {
+            continue;
+        }
+
+        let mut length = node_length(&text);
+        let _ = replace_data(&text, length, 0, &data);
+        for n in &contiguous {
+            update_live_ranges_for_normalize(&text, n, length);
+            length += node_length(n);
+        }
+        for n in &contiguous {
+            remove(&parent, n, false);
+        }
+    }
+}
+
+/// Elements that are not rendered by default.
+/// https://html.spec.whatwg.org/multipage/rendering.html#hidden-elements
+const HIDDEN_ELEMENTS: &[&str] = &["base", "head", "link", "meta", "script", "style", "title"];
+
+/// Elements that generate block boxes by default.
+/// https://html.spec.whatwg.org/multipage/rendering.html#flow-content-3
+const BLOCK_ELEMENTS: &[&str] = &["html", "body", "p", "h1", "h2"];
+
+/// Returns the `display` of the element `node`: its computed value once
+/// style is computed, and its default otherwise.
+fn display(node: &Rc<RefCell<Node>>) -> Display {
+    if let Some(style) = node.borrow().computed_style() {
+        return style.display();
+    }
+    let tag_name = node
+        .borrow()
+        .element_kind()
+        .map(|kind| kind.to_string())
+        .unwrap_or_default();
+    if HIDDEN_ELEMENTS.contains(&tag_name.as_str()) {
+        Display::None
+    } else if BLOCK_ELEMENTS.contains(&tag_name.as_str()) {
+        Display::Block
+    } else {
+        Display::Inline
+    }
+}
+
+/// An item of the rendered text collection.
+/// https://html.spec.whatwg.org/multipage/dom.html#rendered-text-collection-steps
+#[derive(Debug, Clone, PartialEq)]
+enum RenderedText {
+    Text(String),
+    RequiredLineBreakCount(usize),
+}
+
+/// Returns the text of `node` as it would be rendered: elements with
+/// `display: none` are skipped, white space is collapsed and block
+/// boundaries become line breaks. Until style is computed, each element's
+/// default `display` is used.
+/// https://html.spec.whatwg.org/multipage/dom.html#dom-innertext
+pub fn inner_text(node: &Rc<RefCell<Node>>) -> String {
+    if node.borrow().element_kind().is_none() || display(node) == Display::None {
+        return text_content(node).unwrap_or_default();
+    }
+
+    let mut items = Vec::new();
+    let mut preceded_by_space = true;
+    let children: Vec<Rc<RefCell<Node>>> = node.borrow().children().collect();
+    for child in children {
+        rendered_text_collection(&child, &mut items, &mut preceded_by_space);
+    }
+
+    let mut s = String::new();
+    let mut pending_line_breaks = 0;
+    for item in items {
+        match item {
+            RenderedText::Text(text) => {
+                if !s.is_empty() {
+                    for _ in 0..pending_line_breaks {
+                        s.push('\n');
+                    }
+                }
+                pending_line_breaks = 0;
+                s.push_str(&text);
+            }
+            RenderedText::RequiredLineBreakCount(count) => {
+                pending_line_breaks = pending_line_breaks.max(count);
+            }
+        }
+    }
+    s
+}
+
+/// https://html.spec.whatwg.org/multipage/dom.html#rendered-text-collection-steps
+fn rendered_text_collection(
+    node: &Rc<RefCell<Node>>,
+    items: &mut Vec<RenderedText>,
+    preceded_by_space: &mut bool,
+) {
+    let kind = node.borrow().kind();
+    match kind {
+        NodeKind::Text(ref text) => {
+            // https://drafts.csswg.org/css-text/#white-space-phase-1
+            let mut collapsed = String::new();
+            for c in text.chars() {
+                if c.is_ascii_whitespace() {
+                    if !*preceded_by_space {
+                        collapsed.push(' ');
+                        *preceded_by_space = true;
+                    }
+                } else {
+                    collapsed.push(c);
+                    *preceded_by_space = false;
+                }
+            }
+            if !collapsed.is_empty() {
+                items.push(RenderedText::Text(collapsed));
+            }
+        }
+        NodeKind::Element(ref e) => {
+            let line_breaks = match (e.kind(), display(node)) {
+                (_, Display::None) => return,
+                (ElementKind::P, _) => 2,
+                (_, Display::Block | Display::ListItem) => 1,
+                _ => 0,
+            };
+            if line_breaks > 0 {
+                trim_trailing_space(items);
+                items.push(RenderedText::RequiredLineBreakCount(line_breaks));
+                *preceded_by_space = true;
+            }
+            let children: Vec<Rc<RefCell<Node>>> = node.borrow().children().collect();
+            for child in children {
+                rendered_text_collection(&child, items, preceded_by_space);
+            }
+            if line_breaks > 0 {
+                trim_trailing_space(items);
+                items.push(RenderedText::RequiredLineBreakCount(line_breaks));
+                *preceded_by_space = true;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Removes a collapsible space at the end of a line.
+/// https://drafts.csswg.org/css-text/#white-space-phase-2
+fn trim_trailing_space(items: &mut Vec<RenderedText>) {
+    if let Some(RenderedText::Text(ref mut text)) = items.last_mut() {
+        if text.ends_with(' ') {
+            text.pop();
+        }
+        if text.is_empty() {
+            items.pop();
+        }
+    }
+}
+
+fn element_id(node: &Rc<RefCell<Node>>) -> Option<String> {
+    node.borrow().get_element().and_then(|e| e.id())
+}
+
+fn register_id(document: &Rc<RefCell<Node>>, node: &Rc<RefCell<Node>>) {
+    if let Some(id) = element_id(node) {
+        document
+            .borrow_mut()
+            .id_index_mut()
+            .entry(id)
+            .or_default()
+            .push(Rc::downgrade(node));
+    }
+}
+
+fn unregister_id(document: &Rc<RefCell<Node>>, node: &Rc<RefCell<Node>>) {
+    if let Some(id) = element_id(node) {
+        let mut document = document.borrow_mut();
+        let index = document.id_index_mut();
+        if let Some(entries) = index.get_mut(&id) {
+            entries.retain(|e| !core::ptr::eq(e.as_ptr(), Rc::as_ptr(node)));
+            if entries.is_empty() {
+                index.remove(&id);
+            }
+        }
+    }
+}
+
+fn register_ids(document: &Rc<RefCell<Node>>, subtree: &Rc<RefCell<Node>>) {
+    for node in inclusive_descendants(subtree) {
+        register_id(document, &node);
+    }
+}
+
+fn unregister_ids(document: &Rc<RefCell<Node>>, subtree: &Rc<RefCell<Node>>) {
+    for node in inclusive_descendants(subtree) {
+        unregister_id(document, &node);
+    }
+}
+
+/// Returns the first element in tree order whose id is `id`.
+///
+/// Documents answer from their id index. For other nodes the subtree is
+/// walked.
+/// https://dom.spec.whatwg.org/#dom-nonelementparentnode-getelementbyid
+pub fn get_element_by_id(root: &Rc<RefCell<Node>>, id: &str) -> Option<Rc<RefCell<Node>>> {
+    if root.borrow().kind() != NodeKind::Document {
+        let found = root
+            .borrow()
+            .descendants()
+            .find(|n| element_id(n).as_deref() == Some(id));
+        return found;
+    }
+
+    let candidates: Vec<Rc<RefCell<Node>>> = match root.borrow().id_index().get(id) {
+        Some(entries) => entries.iter().filter_map(|e| e.upgrade()).collect(),
+        None => return None,
+    };
+    if candidates.len() <= 1 {
+        return candidates.into_iter().next();
+    }
+
+    // Several elements share the id; the first one in tree order wins.
+    let found = root
+        .borrow()
+        .descendants()
+        .find(|n| candidates.iter().any(|c| Rc::ptr_eq(c, n)));
+    found
+}
+
+/// Returns a live collection of the elements under `root` whose tag name is
+/// `qualified_name`, or all elements for `*`.
+/// https://dom.spec.whatwg.org/#concept-getelementsbytagname
+pub fn get_elements_by_tag_name(root: &Rc<RefCell<Node>>, qualified_name: &str) -> HtmlCollection {
+    if qualified_name == "*" {
+        return HtmlCollection::new(root.clone(), Box::new(|_| true));
+    }
+    match ElementKind::from_str(&qualified_name.to_ascii_lowercase()) {
+        Ok(kind) => HtmlCollection::new(root.clone(), Box::new(move |e| e.kind() == kind)),
+        Err(_) => HtmlCollection::new(root.clone(), Box::new(|_| false)),
+    }
+}
+
+/// Returns a live collection of the elements under `root` that have all the
+/// classes in `class_names`.
+/// https://dom.spec.whatwg.org/#concept-getelementsbyclassname
+pub fn get_elements_by_class_name(root: &Rc<RefCell<Node>>, class_names: &str) -> HtmlCollection {
+    let classes: Vec<String> = class_names
+        .split_ascii_whitespace()
+        .map(String::from)
+        .collect();
+    if classes.is_empty() {
+        return HtmlCollection::new(root.clone(), Box::new(|_| false));
+    }
+    HtmlCollection::new(
+        root.clone(),
+        Box::new(move |e| {
+            let class_list = e.class_list();
+            classes.iter().all(|c| class_list.contains(c))
+        }),
+    )
+}
+
+/// Returns the first element under `root` in tree order that matches
+/// `selectors`.
+/// https://dom.spec.whatwg.org/#dom-parentnode-queryselector
+pub fn query_selector(
+    root: &Rc<RefCell<Node>>,
+    selectors: &str,
+) -> Result<Option<Rc<RefCell<Node>>>, Error> {
+    let list = SelectorList::parse(selectors)?;
+    let found = root.borrow().descendants().find(|n| list.matches(n));
+    Ok(found)
+}
+
+/// Returns all elements under `root` that match `selectors`, in tree order.
+/// https://dom.spec.whatwg.org/#dom-parentnode-queryselectorall
+pub fn query_selector_all(
+    root: &Rc<RefCell<Node>>,
+    selectors: &str,
+) -> Result<Vec<Rc<RefCell<Node>>>, Error> {
+    let list = SelectorList::parse(selectors)?;
+    let found = root
+        .borrow()
+        .descendants()
+        .filter(|n| list.matches(n))
+        .collect();
+    Ok(found)
+}
+
+/// https://dom.spec.whatwg.org/#dom-element-matches
+pub fn matches(node: &Rc<RefCell<Node>>, selectors: &str) -> Result<bool, Error> {
+    let list = SelectorList::parse(selectors)?;
+    Ok(list.matches(node))
+}
+
+/// Returns the first inclusive ancestor of `node` that matches `selectors`.
+/// https://dom.spec.whatwg.org/#dom-element-closest
+pub fn closest(
+    node: &Rc<RefCell<Node>>,
+    selectors: &str,
+) -> Result<Option<Rc<RefCell<Node>>>, Error> {
+    let list = SelectorList::parse(selectors)?;
+    let found = iter::once(node.clone())
+        .chain(node.borrow().ancestors())
+        .find(|n| list.matches(n));
+    Ok(found)
+}
+
+/// A collection of the elements under a root that match a filter.
+///
+/// The collection is live: the tree is walked again on every access, so it
+/// always reflects the current state of the tree.
+/// https://dom.spec.whatwg.org/#interface-htmlcollection
+pub struct HtmlCollection {
+    root: Rc<RefCell<Node>>,
+    filter: Box<dyn Fn(&Element) -> bool>,
+}
+
+impl HtmlCollection {
+    fn new(root: Rc<RefCell<Node>>, filter: Box<dyn Fn(&Element) -> bool>) -> Self {
+        Self { root, filter }
+    }
+
+    /// Returns the matching elements in tree order.
+    pub fn iter(&self) -> impl Iterator<Item = Rc<RefCell<Node>>> + '_ {
+        let descendants = self.root.borrow().descendants();
+        descendants.filter(|n| match n.borrow().kind {
+            NodeKind::Element(ref e) => (self.filter)(e),
+            _ => false,
+        })
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-htmlcollection-length
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-htmlcollection-item
+    pub fn item(&self, index: usize) -> Option<Rc<RefCell<Node>>> {
+        self.iter().nth(index)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-htmlcollection-nameditem
+    pub fn named_item(&self, key: &str) -> Option<Rc<RefCell<Node>>> {
+        if key.is_empty() {
+            return None;
+        }
+        self.iter().find(|n| match n.borrow().get_element() {
+            Some(e) => {
+                e.id().as_deref() == Some(key) || e.get_attribute("name").as_deref() == Some(key)
+            }
+            None => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::css::cascade::Cascade;
+    use crate::renderer::css::stylesheet::StyleSheet;
+    use crate::renderer::dom::node::Window;
+    use crate::renderer::dom::range::Range;
+    use crate::renderer::html::attribute::Attribute;
+    use crate::renderer::html::parser::tests::body;
+    use crate::renderer::html::parser::tests::parse;
+    use alloc::vec;
+
+    fn element(name: &str, attributes: &[(&str, &str)]) -> Rc<RefCell<Node>> {
+        let attributes = attributes
+            .iter()
+            .map(|(n, v)| Attribute::with_value(n, v))
+            .collect();
+        Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            name, attributes,
+        )))))
+    }
+
+    fn text(s: &str) -> Rc<RefCell<Node>> {
+        Rc::new(RefCell::new(Node::new(NodeKind::Text(s.to_string()))))
+    }
+
+    fn ids(nodes: impl Iterator<Item = Rc<RefCell<Node>>>) -> Vec<String> {
+        nodes.map(|n| element_id(&n).unwrap_or_default()).collect()
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let parent = element("p", &[]);
+        let a = text("a");
+        let b = text("b");
+        let c = text("c");
+        append_child(&parent, a.clone()).unwrap();
+        append_child(&parent, c.clone()).unwrap();
+        insert_before(&parent, b.clone(), Some(c.clone())).unwrap();
+        let children: Vec<NodeKind> = parent
+            .borrow()
+            .children()
+            .map(|n| n.borrow().kind())
+            .collect();
+        assert_eq!(3, children.len());
+        assert!(Rc::ptr_eq(&b, &a.borrow().next_sibling().unwrap()));
+        assert!(Rc::ptr_eq(
+            &a,
+            &b.borrow().previous_sibling().upgrade().unwrap()
+        ));
+        assert!(Rc::ptr_eq(
+            &c,
+            &parent.borrow().last_child().upgrade().unwrap()
+        ));
+
+        remove_child(&parent, &b).unwrap();
+        assert!(Rc::ptr_eq(&c, &a.borrow().next_sibling().unwrap()));
+        assert!(b.borrow().parent().upgrade().is_none());
+
+        remove_child(&parent, &a).unwrap();
+        remove_child(&parent, &c).unwrap();
+        assert!(parent.borrow().first_child().is_none());
+        assert!(parent.borrow().last_child().upgrade().is_none());
+        assert!(remove_child(&parent, &c).is_err());
+    }
+
+    #[test]
+    fn test_insert_moves_node() {
+        let p1 = element("p", &[]);
+        let p2 = element("p", &[]);
+        let t = text("t");
+        append_child(&p1, t.clone()).unwrap();
+        append_child(&p2, t.clone()).unwrap();
+        assert!(p1.borrow().first_child().is_none());
+        assert!(Rc::ptr_eq(&p2, &t.borrow().parent().upgrade().unwrap()));
+    }
+
+    #[test]
+    fn test_insert_into_itself() {
+        let p = element("p", &[]);
+        let a = element("a", &[]);
+        append_child(&p, a.clone()).unwrap();
+        assert!(append_child(&a, p.clone()).is_err());
+        assert!(append_child(&a, a.clone()).is_err());
+    }
+
+    #[test]
+    fn test_insertion_rules() {
+        let document = Window::new().document();
+        let doctype = Rc::new(RefCell::new(Node::new(NodeKind::DocumentType {
+            name: "html".to_string(),
+            public_id: String::new(),
+            system_id: String::new(),
+        })));
+        let html = element("html", &[]);
+        let comment = Rc::new(RefCell::new(Node::new(NodeKind::Comment("c".to_string()))));
+
+        assert!(append_child(&document, text("t")).is_err());
+        append_child(&document, html.clone()).unwrap();
+        assert!(append_child(&document, element("body", &[])).is_err());
+        // The doctype cannot come after the document element.
+        assert!(append_child(&document, doctype.clone()).is_err());
+        insert_before(&document, doctype.clone(), Some(html.clone())).unwrap();
+        assert!(append_child(&html, doctype.clone()).is_err());
+        append_child(&document, comment.clone()).unwrap();
+
+        assert!(append_child(&comment, text("t")).is_err());
+        assert!(append_child(&html, document.clone()).is_err());
+        assert!(append_child(&html, Window::new().document()).is_err());
+    }
+
+    #[test]
+    fn test_insert_fragment() {
+        let fragment = Rc::new(RefCell::new(Node::new(NodeKind::DocumentFragment)));
+        append_child(&fragment, text("a")).unwrap();
+        append_child(&fragment, text("b")).unwrap();
+        let p = element("p", &[]);
+        let c = text("c");
+        append_child(&p, c.clone()).unwrap();
+        insert_before(&p, fragment.clone(), Some(c)).unwrap();
+
+        let texts: Vec<String> = p
+            .borrow()
+            .children()
+            .map(|n| match n.borrow().kind() {
+                NodeKind::Text(s) => s,
+                _ => String::new(),
+            })
+            .collect();
+        assert_eq!(vec!["a", "b", "c"], texts);
+        assert!(fragment.borrow().first_child().is_none());
+
+        let document = Window::new().document();
+        let fragment = Rc::new(RefCell::new(Node::new(NodeKind::DocumentFragment)));
+        append_child(&fragment, element("html", &[])).unwrap();
+        append_child(&fragment, element("body", &[])).unwrap();
+        assert!(append_child(&document, fragment).is_err());
+    }
+
+    #[test]
+    fn test_get_element_by_id() {
+        let window = Window::new();
+        let document = window.document();
+        let body = element("body", &[]);
+        let p = element("p", &[("id", "section")]);
+        let a = element("a", &[("id", "link")]);
+        append_child(&p, a.clone()).unwrap();
+        append_child(&document, body.clone()).unwrap();
+        // Nodes become indexed when their subtree is connected.
+        append_child(&body, p.clone()).unwrap();
+
+        assert!(Rc::ptr_eq(
+            &p,
+            &get_element_by_id(&document, "section").unwrap()
+        ));
+        assert!(Rc::ptr_eq(
+            &a,
+            &get_element_by_id(&document, "link").unwrap()
+        ));
+        assert!(get_element_by_id(&document, "missing").is_none());
+
+        set_attribute(&a, "id", "anchor");
+        assert!(get_element_by_id(&document, "link").is_none());
+        assert!(Rc::ptr_eq(
+            &a,
+            &get_element_by_id(&document, "anchor").unwrap()
+        ));
+
+        remove_attribute(&a, "id");
+        assert!(get_element_by_id(&document, "anchor").is_none());
+
+        remove_child(&body, &p).unwrap();
+        assert!(get_element_by_id(&document, "section").is_none());
+        assert!(document.borrow().id_index().is_empty());
+    }
+
+    #[test]
+    fn test_attribute_ns() {
+        use crate::renderer::dom::node::XLINK_NAMESPACE;
+        use crate::renderer::dom::node::XMLNS_NAMESPACE;
+
+        let window = Window::new();
+        let document = window.document();
+        let a = element("a", &[("href", "plain")]);
+        append_child(&document, a.clone()).unwrap();
+
+        set_attribute_ns(&a, Some(XLINK_NAMESPACE), "xlink:href", "#target").unwrap();
+        set_attribute_ns(&a, Some("urn:x"), "id", "namespaced").unwrap();
+        let element = a.borrow().get_element().unwrap();
+        assert_eq!(Some("plain".to_string()), element.get_attribute("href"));
+        assert_eq!(
+            Some("#target".to_string()),
+            element.get_attribute_ns(Some(XLINK_NAMESPACE), "href")
+        );
+        assert_eq!(
+            Some("#target".to_string()),
+            element.get_attribute("xlink:href")
+        );
+        let attr = element
+            .get_attribute_node_ns(Some(XLINK_NAMESPACE), "href")
+            .unwrap();
+        assert_eq!(Some("xlink".to_string()), attr.prefix());
+        assert_eq!("href", attr.local_name());
+        // Only an id without a namespace is an element's id.
+        assert_eq!(None, element.id());
+        assert!(get_element_by_id(&document, "namespaced").is_none());
+
+        // An attribute is matched by namespace and local name, whatever its
+        // prefix.
+        set_attribute_ns(&a, Some(XLINK_NAMESPACE), "l:href", "#other").unwrap();
+        let element = a.borrow().get_element().unwrap();
+        assert_eq!(3, element.attributes().len());
+        assert_eq!(
+            Some("#other".to_string()),
+            element.get_attribute("xlink:href")
+        );
+
+        remove_attribute_ns(&a, Some(XLINK_NAMESPACE), "href");
+        let element = a.borrow().get_element().unwrap();
+        assert_eq!(
+            None,
+            element.get_attribute_ns(Some(XLINK_NAMESPACE), "href")
+        );
+        assert_eq!(Some("plain".to_string()), element.get_attribute("href"));
+
+        assert!(set_attribute_ns(&a, None, "xlink:href", "x").is_err());
+        assert!(set_attribute_ns(&a, Some("urn:x"), "xml:lang", "en").is_err());
+        assert!(set_attribute_ns(&a, Some("urn:x"), "xmlns", "x").is_err());
+        assert!(set_attribute_ns(&a, Some(XMLNS_NAMESPACE), "foo", "x").is_err());
+        assert!(set_attribute_ns(&a, Some("urn:x"), "a:b:c", "x").is_err());
+        assert!(set_attribute_ns(&a, Some("urn:x"), ":b", "x").is_err());
+        assert!(set_attribute_ns(&a, Some(XMLNS_NAMESPACE), "xmlns:x", "urn:x").is_ok());
+    }
+
+    #[test]
+    fn test_get_element_by_id_duplicates() {
+        let document = Window::new().document();
+        let body = element("body", &[]);
+        let first = element("p", &[("id", "x")]);
+        let second = element("h1", &[("id", "x")]);
+        append_child(&document, body.clone()).unwrap();
+        append_child(&body, second.clone()).unwrap();
+        insert_before(&body, first.clone(), Some(second.clone())).unwrap();
+        assert!(Rc::ptr_eq(
+            &first,
+            &get_element_by_id(&document, "x").unwrap()
+        ));
+
+        remove_child(&body, &first).unwrap();
+        assert!(Rc::ptr_eq(
+            &second,
+            &get_element_by_id(&document, "x").unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_get_element_by_id_detached() {
+        let body = element("body", &[]);
+        let p = element("p", &[("id", "x")]);
+        append_child(&body, p.clone()).unwrap();
+        assert!(Rc::ptr_eq(&p, &get_element_by_id(&body, "x").unwrap()));
+    }
+
+    #[test]
+    fn test_get_elements_by_tag_name() {
+        let document = Window::new().document();
+        let body = element("body", &[]);
+        append_child(&document, body.clone()).unwrap();
+        append_child(&body, element("p", &[("id", "1")])).unwrap();
+        append_child(&body, element("h1", &[("id", "2")])).unwrap();
+
+        let collection = get_elements_by_tag_name(&document, "P");
+        assert_eq!(vec!["1"], ids(collection.iter()));
+
+        append_child(&body, element("p", &[("id", "3")])).unwrap();
+        assert_eq!(2, collection.len());
+        assert_eq!(vec!["1", "3"], ids(collection.iter()));
+        assert_eq!(4, get_elements_by_tag_name(&document, "*").len());
+        assert!(get_elements_by_tag_name(&document, "unknown").is_empty());
+    }
+
+    #[test]
+    fn test_text_content() {
+        let p = element("p", &[]);
+        let a = element("a", &[]);
+        append_child(&p, text("Hello, ")).unwrap();
+        append_child(&p, a.clone()).unwrap();
+        append_child(&a, text("world")).unwrap();
+        append_child(
+            &p,
+            Rc::new(RefCell::new(Node::new(NodeKind::Comment("c".to_string())))),
+        )
+        .unwrap();
+        append_child(&p, text("!")).unwrap();
+
+        assert_eq!(Some("Hello, world!".to_string()), text_content(&p));
+        assert_eq!(Some("world".to_string()), text_content(&a));
+        let document = Rc::new(RefCell::new(Node::new(NodeKind::Document)));
+        assert_eq!(None, text_content(&document));
+
+        set_text_content(&p, "replaced");
+        let children: Vec<Rc<RefCell<Node>>> = p.borrow().children().collect();
+        assert_eq!(1, children.len());
+        assert_eq!(
+            NodeKind::Text("replaced".to_string()),
+            children[0].borrow().kind()
+        );
+        assert!(a.borrow().parent().upgrade().is_none());
+
+        set_text_content(&children[0], "text");
+        assert_eq!(Some("text".to_string()), text_content(&p));
+
+        set_text_content(&p, "");
+        assert!(p.borrow().first_child().is_none());
+        assert_eq!(Some(String::new()), text_content(&p));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let body = element("body", &[]);
+        let p = element("p", &[]);
+        append_child(&body, text("")).unwrap();
+        append_child(&body, text("a")).unwrap();
+        append_child(&body, text("b")).unwrap();
+        append_child(&body, p.clone()).unwrap();
+        append_child(&body, text("c")).unwrap();
+        append_child(&p, text("d")).unwrap();
+        append_child(&p, text("")).unwrap();
+        append_child(&p, text("e")).unwrap();
+
+        normalize(&body);
+
+        let kinds = |node: &Rc<RefCell<Node>>| -> Vec<NodeKind> {
+            node.borrow()
+                .children()
+                .map(|n| n.borrow().kind())
+                .collect()
+        };
+        assert_eq!(
+            vec![
+                NodeKind::Text("ab".to_string()),
+                p.borrow().kind(),
+                NodeKind::Text("c".to_string()),
+            ],
+            kinds(&body)
+        );
+        assert_eq!(vec![NodeKind::Text("de".to_string())], kinds(&p));
+    }
+
+    #[test]
+    fn test_normalize_updates_live_ranges() {
+        let html = "<p>ab</p>";
+        let window = parse(html);
+        let document = window.borrow().document();
+        let p = body(&document).borrow().first_child().unwrap();
+        let first = p.borrow().first_child().unwrap();
+        let second = text("cd");
+        append_child(&p, second.clone()).unwrap();
+
+        // Inside the second text node.
+        let inside = Range::new(&document);
+        inside.set_start(&second, 1).unwrap();
+        // Right before the second text node.
+        let before = Range::new(&document);
+        before.set_start(&p, 1).unwrap();
+        // After the second text node.
+        let after = Range::new(&document);
+        after.set_start(&p, 2).unwrap();
+
+        normalize(&p);
+
+        assert_eq!(
+            vec![NodeKind::Text("abcd".to_string())],
+            p.borrow()
+                .children()
+                .map(|n| n.borrow().kind())
+                .collect::<Vec<NodeKind>>()
+        );
+        assert!(Rc::ptr_eq(&first, &inside.start_container()));
+        assert_eq!(3, inside.start_offset());
+        assert!(Rc::ptr_eq(&first, &inside.end_container()));
+        assert_eq!(3, inside.end_offset());
+        assert!(Rc::ptr_eq(&first, &before.start_container()));
+        assert_eq!(2, before.start_offset());
+        assert!(Rc::ptr_eq(&p, &after.start_container()));
+        assert_eq!(1, after.start_offset());
+    }
+
+    #[test]
+    fn test_inner_text() {
+        let body = element("body", &[]);
+        let h1 = element("h1", &[]);
+        let p1 = element("p", &[]);
+        let p2 = element("p", &[]);
+        let a = element("a", &[]);
+        let style = element("style", &[]);
+        append_child(&body, text("\n  ")).unwrap();
+        append_child(&body, h1.clone()).unwrap();
+        append_child(&h1, text("  Title  ")).unwrap();
+        append_child(&body, style.clone()).unwrap();
+        append_child(&style, text("p { }")).unwrap();
+        append_child(&body, p1.clone()).unwrap();
+        append_child(&p1, text("Hello,\n   ")).unwrap();
+        append_child(&p1, a.clone()).unwrap();
+        append_child(&a, text(" world ")).unwrap();
+        append_child(&p1, text(" !")).unwrap();
+        append_child(&body, p2.clone()).unwrap();
+        append_child(&p2, text("Bye")).unwrap();
+        append_child(&body, text("\n")).unwrap();
+
+        assert_eq!("Title\n\nHello, world !\n\nBye", inner_text(&body));
+        assert_eq!("Hello, world !", inner_text(&p1));
+        // Elements that are not rendered fall back to their text content.
+        assert_eq!("p { }", inner_text(&style));
+    }
+
+    #[test]
+    fn test_inner_text_with_style() {
+        let html = "<style>.h { display: none } a { display: block }</style>\
+                    <p class=h>hidden</p><p>shown <a>block</a> text</p>";
+        let window = parse(html);
+        let document = window.borrow().document();
+        Cascade::new(&StyleSheet::from_style_elements(&document)).apply(&document);
+        let body = body(&document);
+        assert_eq!("shown\nblock\ntext", inner_text(&body));
+    }
+
+    #[test]
+    fn test_is_equal_node() {
+        let build = |class: &str, title: &str, data: &str| {
+            let p = element("p", &[("class", class), ("title", title)]);
+            let a = element("a", &[]);
+            append_child(&p, a.clone()).unwrap();
+            append_child(&a, text(data)).unwrap();
+            p
+        };
+        let p = build("x", "t", "hello");
+
+        assert!(is_equal_node(&p, &p));
+        assert!(is_equal_node(&p, &build("x", "t", "hello")));
+        assert!(is_equal_node(&p, &clone_node(&p, true)));
+        assert!(!is_equal_node(&p, &clone_node(&p, false)));
+        assert!(!is_equal_node(&p, &build("y", "t", "hello")));
+        assert!(!is_equal_node(&p, &build("x", "t", "world")));
+        assert!(!is_equal_node(&text("a"), &text("b")));
+        assert!(!is_equal_node(
+            &text("a"),
+            &Rc::new(RefCell::new(Node::new(NodeKind::Comment("a".to_string()))))
+        ));
+
+        // Attribute order does not matter, but their number does.
+        let p1 = element("p", &[("id", "1"), ("class", "c")]);
+        let p2 = element("p", &[("class", "c"), ("id", "1")]);
+        assert!(is_equal_node(&p1, &p2));
+        set_attribute(&p2, "title", "");
+        assert!(!is_equal_node(&p1, &p2));
+
+        assert!(is_same_node(&p1, &p1));
+        assert!(!is_same_node(&p1, &clone_node(&p1, true)));
+    }
+
+    #[test]
+    fn test_compare_document_position() {
+        let body = element("body", &[]);
+        let p1 = element("p", &[]);
+        let p2 = element("p", &[]);
+        let a = element("a", &[]);
+        append_child(&body, p1.clone()).unwrap();
+        append_child(&body, p2.clone()).unwrap();
+        append_child(&p1, a.clone()).unwrap();
+
+        assert_eq!(0, compare_document_position(&p1, &p1));
+        assert_eq!(
+            DOCUMENT_POSITION_CONTAINS | DOCUMENT_POSITION_PRECEDING,
+            compare_document_position(&a, &body)
+        );
+        assert_eq!(
+            DOCUMENT_POSITION_CONTAINED_BY | DOCUMENT_POSITION_FOLLOWING,
+            compare_document_position(&body, &a)
+        );
+        assert_eq!(
+            DOCUMENT_POSITION_FOLLOWING,
+            compare_document_position(&a, &p2)
+        );
+        assert_eq!(
+            DOCUMENT_POSITION_PRECEDING,
+            compare_document_position(&p2, &a)
+        );
+
+        let detached = element("p", &[]);
+        let position = compare_document_position(&a, &detached);
+        assert_ne!(0, position & DOCUMENT_POSITION_DISCONNECTED);
+        assert_ne!(0, position & DOCUMENT_POSITION_IMPLEMENTATION_SPECIFIC);
+        let reverse = compare_document_position(&detached, &a);
+        assert_eq!(
+            DOCUMENT_POSITION_PRECEDING | DOCUMENT_POSITION_FOLLOWING,
+            (position | reverse) & (DOCUMENT_POSITION_PRECEDING | DOCUMENT_POSITION_FOLLOWING)
+        );
+    }
+
+    #[test]
+    fn test_get_elements_by_class_name() {
+        let body = element("body", &[]);
+        append_child(&body, element("p", &[("id", "1"), ("class", "a b")])).unwrap();
+        append_child(&body, element("p", &[("id", "2"), ("class", "b")])).unwrap();
+        append_child(&body, element("p", &[("id", "3"), ("class", " b  a ")])).unwrap();
+
+        assert_eq!(
+            vec!["1", "3"],
+            ids(get_elements_by_class_name(&body, "a b").iter())
+        );
+        assert_eq!(
+            vec!["1", "2", "3"],
+            ids(get_elements_by_class_name(&body, "b").iter())
+        );
+        assert!(get_elements_by_class_name(&body, "  ").is_empty());
+
+        let collection = get_elements_by_class_name(&body, "a");
+        let second = collection.item(1).unwrap();
+        set_attribute(&second, "class", "c");
+        assert_eq!(vec!["1"], ids(collection.iter()));
+        assert!(Rc::ptr_eq(
+            &collection.item(0).unwrap(),
+            &collection.named_item("1").unwrap()
+        ));
+    }
+}
@@ -0,0 +1,617 @@
+//! https://dom.spec.whatwg.org/#events
+
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use crate::renderer::dom::node::Window;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::cell::RefCell;
+use core::fmt;
+
+/// https://dom.spec.whatwg.org/#callbackdef-eventlistener
+pub type EventListenerCallback = Rc<dyn Fn(&mut Event)>;
+
+/// https://dom.spec.whatwg.org/#dictdef-eventinit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventInit {
+    pub bubbles: bool,
+    pub cancelable: bool,
+    pub composed: bool,
+}
+
+/// https://dom.spec.whatwg.org/#dictdef-addeventlisteneroptions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AddEventListenerOptions {
+    pub capture: bool,
+    pub once: bool,
+    pub passive: bool,
+}
+
+/// https://dom.spec.whatwg.org/#dom-event-eventphase
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventPhase {
+    None,
+    Capturing,
+    AtTarget,
+    Bubbling,
+}
+
+/// https://dom.spec.whatwg.org/#concept-event-listener
+pub(crate) struct EventListener {
+    event_type: String,
+    callback: EventListenerCallback,
+    capture: bool,
+    once: bool,
+    passive: bool,
+    removed: Cell<bool>,
+}
+
+impl EventListener {
+    fn is_same(&self, event_type: &str, callback: &EventListenerCallback, capture: bool) -> bool {
+        self.event_type == event_type
+            && core::ptr::eq(
+                Rc::as_ptr(&self.callback) as *const (),
+                Rc::as_ptr(callback) as *const (),
+            )
+            && self.capture == capture
+    }
+}
+
+impl fmt::Debug for EventListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventListener")
+            .field("event_type", &self.event_type)
+            .field("capture", &self.capture)
+            .field("once", &self.once)
+            .field("passive", &self.passive)
+            .finish()
+    }
+}
+
+/// An object that can receive events.
+/// https://dom.spec.whatwg.org/#interface-eventtarget
+#[derive(Debug, Clone)]
+pub enum EventTarget {
+    Node(Rc<RefCell<Node>>),
+    Window(Rc<RefCell<Window>>),
+}
+
+impl PartialEq for EventTarget {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (EventTarget::Node(a), EventTarget::Node(b)) => Rc::ptr_eq(a, b),
+            (EventTarget::Window(a), EventTarget::Window(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl EventTarget {
+    /// https://dom.spec.whatwg.org/#dom-eventtarget-addeventlistener
+    pub fn add_event_listener(
+        &self,
+        event_type: &str,
+        callback: EventListenerCallback,
+        options: AddEventListenerOptions,
+    ) {
+        self.with_listeners_mut(|listeners| {
+            if listeners
+                .iter()
+                .any(|l| l.is_same(event_type, &callback, options.capture))
+            {
+                return;
+            }
+            listeners.push(Rc::new(EventListener {
+                event_type: event_type.to_string(),
+                callback,
+                capture: options.capture,
+                once: options.once,
+                passive: options.passive,
+                removed: Cell::new(false),
+            }));
+        });
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-eventtarget-removeeventlistener
+    pub fn remove_event_listener(
+        &self,
+        event_type: &str,
+        callback: &EventListenerCallback,
+        capture: bool,
+    ) {
+        self.with_listeners_mut(|listeners| {
+            listeners.retain(|l| {
+                let same = l.is_same(event_type, callback, capture);
+                if same {
+                    l.removed.set(true);
+                }
+                !same
+            });
+        });
+    }
+
+    /// Dispatches `event` to this target. Returns false if a listener
+    /// canceled the event.
+    /// https://dom.spec.whatwg.org/#dom-eventtarget-dispatchevent
+    pub fn dispatch_event(&self, event: &mut Event) -> bool {
+        dispatch(self, event)
+    }
+
+    fn listeners(&self) -> Vec<Rc<EventListener>> {
+        match self {
+            EventTarget::Node(node) => node.borrow().event_listeners().to_vec(),
+            EventTarget::Window(window) => window.borrow().event_listeners().to_vec(),
+        }
+    }
+
+    fn with_listeners_mut<F: FnOnce(&mut Vec<Rc<EventListener>>)>(&self, f: F) {
+        match self {
+            EventTarget::Node(node) => f(node.borrow_mut().event_listeners_mut()),
+            EventTarget::Window(window) => f(window.borrow_mut().event_listeners_mut()),
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#get-the-parent
+    fn parent(&self, event: &Event) -> Option<EventTarget> {
+        let node = match self {
+            EventTarget::Node(node) => node,
+            EventTarget::Window(_) => return None,
+        };
+        if let Some(parent) = node.borrow().parent().upgrade() {
+            return Some(EventTarget::Node(parent));
+        }
+        // A document's parent is its window, except for `load` events.
+        let is_document = node.borrow().kind() == NodeKind::Document;
+        if is_document && event.event_type != "load" {
+            return node.borrow().window().upgrade().map(EventTarget::Window);
+        }
+        None
+    }
+}
+
+/// https://dom.spec.whatwg.org/#interface-event
+#[derive(Debug, Clone)]
+pub struct Event {
+    event_type: String,
+    bubbles: bool,
+    cancelable: bool,
+    composed: bool,
+    target: Option<EventTarget>,
+    current_target: Option<EventTarget>,
+    event_phase: EventPhase,
+    /// https://dom.spec.whatwg.org/#event-path
+    path: Vec<EventTarget>,
+    stop_propagation: bool,
+    stop_immediate_propagation: bool,
+    canceled: bool,
+    in_passive_listener: bool,
+}
+
+impl Event {
+    pub fn new(event_type: &str, init: EventInit) -> Self {
+        Self {
+            event_type: event_type.to_string(),
+            bubbles: init.bubbles,
+            cancelable: init.cancelable,
+            composed: init.composed,
+            target: None,
+            current_target: None,
+            event_phase: EventPhase::None,
+            path: Vec::new(),
+            stop_propagation: false,
+            stop_immediate_propagation: false,
+            canceled: false,
+            in_passive_listener: false,
+        }
+    }
+
+    pub fn event_type(&self) -> String {
+        self.event_type.clone()
+    }
+
+    pub fn bubbles(&self) -> bool {
+        self.bubbles
+    }
+
+    pub fn cancelable(&self) -> bool {
+        self.cancelable
+    }
+
+    pub fn composed(&self) -> bool {
+        self.composed
+    }
+
+    pub fn target(&self) -> Option<EventTarget> {
+        self.target.clone()
+    }
+
+    pub fn current_target(&self) -> Option<EventTarget> {
+        self.current_target.clone()
+    }
+
+    pub fn event_phase(&self) -> EventPhase {
+        self.event_phase
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-composedpath
+    pub fn composed_path(&self) -> Vec<EventTarget> {
+        self.path.clone()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-stoppropagation
+    pub fn stop_propagation(&mut self) {
+        self.stop_propagation = true;
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-stopimmediatepropagation
+    pub fn stop_immediate_propagation(&mut self) {
+        self.stop_propagation = true;
+        self.stop_immediate_propagation = true;
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-preventdefault
+    pub fn prevent_default(&mut self) {
+        if self.cancelable && !self.in_passive_listener {
+            self.canceled = true;
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-defaultprevented
+    pub fn default_prevented(&self) -> bool {
+        self.canceled
+    }
+}
+
+/// https://dom.spec.whatwg.org/#concept-event-dispatch
+fn dispatch(target: &EventTarget, event: &mut Event) -> bool {
+    event.target = Some(target.clone());
+
+    let mut path = Vec::new();
+    let mut current = Some(target.clone());
+    while let Some(t) = current {
+        current = t.parent(event);
+        path.push(t);
+    }
+    event.path = path.clone();
+
+    for t in path.iter().rev() {
+        event.event_phase = if t == target {
+            EventPhase::AtTarget
+        } else {
+            EventPhase::Capturing
+        };
+        invoke(t, event, EventPhase::Capturing);
+    }
+
+    for t in path.iter() {
+        if t == target {
+            event.event_phase = EventPhase::AtTarget;
+        } else if event.bubbles {
+            event.event_phase = EventPhase::Bubbling;
+        } else {
+            continue;
+        }
+        invoke(t, event, EventPhase::Bubbling);
+    }
+
+    event.event_phase = EventPhase::None;
+    event.current_target = None;
+    event.path.clear();
+    event.stop_propagation = false;
+    event.stop_immediate_propagation = false;
+
+    !event.canceled
+}
+
+/// Runs the listeners of `target` that match `phase`, which is either
+/// `EventPhase::Capturing` or `EventPhase::Bubbling`.
+/// https://dom.spec.whatwg.org/#concept-event-listener-invoke
+fn invoke(target: &EventTarget, event: &mut Event, phase: EventPhase) {
+    if event.stop_propagation {
+        return;
+    }
+    event.current_target = Some(target.clone());
+
+    // https://dom.spec.whatwg.org/#concept-event-listener-inner-invoke
+    // Listeners added during dispatch are not run, so iterate over a clone.
+    for listener in target.listeners() {
+        if listener.removed.get() || listener.event_type != event.event_type {
+            continue;
+        }
+        if (phase == EventPhase::Capturing) != listener.capture {
+            continue;
+        }
+        if listener.once {
+            target.remove_event_listener(
+                &listener.event_type,
+                &listener.callback,
+                listener.capture,
+            );
+        }
+
+        event.in_passive_listener = listener.passive;
+        (listener.callback)(event);
+        event.in_passive_listener = false;
+
+        if event.stop_immediate_propagation {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::append_child;
+    use crate::renderer::dom::node::Element;
+    use crate::renderer::html::parser::tests::parse;
+    use alloc::format;
+    use alloc::vec;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    fn element(name: &str) -> Rc<RefCell<Node>> {
+        Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            name,
+            Vec::new(),
+        )))))
+    }
+
+    fn logger(log: &Log, label: &str) -> EventListenerCallback {
+        let log = log.clone();
+        let label = label.to_string();
+        Rc::new(move |event: &mut Event| {
+            log.borrow_mut()
+                .push(format!("{}:{:?}", label, event.event_phase()));
+        })
+    }
+
+    fn capture() -> AddEventListenerOptions {
+        AddEventListenerOptions {
+            capture: true,
+            ..Default::default()
+        }
+    }
+
+    fn bubbles() -> EventInit {
+        EventInit {
+            bubbles: true,
+            ..Default::default()
+        }
+    }
+
+    /// Returns a `body > p > a` tree.
+    fn tree() -> (EventTarget, EventTarget, EventTarget) {
+        let body = element("body");
+        let p = element("p");
+        let a = element("a");
+        append_child(&body, p.clone()).unwrap();
+        append_child(&p, a.clone()).unwrap();
+        (
+            EventTarget::Node(body),
+            EventTarget::Node(p),
+            EventTarget::Node(a),
+        )
+    }
+
+    #[test]
+    fn test_capture_target_bubble() {
+        let (body, p, a) = tree();
+        let log: Log = Rc::new(RefCell::new(Vec::new()));
+        for (target, name) in [(&body, "body"), (&p, "p"), (&a, "a")] {
+            target.add_event_listener(
+                "click",
+                logger(&log, &format!("{}-bubble", name)),
+                Default::default(),
+            );
+            target.add_event_listener(
+                "click",
+                logger(&log, &format!("{}-capture", name)),
+                capture(),
+            );
+        }
+
+        assert!(a.dispatch_event(&mut Event::new("click", bubbles())));
+        assert_eq!(
+            vec![
+                "body-capture:Capturing",
+                "p-capture:Capturing",
+                "a-capture:AtTarget",
+                "a-bubble:AtTarget",
+                "p-bubble:Bubbling",
+                "body-bubble:Bubbling",
+            ],
+            *log.borrow()
+        );
+
+        log.borrow_mut().clear();
+        a.dispatch_event(&mut Event::new("click", EventInit::default()));
+        assert_eq!(
+            vec![
+                "body-capture:Capturing",
+                "p-capture:Capturing",
+                "a-capture:AtTarget",
+                "a-bubble:AtTarget",
+            ],
+            *log.borrow()
+        );
+    }
+
+    #[test]
+    fn test_stop_propagation() {
+        let (body, p, a) = tree();
+        let log: Log = Rc::new(RefCell::new(Vec::new()));
+        p.add_event_listener(
+            "click",
+            Rc::new(|event: &mut Event| event.stop_propagation()),
+            Default::default(),
+        );
+        p.add_event_listener("click", logger(&log, "p"), Default::default());
+        body.add_event_listener("click", logger(&log, "body"), Default::default());
+        a.add_event_listener(
+            "focus",
+            Rc::new(|event: &mut Event| event.stop_immediate_propagation()),
+            Default::default(),
+        );
+        a.add_event_listener("focus", logger(&log, "a"), Default::default());
+
+        a.dispatch_event(&mut Event::new("click", bubbles()));
+        a.dispatch_event(&mut Event::new("focus", bubbles()));
+        assert_eq!(vec!["p:Bubbling"], *log.borrow());
+    }
+
+    #[test]
+    fn test_prevent_default() {
+        let (_, p, a) = tree();
+        p.add_event_listener(
+            "click",
+            Rc::new(|event: &mut Event| event.prevent_default()),
+            Default::default(),
+        );
+
+        let mut event = Event::new(
+            "click",
+            EventInit {
+                bubbles: true,
+                cancelable: true,
+                ..Default::default()
+            },
+        );
+        assert!(!a.dispatch_event(&mut event));
+        assert!(event.default_prevented());
+
+        // Not cancelable.
+        let mut event = Event::new("click", bubbles());
+        assert!(a.dispatch_event(&mut event));
+
+        // Passive listeners cannot cancel.
+        let (_, p, a) = tree();
+        p.add_event_listener(
+            "click",
+            Rc::new(|event: &mut Event| event.prevent_default()),
+            AddEventListenerOptions {
+                passive: true,
+                ..Default::default()
+            },
+        );
+        let mut event = Event::new(
+            "click",
+            EventInit {
+                bubbles: true,
+                cancelable: true,
+                ..Default::default()
+            },
+        );
+        assert!(a.dispatch_event(&mut event));
+    }
+
+    #[test]
+    fn test_once_and_remove() {
+        let (_, _, a) = tree();
+        let log: Log = Rc::new(RefCell::new(Vec::new()));
+        let once = logger(&log, "once");
+        let always = logger(&log, "always");
+        a.add_event_listener(
+            "click",
+            once,
+            AddEventListenerOptions {
+                once: true,
+                ..Default::default()
+            },
+        );
+        a.add_event_listener("click", always.clone(), Default::default());
+        // Duplicates are ignored.
+        a.add_event_listener("click", always.clone(), Default::default());
+
+        a.dispatch_event(&mut Event::new("click", EventInit::default()));
+        a.dispatch_event(&mut Event::new("click", EventInit::default()));
+        // A listener registered for the capture phase is a different one.
+        a.remove_event_listener("click", &always, true);
+        a.dispatch_event(&mut Event::new("click", EventInit::default()));
+        a.remove_event_listener("click", &always, false);
+        a.dispatch_event(&mut Event::new("click", EventInit::default()));
+
+        assert_eq!(
+            vec![
+                "once:AtTarget",
+                "always:AtTarget",
+                "always:AtTarget",
+                "always:AtTarget",
+            ],
+            *log.borrow()
+        );
+    }
+
+    #[test]
+    fn test_listener_removed_during_dispatch() {
+        let (_, p, _) = tree();
+        let log: Log = Rc::new(RefCell::new(Vec::new()));
+        let second = logger(&log, "second");
+        let target = p.clone();
+        let removed = second.clone();
+        p.add_event_listener(
+            "click",
+            Rc::new(move |_: &mut Event| target.remove_event_listener("click", &removed, false)),
+            Default::default(),
+        );
+        p.add_event_listener("click", second, Default::default());
+
+        p.dispatch_event(&mut Event::new("click", EventInit::default()));
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_composed_path_and_window() {
+        let window = parse("<p>a</p>");
+        let document = window.borrow().document();
+        let html = document.borrow().first_child().unwrap();
+        let body = html.borrow().last_child().upgrade().unwrap();
+        let p = body.borrow().first_child().unwrap();
+
+        let path: Rc<RefCell<Vec<EventTarget>>> = Rc::new(RefCell::new(Vec::new()));
+        let current: Rc<RefCell<Option<EventTarget>>> = Rc::new(RefCell::new(None));
+        let (path_clone, current_clone) = (path.clone(), current.clone());
+        EventTarget::Window(window.clone()).add_event_listener(
+            "click",
+            Rc::new(move |event: &mut Event| {
+                *path_clone.borrow_mut() = event.composed_path();
+                *current_clone.borrow_mut() = event.current_target();
+            }),
+            Default::default(),
+        );
+
+        let target = EventTarget::Node(p.clone());
+        let mut event = Event::new("click", bubbles());
+        target.dispatch_event(&mut event);
+
+        assert_eq!(
+            vec![
+                EventTarget::Node(p),
+                EventTarget::Node(body),
+                EventTarget::Node(html),
+                EventTarget::Node(document.clone()),
+                EventTarget::Window(window.clone()),
+            ],
+            *path.borrow()
+        );
+        assert_eq!(Some(EventTarget::Window(window.clone())), *current.borrow());
+        assert!(event.composed_path().is_empty());
+        assert_eq!(None, event.current_target());
+        assert_eq!(Some(target), event.target());
+        assert_eq!(EventPhase::None, event.event_phase());
+
+        // `load` events do not propagate from the document to the window.
+        let log: Log = Rc::new(RefCell::new(Vec::new()));
+        EventTarget::Window(window).add_event_listener(
+            "load",
+            logger(&log, "window"),
+            Default::default(),
+        );
+        let mut load = Event::new("load", bubbles());
+        EventTarget::Node(document).dispatch_event(&mut load);
+        assert!(log.borrow().is_empty());
+    }
+}
@@ -1 +1,8 @@
+pub mod api;
+pub mod event;
+pub mod mutation;
 pub mod node;
+pub mod range;
+pub mod selection;
+pub mod selector;
+pub mod traversal;
@@ -0,0 +1,506 @@
+//! https://dom.spec.whatwg.org/#mutation-observers
+//!
+//! Records are queued synchronously by the tree mutation functions in
+//! `renderer::dom::api` and kept by each observer until they are collected
+//! with `MutationObserver::take_records`.
+
+use crate::error::Error;
+use crate::renderer::dom::node::Node;
+use alloc::rc::Rc;
+use alloc::rc::Weak;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::iter;
+
+/// https://dom.spec.whatwg.org/#dictdef-mutationobserverinit
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MutationObserverInit {
+    pub child_list: bool,
+    pub attributes: bool,
+    pub character_data: bool,
+    pub subtree: bool,
+    pub attribute_old_value: bool,
+    pub character_data_old_value: bool,
+    pub attribute_filter: Option<Vec<String>>,
+}
+
+/// https://dom.spec.whatwg.org/#dom-mutationrecord-type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutationRecordType {
+    ChildList,
+    Attributes,
+    CharacterData,
+}
+
+/// https://dom.spec.whatwg.org/#interface-mutationrecord
+#[derive(Debug, Clone)]
+pub struct MutationRecord {
+    pub kind: MutationRecordType,
+    pub target: Rc<RefCell<Node>>,
+    pub added_nodes: Vec<Rc<RefCell<Node>>>,
+    pub removed_nodes: Vec<Rc<RefCell<Node>>>,
+    pub previous_sibling: Option<Rc<RefCell<Node>>>,
+    pub next_sibling: Option<Rc<RefCell<Node>>>,
+    pub attribute_name: Option<String>,
+    pub attribute_namespace: Option<String>,
+    pub old_value: Option<String>,
+}
+
+impl MutationRecord {
+    fn new(kind: MutationRecordType, target: &Rc<RefCell<Node>>) -> Self {
+        Self {
+            kind,
+            target: target.clone(),
+            added_nodes: Vec::new(),
+            removed_nodes: Vec::new(),
+            previous_sibling: None,
+            next_sibling: None,
+            attribute_name: None,
+            attribute_namespace: None,
+            old_value: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct MutationObserverState {
+    /// https://dom.spec.whatwg.org/#concept-mo-queue
+    records: Vec<MutationRecord>,
+    /// https://dom.spec.whatwg.org/#mutationobserver-node-list
+    nodes: Vec<Weak<RefCell<Node>>>,
+}
+
+/// https://dom.spec.whatwg.org/#registered-observer
+#[derive(Debug, Clone)]
+pub(crate) struct RegisteredObserver {
+    observer: Weak<RefCell<MutationObserverState>>,
+    options: MutationObserverInit,
+}
+
+/// https://dom.spec.whatwg.org/#interface-mutationobserver
+#[derive(Debug, Clone, Default)]
+pub struct MutationObserver {
+    state: Rc<RefCell<MutationObserverState>>,
+}
+
+impl MutationObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-mutationobserver-observe
+    pub fn observe(
+        &self,
+        target: &Rc<RefCell<Node>>,
+        options: MutationObserverInit,
+    ) -> Result<(), Error> {
+        let mut options = options;
+        if options.attribute_old_value || options.attribute_filter.is_some() {
+            options.attributes = true;
+        }
+        if options.character_data_old_value {
+            options.character_data = true;
+        }
+        if !options.child_list && !options.attributes && !options.character_data {
+            return Err(Error::UnexpectedInput(
+                "TypeError: one of child_list, attributes or character_data must be set"
+                    .to_string(),
+            ));
+        }
+
+        let mut node = target.borrow_mut();
+        let registered = node
+            .registered_observers_mut()
+            .iter_mut()
+            .find(|r| Weak::ptr_eq(&r.observer, &Rc::downgrade(&self.state)));
+        match registered {
+            Some(registered) => registered.options = options,
+            None => {
+                node.registered_observers_mut().push(RegisteredObserver {
+                    observer: Rc::downgrade(&self.state),
+                    options,
+                });
+                self.state.borrow_mut().nodes.push(Rc::downgrade(target));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-mutationobserver-disconnect
+    pub fn disconnect(&self) {
+        let mut state = self.state.borrow_mut();
+        for node in state.nodes.drain(..).filter_map(|n| n.upgrade()) {
+            node.borrow_mut()
+                .registered_observers_mut()
+                .retain(|r| !Weak::ptr_eq(&r.observer, &Rc::downgrade(&self.state)));
+        }
+        state.records.clear();
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-mutationobserver-takerecords
+    pub fn take_records(&self) -> Vec<MutationRecord> {
+        core::mem::take(&mut self.state.borrow_mut().records)
+    }
+}
+
+fn is_interested(options: &MutationObserverInit, is_target: bool, record: &MutationRecord) -> bool {
+    if !is_target && !options.subtree {
+        return false;
+    }
+    match record.kind {
+        MutationRecordType::ChildList => options.child_list,
+        MutationRecordType::Attributes => {
+            options.attributes
+                && match (&options.attribute_filter, &record.attribute_name) {
+                    (Some(filter), Some(name)) => {
+                        record.attribute_namespace.is_none() && filter.contains(name)
+                    }
+                    _ => true,
+                }
+        }
+        MutationRecordType::CharacterData => options.character_data,
+    }
+}
+
+fn wants_old_value(options: &MutationObserverInit, kind: MutationRecordType) -> bool {
+    match kind {
+        MutationRecordType::ChildList => false,
+        MutationRecordType::Attributes => options.attribute_old_value,
+        MutationRecordType::CharacterData => options.character_data_old_value,
+    }
+}
+
+/// Delivers `record` to every observer registered on the target or on one
+/// of its ancestors with `subtree`. `record.old_value` is only kept for
+/// observers that asked for it.
+/// https://dom.spec.whatwg.org/#queue-a-mutation-record
+fn queue_mutation_record(record: MutationRecord) {
+    let mut interested: Vec<(Rc<RefCell<MutationObserverState>>, bool)> = Vec::new();
+
+    let target = record.target.clone();
+    let inclusive_ancestors = iter::once(target.clone()).chain(target.borrow().ancestors());
+    for node in inclusive_ancestors {
+        let is_target = Rc::ptr_eq(&node, &target);
+        for registered in node.borrow().registered_observers() {
+            if !is_interested(&registered.options, is_target, &record) {
+                continue;
+            }
+            let observer = match registered.observer.upgrade() {
+                Some(observer) => observer,
+                None => continue,
+            };
+            let old_value = wants_old_value(&registered.options, record.kind);
+            match interested
+                .iter_mut()
+                .find(|(o, _)| Rc::ptr_eq(o, &observer))
+            {
+                Some((_, o)) => *o |= old_value,
+                None => interested.push((observer, old_value)),
+            }
+        }
+    }
+
+    for (observer, old_value) in interested {
+        let mut r = record.clone();
+        if !old_value {
+            r.old_value = None;
+        }
+        observer.borrow_mut().records.push(r);
+    }
+}
+
+/// https://dom.spec.whatwg.org/#queue-a-tree-mutation-record
+pub(crate) fn queue_tree_mutation_record(
+    target: &Rc<RefCell<Node>>,
+    added_nodes: Vec<Rc<RefCell<Node>>>,
+    removed_nodes: Vec<Rc<RefCell<Node>>>,
+    previous_sibling: Option<Rc<RefCell<Node>>>,
+    next_sibling: Option<Rc<RefCell<Node>>>,
+) {
+    if added_nodes.is_empty() && removed_nodes.is_empty() {
+        return;
+    }
+    let mut record = MutationRecord::new(MutationRecordType::ChildList, target);
+    record.added_nodes = added_nodes;
+    record.removed_nodes = removed_nodes;
+    record.previous_sibling = previous_sibling;
+    record.next_sibling = next_sibling;
+    queue_mutation_record(record);
+}
+
+/// https://dom.spec.whatwg.org/#concept-element-attributes-change
+pub(crate) fn queue_attribute_mutation_record(
+    target: &Rc<RefCell<Node>>,
+    namespace: Option<&str>,
+    local_name: &str,
+    old_value: Option<String>,
+) {
+    let mut record = MutationRecord::new(MutationRecordType::Attributes, target);
+    record.attribute_name = Some(local_name.to_string());
+    record.attribute_namespace = namespace.map(|ns| ns.to_string());
+    record.old_value = old_value;
+    queue_mutation_record(record);
+}
+
+/// https://dom.spec.whatwg.org/#concept-cd-replace
+pub(crate) fn queue_character_data_mutation_record(target: &Rc<RefCell<Node>>, old_value: String) {
+    let mut record = MutationRecord::new(MutationRecordType::CharacterData, target);
+    record.old_value = Some(old_value);
+    queue_mutation_record(record);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::append_child;
+    use crate::renderer::dom::api::insert_before;
+    use crate::renderer::dom::api::remove_attribute;
+    use crate::renderer::dom::api::remove_child;
+    use crate::renderer::dom::api::set_attribute;
+    use crate::renderer::dom::api::set_character_data;
+    use crate::renderer::dom::node::Element;
+    use crate::renderer::dom::node::NodeKind;
+    use alloc::vec;
+
+    fn element(name: &str) -> Rc<RefCell<Node>> {
+        Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            name,
+            Vec::new(),
+        )))))
+    }
+
+    fn text(data: &str) -> Rc<RefCell<Node>> {
+        Rc::new(RefCell::new(Node::new(NodeKind::Text(data.to_string()))))
+    }
+
+    fn child_list() -> MutationObserverInit {
+        MutationObserverInit {
+            child_list: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_observe_requires_a_type() {
+        let observer = MutationObserver::new();
+        let body = element("body");
+        assert!(observer
+            .observe(&body, MutationObserverInit::default())
+            .is_err());
+        assert!(observer
+            .observe(
+                &body,
+                MutationObserverInit {
+                    attribute_old_value: true,
+                    ..Default::default()
+                }
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_child_list() {
+        let observer = MutationObserver::new();
+        let body = element("body");
+        let p1 = element("p");
+        let p2 = element("p");
+        observer.observe(&body, child_list()).unwrap();
+
+        append_child(&body, p2.clone()).unwrap();
+        insert_before(&body, p1.clone(), Some(p2.clone())).unwrap();
+        remove_child(&body, &p2).unwrap();
+
+        let records = observer.take_records();
+        assert_eq!(3, records.len());
+        assert!(records
+            .iter()
+            .all(|r| r.kind == MutationRecordType::ChildList && Rc::ptr_eq(&r.target, &body)));
+
+        assert!(Rc::ptr_eq(&p2, &records[0].added_nodes[0]));
+        assert!(records[0].previous_sibling.is_none());
+        assert!(records[0].next_sibling.is_none());
+
+        assert!(Rc::ptr_eq(&p1, &records[1].added_nodes[0]));
+        assert!(records[1].previous_sibling.is_none());
+        assert!(Rc::ptr_eq(&p2, records[1].next_sibling.as_ref().unwrap()));
+
+        assert!(Rc::ptr_eq(&p2, &records[2].removed_nodes[0]));
+        assert!(Rc::ptr_eq(
+            &p1,
+            records[2].previous_sibling.as_ref().unwrap()
+        ));
+        assert!(records[2].next_sibling.is_none());
+
+        assert!(observer.take_records().is_empty());
+    }
+
+    #[test]
+    fn test_move_and_fragment() {
+        let observer = MutationObserver::new();
+        let body = element("body");
+        let div = element("p");
+        let a = element("a");
+        append_child(&body, div.clone()).unwrap();
+        append_child(&body, a.clone()).unwrap();
+        observer
+            .observe(
+                &body,
+                MutationObserverInit {
+                    child_list: true,
+                    subtree: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        // Moving a node queues a removal on the old parent and an addition on
+        // the new one.
+        append_child(&div, a.clone()).unwrap();
+        let records = observer.take_records();
+        assert_eq!(2, records.len());
+        assert!(Rc::ptr_eq(&body, &records[0].target));
+        assert!(Rc::ptr_eq(&a, &records[0].removed_nodes[0]));
+        assert!(Rc::ptr_eq(&div, &records[1].target));
+        assert!(Rc::ptr_eq(&a, &records[1].added_nodes[0]));
+
+        // The children of a fragment are reported as a single addition.
+        let fragment = Rc::new(RefCell::new(Node::new(NodeKind::DocumentFragment)));
+        let t1 = text("1");
+        let t2 = text("2");
+        append_child(&fragment, t1.clone()).unwrap();
+        append_child(&fragment, t2.clone()).unwrap();
+        append_child(&body, fragment).unwrap();
+        let records = observer.take_records();
+        assert_eq!(1, records.len());
+        assert_eq!(2, records[0].added_nodes.len());
+        assert!(Rc::ptr_eq(&t1, &records[0].added_nodes[0]));
+        assert!(Rc::ptr_eq(&t2, &records[0].added_nodes[1]));
+        assert!(Rc::ptr_eq(
+            &div,
+            records[0].previous_sibling.as_ref().unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_subtree() {
+        let observer = MutationObserver::new();
+        let body = element("body");
+        let p = element("p");
+        append_child(&body, p.clone()).unwrap();
+        observer.observe(&body, child_list()).unwrap();
+
+        append_child(&p, text("a")).unwrap();
+        assert!(observer.take_records().is_empty());
+
+        observer
+            .observe(
+                &body,
+                MutationObserverInit {
+                    child_list: true,
+                    subtree: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        append_child(&p, text("b")).unwrap();
+        let records = observer.take_records();
+        assert_eq!(1, records.len());
+        assert!(Rc::ptr_eq(&p, &records[0].target));
+    }
+
+    #[test]
+    fn test_attributes() {
+        let observer = MutationObserver::new();
+        let p = element("p");
+        set_attribute(&p, "class", "a");
+        observer
+            .observe(
+                &p,
+                MutationObserverInit {
+                    attribute_old_value: true,
+                    attribute_filter: Some(vec!["class".to_string(), "id".to_string()]),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        set_attribute(&p, "class", "b");
+        set_attribute(&p, "title", "ignored");
+        set_attribute(&p, "id", "x");
+        remove_attribute(&p, "id");
+        remove_attribute(&p, "id");
+
+        let records = observer.take_records();
+        let summary: Vec<(Option<String>, Option<String>)> = records
+            .iter()
+            .map(|r| (r.attribute_name.clone(), r.old_value.clone()))
+            .collect();
+        assert_eq!(
+            vec![
+                (Some("class".to_string()), Some("a".to_string())),
+                (Some("id".to_string()), None),
+                (Some("id".to_string()), Some("x".to_string())),
+            ],
+            summary
+        );
+        assert!(records
+            .iter()
+            .all(|r| r.kind == MutationRecordType::Attributes));
+    }
+
+    #[test]
+    fn test_old_value_is_per_observer() {
+        let with_old_value = MutationObserver::new();
+        let without_old_value = MutationObserver::new();
+        let p = element("p");
+        let t = text("a");
+        append_child(&p, t.clone()).unwrap();
+        with_old_value
+            .observe(
+                &p,
+                MutationObserverInit {
+                    character_data_old_value: true,
+                    subtree: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        without_old_value
+            .observe(
+                &t,
+                MutationObserverInit {
+                    character_data: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        set_character_data(&t, "b");
+
+        let records = with_old_value.take_records();
+        assert_eq!(1, records.len());
+        assert_eq!(MutationRecordType::CharacterData, records[0].kind);
+        assert_eq!(Some("a".to_string()), records[0].old_value);
+        let records = without_old_value.take_records();
+        assert_eq!(1, records.len());
+        assert_eq!(None, records[0].old_value);
+        assert_eq!(NodeKind::Text("b".to_string()), t.borrow().kind());
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let observer = MutationObserver::new();
+        let body = element("body");
+        observer.observe(&body, child_list()).unwrap();
+        append_child(&body, element("p")).unwrap();
+
+        observer.disconnect();
+        assert!(observer.take_records().is_empty());
+        append_child(&body, element("p")).unwrap();
+        assert!(observer.take_records().is_empty());
+        assert!(body.borrow().registered_observers().is_empty());
+    }
+}
@@ -1,21 +1,43 @@
+use crate::error::Error;
+use crate::renderer::css::computed_style::ComputedStyle;
+use crate::renderer::css::invalidation::Restyle;
+use crate::renderer::dom::event::EventListener;
+use crate::renderer::dom::mutation::RegisteredObserver;
+use crate::renderer::dom::range::RangeState;
+use crate::renderer::dom::selection::Selection;
+use crate::renderer::dom::selector::PseudoElement;
+use crate::renderer::dom::traversal::Ancestors;
+use crate::renderer::dom::traversal::Children;
+use crate::renderer::dom::traversal::Descendants;
+use crate::renderer::dom::traversal::Following;
+use crate::renderer::dom::traversal::NodeIteratorState;
+use crate::renderer::dom::traversal::Preceding;
 use crate::renderer::html::attribute::Attribute;
+use alloc::collections::BTreeMap;
 use alloc::format;
 use alloc::rc::Rc;
 use alloc::rc::Weak;
 use alloc::string::String;
+use alloc::string::ToString;
 use alloc::vec::Vec;
 use core::cell::RefCell;
+use core::fmt;
 use core::str::FromStr;
 
 #[derive(Debug, Clone)]
 pub struct Window {
     document: Rc<RefCell<Node>>,
+    selection: Selection,
+    event_listeners: Vec<Rc<EventListener>>,
 }
 
 impl Window {
     pub fn new() -> Self {
+        let document = Rc::new(RefCell::new(Node::new(NodeKind::Document)));
         let window = Self {
-            document: Rc::new(RefCell::new(Node::new(NodeKind::Document))),
+            selection: Selection::new(&document),
+            document,
+            event_listeners: Vec::new(),
         };
 
         window
@@ -29,6 +51,26 @@ impl Window {
     pub fn document(&self) -> Rc<RefCell<Node>> {
         self.document.clone()
     }
+
+    /// Returns the selection of the document.
+    /// https://w3c.github.io/selection-api/#dom-window-getselection
+    pub fn selection(&self) -> Selection {
+        self.selection.clone()
+    }
+
+    pub(crate) fn event_listeners(&self) -> &[Rc<EventListener>] {
+        &self.event_listeners
+    }
+
+    pub(crate) fn event_listeners_mut(&mut self) -> &mut Vec<Rc<EventListener>> {
+        &mut self.event_listeners
+    }
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -40,6 +82,27 @@ pub struct Node {
     last_child: Weak<RefCell<Node>>,
     previous_sibling: Weak<RefCell<Node>>,
     next_sibling: Option<Rc<RefCell<Node>>>,
+    /// Elements keyed by their `id` attribute. Only a `NodeKind::Document`
+    /// node maintains it, through the functions in `renderer::dom::api`.
+    id_index: BTreeMap<String, Vec<Weak<RefCell<Node>>>>,
+    /// https://dom.spec.whatwg.org/#concept-live-range
+    /// Only a `NodeKind::Document` node maintains it.
+    live_ranges: Vec<Weak<RefCell<RangeState>>>,
+    /// The `NodeIterator`s whose root is in this document, which node
+    /// removal updates. Only a `NodeKind::Document` node maintains it.
+    node_iterators: Vec<Weak<RefCell<NodeIteratorState>>>,
+    /// https://dom.spec.whatwg.org/#registered-observer-list
+    registered_observers: Vec<RegisteredObserver>,
+    /// https://dom.spec.whatwg.org/#eventtarget-event-listener-list
+    event_listeners: Vec<Rc<EventListener>>,
+    /// Only element nodes have one, once `Cascade::apply` has run.
+    computed_style: Option<Rc<ComputedStyle>>,
+    /// The styles of the `::before` and `::after` pseudo-elements that
+    /// generate a box.
+    pseudo_element_styles: Vec<(PseudoElement, Rc<ComputedStyle>)>,
+    /// Set by `Cascade::invalidate` when the style must be computed again,
+    /// and cleared by `Cascade::restyle`.
+    restyle: Option<Restyle>,
 }
 
 impl PartialEq for Node {
@@ -58,6 +121,14 @@ impl Node {
             last_child: Weak::new(),
             previous_sibling: Weak::new(),
             next_sibling: None,
+            id_index: BTreeMap::new(),
+            live_ranges: Vec::new(),
+            node_iterators: Vec::new(),
+            registered_observers: Vec::new(),
+            event_listeners: Vec::new(),
+            computed_style: None,
+            pseudo_element_styles: Vec::new(),
+            restyle: None,
         }
     }
 
@@ -65,6 +136,10 @@ impl Node {
         self.window = window;
     }
 
+    pub fn window(&self) -> Weak<RefCell<Window>> {
+        self.window.clone()
+    }
+
     pub fn set_parent(&mut self, parent: Weak<RefCell<Node>>) {
         self.parent = parent;
     }
@@ -109,17 +184,163 @@ impl Node {
         self.kind.clone()
     }
 
+    /// https://dom.spec.whatwg.org/#dom-node-nodetype
+    pub fn node_type(&self) -> u16 {
+        match self.kind {
+            NodeKind::Element(_) => 1,
+            NodeKind::Text(_) => 3,
+            NodeKind::ProcessingInstruction { .. } => 7,
+            NodeKind::Comment(_) => 8,
+            NodeKind::Document => 9,
+            NodeKind::DocumentType { .. } => 10,
+            NodeKind::DocumentFragment => 11,
+        }
+    }
+
+    /// Returns an iterator over the children of this node.
+    pub fn children(&self) -> Children {
+        Children::new(self.first_child())
+    }
+
+    /// Returns an iterator over the descendants of this node in pre-order.
+    /// The node itself is not included.
+    /// https://dom.spec.whatwg.org/#concept-tree-descendant
+    pub fn descendants(&self) -> Descendants {
+        Descendants::new(self.first_child())
+    }
+
+    /// Returns an iterator from the parent of this node up to the root.
+    /// https://dom.spec.whatwg.org/#concept-tree-ancestor
+    pub fn ancestors(&self) -> Ancestors {
+        Ancestors::new(self.parent().upgrade())
+    }
+
+    /// Returns an iterator over the nodes after this node in tree order,
+    /// including its descendants.
+    /// https://dom.spec.whatwg.org/#concept-tree-following
+    pub fn following(&self) -> Following {
+        if let Some(first_child) = self.first_child() {
+            return Following::new(Some(first_child));
+        }
+        if let Some(next_sibling) = self.next_sibling() {
+            return Following::new(Some(next_sibling));
+        }
+        let next = self
+            .ancestors()
+            .find_map(|ancestor| ancestor.borrow().next_sibling());
+        Following::new(next)
+    }
+
+    /// Returns an iterator over the nodes before this node in reverse tree
+    /// order, including its ancestors.
+    /// https://dom.spec.whatwg.org/#concept-tree-preceding
+    pub fn preceding(&self) -> Preceding {
+        match self.previous_sibling().upgrade() {
+            Some(previous_sibling) => {
+                Preceding::new(Some(last_inclusive_descendant(previous_sibling)))
+            }
+            None => Preceding::new(self.parent().upgrade()),
+        }
+    }
+
     pub fn get_element(&self) -> Option<Element> {
         match self.kind {
-            NodeKind::Document | NodeKind::Text(_) => None,
             NodeKind::Element(ref e) => Some(e.clone()),
+            _ => None,
         }
     }
 
+    pub fn get_element_mut(&mut self) -> Option<&mut Element> {
+        match self.kind {
+            NodeKind::Element(ref mut e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn id_index(&self) -> &BTreeMap<String, Vec<Weak<RefCell<Node>>>> {
+        &self.id_index
+    }
+
+    pub(crate) fn id_index_mut(&mut self) -> &mut BTreeMap<String, Vec<Weak<RefCell<Node>>>> {
+        &mut self.id_index
+    }
+
+    pub(crate) fn registered_observers(&self) -> &[RegisteredObserver] {
+        &self.registered_observers
+    }
+
+    pub(crate) fn registered_observers_mut(&mut self) -> &mut Vec<RegisteredObserver> {
+        &mut self.registered_observers
+    }
+
+    pub(crate) fn event_listeners(&self) -> &[Rc<EventListener>] {
+        &self.event_listeners
+    }
+
+    pub(crate) fn event_listeners_mut(&mut self) -> &mut Vec<Rc<EventListener>> {
+        &mut self.event_listeners
+    }
+
+    pub(crate) fn live_ranges_mut(&mut self) -> &mut Vec<Weak<RefCell<RangeState>>> {
+        &mut self.live_ranges
+    }
+
+    pub(crate) fn node_iterators_mut(&mut self) -> &mut Vec<Weak<RefCell<NodeIteratorState>>> {
+        &mut self.node_iterators
+    }
+
+    /// https://www.w3.org/TR/css-cascade-4/#computed-value
+    pub fn computed_style(&self) -> Option<Rc<ComputedStyle>> {
+        self.computed_style.clone()
+    }
+
+    pub fn set_computed_style(&mut self, computed_style: Option<Rc<ComputedStyle>>) {
+        self.computed_style = computed_style;
+    }
+
+    pub fn pseudo_element_style(&self, pseudo_element: PseudoElement) -> Option<Rc<ComputedStyle>> {
+        self.pseudo_element_styles
+            .iter()
+            .find(|(p, _)| *p == pseudo_element)
+            .map(|(_, style)| style.clone())
+    }
+
+    pub fn set_pseudo_element_style(
+        &mut self,
+        pseudo_element: PseudoElement,
+        style: Option<Rc<ComputedStyle>>,
+    ) {
+        self.pseudo_element_styles
+            .retain(|(p, _)| *p != pseudo_element);
+        if let Some(style) = style {
+            self.pseudo_element_styles.push((pseudo_element, style));
+        }
+    }
+
+    pub fn restyle(&self) -> Option<Restyle> {
+        self.restyle
+    }
+
+    pub fn set_restyle(&mut self, restyle: Option<Restyle>) {
+        self.restyle = restyle;
+    }
+
     pub fn element_kind(&self) -> Option<ElementKind> {
         match self.kind {
-            NodeKind::Document | NodeKind::Text(_) => None,
             NodeKind::Element(ref e) => Some(e.kind()),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the last node of the subtree rooted at `node` in tree order.
+pub(crate) fn last_inclusive_descendant(node: Rc<RefCell<Node>>) -> Rc<RefCell<Node>> {
+    let mut current = node;
+    loop {
+        let last_child = current.borrow().last_child().upgrade();
+        match last_child {
+            Some(last_child) => current = last_child,
+            None => return current,
         }
     }
 }
@@ -132,6 +353,18 @@ pub enum NodeKind {
     Element(Element),
     /// https://dom.spec.whatwg.org/#interface-text
     Text(String),
+    /// https://dom.spec.whatwg.org/#interface-comment
+    Comment(String),
+    /// https://dom.spec.whatwg.org/#interface-documenttype
+    DocumentType {
+        name: String,
+        public_id: String,
+        system_id: String,
+    },
+    /// https://dom.spec.whatwg.org/#interface-documentfragment
+    DocumentFragment,
+    /// https://dom.spec.whatwg.org/#interface-processinginstruction
+    ProcessingInstruction { target: String, data: String },
 }
 
 impl PartialEq for NodeKind {
@@ -143,22 +376,61 @@ impl PartialEq for NodeKind {
                 _ => false,
             },
             NodeKind::Text(_) => matches!(other, NodeKind::Text(_)),
+            NodeKind::Comment(_) => matches!(other, NodeKind::Comment(_)),
+            NodeKind::DocumentType { name: n1, .. } => match &other {
+                NodeKind::DocumentType { name: n2, .. } => n1 == n2,
+                _ => false,
+            },
+            NodeKind::DocumentFragment => matches!(other, NodeKind::DocumentFragment),
+            NodeKind::ProcessingInstruction { target: t1, .. } => match &other {
+                NodeKind::ProcessingInstruction { target: t2, .. } => t1 == t2,
+                _ => false,
+            },
         }
     }
 }
 
+/// https://infra.spec.whatwg.org/#html-namespace
+pub const HTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+/// https://infra.spec.whatwg.org/#mathml-namespace
+pub const MATHML_NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+/// https://infra.spec.whatwg.org/#svg-namespace
+pub const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+/// https://infra.spec.whatwg.org/#xlink-namespace
+pub const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+/// https://infra.spec.whatwg.org/#xml-namespace
+pub const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+/// https://infra.spec.whatwg.org/#xmlns-namespace
+pub const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+
 /// https://dom.spec.whatwg.org/#interface-element
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Element {
     kind: ElementKind,
+    /// https://dom.spec.whatwg.org/#concept-element-namespace
+    namespace: Option<String>,
+    /// https://dom.spec.whatwg.org/#concept-element-namespace-prefix
+    prefix: Option<String>,
     attributes: Vec<Attribute>,
 }
 
 impl Element {
+    /// Creates an element in the HTML namespace.
     pub fn new(element_name: &str, attributes: Vec<Attribute>) -> Self {
+        Self::new_ns(Some(HTML_NAMESPACE), None, element_name, attributes)
+    }
+
+    pub fn new_ns(
+        namespace: Option<&str>,
+        prefix: Option<&str>,
+        local_name: &str,
+        attributes: Vec<Attribute>,
+    ) -> Self {
         Self {
-            kind: ElementKind::from_str(element_name)
+            kind: ElementKind::from_str(local_name)
                 .expect("failed to convert string to ElementKind"),
+            namespace: namespace.map(String::from),
+            prefix: prefix.map(String::from),
             attributes,
         }
     }
@@ -166,6 +438,179 @@ impl Element {
     pub fn kind(&self) -> ElementKind {
         self.kind
     }
+
+    /// https://dom.spec.whatwg.org/#dom-element-namespaceuri
+    pub fn namespace_uri(&self) -> Option<String> {
+        self.namespace.clone()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-prefix
+    pub fn prefix(&self) -> Option<String> {
+        self.prefix.clone()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-localname
+    pub fn local_name(&self) -> String {
+        self.kind.to_string()
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-element-qualified-name
+    pub fn qualified_name(&self) -> String {
+        match self.prefix {
+            Some(ref prefix) => format!("{}:{}", prefix, self.kind),
+            None => self.kind.to_string(),
+        }
+    }
+
+    pub fn attributes(&self) -> Vec<Attribute> {
+        self.attributes.clone()
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-element-attributes-get-by-name
+    pub fn get_attribute_node(&self, name: &str) -> Option<Attribute> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.name() == name)
+            .cloned()
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-element-attributes-get-by-namespace
+    pub fn get_attribute_node_ns(
+        &self,
+        namespace: Option<&str>,
+        local_name: &str,
+    ) -> Option<Attribute> {
+        let namespace = namespace.filter(|ns| !ns.is_empty());
+        self.attributes
+            .iter()
+            .find(|attr| {
+                attr.namespace().as_deref() == namespace && attr.local_name() == local_name
+            })
+            .cloned()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-getattribute
+    pub fn get_attribute(&self, name: &str) -> Option<String> {
+        self.get_attribute_node(name).map(|attr| attr.value())
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-getattributens
+    pub fn get_attribute_ns(&self, namespace: Option<&str>, local_name: &str) -> Option<String> {
+        self.get_attribute_node_ns(namespace, local_name)
+            .map(|attr| attr.value())
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-setattribute
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        match self.attributes.iter_mut().find(|attr| attr.name() == name) {
+            Some(attr) => attr.set_value(value),
+            None => self.attributes.push(Attribute::with_value(name, value)),
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-setattributens
+    pub fn set_attribute_ns(
+        &mut self,
+        namespace: Option<&str>,
+        qualified_name: &str,
+        value: &str,
+    ) -> Result<(), Error> {
+        let (namespace, prefix, local_name) = validate_and_extract(namespace, qualified_name)?;
+        match self
+            .attributes
+            .iter_mut()
+            .find(|attr| attr.namespace() == namespace && attr.local_name() == local_name)
+        {
+            Some(attr) => attr.set_value(value),
+            None => self.attributes.push(Attribute::with_namespace(
+                namespace.as_deref(),
+                prefix.as_deref(),
+                &local_name,
+                value,
+            )),
+        }
+        Ok(())
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-removeattribute
+    pub fn remove_attribute(&mut self, name: &str) {
+        if let Some(i) = self.attributes.iter().position(|attr| attr.name() == name) {
+            self.attributes.remove(i);
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-removeattributens
+    pub fn remove_attribute_ns(&mut self, namespace: Option<&str>, local_name: &str) {
+        let namespace = namespace.filter(|ns| !ns.is_empty());
+        self.attributes.retain(|attr| {
+            attr.namespace().as_deref() != namespace || attr.local_name() != local_name
+        });
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-element-id
+    pub fn id(&self) -> Option<String> {
+        self.get_attribute_ns(None, "id")
+            .filter(|id| !id.is_empty())
+    }
+
+    /// Returns the classes in the `class` attribute.
+    /// https://dom.spec.whatwg.org/#dom-element-classlist
+    pub fn class_list(&self) -> Vec<String> {
+        match self.get_attribute_ns(None, "class") {
+            Some(class) => class.split_ascii_whitespace().map(String::from).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Splits `qualified_name` into a prefix and a local name, and checks that
+/// they are allowed in `namespace`. Returns the namespace, prefix and local
+/// name.
+/// https://dom.spec.whatwg.org/#validate-and-extract
+pub fn validate_and_extract(
+    namespace: Option<&str>,
+    qualified_name: &str,
+) -> Result<(Option<String>, Option<String>, String), Error> {
+    let namespace = namespace.filter(|ns| !ns.is_empty());
+
+    // https://dom.spec.whatwg.org/#validate
+    let is_valid_name = |name: &str| {
+        !name.is_empty()
+            && !name.contains(|c: char| {
+                c.is_ascii_whitespace() || matches!(c, '\0' | '/' | '>' | '<' | '=' | '"' | '\'')
+            })
+    };
+    let (prefix, local_name) = match qualified_name.split_once(':') {
+        Some((prefix, local_name)) => (Some(prefix), local_name),
+        None => (None, qualified_name),
+    };
+    if !is_valid_name(local_name)
+        || local_name.contains(':')
+        || prefix.is_some_and(|p| !is_valid_name(p))
+    {
+        return Err(Error::UnexpectedInput(format!(
+            "InvalidCharacterError: {:?} is not a valid qualified name",
+            qualified_name
+        )));
+    }
+
+    let is_xmlns = qualified_name == "xmlns" || prefix == Some("xmlns");
+    if (prefix.is_some() && namespace.is_none())
+        || (prefix == Some("xml") && namespace != Some(XML_NAMESPACE))
+        || (is_xmlns && namespace != Some(XMLNS_NAMESPACE))
+        || (!is_xmlns && namespace == Some(XMLNS_NAMESPACE))
+    {
+        return Err(Error::UnexpectedInput(format!(
+            "NamespaceError: {:?} is not allowed in namespace {:?}",
+            qualified_name, namespace
+        )));
+    }
+
+    Ok((
+        namespace.map(String::from),
+        prefix.map(String::from),
+        local_name.to_string(),
+    ))
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -175,6 +620,14 @@ pub enum ElementKind {
     Html,
     /// https://html.spec.whatwg.org/multipage/semantics.html#the-head-element
     Head,
+    /// https://html.spec.whatwg.org/multipage/semantics.html#the-title-element
+    Title,
+    /// https://html.spec.whatwg.org/multipage/semantics.html#the-base-element
+    Base,
+    /// https://html.spec.whatwg.org/multipage/semantics.html#the-link-element
+    Link,
+    /// https://html.spec.whatwg.org/multipage/semantics.html#the-meta-element
+    Meta,
     /// https://html.spec.whatwg.org/multipage/semantics.html#the-style-element
     Style,
     /// https://html.spec.whatwg.org/multipage/scripting.html#the-script-element
@@ -188,6 +641,10 @@ pub enum ElementKind {
     H2,
     /// https://html.spec.whatwg.org/multipage/text-level-semantics.html#the-a-element
     A,
+    /// https://svgwg.org/svg2-draft/struct.html#SVGElement
+    Svg,
+    /// https://w3c.github.io/mathml-core/#the-top-level-math-element
+    Math,
 }
 
 impl FromStr for ElementKind {
@@ -197,6 +654,10 @@ impl FromStr for ElementKind {
         match s {
             "html" => Ok(ElementKind::Html),
             "head" => Ok(ElementKind::Head),
+            "title" => Ok(ElementKind::Title),
+            "base" => Ok(ElementKind::Base),
+            "link" => Ok(ElementKind::Link),
+            "meta" => Ok(ElementKind::Meta),
             "style" => Ok(ElementKind::Style),
             "script" => Ok(ElementKind::Script),
             "body" => Ok(ElementKind::Body),
@@ -204,7 +665,32 @@ impl FromStr for ElementKind {
             "h1" => Ok(ElementKind::H1),
             "h2" => Ok(ElementKind::H2),
             "a" => Ok(ElementKind::A),
+            "svg" => Ok(ElementKind::Svg),
+            "math" => Ok(ElementKind::Math),
             _ => Err(format!("unimplemented element name {:?}", s)),
         }
     }
 }
+
+impl fmt::Display for ElementKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            ElementKind::Html => "html",
+            ElementKind::Head => "head",
+            ElementKind::Title => "title",
+            ElementKind::Base => "base",
+            ElementKind::Link => "link",
+            ElementKind::Meta => "meta",
+            ElementKind::Style => "style",
+            ElementKind::Script => "script",
+            ElementKind::Body => "body",
+            ElementKind::P => "p",
+            ElementKind::H1 => "h1",
+            ElementKind::H2 => "h2",
+            ElementKind::A => "a",
+            ElementKind::Svg => "svg",
+            ElementKind::Math => "math",
+        };
+        write!(f, "{}", s)
+    }
+}
@@ -0,0 +1,832 @@
+//! https://dom.spec.whatwg.org/#ranges
+//!
+//! Ranges are live: the document they were created for keeps a weak list of
+//! them, and the tree mutation functions in `renderer::dom::api` update their
+//! boundary points.
+
+use crate::error::Error;
+use crate::renderer::dom::api::append_child;
+use crate::renderer::dom::api::clone_node;
+use crate::renderer::dom::api::index;
+use crate::renderer::dom::api::is_inclusive_ancestor;
+use crate::renderer::dom::api::node_length;
+use crate::renderer::dom::api::owner_document;
+use crate::renderer::dom::api::remove_child;
+use crate::renderer::dom::api::replace_data;
+use crate::renderer::dom::api::set_character_data;
+use crate::renderer::dom::api::tree_order;
+use crate::renderer::dom::api::tree_root;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use alloc::rc::Rc;
+use alloc::rc::Weak;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::fmt;
+
+/// https://dom.spec.whatwg.org/#concept-range-bp
+#[derive(Debug, Clone)]
+pub struct BoundaryPoint {
+    pub node: Rc<RefCell<Node>>,
+    pub offset: usize,
+}
+
+impl BoundaryPoint {
+    pub fn new(node: &Rc<RefCell<Node>>, offset: usize) -> Self {
+        Self {
+            node: node.clone(),
+            offset,
+        }
+    }
+
+    /// Compares two boundary points of the same tree.
+    /// https://dom.spec.whatwg.org/#concept-range-bp-position
+    pub fn position(&self, other: &BoundaryPoint) -> Ordering {
+        if Rc::ptr_eq(&self.node, &other.node) {
+            return self.offset.cmp(&other.offset);
+        }
+        if tree_order(&self.node, &other.node) == Ordering::Greater {
+            return other.position(self).reverse();
+        }
+        if is_inclusive_ancestor(&self.node, &other.node) {
+            let mut child = other.node.clone();
+            loop {
+                let parent = child
+                    .borrow()
+                    .parent()
+                    .upgrade()
+                    .expect("an ancestor of the node exists");
+                if Rc::ptr_eq(&parent, &self.node) {
+                    break;
+                }
+                child = parent;
+            }
+            if index(&child) < self.offset {
+                return Ordering::Greater;
+            }
+        }
+        Ordering::Less
+    }
+}
+
+impl PartialEq for BoundaryPoint {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.node, &other.node) && self.offset == other.offset
+    }
+}
+
+/// Checks that (`node`, `offset`) can be used as a boundary point.
+pub(crate) fn validate_boundary_point(
+    node: &Rc<RefCell<Node>>,
+    offset: usize,
+) -> Result<(), Error> {
+    if matches!(node.borrow().kind, NodeKind::DocumentType { .. }) {
+        return Err(Error::UnexpectedInput(
+            "InvalidNodeTypeError: a doctype cannot be a boundary point".to_string(),
+        ));
+    }
+    if offset > node_length(node) {
+        return Err(Error::UnexpectedInput(
+            "IndexSizeError: the offset is larger than the length of the node".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub(crate) struct RangeState {
+    start: BoundaryPoint,
+    end: BoundaryPoint,
+}
+
+/// A handle to a live range. Clones refer to the same range; use
+/// `Range::clone_range` to copy it.
+/// https://dom.spec.whatwg.org/#interface-range
+#[derive(Debug, Clone)]
+pub struct Range {
+    state: Rc<RefCell<RangeState>>,
+}
+
+impl PartialEq for Range {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Range {
+    /// Creates a range collapsed at the start of `document`.
+    /// https://dom.spec.whatwg.org/#dom-range-range
+    pub fn new(document: &Rc<RefCell<Node>>) -> Self {
+        let range = Self {
+            state: Rc::new(RefCell::new(RangeState {
+                start: BoundaryPoint::new(document, 0),
+                end: BoundaryPoint::new(document, 0),
+            })),
+        };
+        range.register(document);
+        range
+    }
+
+    /// Adds this range to the live ranges of the document `node` is in.
+    fn register(&self, node: &Rc<RefCell<Node>>) {
+        let document = match owner_document(node) {
+            Some(document) => document,
+            None => return,
+        };
+        let weak = Rc::downgrade(&self.state);
+        let mut document = document.borrow_mut();
+        let live_ranges = document.live_ranges_mut();
+        live_ranges.retain(|r| r.strong_count() > 0);
+        if !live_ranges.iter().any(|r| Weak::ptr_eq(r, &weak)) {
+            live_ranges.push(weak);
+        }
+    }
+
+    pub fn start(&self) -> BoundaryPoint {
+        self.state.borrow().start.clone()
+    }
+
+    pub fn end(&self) -> BoundaryPoint {
+        self.state.borrow().end.clone()
+    }
+
+    pub fn start_container(&self) -> Rc<RefCell<Node>> {
+        self.state.borrow().start.node.clone()
+    }
+
+    pub fn start_offset(&self) -> usize {
+        self.state.borrow().start.offset
+    }
+
+    pub fn end_container(&self) -> Rc<RefCell<Node>> {
+        self.state.borrow().end.node.clone()
+    }
+
+    pub fn end_offset(&self) -> usize {
+        self.state.borrow().end.offset
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-range-collapsed
+    pub fn collapsed(&self) -> bool {
+        let state = self.state.borrow();
+        state.start == state.end
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-range-commonancestorcontainer
+    pub fn common_ancestor_container(&self) -> Rc<RefCell<Node>> {
+        common_ancestor(&self.start_container(), &self.end_container())
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-range-bp-set
+    fn set_boundary(
+        &self,
+        node: &Rc<RefCell<Node>>,
+        offset: usize,
+        is_start: bool,
+    ) -> Result<(), Error> {
+        validate_boundary_point(node, offset)?;
+        let bp = BoundaryPoint::new(node, offset);
+
+        let (start, end) = (self.start(), self.end());
+        let same_root = Rc::ptr_eq(&tree_root(node), &tree_root(&start.node));
+        {
+            let mut state = self.state.borrow_mut();
+            if is_start {
+                if !same_root || bp.position(&end) == Ordering::Greater {
+                    state.end = bp.clone();
+                }
+                state.start = bp;
+            } else {
+                if !same_root || bp.position(&start) == Ordering::Less {
+                    state.start = bp.clone();
+                }
+                state.end = bp;
+            }
+        }
+        self.register(node);
+        Ok(())
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-range-setstart
+    pub fn set_start(&self, node: &Rc<RefCell<Node>>, offset: usize) -> Result<(), Error> {
+        self.set_boundary(node, offset, true)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-range-setend
+    pub fn set_end(&self, node: &Rc<RefCell<Node>>, offset: usize) -> Result<(), Error> {
+        self.set_boundary(node, offset, false)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-range-setstartbefore
+    pub fn set_start_before(&self, node: &Rc<RefCell<Node>>) -> Result<(), Error> {
+        self.set_start(&parent_of(node)?, index(node))
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-range-setstartafter
+    pub fn set_start_after(&self, node: &Rc<RefCell<Node>>) -> Result<(), Error> {
+        self.set_start(&parent_of(node)?, index(node) + 1)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-range-setendbefore
+    pub fn set_end_before(&self, node: &Rc<RefCell<Node>>) -> Result<(), Error> {
+        self.set_end(&parent_of(node)?, index(node))
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-range-setendafter
+    pub fn set_end_after(&self, node: &Rc<RefCell<Node>>) -> Result<(), Error> {
+        self.set_end(&parent_of(node)?, index(node) + 1)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-range-collapse
+    pub fn collapse(&self, to_start: bool) {
+        let mut state = self.state.borrow_mut();
+        if to_start {
+            state.end = state.start.clone();
+        } else {
+            state.start = state.end.clone();
+        }
+    }
+
+    fn set(&self, start: BoundaryPoint, end: BoundaryPoint) {
+        let node = start.node.clone();
+        *self.state.borrow_mut() = RangeState { start, end };
+        self.register(&node);
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-range-select
+    pub fn select_node(&self, node: &Rc<RefCell<Node>>) -> Result<(), Error> {
+        let parent = parent_of(node)?;
+        let index = index(node);
+        self.set(
+            BoundaryPoint::new(&parent, index),
+            BoundaryPoint::new(&parent, index + 1),
+        );
+        Ok(())
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-range-selectnodecontents
+    pub fn select_node_contents(&self, node: &Rc<RefCell<Node>>) -> Result<(), Error> {
+        validate_boundary_point(node, 0)?;
+        self.set(
+            BoundaryPoint::new(node, 0),
+            BoundaryPoint::new(node, node_length(node)),
+        );
+        Ok(())
+    }
+
+    /// Returns a new live range with the same boundary points.
+    /// https://dom.spec.whatwg.org/#dom-range-clonerange
+    pub fn clone_range(&self) -> Range {
+        let range = Self {
+            state: Rc::new(RefCell::new(RangeState {
+                start: self.start(),
+                end: self.end(),
+            })),
+        };
+        range.register(&self.start_container());
+        range
+    }
+
+    /// Returns true if `node` is entirely within this range.
+    /// https://dom.spec.whatwg.org/#contained
+    pub fn contains_node(&self, node: &Rc<RefCell<Node>>) -> bool {
+        is_contained(node, &self.start(), &self.end())
+    }
+
+    /// Returns a fragment with copies of the nodes in this range.
+    /// https://dom.spec.whatwg.org/#dom-range-clonecontents
+    pub fn clone_contents(&self) -> Result<Rc<RefCell<Node>>, Error> {
+        process_contents(&self.start(), &self.end(), false)
+    }
+
+    /// Moves the contents of this range into a new fragment and collapses
+    /// the range.
+    /// https://dom.spec.whatwg.org/#dom-range-extractcontents
+    pub fn extract_contents(&self) -> Result<Rc<RefCell<Node>>, Error> {
+        let (start, end) = (self.start(), self.end());
+        let new_point = point_after_removal(&start, &end);
+        let fragment = process_contents(&start, &end, true)?;
+        if start != end {
+            self.set(new_point.clone(), new_point);
+        }
+        Ok(fragment)
+    }
+
+    /// Removes the contents of this range from the tree and collapses the
+    /// range.
+    /// https://dom.spec.whatwg.org/#dom-range-deletecontents
+    pub fn delete_contents(&self) -> Result<(), Error> {
+        let (start, end) = (self.start(), self.end());
+        if start == end {
+            return Ok(());
+        }
+        if Rc::ptr_eq(&start.node, &end.node) && is_character_data(&start.node) {
+            return replace_data(&start.node, start.offset, end.offset - start.offset, "");
+        }
+
+        let root = tree_root(&start.node);
+        let nodes_to_remove: Vec<Rc<RefCell<Node>>> = root
+            .borrow()
+            .descendants()
+            .filter(|n| is_contained(n, &start, &end))
+            .filter(|n| match n.borrow().parent().upgrade() {
+                Some(parent) => !is_contained(&parent, &start, &end),
+                None => true,
+            })
+            .collect();
+        let new_point = point_after_removal(&start, &end);
+
+        if is_character_data(&start.node) {
+            let count = node_length(&start.node) - start.offset;
+            replace_data(&start.node, start.offset, count, "")?;
+        }
+        for node in nodes_to_remove {
+            let parent = parent_of(&node)?;
+            remove_child(&parent, &node)?;
+        }
+        if is_character_data(&end.node) {
+            replace_data(&end.node, 0, end.offset, "")?;
+        }
+
+        self.set(new_point.clone(), new_point);
+        Ok(())
+    }
+}
+
+/// Concatenates the data of the `Text` nodes in this range.
+/// https://dom.spec.whatwg.org/#dom-range-stringifier
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (start, end) = (self.start(), self.end());
+        let is_text = |node: &Rc<RefCell<Node>>| matches!(node.borrow().kind, NodeKind::Text(_));
+
+        if Rc::ptr_eq(&start.node, &end.node) && is_text(&start.node) {
+            return write!(
+                f,
+                "{}",
+                substring(&start.node, start.offset, end.offset - start.offset)
+            );
+        }
+
+        let mut s = String::new();
+        if is_text(&start.node) {
+            let count = node_length(&start.node) - start.offset;
+            s.push_str(&substring(&start.node, start.offset, count));
+        }
+        let root = tree_root(&start.node);
+        for node in root.borrow().descendants() {
+            if let NodeKind::Text(ref data) = node.borrow().kind {
+                if is_contained(&node, &start, &end) {
+                    s.push_str(data);
+                }
+            }
+        }
+        if is_text(&end.node) {
+            s.push_str(&substring(&end.node, 0, end.offset));
+        }
+        write!(f, "{}", s)
+    }
+}
+
+fn parent_of(node: &Rc<RefCell<Node>>) -> Result<Rc<RefCell<Node>>, Error> {
+    node.borrow().parent().upgrade().ok_or_else(|| {
+        Error::UnexpectedInput("InvalidNodeTypeError: the node has no parent".to_string())
+    })
+}
+
+fn is_character_data(node: &Rc<RefCell<Node>>) -> bool {
+    matches!(
+        node.borrow().kind,
+        NodeKind::Text(_) | NodeKind::Comment(_) | NodeKind::ProcessingInstruction { .. }
+    )
+}
+
+/// https://dom.spec.whatwg.org/#concept-cd-substring
+fn substring(node: &Rc<RefCell<Node>>, offset: usize, count: usize) -> String {
+    match node.borrow().kind {
+        NodeKind::Text(ref s)
+        | NodeKind::Comment(ref s)
+        | NodeKind::ProcessingInstruction { data: ref s, .. } => {
+            s.chars().skip(offset).take(count).collect()
+        }
+        _ => String::new(),
+    }
+}
+
+fn common_ancestor(a: &Rc<RefCell<Node>>, b: &Rc<RefCell<Node>>) -> Rc<RefCell<Node>> {
+    let mut ancestor = a.clone();
+    while !is_inclusive_ancestor(&ancestor, b) {
+        let parent = ancestor.borrow().parent().upgrade();
+        match parent {
+            Some(parent) => ancestor = parent,
+            None => break,
+        }
+    }
+    ancestor
+}
+
+/// https://dom.spec.whatwg.org/#contained
+fn is_contained(node: &Rc<RefCell<Node>>, start: &BoundaryPoint, end: &BoundaryPoint) -> bool {
+    Rc::ptr_eq(&tree_root(node), &tree_root(&start.node))
+        && BoundaryPoint::new(node, 0).position(start) == Ordering::Greater
+        && BoundaryPoint::new(node, node_length(node)).position(end) == Ordering::Less
+}
+
+/// https://dom.spec.whatwg.org/#partially-contained
+fn is_partially_contained(
+    node: &Rc<RefCell<Node>>,
+    start: &BoundaryPoint,
+    end: &BoundaryPoint,
+) -> bool {
+    is_inclusive_ancestor(node, &start.node) != is_inclusive_ancestor(node, &end.node)
+}
+
+/// Returns where a range collapses after its contents are removed.
+fn point_after_removal(start: &BoundaryPoint, end: &BoundaryPoint) -> BoundaryPoint {
+    if is_inclusive_ancestor(&start.node, &end.node) {
+        return start.clone();
+    }
+    let mut reference = start.node.clone();
+    loop {
+        let parent = reference
+            .borrow()
+            .parent()
+            .upgrade()
+            .expect("the common ancestor is an ancestor of the start node");
+        if is_inclusive_ancestor(&parent, &end.node) {
+            return BoundaryPoint::new(&parent, index(&reference) + 1);
+        }
+        reference = parent;
+    }
+}
+
+/// Copies a character data node with only `count` characters of its data
+/// from `offset`.
+fn clone_character_data(
+    node: &Rc<RefCell<Node>>,
+    offset: usize,
+    count: usize,
+) -> Rc<RefCell<Node>> {
+    let clone = clone_node(node, false);
+    set_character_data(&clone, &substring(node, offset, count));
+    clone
+}
+
+/// Implements both clone the contents and extract, which only differ in
+/// whether the original nodes are kept.
+/// https://dom.spec.whatwg.org/#concept-range-clone
+/// https://dom.spec.whatwg.org/#concept-range-extract
+fn process_contents(
+    start: &BoundaryPoint,
+    end: &BoundaryPoint,
+    extract: bool,
+) -> Result<Rc<RefCell<Node>>, Error> {
+    let fragment = Rc::new(RefCell::new(Node::new(NodeKind::DocumentFragment)));
+    if start == end {
+        return Ok(fragment);
+    }
+
+    if Rc::ptr_eq(&start.node, &end.node) && is_character_data(&start.node) {
+        let count = end.offset - start.offset;
+        append_child(
+            &fragment,
+            clone_character_data(&start.node, start.offset, count),
+        )?;
+        if extract {
+            replace_data(&start.node, start.offset, count, "")?;
+        }
+        return Ok(fragment);
+    }
+
+    let common = common_ancestor(&start.node, &end.node);
+    let children: Vec<Rc<RefCell<Node>>> = common.borrow().children().collect();
+    let first_partially_contained = if is_inclusive_ancestor(&start.node, &end.node) {
+        None
+    } else {
+        children
+            .iter()
+            .find(|c| is_partially_contained(c, start, end))
+            .cloned()
+    };
+    let last_partially_contained = if is_inclusive_ancestor(&end.node, &start.node) {
+        None
+    } else {
+        children
+            .iter()
+            .rev()
+            .find(|c| is_partially_contained(c, start, end))
+            .cloned()
+    };
+    let contained_children: Vec<Rc<RefCell<Node>>> = children
+        .iter()
+        .filter(|c| is_contained(c, start, end))
+        .cloned()
+        .collect();
+    if contained_children
+        .iter()
+        .any(|c| matches!(c.borrow().kind, NodeKind::DocumentType { .. }))
+    {
+        return Err(Error::UnexpectedInput(
+            "HierarchyRequestError: the range contains a doctype".to_string(),
+        ));
+    }
+
+    if let Some(first) = first_partially_contained {
+        if is_character_data(&first) {
+            let count = node_length(&start.node) - start.offset;
+            append_child(
+                &fragment,
+                clone_character_data(&start.node, start.offset, count),
+            )?;
+            if extract {
+                replace_data(&start.node, start.offset, count, "")?;
+            }
+        } else {
+            let clone = clone_node(&first, false);
+            append_child(&fragment, clone.clone())?;
+            let subrange_end = BoundaryPoint::new(&first, node_length(&first));
+            append_child(&clone, process_contents(start, &subrange_end, extract)?)?;
+        }
+    }
+
+    for child in contained_children {
+        if extract {
+            append_child(&fragment, child)?;
+        } else {
+            append_child(&fragment, clone_node(&child, true))?;
+        }
+    }
+
+    if let Some(last) = last_partially_contained {
+        if is_character_data(&last) {
+            append_child(&fragment, clone_character_data(&end.node, 0, end.offset))?;
+            if extract {
+                replace_data(&end.node, 0, end.offset, "")?;
+            }
+        } else {
+            let clone = clone_node(&last, false);
+            append_child(&fragment, clone.clone())?;
+            let subrange_start = BoundaryPoint::new(&last, 0);
+            append_child(&clone, process_contents(&subrange_start, end, extract)?)?;
+        }
+    }
+
+    Ok(fragment)
+}
+
+/// Runs `f` on every live range of the document `node` is in.
+fn for_each_live_range<F: FnMut(&mut RangeState)>(node: &Rc<RefCell<Node>>, mut f: F) {
+    let document = match owner_document(node) {
+        Some(document) => document,
+        None => return,
+    };
+    let ranges: Vec<Rc<RefCell<RangeState>>> = {
+        let mut document = document.borrow_mut();
+        let live_ranges = document.live_ranges_mut();
+        live_ranges.retain(|r| r.strong_count() > 0);
+        live_ranges.iter().filter_map(|r| r.upgrade()).collect()
+    };
+    for range in ranges {
+        f(&mut range.borrow_mut());
+    }
+}
+
+/// https://dom.spec.whatwg.org/#concept-node-insert
+pub(crate) fn update_live_ranges_for_insert(parent: &Rc<RefCell<Node>>, index: usize) {
+    for_each_live_range(parent, |range| {
+        for bp in [&mut range.start, &mut range.end] {
+            if Rc::ptr_eq(&bp.node, parent) && bp.offset > index {
+                bp.offset += 1;
+            }
+        }
+    });
+}
+
+/// https://dom.spec.whatwg.org/#concept-node-remove
+pub(crate) fn update_live_ranges_for_remove(
+    parent: &Rc<RefCell<Node>>,
+    child: &Rc<RefCell<Node>>,
+    index: usize,
+) {
+    for_each_live_range(parent, |range| {
+        for bp in [&mut range.start, &mut range.end] {
+            if is_inclusive_ancestor(child, &bp.node) {
+                *bp = BoundaryPoint::new(parent, index);
+            } else if Rc::ptr_eq(&bp.node, parent) && bp.offset > index {
+                bp.offset -= 1;
+            }
+        }
+    });
+}
+
+/// https://dom.spec.whatwg.org/#concept-cd-replace
+pub(crate) fn update_live_ranges_for_replace_data(
+    node: &Rc<RefCell<Node>>,
+    offset: usize,
+    count: usize,
+    data_length: usize,
+) {
+    for_each_live_range(node, |range| {
+        for bp in [&mut range.start, &mut range.end] {
+            if !Rc::ptr_eq(&bp.node, node) {
+                continue;
+            }
+            if bp.offset > offset && bp.offset <= offset + count {
+                bp.offset = offset;
+            } else if bp.offset > offset + count {
+                bp.offset = bp.offset + data_length - count;
+            }
+        }
+    });
+}
+
+/// Moves boundary points in or right before `current`, a text node being
+/// merged into `node` at offset `length`, into `node`.
+/// https://dom.spec.whatwg.org/#dom-node-normalize
+pub(crate) fn update_live_ranges_for_normalize(
+    node: &Rc<RefCell<Node>>,
+    current: &Rc<RefCell<Node>>,
+    length: usize,
+) {
+    let parent = match current.borrow().parent().upgrade() {
+        Some(parent) => parent,
+        None => return,
+    };
+    let current_index = index(current);
+    for_each_live_range(node, |range| {
+        for bp in [&mut range.start, &mut range.end] {
+            if Rc::ptr_eq(&bp.node, current) {
+                *bp = BoundaryPoint::new(node, bp.offset + length);
+            } else if Rc::ptr_eq(&bp.node, &parent) && bp.offset == current_index {
+                *bp = BoundaryPoint::new(node, length);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::insert_before;
+    use crate::renderer::dom::api::text_content;
+    use crate::renderer::html::parser::tests::body;
+    use crate::renderer::html::parser::tests::parse;
+    use crate::renderer::html::serializer::inner_html;
+    use crate::renderer::html::serializer::outer_html;
+
+    fn child(node: &Rc<RefCell<Node>>, index: usize) -> Rc<RefCell<Node>> {
+        node.borrow().children().nth(index).unwrap()
+    }
+
+    #[test]
+    fn test_boundary_points() {
+        let window = parse("<p>abc</p><p>def</p>");
+        let document = window.borrow().document();
+        let body = body(&document);
+        let range = Range::new(&document);
+        assert!(range.collapsed());
+        assert!(Rc::ptr_eq(&document, &range.start_container()));
+
+        let p1_text = child(&child(&body, 0), 0);
+        let p2_text = child(&child(&body, 1), 0);
+        range.set_start(&p2_text, 1).unwrap();
+        assert!(range.collapsed());
+        // Setting the end before the start collapses the range.
+        range.set_end(&p1_text, 1).unwrap();
+        assert!(range.collapsed());
+        assert!(Rc::ptr_eq(&p1_text, &range.start_container()));
+
+        range.set_end(&p2_text, 2).unwrap();
+        assert!(!range.collapsed());
+        assert!(Rc::ptr_eq(&body, &range.common_ancestor_container()));
+        assert!(range.set_end(&p2_text, 4).is_err());
+
+        range.collapse(true);
+        assert_eq!(BoundaryPoint::new(&p1_text, 1), range.end());
+    }
+
+    #[test]
+    fn test_to_string_and_clone_contents() {
+        let window = parse("<p>abc<a>def</a></p><p>ghi</p><h1>jkl</h1>");
+        let document = window.borrow().document();
+        let body = body(&document);
+        let range = Range::new(&document);
+        let p1 = child(&body, 0);
+        range.set_start(&child(&p1, 0), 1).unwrap();
+        range.set_end(&child(&child(&body, 2), 0), 2).unwrap();
+
+        assert_eq!("bcdefghijk", range.to_string());
+        let fragment = range.clone_contents().unwrap();
+        assert_eq!(
+            "<p>bc<a>def</a></p><p>ghi</p><h1>jk</h1>",
+            inner_html(&fragment)
+        );
+        // The tree is unchanged.
+        assert_eq!(
+            "<body><p>abc<a>def</a></p><p>ghi</p><h1>jkl</h1></body>",
+            outer_html(&body)
+        );
+
+        let text = child(&child(&body, 1), 0);
+        range.set_start(&text, 0).unwrap();
+        range.set_end(&text, 2).unwrap();
+        assert_eq!("gh", range.to_string());
+        assert_eq!("gh", inner_html(&range.clone_contents().unwrap()));
+    }
+
+    #[test]
+    fn test_extract_contents() {
+        let window = parse("<p>abc<a>def</a></p><p>ghi</p><h1>jkl</h1>");
+        let document = window.borrow().document();
+        let body = body(&document);
+        let range = Range::new(&document);
+        let p1 = child(&body, 0);
+        range.set_start(&child(&p1, 0), 1).unwrap();
+        range.set_end(&child(&child(&body, 2), 0), 2).unwrap();
+
+        let fragment = range.extract_contents().unwrap();
+        assert_eq!(
+            "<p>bc<a>def</a></p><p>ghi</p><h1>jk</h1>",
+            inner_html(&fragment)
+        );
+        assert_eq!("<body><p>a</p><h1>l</h1></body>", outer_html(&body));
+        assert!(range.collapsed());
+        assert_eq!(BoundaryPoint::new(&body, 1), range.start());
+    }
+
+    #[test]
+    fn test_delete_contents() {
+        let window = parse("<p>abc<a>def</a></p><p>ghi</p><h1>jkl</h1>");
+        let document = window.borrow().document();
+        let body = body(&document);
+        let range = Range::new(&document);
+        let a_text = child(&child(&child(&body, 0), 1), 0);
+        range.set_start(&a_text, 1).unwrap();
+        range.set_end(&child(&child(&body, 2), 0), 1).unwrap();
+
+        range.delete_contents().unwrap();
+        assert_eq!(
+            "<body><p>abc<a>d</a></p><h1>kl</h1></body>",
+            outer_html(&body)
+        );
+        assert!(range.collapsed());
+        assert_eq!(BoundaryPoint::new(&body, 1), range.start());
+    }
+
+    #[test]
+    fn test_select_node() {
+        let window = parse("<p>a</p><p>b</p><p>c</p>");
+        let document = window.borrow().document();
+        let body = body(&document);
+        let range = Range::new(&document);
+        let p2 = child(&body, 1);
+        range.select_node(&p2).unwrap();
+        assert_eq!(BoundaryPoint::new(&body, 1), range.start());
+        assert_eq!(BoundaryPoint::new(&body, 2), range.end());
+        assert!(range.contains_node(&p2));
+        assert!(!range.contains_node(&child(&body, 0)));
+        assert_eq!("b", range.to_string());
+
+        range.select_node_contents(&body).unwrap();
+        assert_eq!(BoundaryPoint::new(&body, 3), range.end());
+        assert_eq!("abc", range.to_string());
+    }
+
+    #[test]
+    fn test_live_range() {
+        let window = parse("<p>a</p><p>b</p><p>c</p>");
+        let document = window.borrow().document();
+        let body = body(&document);
+        let range = Range::new(&document);
+        let p2 = child(&body, 1);
+        let p3 = child(&body, 2);
+        range.set_start(&body, 1).unwrap();
+        range.set_end(&child(&p3, 0), 1).unwrap();
+
+        // Inserting before the start moves it.
+        let new_p = Rc::new(RefCell::new(Node::new(NodeKind::Text("x".to_string()))));
+        insert_before(&body, new_p.clone(), Some(child(&body, 0))).unwrap();
+        assert_eq!(BoundaryPoint::new(&body, 2), range.start());
+
+        // Removing a node that contains the end moves it to the parent.
+        remove_child(&body, &p3).unwrap();
+        assert_eq!(BoundaryPoint::new(&body, 3), range.end());
+        remove_child(&body, &new_p).unwrap();
+        assert_eq!(BoundaryPoint::new(&body, 1), range.start());
+        assert_eq!(BoundaryPoint::new(&body, 2), range.end());
+        assert!(range.contains_node(&p2));
+
+        // Replacing data moves offsets within the replaced characters.
+        let text = child(&p2, 0);
+        replace_data(&text, 0, 0, "xyz").unwrap();
+        range.set_start(&text, 2).unwrap();
+        range.set_end(&text, 4).unwrap();
+        replace_data(&text, 1, 2, "").unwrap();
+        assert_eq!(BoundaryPoint::new(&text, 1), range.start());
+        assert_eq!(BoundaryPoint::new(&text, 2), range.end());
+        assert_eq!(Some("xb".to_string()), text_content(&text));
+    }
+}
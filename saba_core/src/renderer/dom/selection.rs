@@ -0,0 +1,330 @@
+//! https://w3c.github.io/selection-api/
+
+use crate::error::Error;
+use crate::renderer::dom::api::node_length;
+use crate::renderer::dom::api::tree_root;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::range::validate_boundary_point;
+use crate::renderer::dom::range::BoundaryPoint;
+use crate::renderer::dom::range::Range;
+use alloc::rc::Rc;
+use alloc::string::ToString;
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::fmt;
+
+/// https://w3c.github.io/selection-api/#dfn-direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionDirection {
+    Forwards,
+    Backwards,
+    Directionless,
+}
+
+#[derive(Debug)]
+struct SelectionState {
+    document: Rc<RefCell<Node>>,
+    range: Option<Range>,
+    direction: SelectionDirection,
+}
+
+/// The selection of a document. It has at most one range, which is live, so
+/// the selection follows changes to the tree. Clones refer to the same
+/// selection.
+/// https://w3c.github.io/selection-api/#selection-interface
+#[derive(Debug, Clone)]
+pub struct Selection {
+    state: Rc<RefCell<SelectionState>>,
+}
+
+impl Selection {
+    pub fn new(document: &Rc<RefCell<Node>>) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(SelectionState {
+                document: document.clone(),
+                range: None,
+                direction: SelectionDirection::Directionless,
+            })),
+        }
+    }
+
+    fn range(&self) -> Option<Range> {
+        self.state.borrow().range.clone()
+    }
+
+    fn set_range(&self, range: Option<Range>, direction: SelectionDirection) {
+        let mut state = self.state.borrow_mut();
+        state.range = range;
+        state.direction = direction;
+    }
+
+    fn document(&self) -> Rc<RefCell<Node>> {
+        self.state.borrow().document.clone()
+    }
+
+    fn is_in_document(&self, node: &Rc<RefCell<Node>>) -> bool {
+        Rc::ptr_eq(&tree_root(node), &self.document())
+    }
+
+    pub fn direction(&self) -> SelectionDirection {
+        self.state.borrow().direction
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-anchornode
+    pub fn anchor(&self) -> Option<BoundaryPoint> {
+        let range = self.range()?;
+        match self.direction() {
+            SelectionDirection::Backwards => Some(range.end()),
+            _ => Some(range.start()),
+        }
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-focusnode
+    pub fn focus(&self) -> Option<BoundaryPoint> {
+        let range = self.range()?;
+        match self.direction() {
+            SelectionDirection::Backwards => Some(range.start()),
+            _ => Some(range.end()),
+        }
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-iscollapsed
+    pub fn is_collapsed(&self) -> bool {
+        self.range().map_or(true, |r| r.collapsed())
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-rangecount
+    pub fn range_count(&self) -> usize {
+        self.range().map_or(0, |_| 1)
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-getrangeat
+    pub fn get_range_at(&self, index: usize) -> Result<Range, Error> {
+        match (index, self.range()) {
+            (0, Some(range)) => Ok(range),
+            _ => Err(Error::UnexpectedInput(
+                "IndexSizeError: the selection has no range at the index".to_string(),
+            )),
+        }
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-addrange
+    pub fn add_range(&self, range: Range) {
+        if !self.is_in_document(&range.start_container()) || self.range().is_some() {
+            return;
+        }
+        self.set_range(Some(range), SelectionDirection::Forwards);
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-removerange
+    pub fn remove_range(&self, range: &Range) -> Result<(), Error> {
+        if self.range().as_ref() != Some(range) {
+            return Err(Error::UnexpectedInput(
+                "NotFoundError: the range is not in the selection".to_string(),
+            ));
+        }
+        self.remove_all_ranges();
+        Ok(())
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-removeallranges
+    pub fn remove_all_ranges(&self) {
+        self.set_range(None, SelectionDirection::Directionless);
+    }
+
+    /// Replaces the selection with a collapsed range at (`node`, `offset`).
+    /// https://w3c.github.io/selection-api/#dom-selection-collapse
+    pub fn collapse(&self, node: &Rc<RefCell<Node>>, offset: usize) -> Result<(), Error> {
+        validate_boundary_point(node, offset)?;
+        if !self.is_in_document(node) {
+            return Ok(());
+        }
+        let range = Range::new(&self.document());
+        range.set_start(node, offset)?;
+        range.set_end(node, offset)?;
+        self.set_range(Some(range), SelectionDirection::Directionless);
+        Ok(())
+    }
+
+    fn no_range_error() -> Error {
+        Error::UnexpectedInput("InvalidStateError: the selection has no range".to_string())
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-collapsetostart
+    pub fn collapse_to_start(&self) -> Result<(), Error> {
+        let start = self.range().ok_or_else(Self::no_range_error)?.start();
+        self.collapse(&start.node, start.offset)
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-collapsetoend
+    pub fn collapse_to_end(&self) -> Result<(), Error> {
+        let end = self.range().ok_or_else(Self::no_range_error)?.end();
+        self.collapse(&end.node, end.offset)
+    }
+
+    /// Moves the focus to (`node`, `offset`), keeping the anchor.
+    /// https://w3c.github.io/selection-api/#dom-selection-extend
+    pub fn extend(&self, node: &Rc<RefCell<Node>>, offset: usize) -> Result<(), Error> {
+        let anchor = self.anchor().ok_or_else(Self::no_range_error)?;
+        validate_boundary_point(node, offset)?;
+        if !self.is_in_document(node) {
+            return Ok(());
+        }
+        self.set_base_and_extent(&anchor.node, anchor.offset, node, offset)
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-setbaseandextent
+    pub fn set_base_and_extent(
+        &self,
+        anchor_node: &Rc<RefCell<Node>>,
+        anchor_offset: usize,
+        focus_node: &Rc<RefCell<Node>>,
+        focus_offset: usize,
+    ) -> Result<(), Error> {
+        validate_boundary_point(anchor_node, anchor_offset)?;
+        validate_boundary_point(focus_node, focus_offset)?;
+        if !self.is_in_document(anchor_node) || !self.is_in_document(focus_node) {
+            return Ok(());
+        }
+
+        let anchor = BoundaryPoint::new(anchor_node, anchor_offset);
+        let focus = BoundaryPoint::new(focus_node, focus_offset);
+        let range = Range::new(&self.document());
+        let direction = if focus.position(&anchor) == Ordering::Less {
+            range.set_start(focus_node, focus_offset)?;
+            range.set_end(anchor_node, anchor_offset)?;
+            SelectionDirection::Backwards
+        } else {
+            range.set_start(anchor_node, anchor_offset)?;
+            range.set_end(focus_node, focus_offset)?;
+            SelectionDirection::Forwards
+        };
+        self.set_range(Some(range), direction);
+        Ok(())
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-selectallchildren
+    pub fn select_all_children(&self, node: &Rc<RefCell<Node>>) -> Result<(), Error> {
+        self.set_base_and_extent(node, 0, node, node_length(node))
+    }
+
+    /// https://w3c.github.io/selection-api/#dom-selection-deletefromdocument
+    pub fn delete_from_document(&self) -> Result<(), Error> {
+        match self.range() {
+            Some(range) => range.delete_contents(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// https://w3c.github.io/selection-api/#dom-selection-stringifier
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.range() {
+            Some(range) => write!(f, "{}", range),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::append_child;
+    use crate::renderer::dom::api::remove_child;
+    use crate::renderer::dom::api::text_content;
+    use crate::renderer::dom::node::NodeKind;
+    use crate::renderer::html::parser::tests::body;
+    use crate::renderer::html::parser::tests::parse;
+
+    fn child(node: &Rc<RefCell<Node>>, index: usize) -> Rc<RefCell<Node>> {
+        node.borrow().children().nth(index).unwrap()
+    }
+
+    #[test]
+    fn test_collapse_and_extend() {
+        let window = parse("<p>hello</p><p>world</p>");
+        let body = body(&window.borrow().document());
+        let selection = window.borrow().selection();
+        assert_eq!(0, selection.range_count());
+        assert!(selection.is_collapsed());
+        assert!(selection.extend(&body, 0).is_err());
+
+        let hello = child(&child(&body, 0), 0);
+        let world = child(&child(&body, 1), 0);
+        selection.collapse(&world, 3).unwrap();
+        assert_eq!(1, selection.range_count());
+        assert!(selection.is_collapsed());
+        assert_eq!(SelectionDirection::Directionless, selection.direction());
+
+        // Extending backwards keeps the anchor at the end of the range.
+        selection.extend(&hello, 2).unwrap();
+        assert_eq!(SelectionDirection::Backwards, selection.direction());
+        assert_eq!(Some(BoundaryPoint::new(&world, 3)), selection.anchor());
+        assert_eq!(Some(BoundaryPoint::new(&hello, 2)), selection.focus());
+        assert_eq!("llowor", selection.to_string());
+        let range = selection.get_range_at(0).unwrap();
+        assert_eq!(BoundaryPoint::new(&hello, 2), range.start());
+
+        selection.extend(&world, 5).unwrap();
+        assert_eq!(SelectionDirection::Forwards, selection.direction());
+        assert_eq!("ld", selection.to_string());
+
+        selection.collapse_to_start().unwrap();
+        assert!(selection.is_collapsed());
+        assert_eq!(Some(BoundaryPoint::new(&world, 3)), selection.focus());
+
+        assert!(selection.collapse(&world, 6).is_err());
+        assert!(selection.get_range_at(1).is_err());
+    }
+
+    #[test]
+    fn test_ranges() {
+        let window = parse("<p>a</p><p>b</p>");
+        let document = window.borrow().document();
+        let body = body(&document);
+        let selection = window.borrow().selection();
+
+        let range = Range::new(&document);
+        range.select_node_contents(&body).unwrap();
+        selection.add_range(range.clone());
+        assert_eq!("ab", selection.to_string());
+
+        // Only one range is kept.
+        selection.add_range(Range::new(&document));
+        assert_eq!(range, selection.get_range_at(0).unwrap());
+
+        // Nodes outside of the document cannot be selected.
+        let detached = Rc::new(RefCell::new(Node::new(NodeKind::Text("x".to_string()))));
+        selection.collapse(&detached, 0).unwrap();
+        assert_eq!(range, selection.get_range_at(0).unwrap());
+
+        assert!(selection.remove_range(&Range::new(&document)).is_err());
+        selection.remove_range(&range).unwrap();
+        assert_eq!(0, selection.range_count());
+        assert_eq!("", selection.to_string());
+    }
+
+    #[test]
+    fn test_follows_tree_changes() {
+        let window = parse("<p>a</p><p>b</p><p>c</p>");
+        let body = body(&window.borrow().document());
+        let selection = window.borrow().selection();
+        selection.select_all_children(&body).unwrap();
+        assert_eq!("abc", selection.to_string());
+
+        let p2 = child(&body, 1);
+        remove_child(&body, &p2).unwrap();
+        assert_eq!("ac", selection.to_string());
+        let focus = selection.focus().unwrap();
+        assert_eq!(BoundaryPoint::new(&body, 2), focus);
+
+        append_child(&body, p2).unwrap();
+        assert_eq!(BoundaryPoint::new(&body, 2), selection.focus().unwrap());
+
+        selection.delete_from_document().unwrap();
+        assert!(body.borrow().first_child().is_some());
+        assert_eq!(Some("b".to_string()), text_content(&body));
+        assert!(selection.is_collapsed());
+    }
+}
@@ -0,0 +1,1149 @@
+//! https://www.w3.org/TR/selectors-4/
+
+use crate::error::Error;
+use crate::renderer::css::token::CssToken;
+use crate::renderer::css::token::CssTokenizer;
+use crate::renderer::dom::node::Element;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// https://www.w3.org/TR/selectors-4/#selector-list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorList {
+    selectors: Vec<ComplexSelector>,
+}
+
+impl SelectorList {
+    /// https://www.w3.org/TR/selectors-4/#parse-a-selector
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let tokens: Vec<CssToken> = CssTokenizer::new(input.to_string()).collect();
+        Self::parse_tokens(&tokens)
+    }
+
+    /// Parses a selector list from CSS tokens, such as the prelude of a
+    /// style rule.
+    pub(crate) fn parse_tokens(tokens: &[CssToken]) -> Result<Self, Error> {
+        let mut parser = SelectorParser::new(tokens);
+        let list = parser.parse_selector_list()?;
+        parser.skip_whitespace();
+        if let Some(t) = parser.peek() {
+            return Err(parser.error(&format!("unexpected token {:?}", t)));
+        }
+        Ok(list)
+    }
+
+    pub fn selectors(&self) -> &[ComplexSelector] {
+        &self.selectors
+    }
+
+    /// Returns true if any selector in the list matches `node`.
+    /// https://www.w3.org/TR/selectors-4/#match-a-selector-against-an-element
+    pub fn matches(&self, node: &Rc<RefCell<Node>>) -> bool {
+        self.selectors.iter().any(|s| s.matches(node))
+    }
+
+    /// Returns the largest specificity among the selectors in the list.
+    fn max_specificity(&self) -> Specificity {
+        self.selectors
+            .iter()
+            .map(|s| s.specificity())
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// A sequence of compound selectors separated by combinators.
+/// https://www.w3.org/TR/selectors-4/#complex
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComplexSelector {
+    compounds: Vec<CompoundSelector>,
+    /// `combinators[i]` sits between `compounds[i]` and `compounds[i + 1]`.
+    combinators: Vec<Combinator>,
+    /// The pseudo-element after the last compound selector, if any. The
+    /// selector then represents that pseudo-element of the elements that
+    /// match the compound selectors.
+    pseudo_element: Option<PseudoElement>,
+}
+
+impl ComplexSelector {
+    pub fn compounds(&self) -> &[CompoundSelector] {
+        &self.compounds
+    }
+
+    pub fn combinators(&self) -> &[Combinator] {
+        &self.combinators
+    }
+
+    pub fn pseudo_element(&self) -> Option<PseudoElement> {
+        self.pseudo_element
+    }
+
+    /// https://www.w3.org/TR/selectors-4/#specificity-rules
+    pub fn specificity(&self) -> Specificity {
+        let pseudo_element = match self.pseudo_element {
+            Some(_) => Specificity(0, 0, 1),
+            None => Specificity::default(),
+        };
+        self.compounds
+            .iter()
+            .flat_map(|c| c.selectors.iter())
+            .fold(pseudo_element, |acc, s| acc + s.specificity())
+    }
+
+    /// Returns true if `node` matches. A selector with a pseudo-element
+    /// never matches an element.
+    pub fn matches(&self, node: &Rc<RefCell<Node>>) -> bool {
+        self.pseudo_element.is_none() && self.matches_at(self.compounds.len() - 1, node)
+    }
+
+    /// Returns true if the pseudo-element `pseudo_element` of `node` matches.
+    pub fn matches_pseudo_element(
+        &self,
+        node: &Rc<RefCell<Node>>,
+        pseudo_element: PseudoElement,
+    ) -> bool {
+        self.pseudo_element == Some(pseudo_element)
+            && self.matches_at(self.compounds.len() - 1, node)
+    }
+
+    /// Matches `compounds[..=index]` from right to left with `node` as the
+    /// subject of `compounds[index]`.
+    fn matches_at(&self, index: usize, node: &Rc<RefCell<Node>>) -> bool {
+        if !self.compounds[index].matches(node) {
+            return false;
+        }
+        if index == 0 {
+            return true;
+        }
+
+        match self.combinators[index - 1] {
+            Combinator::Descendant => node
+                .borrow()
+                .ancestors()
+                .filter(is_element)
+                .any(|a| self.matches_at(index - 1, &a)),
+            Combinator::Child => match parent_element(node) {
+                Some(p) => self.matches_at(index - 1, &p),
+                None => false,
+            },
+            Combinator::NextSibling => match previous_element_siblings(node).next() {
+                Some(s) => self.matches_at(index - 1, &s),
+                None => false,
+            },
+            Combinator::SubsequentSibling => {
+                previous_element_siblings(node).any(|s| self.matches_at(index - 1, &s))
+            }
+        }
+    }
+}
+
+/// https://www.w3.org/TR/css-pseudo-4/#generated-content
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PseudoElement {
+    /// https://www.w3.org/TR/css-pseudo-4/#selectordef-before
+    Before,
+    /// https://www.w3.org/TR/css-pseudo-4/#selectordef-after
+    After,
+}
+
+/// https://www.w3.org/TR/selectors-4/#combinators
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Combinator {
+    /// https://www.w3.org/TR/selectors-4/#descendant-combinators
+    Descendant,
+    /// https://www.w3.org/TR/selectors-4/#child-combinators
+    Child,
+    /// https://www.w3.org/TR/selectors-4/#adjacent-sibling-combinators
+    NextSibling,
+    /// https://www.w3.org/TR/selectors-4/#general-sibling-combinators
+    SubsequentSibling,
+}
+
+/// https://www.w3.org/TR/selectors-4/#compound
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompoundSelector {
+    selectors: Vec<SimpleSelector>,
+}
+
+impl CompoundSelector {
+    pub fn selectors(&self) -> &[SimpleSelector] {
+        &self.selectors
+    }
+
+    fn matches(&self, node: &Rc<RefCell<Node>>) -> bool {
+        let element = match node.borrow().get_element() {
+            Some(e) => e,
+            None => return false,
+        };
+        self.selectors.iter().all(|s| s.matches(node, &element))
+    }
+}
+
+/// https://www.w3.org/TR/selectors-4/#simple
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimpleSelector {
+    /// https://www.w3.org/TR/selectors-4/#the-universal-selector
+    Universal,
+    /// https://www.w3.org/TR/selectors-4/#type-selectors
+    Type(String),
+    /// https://www.w3.org/TR/selectors-4/#id-selectors
+    Id(String),
+    /// https://www.w3.org/TR/selectors-4/#class-html
+    Class(String),
+    /// https://www.w3.org/TR/selectors-4/#attribute-selectors
+    Attribute {
+        name: String,
+        matcher: Option<(AttributeOperator, String)>,
+        case_insensitive: bool,
+    },
+    /// https://www.w3.org/TR/selectors-4/#pseudo-classes
+    PseudoClass(PseudoClass),
+}
+
+impl SimpleSelector {
+    fn specificity(&self) -> Specificity {
+        match self {
+            SimpleSelector::Universal => Specificity(0, 0, 0),
+            SimpleSelector::Type(_) => Specificity(0, 0, 1),
+            SimpleSelector::Id(_) => Specificity(1, 0, 0),
+            SimpleSelector::Class(_) | SimpleSelector::Attribute { .. } => Specificity(0, 1, 0),
+            SimpleSelector::PseudoClass(p) => match p {
+                PseudoClass::Not(list) | PseudoClass::Is(list) => list.max_specificity(),
+                PseudoClass::Where(_) => Specificity(0, 0, 0),
+                PseudoClass::NthChild(_, Some(list)) | PseudoClass::NthLastChild(_, Some(list)) => {
+                    Specificity(0, 1, 0) + list.max_specificity()
+                }
+                _ => Specificity(0, 1, 0),
+            },
+        }
+    }
+
+    fn matches(&self, node: &Rc<RefCell<Node>>, element: &Element) -> bool {
+        match self {
+            SimpleSelector::Universal => true,
+            SimpleSelector::Type(name) => element.kind().to_string() == *name,
+            SimpleSelector::Id(id) => element.id().as_ref() == Some(id),
+            SimpleSelector::Class(class) => element.class_list().contains(class),
+            SimpleSelector::Attribute {
+                name,
+                matcher,
+                case_insensitive,
+            } => {
+                let value = match element.get_attribute(name) {
+                    Some(v) => v,
+                    None => return false,
+                };
+                match matcher {
+                    Some((operator, expected)) => {
+                        if *case_insensitive {
+                            operator.matches(
+                                &value.to_ascii_lowercase(),
+                                &expected.to_ascii_lowercase(),
+                            )
+                        } else {
+                            operator.matches(&value, expected)
+                        }
+                    }
+                    None => true,
+                }
+            }
+            SimpleSelector::PseudoClass(p) => p.matches(node, element),
+        }
+    }
+}
+
+/// https://www.w3.org/TR/selectors-4/#attribute-representation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AttributeOperator {
+    /// `[att=val]`
+    Equal,
+    /// `[att~=val]`
+    Includes,
+    /// `[att|=val]`
+    DashMatch,
+    /// `[att^=val]`
+    Prefix,
+    /// `[att$=val]`
+    Suffix,
+    /// `[att*=val]`
+    Substring,
+}
+
+impl AttributeOperator {
+    fn matches(&self, value: &str, expected: &str) -> bool {
+        match self {
+            AttributeOperator::Equal => value == expected,
+            AttributeOperator::Includes => {
+                !expected.is_empty()
+                    && !expected.contains(|c: char| c.is_ascii_whitespace())
+                    && value.split_ascii_whitespace().any(|v| v == expected)
+            }
+            AttributeOperator::DashMatch => {
+                value == expected
+                    || (value.starts_with(expected) && value[expected.len()..].starts_with('-'))
+            }
+            AttributeOperator::Prefix => !expected.is_empty() && value.starts_with(expected),
+            AttributeOperator::Suffix => !expected.is_empty() && value.ends_with(expected),
+            AttributeOperator::Substring => !expected.is_empty() && value.contains(expected),
+        }
+    }
+}
+
+/// https://www.w3.org/TR/selectors-4/#pseudo-classes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PseudoClass {
+    /// https://www.w3.org/TR/selectors-4/#negation
+    Not(SelectorList),
+    /// https://www.w3.org/TR/selectors-4/#matches
+    Is(SelectorList),
+    /// https://www.w3.org/TR/selectors-4/#zero-matches
+    Where(SelectorList),
+    /// https://www.w3.org/TR/selectors-4/#the-nth-child-pseudo
+    NthChild(Nth, Option<SelectorList>),
+    /// https://www.w3.org/TR/selectors-4/#the-nth-last-child-pseudo
+    NthLastChild(Nth, Option<SelectorList>),
+    /// https://www.w3.org/TR/selectors-4/#the-nth-of-type-pseudo
+    NthOfType(Nth),
+    /// https://www.w3.org/TR/selectors-4/#the-nth-last-of-type-pseudo
+    NthLastOfType(Nth),
+    /// https://www.w3.org/TR/selectors-4/#the-first-child-pseudo
+    FirstChild,
+    /// https://www.w3.org/TR/selectors-4/#the-last-child-pseudo
+    LastChild,
+    /// https://www.w3.org/TR/selectors-4/#the-only-child-pseudo
+    OnlyChild,
+    /// https://www.w3.org/TR/selectors-4/#the-first-of-type-pseudo
+    FirstOfType,
+    /// https://www.w3.org/TR/selectors-4/#the-last-of-type-pseudo
+    LastOfType,
+    /// https://www.w3.org/TR/selectors-4/#the-only-of-type-pseudo
+    OnlyOfType,
+    /// https://www.w3.org/TR/selectors-4/#the-empty-pseudo
+    Empty,
+    /// https://www.w3.org/TR/selectors-4/#the-root-pseudo
+    Root,
+    /// https://www.w3.org/TR/selectors-4/#the-link-pseudo
+    Link,
+}
+
+impl PseudoClass {
+    fn matches(&self, node: &Rc<RefCell<Node>>, element: &Element) -> bool {
+        match self {
+            PseudoClass::Not(list) => !list.matches(node),
+            PseudoClass::Is(list) | PseudoClass::Where(list) => list.matches(node),
+            PseudoClass::NthChild(nth, of) => {
+                if let Some(of) = of {
+                    if !of.matches(node) {
+                        return false;
+                    }
+                }
+                let index = previous_element_siblings(node)
+                    .filter(|s| of.as_ref().map_or(true, |of| of.matches(s)))
+                    .count();
+                nth.matches(index + 1)
+            }
+            PseudoClass::NthLastChild(nth, of) => {
+                if let Some(of) = of {
+                    if !of.matches(node) {
+                        return false;
+                    }
+                }
+                let index = next_element_siblings(node)
+                    .filter(|s| of.as_ref().map_or(true, |of| of.matches(s)))
+                    .count();
+                nth.matches(index + 1)
+            }
+            PseudoClass::NthOfType(nth) => {
+                let index = previous_element_siblings(node)
+                    .filter(|s| is_same_type(s, element))
+                    .count();
+                nth.matches(index + 1)
+            }
+            PseudoClass::NthLastOfType(nth) => {
+                let index = next_element_siblings(node)
+                    .filter(|s| is_same_type(s, element))
+                    .count();
+                nth.matches(index + 1)
+            }
+            PseudoClass::FirstChild => previous_element_siblings(node).next().is_none(),
+            PseudoClass::LastChild => next_element_siblings(node).next().is_none(),
+            PseudoClass::OnlyChild => {
+                previous_element_siblings(node).next().is_none()
+                    && next_element_siblings(node).next().is_none()
+            }
+            PseudoClass::FirstOfType => {
+                !previous_element_siblings(node).any(|s| is_same_type(&s, element))
+            }
+            PseudoClass::LastOfType => {
+                !next_element_siblings(node).any(|s| is_same_type(&s, element))
+            }
+            PseudoClass::OnlyOfType => {
+                !previous_element_siblings(node).any(|s| is_same_type(&s, element))
+                    && !next_element_siblings(node).any(|s| is_same_type(&s, element))
+            }
+            PseudoClass::Empty => node.borrow().children().all(|c| match c.borrow().kind {
+                NodeKind::Element(_) => false,
+                NodeKind::Text(ref s) => s.is_empty(),
+                _ => true,
+            }),
+            PseudoClass::Root => match node.borrow().parent().upgrade() {
+                Some(p) => p.borrow().kind() == NodeKind::Document,
+                None => false,
+            },
+            PseudoClass::Link => {
+                element.kind().to_string() == "a" && element.get_attribute("href").is_some()
+            }
+        }
+    }
+}
+
+/// The `An+B` argument of the `:nth-*` pseudo-classes.
+/// https://www.w3.org/TR/css-syntax-3/#anb-microsyntax
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Nth {
+    a: i32,
+    b: i32,
+}
+
+impl Nth {
+    pub fn new(a: i32, b: i32) -> Self {
+        Self { a, b }
+    }
+
+    /// Returns true if `index` (1-based) is `a * n + b` for some `n >= 0`.
+    fn matches(&self, index: usize) -> bool {
+        let index = index as i32;
+        if self.a == 0 {
+            return index == self.b;
+        }
+        let diff = index - self.b;
+        diff % self.a == 0 && diff / self.a >= 0
+    }
+}
+
+/// https://www.w3.org/TR/selectors-4/#specificity
+///
+/// The components are the number of id selectors, the number of class,
+/// attribute and pseudo-class selectors, and the number of type selectors.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Specificity(pub u32, pub u32, pub u32);
+
+impl core::ops::Add for Specificity {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Specificity(self.0 + other.0, self.1 + other.1, self.2 + other.2)
+    }
+}
+
+fn is_element(node: &Rc<RefCell<Node>>) -> bool {
+    matches!(node.borrow().kind, NodeKind::Element(_))
+}
+
+fn parent_element(node: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    node.borrow().parent().upgrade().filter(is_element)
+}
+
+fn previous_element_siblings(node: &Rc<RefCell<Node>>) -> impl Iterator<Item = Rc<RefCell<Node>>> {
+    let first = node.borrow().previous_sibling().upgrade();
+    core::iter::successors(first, |n| n.borrow().previous_sibling().upgrade()).filter(is_element)
+}
+
+fn next_element_siblings(node: &Rc<RefCell<Node>>) -> impl Iterator<Item = Rc<RefCell<Node>>> {
+    let first = node.borrow().next_sibling();
+    core::iter::successors(first, |n| n.borrow().next_sibling()).filter(is_element)
+}
+
+fn is_same_type(node: &Rc<RefCell<Node>>, element: &Element) -> bool {
+    node.borrow().element_kind() == Some(element.kind())
+}
+
+struct SelectorParser<'a> {
+    tokens: &'a [CssToken],
+    pos: usize,
+}
+
+impl<'a> SelectorParser<'a> {
+    fn new(tokens: &'a [CssToken]) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::UnexpectedInput(format!("invalid selector at {}: {}", self.pos, message))
+    }
+
+    fn peek(&self) -> Option<&'a CssToken> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&'a CssToken> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn consume(&mut self) -> Option<&'a CssToken> {
+        let token = self.tokens.get(self.pos);
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn consume_if(&mut self, token: &CssToken) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &CssToken) -> Result<(), Error> {
+        if self.consume_if(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {:?}", token)))
+        }
+    }
+
+    /// Skips whitespace and returns true if any was skipped.
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek() == Some(&CssToken::Whitespace) {
+            self.pos += 1;
+        }
+        self.pos != start
+    }
+
+    fn parse_selector_list(&mut self) -> Result<SelectorList, Error> {
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.parse_complex_selector()?);
+            self.skip_whitespace();
+            if !self.consume_if(&CssToken::Comma) {
+                break;
+            }
+        }
+        Ok(SelectorList { selectors })
+    }
+
+    fn parse_complex_selector(&mut self) -> Result<ComplexSelector, Error> {
+        let mut compounds = Vec::new();
+        let mut combinators = Vec::new();
+        let (compound, mut pseudo_element) = self.parse_compound_selector()?;
+        compounds.push(compound);
+
+        loop {
+            let skipped_whitespace = self.skip_whitespace();
+            let combinator = match self.peek() {
+                None | Some(CssToken::Comma) | Some(CssToken::CloseParenthesis) => break,
+                // A pseudo-element must be at the end of the selector.
+                Some(t) if pseudo_element.is_some() => {
+                    return Err(self.error(&format!("unexpected token {:?}", t)))
+                }
+                Some(CssToken::Delim('>')) => Combinator::Child,
+                Some(CssToken::Delim('+')) => Combinator::NextSibling,
+                Some(CssToken::Delim('~')) => Combinator::SubsequentSibling,
+                Some(_) if skipped_whitespace => Combinator::Descendant,
+                Some(t) => return Err(self.error(&format!("unexpected token {:?}", t))),
+            };
+            if combinator != Combinator::Descendant {
+                self.pos += 1;
+                self.skip_whitespace();
+            }
+            combinators.push(combinator);
+            let (compound, pseudo) = self.parse_compound_selector()?;
+            compounds.push(compound);
+            pseudo_element = pseudo;
+        }
+
+        Ok(ComplexSelector {
+            compounds,
+            combinators,
+            pseudo_element,
+        })
+    }
+
+    /// Parses a compound selector and the pseudo-element that follows it.
+    fn parse_compound_selector(
+        &mut self,
+    ) -> Result<(CompoundSelector, Option<PseudoElement>), Error> {
+        let mut selectors = Vec::new();
+        let mut pseudo_element = None;
+
+        match self.peek() {
+            Some(CssToken::Delim('*')) => {
+                self.pos += 1;
+                selectors.push(SimpleSelector::Universal);
+            }
+            Some(CssToken::Ident(name)) => {
+                self.pos += 1;
+                selectors.push(SimpleSelector::Type(name.to_ascii_lowercase()));
+            }
+            _ => {}
+        }
+
+        loop {
+            match self.peek() {
+                Some(CssToken::Hash { value, is_id: true }) => {
+                    self.pos += 1;
+                    selectors.push(SimpleSelector::Id(value.clone()));
+                }
+                Some(CssToken::Delim('.')) => {
+                    self.pos += 1;
+                    match self.consume() {
+                        Some(CssToken::Ident(name)) => {
+                            selectors.push(SimpleSelector::Class(name.clone()))
+                        }
+                        _ => return Err(self.error("expected an identifier")),
+                    }
+                }
+                Some(CssToken::OpenSquareBracket) => {
+                    self.pos += 1;
+                    selectors.push(self.parse_attribute_selector()?);
+                }
+                Some(CssToken::Colon) => {
+                    self.pos += 1;
+                    let is_legacy = !self.consume_if(&CssToken::Colon);
+                    // https://www.w3.org/TR/selectors-4/#pseudo-element-syntax
+                    // `:before` and `:after` are also accepted for
+                    // compatibility with CSS 2.
+                    let name = match self.peek() {
+                        Some(CssToken::Ident(name)) => name.to_ascii_lowercase(),
+                        _ if !is_legacy => return Err(self.error("expected a pseudo-element")),
+                        _ => String::new(),
+                    };
+                    match name.as_str() {
+                        "before" => pseudo_element = Some(PseudoElement::Before),
+                        "after" => pseudo_element = Some(PseudoElement::After),
+                        _ if !is_legacy => {
+                            return Err(
+                                self.error(&format!("unsupported pseudo-element ::{}", name))
+                            )
+                        }
+                        _ => {
+                            selectors.push(SimpleSelector::PseudoClass(self.parse_pseudo_class()?));
+                            continue;
+                        }
+                    }
+                    self.pos += 1;
+                    break;
+                }
+                _ => break,
+            }
+        }
+
+        if selectors.is_empty() && pseudo_element.is_none() {
+            return Err(self.error("expected a compound selector"));
+        }
+        Ok((CompoundSelector { selectors }, pseudo_element))
+    }
+
+    fn parse_attribute_selector(&mut self) -> Result<SimpleSelector, Error> {
+        self.skip_whitespace();
+        let name = match self.consume() {
+            Some(CssToken::Ident(name)) => name.to_ascii_lowercase(),
+            _ => return Err(self.error("expected an identifier")),
+        };
+        self.skip_whitespace();
+
+        let operator = match self.peek() {
+            Some(CssToken::CloseSquareBracket) => {
+                self.pos += 1;
+                return Ok(SimpleSelector::Attribute {
+                    name,
+                    matcher: None,
+                    case_insensitive: false,
+                });
+            }
+            Some(CssToken::Delim('=')) => AttributeOperator::Equal,
+            Some(CssToken::Delim(c)) if self.peek_at(1) == Some(&CssToken::Delim('=')) => {
+                self.pos += 1;
+                match c {
+                    '~' => AttributeOperator::Includes,
+                    '|' => AttributeOperator::DashMatch,
+                    '^' => AttributeOperator::Prefix,
+                    '$' => AttributeOperator::Suffix,
+                    '*' => AttributeOperator::Substring,
+                    _ => return Err(self.error("unknown attribute operator")),
+                }
+            }
+            _ => return Err(self.error("expected an attribute operator")),
+        };
+        self.pos += 1;
+        self.skip_whitespace();
+
+        let value = match self.consume() {
+            Some(CssToken::String(value)) | Some(CssToken::Ident(value)) => value.clone(),
+            _ => return Err(self.error("expected a string or an identifier")),
+        };
+        self.skip_whitespace();
+
+        let mut case_insensitive = false;
+        if let Some(CssToken::Ident(modifier)) = self.peek() {
+            match modifier.as_str() {
+                "i" | "I" => case_insensitive = true,
+                "s" | "S" => {}
+                _ => return Err(self.error("unknown attribute modifier")),
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+        }
+        self.expect(&CssToken::CloseSquareBracket)?;
+
+        Ok(SimpleSelector::Attribute {
+            name,
+            matcher: Some((operator, value)),
+            case_insensitive,
+        })
+    }
+
+    fn parse_pseudo_class(&mut self) -> Result<PseudoClass, Error> {
+        let name = match self.consume() {
+            Some(CssToken::Ident(name)) => {
+                return match name.to_ascii_lowercase().as_str() {
+                    "first-child" => Ok(PseudoClass::FirstChild),
+                    "last-child" => Ok(PseudoClass::LastChild),
+                    "only-child" => Ok(PseudoClass::OnlyChild),
+                    "first-of-type" => Ok(PseudoClass::FirstOfType),
+                    "last-of-type" => Ok(PseudoClass::LastOfType),
+                    "only-of-type" => Ok(PseudoClass::OnlyOfType),
+                    "empty" => Ok(PseudoClass::Empty),
+                    "root" => Ok(PseudoClass::Root),
+                    "link" | "any-link" => Ok(PseudoClass::Link),
+                    _ => Err(self.error(&format!("unsupported pseudo-class :{}", name))),
+                };
+            }
+            Some(CssToken::Function(name)) => name.to_ascii_lowercase(),
+            _ => return Err(self.error("expected a pseudo-class")),
+        };
+
+        self.skip_whitespace();
+        let pseudo_class = match name.as_str() {
+            "not" => PseudoClass::Not(self.parse_nested_selector_list()?),
+            "is" => PseudoClass::Is(self.parse_nested_selector_list()?),
+            "where" => PseudoClass::Where(self.parse_nested_selector_list()?),
+            "nth-child" => {
+                let nth = self.parse_nth()?;
+                PseudoClass::NthChild(nth, self.parse_nth_of()?)
+            }
+            "nth-last-child" => {
+                let nth = self.parse_nth()?;
+                PseudoClass::NthLastChild(nth, self.parse_nth_of()?)
+            }
+            "nth-of-type" => PseudoClass::NthOfType(self.parse_nth()?),
+            "nth-last-of-type" => PseudoClass::NthLastOfType(self.parse_nth()?),
+            _ => return Err(self.error(&format!("unsupported pseudo-class :{}()", name))),
+        };
+        self.skip_whitespace();
+        self.expect(&CssToken::CloseParenthesis)?;
+
+        Ok(pseudo_class)
+    }
+
+    /// Parses the optional `of S` part of `:nth-child()` and `:nth-last-child()`.
+    fn parse_nth_of(&mut self) -> Result<Option<SelectorList>, Error> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(CssToken::Ident(name)) if name.eq_ignore_ascii_case("of") => {
+                self.pos += 1;
+                Ok(Some(self.parse_nested_selector_list()?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Parses the selector list argument of a pseudo-class, which can't
+    /// contain pseudo-elements.
+    fn parse_nested_selector_list(&mut self) -> Result<SelectorList, Error> {
+        let list = self.parse_selector_list()?;
+        if list.selectors.iter().any(|s| s.pseudo_element.is_some()) {
+            return Err(self.error("pseudo-elements are not allowed in a pseudo-class"));
+        }
+        Ok(list)
+    }
+
+    /// https://www.w3.org/TR/css-syntax-3/#anb-microsyntax
+    fn parse_nth(&mut self) -> Result<Nth, Error> {
+        self.skip_whitespace();
+        // A `+` sign before an `n` is a separate delimiter token.
+        let plus = self.peek() == Some(&CssToken::Delim('+'));
+        if plus {
+            self.pos += 1;
+        }
+
+        let (a, rest) = match self.consume() {
+            Some(CssToken::Ident(name)) if !plus && name.eq_ignore_ascii_case("odd") => {
+                return Ok(Nth::new(2, 1))
+            }
+            Some(CssToken::Ident(name)) if !plus && name.eq_ignore_ascii_case("even") => {
+                return Ok(Nth::new(2, 0))
+            }
+            Some(CssToken::Number {
+                value,
+                is_integer: true,
+            }) if !plus => return Ok(Nth::new(0, *value as i32)),
+            Some(CssToken::Dimension {
+                value,
+                is_integer: true,
+                unit,
+            }) if !plus => (*value as i32, unit.to_ascii_lowercase()),
+            Some(CssToken::Ident(name)) => match name.to_ascii_lowercase().strip_prefix('-') {
+                Some(rest) if !plus => (-1, rest.to_string()),
+                Some(_) => return Err(self.error("expected An+B")),
+                None => (1, name.to_ascii_lowercase()),
+            },
+            _ => return Err(self.error("expected An+B")),
+        };
+
+        // `rest` is what follows A: `n`, `n-` or `n-` followed by digits.
+        let b = match rest.strip_prefix('n') {
+            Some("") => self.parse_nth_b()?,
+            Some("-") => {
+                self.skip_whitespace();
+                -self.consume_signless_integer()?
+            }
+            Some(digits) => match digits.strip_prefix('-') {
+                Some(digits)
+                    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) =>
+                {
+                    -digits
+                        .parse::<i32>()
+                        .map_err(|_| self.error("expected an integer"))?
+                }
+                _ => return Err(self.error("expected An+B")),
+            },
+            None => return Err(self.error("expected An+B")),
+        };
+        Ok(Nth::new(a, b))
+    }
+
+    /// Parses the optional B after `An`.
+    fn parse_nth_b(&mut self) -> Result<i32, Error> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(CssToken::Delim('+')) => {
+                self.pos += 1;
+                self.skip_whitespace();
+                self.consume_signless_integer()
+            }
+            Some(CssToken::Delim('-')) => {
+                self.pos += 1;
+                self.skip_whitespace();
+                Ok(-self.consume_signless_integer()?)
+            }
+            // Number tokens don't keep their sign, so a B without one is
+            // accepted as well.
+            Some(CssToken::Number {
+                value,
+                is_integer: true,
+            }) => {
+                self.pos += 1;
+                Ok(*value as i32)
+            }
+            _ => Ok(0),
+        }
+    }
+
+    fn consume_signless_integer(&mut self) -> Result<i32, Error> {
+        match self.consume() {
+            Some(CssToken::Number {
+                value,
+                is_integer: true,
+            }) if *value >= 0.0 => Ok(*value as i32),
+            _ => Err(self.error("expected an integer")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::append_child;
+    use crate::renderer::dom::api::closest;
+    use crate::renderer::dom::api::matches;
+    use crate::renderer::dom::api::query_selector;
+    use crate::renderer::dom::api::query_selector_all;
+    use crate::renderer::dom::node::Window;
+    use crate::renderer::html::attribute::Attribute;
+    use alloc::vec;
+
+    fn element(name: &str, attributes: &[(&str, &str)]) -> Rc<RefCell<Node>> {
+        let attributes = attributes
+            .iter()
+            .map(|(n, v)| Attribute::with_value(n, v))
+            .collect();
+        Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            name, attributes,
+        )))))
+    }
+
+    fn text(s: &str) -> Rc<RefCell<Node>> {
+        Rc::new(RefCell::new(Node::new(NodeKind::Text(s.to_string()))))
+    }
+
+    fn ids(nodes: Vec<Rc<RefCell<Node>>>) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|n| n.borrow().get_element().unwrap().id().unwrap_or_default())
+            .collect()
+    }
+
+    /// ```text
+    /// html#root
+    /// ├── head#head
+    /// └── body#body.main
+    ///     ├── h1#title.big.red [lang=en-US]
+    ///     ├── p#p1.intro [data-x="foo bar"]
+    ///     │   └── a#a1 [href=/index.html]
+    ///     ├── p#p2
+    ///     │   └── "text"
+    ///     ├── h2#sub
+    ///     └── p#p3 [title=Hello]
+    /// ```
+    fn create_document() -> Rc<RefCell<Node>> {
+        let document = Window::new().document();
+        let html = element("html", &[("id", "root")]);
+        let body = element("body", &[("id", "body"), ("class", "main")]);
+        let p1 = element(
+            "p",
+            &[("id", "p1"), ("class", "intro"), ("data-x", "foo bar")],
+        );
+        let p2 = element("p", &[("id", "p2")]);
+        append_child(&document, html.clone()).unwrap();
+        append_child(&html, element("head", &[("id", "head")])).unwrap();
+        append_child(&html, body.clone()).unwrap();
+        append_child(
+            &body,
+            element(
+                "h1",
+                &[("id", "title"), ("class", "big red"), ("lang", "en-US")],
+            ),
+        )
+        .unwrap();
+        append_child(&body, p1.clone()).unwrap();
+        append_child(&p1, element("a", &[("id", "a1"), ("href", "/index.html")])).unwrap();
+        append_child(&body, p2.clone()).unwrap();
+        append_child(&p2, text("text")).unwrap();
+        append_child(&body, element("h2", &[("id", "sub")])).unwrap();
+        append_child(&body, element("p", &[("id", "p3"), ("title", "Hello")])).unwrap();
+        document
+    }
+
+    fn select(document: &Rc<RefCell<Node>>, selectors: &str) -> Vec<String> {
+        ids(query_selector_all(document, selectors).unwrap())
+    }
+
+    #[test]
+    fn test_simple_selectors() {
+        let document = create_document();
+        assert_eq!(vec!["p1", "p2", "p3"], select(&document, "p"));
+        assert_eq!(vec!["p1", "p2", "p3"], select(&document, "P"));
+        assert_eq!(vec!["title"], select(&document, "#title"));
+        assert_eq!(vec!["title"], select(&document, ".big.red"));
+        assert_eq!(vec!["p1"], select(&document, "p.intro"));
+        assert_eq!(9, select(&document, "*").len());
+        assert!(select(&document, "h1.intro").is_empty());
+    }
+
+    #[test]
+    fn test_attribute_selectors() {
+        let document = create_document();
+        assert_eq!(vec!["a1"], select(&document, "[href]"));
+        assert_eq!(vec!["a1"], select(&document, "[href='/index.html']"));
+        assert_eq!(vec!["p1"], select(&document, "[data-x~=bar]"));
+        assert!(select(&document, "[data-x~='foo bar']").is_empty());
+        assert_eq!(vec!["title"], select(&document, "[lang|=en]"));
+        assert!(select(&document, "[lang|=e]").is_empty());
+        assert_eq!(vec!["a1"], select(&document, "[href^=\"/index\"]"));
+        assert_eq!(vec!["a1"], select(&document, "[href$=html]"));
+        assert_eq!(vec!["p1"], select(&document, "[data-x*='o b']"));
+        assert!(select(&document, "[title=hello]").is_empty());
+        assert_eq!(vec!["p3"], select(&document, "[title=hello i]"));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let document = create_document();
+        assert_eq!(vec!["a1"], select(&document, "body a"));
+        assert_eq!(vec!["a1"], select(&document, "html   p > a"));
+        assert!(select(&document, "body > a").is_empty());
+        assert_eq!(vec!["p1"], select(&document, "h1 + p"));
+        assert_eq!(vec!["p1", "p2", "p3"], select(&document, "h1 ~ p"));
+        assert_eq!(vec!["p3"], select(&document, "h2~p"));
+        assert_eq!(vec!["p3"], select(&document, "#root .main h1 ~ h2 + p"));
+    }
+
+    #[test]
+    fn test_selector_list_in_tree_order() {
+        let document = create_document();
+        assert_eq!(
+            vec!["title", "p1", "sub"],
+            select(&document, "h2, p.intro, h1")
+        );
+    }
+
+    #[test]
+    fn test_logical_pseudo_classes() {
+        let document = create_document();
+        assert_eq!(vec!["p2", "p3"], select(&document, "p:not(.intro)"));
+        assert_eq!(vec!["title", "sub"], select(&document, ":is(h1, h2)"));
+        assert_eq!(
+            vec!["title", "sub"],
+            select(&document, "body > :where(h1, h2)")
+        );
+        assert_eq!(vec!["a1"], select(&document, ":is(p:not(#p2)) > a"));
+    }
+
+    #[test]
+    fn test_structural_pseudo_classes() {
+        let document = create_document();
+        assert_eq!(
+            vec!["root", "head", "title", "a1"],
+            select(&document, ":first-child")
+        );
+        assert_eq!(
+            vec!["root", "body", "a1", "p3"],
+            select(&document, ":last-child")
+        );
+        assert_eq!(vec!["root", "a1"], select(&document, ":only-child"));
+        assert_eq!(
+            vec!["p1", "sub"],
+            select(&document, "body > :nth-child(2n)")
+        );
+        assert_eq!(
+            vec!["title", "p2", "p3"],
+            select(&document, "body > :nth-child(odd)")
+        );
+        assert_eq!(
+            vec!["title", "p1", "p2"],
+            select(&document, "body > :nth-child(-n+3)")
+        );
+        assert_eq!(
+            vec!["p1", "p3"],
+            select(&document, "body > :nth-child( 2n + 1 of p)")
+        );
+        assert_eq!(vec!["sub"], select(&document, "body > :nth-last-child(2)"));
+        assert_eq!(vec!["p2"], select(&document, "p:nth-of-type(2)"));
+        assert_eq!(vec!["p3"], select(&document, "p:nth-last-of-type(1)"));
+        assert_eq!(vec!["p1"], select(&document, "p:first-of-type"));
+        assert_eq!(vec!["p3"], select(&document, "p:last-of-type"));
+        assert_eq!(
+            vec!["title", "sub"],
+            select(&document, "body > :only-of-type")
+        );
+        assert_eq!(
+            vec!["head", "title", "a1", "sub", "p3"],
+            select(&document, ":empty")
+        );
+        assert_eq!(vec!["root"], select(&document, ":root"));
+        assert_eq!(vec!["a1"], select(&document, ":link"));
+    }
+
+    #[test]
+    fn test_specificity() {
+        let specificity = |s: &str| SelectorList::parse(s).unwrap().selectors()[0].specificity();
+        assert_eq!(Specificity(0, 0, 0), specificity("*"));
+        assert_eq!(Specificity(0, 0, 2), specificity("body p"));
+        assert_eq!(Specificity(1, 1, 1), specificity("#x p.a"));
+        assert_eq!(Specificity(0, 2, 1), specificity("a[href]:first-child"));
+        assert_eq!(Specificity(1, 0, 1), specificity("p:not(.a, #b)"));
+        assert_eq!(Specificity(0, 0, 1), specificity("p:where(#b)"));
+        assert_eq!(Specificity(0, 2, 0), specificity(":nth-child(2 of .a)"));
+    }
+
+    #[test]
+    fn test_invalid_selectors() {
+        for s in [
+            "",
+            "p >",
+            "> p",
+            "p,",
+            "#",
+            ".1a",
+            "[href",
+            "[a=]",
+            "p::first-line",
+            "p::before.a",
+            "p::before a",
+            ":not(::before)",
+            ":hover",
+            ":nth-child(x)",
+            "p $ a",
+            ".x/**/y",
+            "#1a",
+            ":nth-child(+ n)",
+        ] {
+            assert!(SelectorList::parse(s).is_err(), "{:?} should be invalid", s);
+        }
+        let document = create_document();
+        assert!(query_selector(&document, "p >").is_err());
+    }
+
+    #[test]
+    fn test_pseudo_elements() {
+        let document = create_document();
+        let p = query_selector(&document, "p").unwrap().unwrap();
+        for (selector, expected) in [
+            ("p::before", Some(PseudoElement::Before)),
+            ("p:AFTER", Some(PseudoElement::After)),
+            ("::before", Some(PseudoElement::Before)),
+            ("p", None),
+        ] {
+            let list = SelectorList::parse(selector).unwrap();
+            assert_eq!(
+                expected,
+                list.selectors()[0].pseudo_element(),
+                "{}",
+                selector
+            );
+        }
+
+        let list = SelectorList::parse("body > p::before").unwrap();
+        let before = &list.selectors()[0];
+        assert_eq!(Specificity(0, 0, 3), before.specificity());
+        assert!(!before.matches(&p));
+        assert!(before.matches_pseudo_element(&p, PseudoElement::Before));
+        assert!(!before.matches_pseudo_element(&p, PseudoElement::After));
+        assert!(query_selector(&document, "p::before").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_escapes() {
+        let list = SelectorList::parse(".a\\:b #\\31 23").unwrap();
+        assert_eq!(
+            vec![SimpleSelector::Class("a:b".to_string())],
+            list.selectors()[0].compounds()[0].selectors()
+        );
+        assert_eq!(
+            vec![SimpleSelector::Id("123".to_string())],
+            list.selectors()[0].compounds()[1].selectors()
+        );
+    }
+
+    #[test]
+    fn test_query_selector_matches_closest() {
+        let document = create_document();
+        let a = query_selector(&document, "a").unwrap().unwrap();
+        assert!(matches(&a, "p > a[href]").unwrap());
+        assert!(!matches(&a, "h1").unwrap());
+
+        let body = closest(&a, "body").unwrap().unwrap();
+        assert_eq!(vec!["body"], ids(vec![body.clone()]));
+        assert!(Rc::ptr_eq(&a, &closest(&a, "a").unwrap().unwrap()));
+        assert!(closest(&a, "h1").unwrap().is_none());
+
+        // Only descendants of the node are searched.
+        assert_eq!(
+            vec!["a1"],
+            ids(query_selector_all(&body, "body a").unwrap())
+        );
+        assert!(query_selector(&body, "body").unwrap().is_none());
+    }
+}
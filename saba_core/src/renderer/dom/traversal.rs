@@ -0,0 +1,908 @@
+//! https://dom.spec.whatwg.org/#traversal
+
+use crate::renderer::dom::api::is_inclusive_ancestor;
+use crate::renderer::dom::api::owner_document;
+use crate::renderer::dom::node::last_inclusive_descendant;
+use crate::renderer::dom::node::Node;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// An iterator over the children of a node.
+#[derive(Debug, Clone)]
+pub struct Children {
+    next: Option<Rc<RefCell<Node>>>,
+}
+
+impl Children {
+    pub(crate) fn new(first_child: Option<Rc<RefCell<Node>>>) -> Self {
+        Self { next: first_child }
+    }
+}
+
+impl Iterator for Children {
+    type Item = Rc<RefCell<Node>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        self.next = current.borrow().next_sibling();
+        Some(current)
+    }
+}
+
+/// An iterator over the descendants of a node in pre-order.
+#[derive(Debug, Clone)]
+pub struct Descendants {
+    next: Option<Rc<RefCell<Node>>>,
+    /// The depth of `next` relative to the node the iteration started from.
+    depth: usize,
+}
+
+impl Descendants {
+    pub(crate) fn new(first_child: Option<Rc<RefCell<Node>>>) -> Self {
+        Self {
+            next: first_child,
+            depth: 1,
+        }
+    }
+}
+
+impl Iterator for Descendants {
+    type Item = Rc<RefCell<Node>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+
+        if let Some(first_child) = current.borrow().first_child() {
+            self.next = Some(first_child);
+            self.depth += 1;
+            return Some(current.clone());
+        }
+
+        let mut node = current.clone();
+        loop {
+            let next_sibling = node.borrow().next_sibling();
+            if let Some(next_sibling) = next_sibling {
+                self.next = Some(next_sibling);
+                break;
+            }
+            self.depth -= 1;
+            if self.depth == 0 {
+                break;
+            }
+            let parent = node.borrow().parent().upgrade();
+            match parent {
+                Some(parent) => node = parent,
+                None => break,
+            }
+        }
+
+        Some(current)
+    }
+}
+
+/// An iterator from a node up to the root of its tree.
+#[derive(Debug, Clone)]
+pub struct Ancestors {
+    next: Option<Rc<RefCell<Node>>>,
+}
+
+impl Ancestors {
+    pub(crate) fn new(parent: Option<Rc<RefCell<Node>>>) -> Self {
+        Self { next: parent }
+    }
+}
+
+impl Iterator for Ancestors {
+    type Item = Rc<RefCell<Node>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        self.next = current.borrow().parent().upgrade();
+        Some(current)
+    }
+}
+
+/// An iterator over the nodes that follow a node in tree order.
+#[derive(Debug, Clone)]
+pub struct Following {
+    next: Option<Rc<RefCell<Node>>>,
+}
+
+impl Following {
+    pub(crate) fn new(next: Option<Rc<RefCell<Node>>>) -> Self {
+        Self { next }
+    }
+}
+
+impl Iterator for Following {
+    type Item = Rc<RefCell<Node>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        self.next = next_in_tree_order(&current, None);
+        Some(current)
+    }
+}
+
+/// An iterator over the nodes that precede a node, in reverse tree order.
+#[derive(Debug, Clone)]
+pub struct Preceding {
+    next: Option<Rc<RefCell<Node>>>,
+}
+
+impl Preceding {
+    pub(crate) fn new(next: Option<Rc<RefCell<Node>>>) -> Self {
+        Self { next }
+    }
+}
+
+impl Iterator for Preceding {
+    type Item = Rc<RefCell<Node>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        self.next = previous_in_tree_order(&current, None);
+        Some(current)
+    }
+}
+
+/// Returns the node after `node` in tree order. When `root` is given, the
+/// search does not leave the subtree rooted at `root`.
+fn next_in_tree_order(
+    node: &Rc<RefCell<Node>>,
+    root: Option<&Rc<RefCell<Node>>>,
+) -> Option<Rc<RefCell<Node>>> {
+    if let Some(first_child) = node.borrow().first_child() {
+        return Some(first_child);
+    }
+    next_skipping_descendants(node, root)
+}
+
+/// Returns the first node after `node` in tree order that is not one of its
+/// descendants. When `root` is given, the search does not leave the subtree
+/// rooted at `root`.
+fn next_skipping_descendants(
+    node: &Rc<RefCell<Node>>,
+    root: Option<&Rc<RefCell<Node>>>,
+) -> Option<Rc<RefCell<Node>>> {
+    let mut current = node.clone();
+    loop {
+        if let Some(root) = root {
+            if Rc::ptr_eq(&current, root) {
+                return None;
+            }
+        }
+        let next_sibling = current.borrow().next_sibling();
+        if next_sibling.is_some() {
+            return next_sibling;
+        }
+        let parent = current.borrow().parent().upgrade();
+        current = parent?;
+    }
+}
+
+/// Returns the node before `node` in tree order. When `root` is given, the
+/// search does not leave the subtree rooted at `root`.
+fn previous_in_tree_order(
+    node: &Rc<RefCell<Node>>,
+    root: Option<&Rc<RefCell<Node>>>,
+) -> Option<Rc<RefCell<Node>>> {
+    if let Some(root) = root {
+        if Rc::ptr_eq(node, root) {
+            return None;
+        }
+    }
+
+    let previous_sibling = node.borrow().previous_sibling().upgrade();
+    match previous_sibling {
+        Some(previous_sibling) => Some(last_inclusive_descendant(previous_sibling)),
+        None => node.borrow().parent().upgrade(),
+    }
+}
+
+/// https://dom.spec.whatwg.org/#dom-nodefilter-show_all
+pub const SHOW_ALL: u32 = 0xFFFF_FFFF;
+/// https://dom.spec.whatwg.org/#dom-nodefilter-show_element
+pub const SHOW_ELEMENT: u32 = 0x1;
+/// https://dom.spec.whatwg.org/#dom-nodefilter-show_attribute
+pub const SHOW_ATTRIBUTE: u32 = 0x2;
+/// https://dom.spec.whatwg.org/#dom-nodefilter-show_text
+pub const SHOW_TEXT: u32 = 0x4;
+/// https://dom.spec.whatwg.org/#dom-nodefilter-show_cdata_section
+pub const SHOW_CDATA_SECTION: u32 = 0x8;
+/// https://dom.spec.whatwg.org/#dom-nodefilter-show_processing_instruction
+pub const SHOW_PROCESSING_INSTRUCTION: u32 = 0x40;
+/// https://dom.spec.whatwg.org/#dom-nodefilter-show_comment
+pub const SHOW_COMMENT: u32 = 0x80;
+/// https://dom.spec.whatwg.org/#dom-nodefilter-show_document
+pub const SHOW_DOCUMENT: u32 = 0x100;
+/// https://dom.spec.whatwg.org/#dom-nodefilter-show_document_type
+pub const SHOW_DOCUMENT_TYPE: u32 = 0x200;
+/// https://dom.spec.whatwg.org/#dom-nodefilter-show_document_fragment
+pub const SHOW_DOCUMENT_FRAGMENT: u32 = 0x400;
+
+/// https://dom.spec.whatwg.org/#callbackdef-nodefilter
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FilterResult {
+    /// https://dom.spec.whatwg.org/#dom-nodefilter-filter_accept
+    Accept,
+    /// https://dom.spec.whatwg.org/#dom-nodefilter-filter_reject
+    Reject,
+    /// https://dom.spec.whatwg.org/#dom-nodefilter-filter_skip
+    Skip,
+}
+
+/// https://dom.spec.whatwg.org/#callbackdef-nodefilter
+pub type NodeFilter = Box<dyn Fn(&Rc<RefCell<Node>>) -> FilterResult>;
+
+/// https://dom.spec.whatwg.org/#concept-node-filter
+fn filter_node(
+    node: &Rc<RefCell<Node>>,
+    what_to_show: u32,
+    filter: &Option<NodeFilter>,
+) -> FilterResult {
+    let n = node.borrow().node_type() - 1;
+    if what_to_show & (1 << n) == 0 {
+        return FilterResult::Skip;
+    }
+    match filter {
+        Some(filter) => filter(node),
+        None => FilterResult::Accept,
+    }
+}
+
+fn first_child(node: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    node.borrow().first_child()
+}
+
+fn last_child(node: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    node.borrow().last_child().upgrade()
+}
+
+fn next_sibling(node: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    node.borrow().next_sibling()
+}
+
+fn previous_sibling(node: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    node.borrow().previous_sibling().upgrade()
+}
+
+fn parent(node: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+    node.borrow().parent().upgrade()
+}
+
+#[derive(Copy, Clone)]
+enum Direction {
+    First,
+    Last,
+}
+
+impl Direction {
+    fn child(&self, node: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+        match self {
+            Direction::First => first_child(node),
+            Direction::Last => last_child(node),
+        }
+    }
+
+    /// Returns the sibling in the direction of travel, i.e. the next sibling
+    /// when walking from the first child and the previous one otherwise.
+    fn sibling(&self, node: &Rc<RefCell<Node>>) -> Option<Rc<RefCell<Node>>> {
+        match self {
+            Direction::First => next_sibling(node),
+            Direction::Last => previous_sibling(node),
+        }
+    }
+}
+
+/// https://dom.spec.whatwg.org/#interface-treewalker
+pub struct TreeWalker {
+    root: Rc<RefCell<Node>>,
+    what_to_show: u32,
+    filter: Option<NodeFilter>,
+    current: Rc<RefCell<Node>>,
+}
+
+impl TreeWalker {
+    /// https://dom.spec.whatwg.org/#dom-document-createtreewalker
+    pub fn new(root: Rc<RefCell<Node>>, what_to_show: u32, filter: Option<NodeFilter>) -> Self {
+        Self {
+            root: root.clone(),
+            what_to_show,
+            filter,
+            current: root,
+        }
+    }
+
+    pub fn root(&self) -> Rc<RefCell<Node>> {
+        self.root.clone()
+    }
+
+    pub fn what_to_show(&self) -> u32 {
+        self.what_to_show
+    }
+
+    pub fn current_node(&self) -> Rc<RefCell<Node>> {
+        self.current.clone()
+    }
+
+    pub fn set_current_node(&mut self, node: Rc<RefCell<Node>>) {
+        self.current = node;
+    }
+
+    fn filter(&self, node: &Rc<RefCell<Node>>) -> FilterResult {
+        filter_node(node, self.what_to_show, &self.filter)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-parentnode
+    pub fn parent_node(&mut self) -> Option<Rc<RefCell<Node>>> {
+        let mut node = Some(self.current.clone());
+        while let Some(n) = node {
+            if Rc::ptr_eq(&n, &self.root) {
+                break;
+            }
+            node = parent(&n);
+            if let Some(ref p) = node {
+                if self.filter(p) == FilterResult::Accept {
+                    self.current = p.clone();
+                    return node;
+                }
+            }
+        }
+        None
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-firstchild
+    pub fn first_child(&mut self) -> Option<Rc<RefCell<Node>>> {
+        self.traverse_children(Direction::First)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-lastchild
+    pub fn last_child(&mut self) -> Option<Rc<RefCell<Node>>> {
+        self.traverse_children(Direction::Last)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-nextsibling
+    pub fn next_sibling(&mut self) -> Option<Rc<RefCell<Node>>> {
+        self.traverse_siblings(Direction::First)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-previoussibling
+    pub fn previous_sibling(&mut self) -> Option<Rc<RefCell<Node>>> {
+        self.traverse_siblings(Direction::Last)
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-traverse-children
+    fn traverse_children(&mut self, direction: Direction) -> Option<Rc<RefCell<Node>>> {
+        let mut node = direction.child(&self.current);
+        while let Some(n) = node.clone() {
+            let result = self.filter(&n);
+            if result == FilterResult::Accept {
+                self.current = n;
+                return node;
+            }
+            if result == FilterResult::Skip {
+                if let Some(child) = direction.child(&n) {
+                    node = Some(child);
+                    continue;
+                }
+            }
+            let mut n = n;
+            loop {
+                if let Some(sibling) = direction.sibling(&n) {
+                    node = Some(sibling);
+                    break;
+                }
+                match parent(&n) {
+                    Some(p) if !Rc::ptr_eq(&p, &self.root) && !Rc::ptr_eq(&p, &self.current) => {
+                        n = p
+                    }
+                    _ => return None,
+                }
+            }
+        }
+        None
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-traverse-siblings
+    ///
+    /// `Direction::First` walks towards the next sibling and `Direction::Last`
+    /// towards the previous one.
+    fn traverse_siblings(&mut self, direction: Direction) -> Option<Rc<RefCell<Node>>> {
+        let mut node = self.current.clone();
+        if Rc::ptr_eq(&node, &self.root) {
+            return None;
+        }
+        loop {
+            let mut sibling = direction.sibling(&node);
+            while let Some(s) = sibling {
+                node = s;
+                let result = self.filter(&node);
+                if result == FilterResult::Accept {
+                    self.current = node.clone();
+                    return Some(node);
+                }
+                sibling = direction.child(&node);
+                if result == FilterResult::Reject || sibling.is_none() {
+                    sibling = direction.sibling(&node);
+                }
+            }
+            node = parent(&node)?;
+            if Rc::ptr_eq(&node, &self.root) {
+                return None;
+            }
+            if self.filter(&node) == FilterResult::Accept {
+                return None;
+            }
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-previousnode
+    pub fn previous_node(&mut self) -> Option<Rc<RefCell<Node>>> {
+        let mut node = self.current.clone();
+        while !Rc::ptr_eq(&node, &self.root) {
+            let mut sibling = previous_sibling(&node);
+            while let Some(s) = sibling {
+                node = s;
+                let mut result = self.filter(&node);
+                while result != FilterResult::Reject {
+                    match last_child(&node) {
+                        Some(child) => {
+                            node = child;
+                            result = self.filter(&node);
+                        }
+                        None => break,
+                    }
+                }
+                if result == FilterResult::Accept {
+                    self.current = node.clone();
+                    return Some(node);
+                }
+                sibling = previous_sibling(&node);
+            }
+            if Rc::ptr_eq(&node, &self.root) {
+                return None;
+            }
+            node = parent(&node)?;
+            if self.filter(&node) == FilterResult::Accept {
+                self.current = node.clone();
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-nextnode
+    pub fn next_node(&mut self) -> Option<Rc<RefCell<Node>>> {
+        let mut node = self.current.clone();
+        let mut result = FilterResult::Accept;
+        loop {
+            while result != FilterResult::Reject {
+                match first_child(&node) {
+                    Some(child) => {
+                        node = child;
+                        result = self.filter(&node);
+                        if result == FilterResult::Accept {
+                            self.current = node.clone();
+                            return Some(node);
+                        }
+                    }
+                    None => break,
+                }
+            }
+
+            let mut temporary = Some(node.clone());
+            let mut sibling = None;
+            while let Some(t) = temporary {
+                if Rc::ptr_eq(&t, &self.root) {
+                    return None;
+                }
+                sibling = next_sibling(&t);
+                if sibling.is_some() {
+                    break;
+                }
+                temporary = parent(&t);
+            }
+            node = sibling?;
+
+            result = self.filter(&node);
+            if result == FilterResult::Accept {
+                self.current = node.clone();
+                return Some(node);
+            }
+        }
+    }
+}
+
+/// The position of a `NodeIterator`, which node removal updates.
+#[derive(Debug)]
+pub(crate) struct NodeIteratorState {
+    root: Rc<RefCell<Node>>,
+    reference: Rc<RefCell<Node>>,
+    pointer_before_reference: bool,
+}
+
+/// https://dom.spec.whatwg.org/#interface-nodeiterator
+pub struct NodeIterator {
+    state: Rc<RefCell<NodeIteratorState>>,
+    what_to_show: u32,
+    filter: Option<NodeFilter>,
+}
+
+impl NodeIterator {
+    /// https://dom.spec.whatwg.org/#dom-document-createnodeiterator
+    pub fn new(root: Rc<RefCell<Node>>, what_to_show: u32, filter: Option<NodeFilter>) -> Self {
+        let state = Rc::new(RefCell::new(NodeIteratorState {
+            root: root.clone(),
+            reference: root.clone(),
+            pointer_before_reference: true,
+        }));
+        if let Some(document) = owner_document(&root) {
+            let mut document = document.borrow_mut();
+            let node_iterators = document.node_iterators_mut();
+            node_iterators.retain(|i| i.strong_count() > 0);
+            node_iterators.push(Rc::downgrade(&state));
+        }
+        Self {
+            state,
+            what_to_show,
+            filter,
+        }
+    }
+
+    pub fn root(&self) -> Rc<RefCell<Node>> {
+        self.state.borrow().root.clone()
+    }
+
+    pub fn what_to_show(&self) -> u32 {
+        self.what_to_show
+    }
+
+    pub fn reference_node(&self) -> Rc<RefCell<Node>> {
+        self.state.borrow().reference.clone()
+    }
+
+    pub fn pointer_before_reference_node(&self) -> bool {
+        self.state.borrow().pointer_before_reference
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-nodeiterator-nextnode
+    pub fn next_node(&mut self) -> Option<Rc<RefCell<Node>>> {
+        self.traverse(true)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-nodeiterator-previousnode
+    pub fn previous_node(&mut self) -> Option<Rc<RefCell<Node>>> {
+        self.traverse(false)
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-nodeiterator-traverse
+    fn traverse(&mut self, next: bool) -> Option<Rc<RefCell<Node>>> {
+        let root = self.root();
+        let mut node = self.reference_node();
+        let mut before_node = self.pointer_before_reference_node();
+
+        loop {
+            if next {
+                if before_node {
+                    before_node = false;
+                } else {
+                    node = next_in_tree_order(&node, Some(&root))?;
+                }
+            } else if before_node {
+                node = previous_in_tree_order(&node, Some(&root))?;
+            } else {
+                before_node = true;
+            }
+
+            if filter_node(&node, self.what_to_show, &self.filter) == FilterResult::Accept {
+                break;
+            }
+        }
+
+        let mut state = self.state.borrow_mut();
+        state.reference = node.clone();
+        state.pointer_before_reference = before_node;
+        Some(node)
+    }
+}
+
+/// Moves the `NodeIterator`s whose reference node is about to be removed
+/// with `node` out of it.
+/// https://dom.spec.whatwg.org/#nodeiterator-pre-removing-steps
+pub(crate) fn update_node_iterators_for_remove(node: &Rc<RefCell<Node>>) {
+    let document = match owner_document(node) {
+        Some(document) => document,
+        None => return,
+    };
+    let iterators: Vec<Rc<RefCell<NodeIteratorState>>> = {
+        let mut document = document.borrow_mut();
+        let node_iterators = document.node_iterators_mut();
+        node_iterators.retain(|i| i.strong_count() > 0);
+        node_iterators.iter().filter_map(|i| i.upgrade()).collect()
+    };
+    for iterator in iterators {
+        let mut iterator = iterator.borrow_mut();
+        // Iterators whose root is removed with `node` keep their position.
+        if !is_inclusive_ancestor(node, &iterator.reference)
+            || is_inclusive_ancestor(node, &iterator.root)
+        {
+            continue;
+        }
+        if iterator.pointer_before_reference {
+            match next_skipping_descendants(node, Some(&iterator.root)) {
+                Some(next) => {
+                    iterator.reference = next;
+                    continue;
+                }
+                None => iterator.pointer_before_reference = false,
+            }
+        }
+        let previous_sibling = node.borrow().previous_sibling().upgrade();
+        iterator.reference = match previous_sibling {
+            Some(previous_sibling) => last_inclusive_descendant(previous_sibling),
+            None => node
+                .borrow()
+                .parent()
+                .upgrade()
+                .unwrap_or_else(|| iterator.root.clone()),
+        };
+    }
+}
+
+impl Iterator for NodeIterator {
+    type Item = Rc<RefCell<Node>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_node()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+    use crate::renderer::dom::api::remove_child;
+    use crate::renderer::dom::node::Element;
+    use crate::renderer::dom::node::NodeKind;
+    use alloc::string::String;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn append(parent: &Rc<RefCell<Node>>, child: &Rc<RefCell<Node>>) {
+        let last_child = parent.borrow().last_child().upgrade();
+        match last_child {
+            Some(last_child) => {
+                last_child
+                    .borrow_mut()
+                    .set_next_sibling(Some(child.clone()));
+                child
+                    .borrow_mut()
+                    .set_previous_sibling(Rc::downgrade(&last_child));
+            }
+            None => parent.borrow_mut().set_first_child(Some(child.clone())),
+        }
+        parent.borrow_mut().set_last_child(Rc::downgrade(child));
+        child.borrow_mut().set_parent(Rc::downgrade(parent));
+    }
+
+    fn element(name: &str) -> Rc<RefCell<Node>> {
+        Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            name,
+            Vec::new(),
+        )))))
+    }
+
+    fn text(s: &str) -> Rc<RefCell<Node>> {
+        Rc::new(RefCell::new(Node::new(NodeKind::Text(s.to_string()))))
+    }
+
+    fn name(node: &Rc<RefCell<Node>>) -> String {
+        match node.borrow().kind() {
+            NodeKind::Document => "#document".to_string(),
+            NodeKind::Element(e) => e.kind().to_string(),
+            NodeKind::Text(s) => s,
+            _ => unreachable!(),
+        }
+    }
+
+    fn names(nodes: impl Iterator<Item = Rc<RefCell<Node>>>) -> Vec<String> {
+        nodes.map(|n| name(&n)).collect()
+    }
+
+    /// Builds the following tree and returns the document and the `p` node.
+    ///
+    /// ```text
+    /// #document
+    /// └── html
+    ///     ├── head
+    ///     └── body
+    ///         ├── h1
+    ///         │   └── "title"
+    ///         ├── p
+    ///         │   ├── "hello"
+    ///         │   └── a
+    ///         │       └── "link"
+    ///         └── h2
+    /// ```
+    fn create_tree() -> (Rc<RefCell<Node>>, Rc<RefCell<Node>>) {
+        let document = Rc::new(RefCell::new(Node::new(NodeKind::Document)));
+        let html = element("html");
+        let head = element("head");
+        let body = element("body");
+        let h1 = element("h1");
+        let p = element("p");
+        let a = element("a");
+        let h2 = element("h2");
+        append(&document, &html);
+        append(&html, &head);
+        append(&html, &body);
+        append(&body, &h1);
+        append(&h1, &text("title"));
+        append(&body, &p);
+        append(&p, &text("hello"));
+        append(&p, &a);
+        append(&a, &text("link"));
+        append(&body, &h2);
+        (document, p)
+    }
+
+    #[test]
+    fn test_children() {
+        let (_document, p) = create_tree();
+        assert_eq!(vec!["hello", "a"], names(p.borrow().children()));
+    }
+
+    #[test]
+    fn test_descendants() {
+        let (document, p) = create_tree();
+        assert_eq!(
+            vec!["html", "head", "body", "h1", "title", "p", "hello", "a", "link", "h2"],
+            names(document.borrow().descendants())
+        );
+        assert_eq!(vec!["hello", "a", "link"], names(p.borrow().descendants()));
+    }
+
+    #[test]
+    fn test_descendants_of_leaf() {
+        let leaf = text("leaf");
+        assert_eq!(0, leaf.borrow().descendants().count());
+    }
+
+    #[test]
+    fn test_ancestors() {
+        let (_document, p) = create_tree();
+        assert_eq!(
+            vec!["body", "html", "#document"],
+            names(p.borrow().ancestors())
+        );
+    }
+
+    #[test]
+    fn test_following() {
+        let (_document, p) = create_tree();
+        assert_eq!(
+            vec!["hello", "a", "link", "h2"],
+            names(p.borrow().following())
+        );
+    }
+
+    #[test]
+    fn test_preceding() {
+        let (_document, p) = create_tree();
+        assert_eq!(
+            vec!["title", "h1", "body", "head", "html", "#document"],
+            names(p.borrow().preceding())
+        );
+    }
+
+    #[test]
+    fn test_tree_walker_elements() {
+        let (document, _p) = create_tree();
+        let mut walker = TreeWalker::new(document, SHOW_ELEMENT, None);
+        let mut visited = Vec::new();
+        while let Some(node) = walker.next_node() {
+            visited.push(name(&node));
+        }
+        assert_eq!(vec!["html", "head", "body", "h1", "p", "a", "h2"], visited);
+
+        let mut visited = Vec::new();
+        while let Some(node) = walker.previous_node() {
+            visited.push(name(&node));
+        }
+        assert_eq!(vec!["a", "p", "h1", "body", "head", "html"], visited);
+    }
+
+    #[test]
+    fn test_tree_walker_reject_skips_subtree() {
+        let (document, p) = create_tree();
+        let p_ptr = p.clone();
+        let filter: NodeFilter = Box::new(move |node| {
+            if Rc::ptr_eq(node, &p_ptr) {
+                FilterResult::Reject
+            } else {
+                FilterResult::Accept
+            }
+        });
+        let mut walker = TreeWalker::new(document, SHOW_ALL, Some(filter));
+        let mut visited = Vec::new();
+        while let Some(node) = walker.next_node() {
+            visited.push(name(&node));
+        }
+        assert_eq!(vec!["html", "head", "body", "h1", "title", "h2"], visited);
+    }
+
+    #[test]
+    fn test_tree_walker_skip_flattens_children() {
+        let (document, p) = create_tree();
+        let body = p.borrow().parent().upgrade().unwrap();
+        let p_ptr = p.clone();
+        let filter: NodeFilter = Box::new(move |node| {
+            if Rc::ptr_eq(node, &p_ptr) {
+                FilterResult::Skip
+            } else {
+                FilterResult::Accept
+            }
+        });
+        let mut walker = TreeWalker::new(document, SHOW_ELEMENT, Some(filter));
+        walker.set_current_node(body);
+        assert_eq!("h1", name(&walker.first_child().unwrap()));
+        // `p` is skipped, so its element child `a` becomes the next sibling.
+        assert_eq!("a", name(&walker.next_sibling().unwrap()));
+        assert_eq!("body", name(&walker.parent_node().unwrap()));
+        assert_eq!("h2", name(&walker.last_child().unwrap()));
+        assert_eq!("a", name(&walker.previous_sibling().unwrap()));
+    }
+
+    #[test]
+    fn test_tree_walker_stays_in_root() {
+        let (_document, p) = create_tree();
+        let mut walker = TreeWalker::new(p, SHOW_ALL, None);
+        assert!(walker.parent_node().is_none());
+        assert!(walker.next_sibling().is_none());
+        assert_eq!(
+            vec!["hello", "a", "link"],
+            names(core::iter::from_fn(|| walker.next_node()))
+        );
+    }
+
+    #[test]
+    fn test_node_iterator() {
+        let (document, p) = create_tree();
+        let mut iterator = NodeIterator::new(document, SHOW_TEXT, None);
+        assert_eq!(vec!["title", "hello", "link"], names(iterator.by_ref()));
+        assert_eq!("link", name(&iterator.previous_node().unwrap()));
+        assert_eq!("hello", name(&iterator.previous_node().unwrap()));
+        assert!(iterator.pointer_before_reference_node());
+
+        let mut iterator = NodeIterator::new(p, SHOW_ALL, None);
+        assert_eq!(vec!["p", "hello", "a", "link"], names(iterator.by_ref()));
+        assert!(iterator.next_node().is_none());
+    }
+
+    #[test]
+    fn test_node_iterator_removal() {
+        let (document, p) = create_tree();
+        let body = p.borrow().parent().upgrade().unwrap();
+        let mut after = NodeIterator::new(document.clone(), SHOW_ALL, None);
+        while name(&after.next_node().unwrap()) != "hello" {}
+        let mut before = NodeIterator::new(document.clone(), SHOW_ALL, None);
+        while name(&before.next_node().unwrap()) != "a" {}
+        before.previous_node();
+        assert!(before.pointer_before_reference_node());
+        let mut inside = NodeIterator::new(p.clone(), SHOW_ALL, None);
+        inside.next_node();
+
+        remove_child(&body, &p).unwrap();
+        // The pointer moves to the last node before the removed subtree...
+        assert_eq!("title", name(&after.reference_node()));
+        assert_eq!(vec!["h2"], names(after.by_ref()));
+        // ...or to the first node after it.
+        assert_eq!("h2", name(&before.reference_node()));
+        assert_eq!(vec!["h2"], names(before.by_ref()));
+        // An iterator whose root is removed doesn't move.
+        assert_eq!("p", name(&inside.reference_node()));
+    }
+}
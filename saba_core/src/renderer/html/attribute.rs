@@ -1,7 +1,14 @@
+use alloc::format;
 use alloc::string::String;
 
+/// https://dom.spec.whatwg.org/#concept-attribute
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Attribute {
+    /// https://dom.spec.whatwg.org/#concept-attribute-namespace
+    namespace: Option<String>,
+    /// https://dom.spec.whatwg.org/#concept-attribute-namespace-prefix
+    prefix: Option<String>,
+    /// https://dom.spec.whatwg.org/#concept-attribute-local-name
     name: String,
     value: String,
 }
@@ -9,11 +16,36 @@ pub struct Attribute {
 impl Attribute {
     pub fn new() -> Self {
         Self {
+            namespace: None,
+            prefix: None,
             name: String::new(),
             value: String::new(),
         }
     }
 
+    pub fn with_value(name: &str, value: &str) -> Self {
+        Self {
+            namespace: None,
+            prefix: None,
+            name: String::from(name),
+            value: String::from(value),
+        }
+    }
+
+    pub fn with_namespace(
+        namespace: Option<&str>,
+        prefix: Option<&str>,
+        local_name: &str,
+        value: &str,
+    ) -> Self {
+        Self {
+            namespace: namespace.map(String::from),
+            prefix: prefix.map(String::from),
+            name: String::from(local_name),
+            value: String::from(value),
+        }
+    }
+
     pub fn add_char(&mut self, ch: char, is_name: bool) {
         if is_name {
             self.name.push(ch);
@@ -22,13 +54,34 @@ impl Attribute {
         }
     }
 
+    /// Returns the qualified name, i.e. the local name with the prefix.
+    /// https://dom.spec.whatwg.org/#concept-attribute-qualified-name
     pub fn name(&self) -> String {
+        match self.prefix {
+            Some(ref prefix) => format!("{}:{}", prefix, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    pub fn local_name(&self) -> String {
         self.name.clone()
     }
 
+    pub fn namespace(&self) -> Option<String> {
+        self.namespace.clone()
+    }
+
+    pub fn prefix(&self) -> Option<String> {
+        self.prefix.clone()
+    }
+
     pub fn value(&self) -> String {
         self.value.clone()
     }
+
+    pub fn set_value(&mut self, value: &str) {
+        self.value = String::from(value);
+    }
 }
 
 impl Default for Attribute {
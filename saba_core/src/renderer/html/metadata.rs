@@ -0,0 +1,279 @@
+//! Metadata of an HTML document, such as its title and the resources it
+//! links to.
+//! https://html.spec.whatwg.org/multipage/semantics.html#document-metadata
+
+use crate::renderer::dom::api::inclusive_descendants;
+use crate::renderer::dom::api::text_content;
+use crate::renderer::dom::node::Element;
+use crate::renderer::dom::node::ElementKind;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::HTML_NAMESPACE;
+use crate::url::Url;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// A `<meta http-equiv=refresh>` directive.
+/// https://html.spec.whatwg.org/multipage/semantics.html#attr-meta-http-equiv-refresh
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Refresh {
+    /// Seconds to wait before refreshing.
+    pub delay: u32,
+    /// The URL to navigate to, or `None` to reload the document.
+    pub url: Option<String>,
+}
+
+/// All URLs are resolved against `base_url`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentMetadata {
+    /// https://html.spec.whatwg.org/multipage/dom.html#document.title
+    pub title: Option<String>,
+    /// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#document-base-url
+    pub base_url: String,
+    /// https://html.spec.whatwg.org/multipage/semantics.html#attr-meta-charset
+    pub charset: Option<String>,
+    /// https://html.spec.whatwg.org/multipage/semantics.html#meta-description
+    pub description: Option<String>,
+    pub refresh: Option<Refresh>,
+    /// https://html.spec.whatwg.org/multipage/links.html#rel-icon
+    pub icons: Vec<String>,
+    /// https://html.spec.whatwg.org/multipage/links.html#link-type-stylesheet
+    pub stylesheets: Vec<String>,
+    /// The `href` of every `a` element in document order.
+    pub links: Vec<String>,
+}
+
+impl DocumentMetadata {
+    /// Collects the metadata of `document`, which was loaded from
+    /// `document_url`.
+    pub fn extract(document: &Rc<RefCell<Node>>, document_url: &str) -> Self {
+        let elements: Vec<Element> = inclusive_descendants(document)
+            .filter_map(|n| n.borrow().get_element())
+            .collect();
+        let of_kind = |kind: ElementKind| elements.iter().filter(move |e| e.kind() == kind);
+        let metas = || of_kind(ElementKind::Meta);
+
+        // https://html.spec.whatwg.org/multipage/semantics.html#set-the-frozen-base-url
+        let document_url = Url::new(document_url.to_string());
+        let base_url = match of_kind(ElementKind::Base).find_map(|e| e.get_attribute("href")) {
+            Some(href) => document_url.join(&href),
+            None => document_url.join(""),
+        };
+        let base = Url::new(base_url.clone());
+
+        // An SVG `title` doesn't title the document.
+        let title = inclusive_descendants(document)
+            .find(|n| {
+                n.borrow().get_element().is_some_and(|e| {
+                    e.kind() == ElementKind::Title
+                        && e.namespace_uri().as_deref() == Some(HTML_NAMESPACE)
+                })
+            })
+            .and_then(|n| text_content(&n))
+            .map(|s| strip_and_collapse_whitespace(&s));
+
+        let charset = metas().find_map(|e| e.get_attribute("charset"));
+
+        let description = metas()
+            .find(|e| attribute_eq_ignore_case(e, "name", "description"))
+            .and_then(|e| e.get_attribute("content"));
+
+        let refresh = metas()
+            .find(|e| attribute_eq_ignore_case(e, "http-equiv", "refresh"))
+            .and_then(|e| e.get_attribute("content"))
+            .and_then(|content| parse_refresh(&content))
+            .map(|(delay, url)| Refresh {
+                delay,
+                url: url.map(|u| base.join(&u)),
+            });
+
+        let links_with_rel = |link_type: &'static str| {
+            of_kind(ElementKind::Link)
+                .filter(move |e| has_link_type(e, link_type))
+                .filter_map(|e| e.get_attribute("href"))
+                .map(|href| base.join(&href))
+                .collect::<Vec<String>>()
+        };
+        let icons = links_with_rel("icon");
+        let stylesheets = links_with_rel("stylesheet");
+
+        let links = of_kind(ElementKind::A)
+            .filter_map(|e| e.get_attribute("href"))
+            .map(|href| base.join(&href))
+            .collect();
+
+        Self {
+            title,
+            base_url,
+            charset,
+            description,
+            refresh,
+            icons,
+            stylesheets,
+            links,
+        }
+    }
+}
+
+fn attribute_eq_ignore_case(element: &Element, name: &str, value: &str) -> bool {
+    match element.get_attribute(name) {
+        Some(v) => v.eq_ignore_ascii_case(value),
+        None => false,
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/links.html#attr-link-rel
+pub(crate) fn has_link_type(element: &Element, link_type: &str) -> bool {
+    match element.get_attribute("rel") {
+        Some(rel) => rel
+            .split_ascii_whitespace()
+            .any(|t| t.eq_ignore_ascii_case(link_type)),
+        None => false,
+    }
+}
+
+/// https://infra.spec.whatwg.org/#strip-and-collapse-ascii-whitespace
+fn strip_and_collapse_whitespace(s: &str) -> String {
+    s.split_ascii_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Parses the `content` of a refresh directive, e.g. `5; url=/next`.
+/// https://html.spec.whatwg.org/multipage/semantics.html#shared-declarative-refresh-steps
+fn parse_refresh(input: &str) -> Option<(u32, Option<String>)> {
+    let input = input.trim_start_matches(|c: char| c.is_ascii_whitespace());
+
+    let digits_end = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let delay = if digits_end == 0 {
+        if !input.starts_with('.') {
+            return None;
+        }
+        0
+    } else {
+        input[..digits_end].parse().ok()?
+    };
+    let rest = input[digits_end..].trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    let rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    let rest = match rest.strip_prefix([';', ',']) {
+        Some(rest) => rest,
+        None if rest.is_empty() => return Some((delay, None)),
+        None => return None,
+    };
+    let rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    if rest.is_empty() {
+        return Some((delay, None));
+    }
+
+    // Skip an optional `url=` prefix.
+    let mut url = rest;
+    if url.get(..3).is_some_and(|s| s.eq_ignore_ascii_case("url")) {
+        let after = url[3..].trim_start_matches(|c: char| c.is_ascii_whitespace());
+        if let Some(after) = after.strip_prefix('=') {
+            url = after.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        }
+    }
+
+    let url = match url.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let url = &url[1..];
+            match url.find(quote) {
+                Some(end) => &url[..end],
+                None => url,
+            }
+        }
+        _ => url.trim_end_matches(|c: char| c.is_ascii_whitespace()),
+    };
+    Some((delay, Some(url.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::html::parser::tests::parse;
+    use alloc::vec;
+
+    #[test]
+    fn test_extract() {
+        let window = parse(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>
+    Test   &amp; Page
+  </title>
+  <base href="/docs/">
+  <meta name="Description" content="A test page">
+  <meta http-equiv="Refresh" content="5; URL='next.html'">
+  <link rel="stylesheet" href="style.css">
+  <link rel="shortcut icon" href="/favicon.ico">
+  <link rel="preload" href="font.woff">
+</head>
+<body>
+  <svg><title>Icon</title></svg>
+  <p><a href="a.html">A</a> <a>no href</a> <a href="https://example.org/">B</a></p>
+  <h1><a href="../up.html">Up</a></h1>
+</body>
+</html>"#,
+        );
+        let document = window.borrow().document();
+
+        let metadata = DocumentMetadata::extract(&document, "http://example.com/index.html");
+        assert_eq!(
+            DocumentMetadata {
+                title: Some("Test & Page".to_string()),
+                base_url: "http://example.com/docs/".to_string(),
+                charset: Some("utf-8".to_string()),
+                description: Some("A test page".to_string()),
+                refresh: Some(Refresh {
+                    delay: 5,
+                    url: Some("http://example.com/docs/next.html".to_string()),
+                }),
+                icons: vec!["http://example.com/favicon.ico".to_string()],
+                stylesheets: vec!["http://example.com/docs/style.css".to_string()],
+                links: vec![
+                    "http://example.com/docs/a.html".to_string(),
+                    "https://example.org/".to_string(),
+                    "http://example.com/up.html".to_string(),
+                ],
+            },
+            metadata
+        );
+    }
+
+    #[test]
+    fn test_extract_empty() {
+        let window = parse("<p>text</p>");
+        let document = window.borrow().document();
+        let metadata = DocumentMetadata::extract(&document, "http://example.com/a/b.html#x");
+        assert_eq!(None, metadata.title);
+        assert_eq!("http://example.com/a/b.html", metadata.base_url);
+        assert_eq!(None, metadata.charset);
+        assert_eq!(None, metadata.description);
+        assert_eq!(None, metadata.refresh);
+        assert!(metadata.icons.is_empty());
+        assert!(metadata.stylesheets.is_empty());
+        assert!(metadata.links.is_empty());
+    }
+
+    #[test]
+    fn test_parse_refresh() {
+        assert_eq!(Some((0, None)), parse_refresh("0"));
+        assert_eq!(Some((3, None)), parse_refresh(" 3.5 ; "));
+        assert_eq!(
+            Some((1, Some("a.html".to_string()))),
+            parse_refresh("1,a.html")
+        );
+        assert_eq!(
+            Some((2, Some("/b c".to_string()))),
+            parse_refresh("2; url = \"/b c\" ignored")
+        );
+        assert_eq!(Some((0, Some("x".to_string()))), parse_refresh(".5;url=x "));
+        assert_eq!(None, parse_refresh("soon"));
+        assert_eq!(None, parse_refresh("5 url=x"));
+    }
+}
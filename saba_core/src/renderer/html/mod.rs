@@ -1,3 +1,5 @@
 pub mod attribute;
+pub mod metadata;
 pub mod parser;
+pub mod serializer;
 pub mod token;
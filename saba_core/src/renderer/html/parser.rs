@@ -0,0 +1,866 @@
+//! https://html.spec.whatwg.org/multipage/parsing.html#tree-construction
+
+use crate::renderer::dom::api::append_child;
+use crate::renderer::dom::node::Element;
+use crate::renderer::dom::node::ElementKind;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use crate::renderer::dom::node::Window;
+use crate::renderer::dom::node::HTML_NAMESPACE;
+use crate::renderer::dom::node::MATHML_NAMESPACE;
+use crate::renderer::dom::node::SVG_NAMESPACE;
+use crate::renderer::dom::node::XLINK_NAMESPACE;
+use crate::renderer::dom::node::XMLNS_NAMESPACE;
+use crate::renderer::dom::node::XML_NAMESPACE;
+use crate::renderer::html::attribute::Attribute;
+use crate::renderer::html::token::HtmlToken;
+use crate::renderer::html::token::HtmlTokenizer;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::str::FromStr;
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#the-insertion-mode
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InsertionMode {
+    /// https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
+    Initial,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#the-before-html-insertion-mode
+    BeforeHtml,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#the-before-head-insertion-mode
+    BeforeHead,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inhead
+    InHead,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#the-after-head-insertion-mode
+    AfterHead,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inbody
+    InBody,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-incdata
+    Text,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-afterbody
+    AfterBody,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#the-after-after-body-insertion-mode
+    AfterAfterBody,
+}
+
+/// Builds a DOM tree from the tokens of an `HtmlTokenizer`.
+///
+/// Only the elements in `ElementKind` are supported. Tags of other elements
+/// are dropped while their contents are kept.
+pub struct HtmlParser {
+    window: Rc<RefCell<Window>>,
+    mode: InsertionMode,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#original-insertion-mode
+    original_insertion_mode: InsertionMode,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#the-stack-of-open-elements
+    stack_of_open_elements: Vec<Rc<RefCell<Node>>>,
+    t: HtmlTokenizer,
+}
+
+impl HtmlParser {
+    pub fn new(t: HtmlTokenizer) -> Self {
+        let window = Rc::new(RefCell::new(Window::new()));
+        window
+            .borrow()
+            .document()
+            .borrow_mut()
+            .set_window(Rc::downgrade(&window));
+
+        Self {
+            window,
+            mode: InsertionMode::Initial,
+            original_insertion_mode: InsertionMode::Initial,
+            stack_of_open_elements: Vec::new(),
+            t,
+        }
+    }
+
+    fn document(&self) -> Rc<RefCell<Node>> {
+        self.window.borrow().document()
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#current-node
+    fn current_node(&self) -> Rc<RefCell<Node>> {
+        match self.stack_of_open_elements.last() {
+            Some(n) => n.clone(),
+            None => self.document(),
+        }
+    }
+
+    fn append(&self, parent: &Rc<RefCell<Node>>, node: Rc<RefCell<Node>>) {
+        append_child(parent, node).expect("the parser inserted a node at an invalid position");
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-comment
+    fn insert_comment(&self, data: String, parent: &Rc<RefCell<Node>>) {
+        self.append(
+            parent,
+            Rc::new(RefCell::new(Node::new(NodeKind::Comment(data)))),
+        );
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-an-html-element
+    fn insert_element(&mut self, tag: &str, attributes: Vec<Attribute>) {
+        let node = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+            tag, attributes,
+        )))));
+        self.append(&self.current_node(), node.clone());
+        self.stack_of_open_elements.push(node);
+    }
+
+    /// Inserts an element in `namespace`, e.g. `svg`, and pops it again if its
+    /// tag is self-closing.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-foreign-element
+    fn insert_foreign_element(
+        &mut self,
+        tag: &str,
+        attributes: &[Attribute],
+        namespace: &str,
+        self_closing: bool,
+    ) {
+        let attributes = attributes.iter().map(adjust_foreign_attribute).collect();
+        let node = Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new_ns(
+            Some(namespace),
+            None,
+            tag,
+            attributes,
+        )))));
+        self.append(&self.current_node(), node.clone());
+        if !self_closing {
+            self.stack_of_open_elements.push(node);
+        }
+    }
+
+    /// Returns the namespace of the current node if it is not an HTML element.
+    fn foreign_namespace(&self) -> Option<String> {
+        let current = self.current_node();
+        let namespace = current
+            .borrow()
+            .get_element()
+            .and_then(|e| e.namespace_uri());
+        namespace.filter(|ns| ns != HTML_NAMESPACE)
+    }
+
+    /// Appends `c` to the text node at the end of the current node, creating
+    /// the text node if needed.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-character
+    fn insert_char(&self, c: char) {
+        let current = self.current_node();
+        if current.borrow().kind() == NodeKind::Document {
+            return;
+        }
+
+        let last_child = current.borrow().last_child().upgrade();
+        if let Some(last_child) = last_child {
+            if let NodeKind::Text(ref mut s) = last_child.borrow_mut().kind {
+                s.push(c);
+                return;
+            }
+        }
+
+        let mut s = String::new();
+        s.push(c);
+        self.append(
+            &current,
+            Rc::new(RefCell::new(Node::new(NodeKind::Text(s)))),
+        );
+    }
+
+    fn contain_in_stack(&self, kind: ElementKind) -> bool {
+        self.stack_of_open_elements
+            .iter()
+            .any(|n| n.borrow().element_kind() == Some(kind))
+    }
+
+    /// Pops elements until an element of `kind` has been popped.
+    fn pop_until(&mut self, kind: ElementKind) {
+        while let Some(node) = self.stack_of_open_elements.pop() {
+            if node.borrow().element_kind() == Some(kind) {
+                return;
+            }
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#close-a-p-element
+    fn close_p_element(&mut self) {
+        if self.contain_in_stack(ElementKind::P) {
+            self.pop_until(ElementKind::P);
+        }
+    }
+
+    fn is_whitespace(c: char) -> bool {
+        c == ' ' || c == '\t' || c == '\n' || c == '\x0c' || c == '\r'
+    }
+
+    fn element_kind(tag: &str) -> Option<ElementKind> {
+        ElementKind::from_str(tag).ok()
+    }
+
+    pub fn construct_tree(&mut self) -> Rc<RefCell<Window>> {
+        let mut token = self.t.next();
+        // The tokenizer stops without an end-of-file token when the input
+        // ends right after a token.
+        let eof = HtmlToken::Eof;
+
+        loop {
+            let t = token.as_ref().unwrap_or(&eof);
+            match self.mode {
+                InsertionMode::Initial => {
+                    match t {
+                        HtmlToken::Char(c) if Self::is_whitespace(*c) => {
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Comment(data) => {
+                            self.insert_comment(data.clone(), &self.document());
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Doctype {
+                            name,
+                            public_id,
+                            system_id,
+                        } => {
+                            let doctype = Node::new(NodeKind::DocumentType {
+                                name: name.clone(),
+                                public_id: public_id.clone(),
+                                system_id: system_id.clone(),
+                            });
+                            self.append(&self.document(), Rc::new(RefCell::new(doctype)));
+                            self.mode = InsertionMode::BeforeHtml;
+                            token = self.t.next();
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    self.mode = InsertionMode::BeforeHtml;
+                }
+                InsertionMode::BeforeHtml => {
+                    match t {
+                        HtmlToken::Doctype { .. } => {
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Comment(data) => {
+                            self.insert_comment(data.clone(), &self.document());
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Char(c) if Self::is_whitespace(*c) => {
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::StartTag {
+                            tag, attributes, ..
+                        } if tag == "html" => {
+                            self.insert_element(tag, attributes.clone());
+                            self.mode = InsertionMode::BeforeHead;
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::EndTag { tag }
+                            if !matches!(tag.as_str(), "head" | "body" | "html") =>
+                        {
+                            token = self.t.next();
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    self.insert_element("html", Vec::new());
+                    self.mode = InsertionMode::BeforeHead;
+                }
+                InsertionMode::BeforeHead => {
+                    match t {
+                        HtmlToken::Char(c) if Self::is_whitespace(*c) => {
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Comment(data) => {
+                            self.insert_comment(data.clone(), &self.current_node());
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Doctype { .. } => {
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::StartTag {
+                            tag, attributes, ..
+                        } if tag == "head" => {
+                            self.insert_element(tag, attributes.clone());
+                            self.mode = InsertionMode::InHead;
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::EndTag { tag }
+                            if !matches!(tag.as_str(), "head" | "body" | "html") =>
+                        {
+                            token = self.t.next();
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    self.insert_element("head", Vec::new());
+                    self.mode = InsertionMode::InHead;
+                }
+                InsertionMode::InHead => {
+                    match t {
+                        HtmlToken::Char(c) if Self::is_whitespace(*c) => {
+                            self.insert_char(*c);
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Comment(data) => {
+                            self.insert_comment(data.clone(), &self.current_node());
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Doctype { .. } => {
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::StartTag {
+                            tag, attributes, ..
+                        } if tag == "style" || tag == "script" => {
+                            self.insert_element(tag, attributes.clone());
+                            self.t.switch_to_script_data(tag);
+                            self.original_insertion_mode = self.mode;
+                            self.mode = InsertionMode::Text;
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::StartTag {
+                            tag, attributes, ..
+                        } if tag == "title" => {
+                            self.insert_element(tag, attributes.clone());
+                            self.t.switch_to_rcdata(tag);
+                            self.original_insertion_mode = self.mode;
+                            self.mode = InsertionMode::Text;
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::StartTag {
+                            tag, attributes, ..
+                        } if matches!(tag.as_str(), "base" | "link" | "meta") => {
+                            self.insert_element(tag, attributes.clone());
+                            self.stack_of_open_elements.pop();
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::EndTag { tag } if tag == "head" => {
+                            self.pop_until(ElementKind::Head);
+                            self.mode = InsertionMode::AfterHead;
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::EndTag { tag } if !matches!(tag.as_str(), "body" | "html") => {
+                            token = self.t.next();
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    self.pop_until(ElementKind::Head);
+                    self.mode = InsertionMode::AfterHead;
+                }
+                InsertionMode::AfterHead => {
+                    match t {
+                        HtmlToken::Char(c) if Self::is_whitespace(*c) => {
+                            self.insert_char(*c);
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Comment(data) => {
+                            self.insert_comment(data.clone(), &self.current_node());
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Doctype { .. } => {
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::StartTag {
+                            tag, attributes, ..
+                        } if tag == "body" => {
+                            self.insert_element(tag, attributes.clone());
+                            self.mode = InsertionMode::InBody;
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::EndTag { tag } if !matches!(tag.as_str(), "body" | "html") => {
+                            token = self.t.next();
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    self.insert_element("body", Vec::new());
+                    self.mode = InsertionMode::InBody;
+                }
+                InsertionMode::InBody => match t {
+                    HtmlToken::Char(c) => {
+                        self.insert_char(*c);
+                        token = self.t.next();
+                    }
+                    HtmlToken::Comment(data) => {
+                        self.insert_comment(data.clone(), &self.current_node());
+                        token = self.t.next();
+                    }
+                    HtmlToken::Doctype { .. } => {
+                        token = self.t.next();
+                    }
+                    HtmlToken::StartTag {
+                        tag,
+                        self_closing,
+                        attributes,
+                    } if self.foreign_namespace().is_some() => {
+                        // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inforeign
+                        if matches!(tag.as_str(), "body" | "head" | "h1" | "h2" | "meta" | "p") {
+                            // An HTML element breaks out of the foreign content.
+                            while self.foreign_namespace().is_some() {
+                                self.stack_of_open_elements.pop();
+                            }
+                            continue;
+                        }
+                        if Self::element_kind(tag).is_some() {
+                            let namespace = self.foreign_namespace().unwrap_or_default();
+                            self.insert_foreign_element(tag, attributes, &namespace, *self_closing);
+                        }
+                        token = self.t.next();
+                    }
+                    HtmlToken::StartTag {
+                        tag,
+                        self_closing,
+                        attributes,
+                    } => {
+                        match tag.as_str() {
+                            "html" | "head" | "body" => {}
+                            "svg" => {
+                                self.insert_foreign_element(
+                                    tag,
+                                    attributes,
+                                    SVG_NAMESPACE,
+                                    *self_closing,
+                                );
+                            }
+                            "math" => {
+                                self.insert_foreign_element(
+                                    tag,
+                                    attributes,
+                                    MATHML_NAMESPACE,
+                                    *self_closing,
+                                );
+                            }
+                            "style" | "script" => {
+                                self.insert_element(tag, attributes.clone());
+                                self.t.switch_to_script_data(tag);
+                                self.original_insertion_mode = self.mode;
+                                self.mode = InsertionMode::Text;
+                            }
+                            "title" => {
+                                self.insert_element(tag, attributes.clone());
+                                self.t.switch_to_rcdata(tag);
+                                self.original_insertion_mode = self.mode;
+                                self.mode = InsertionMode::Text;
+                            }
+                            "base" | "link" | "meta" => {
+                                self.insert_element(tag, attributes.clone());
+                                self.stack_of_open_elements.pop();
+                            }
+                            "p" | "h1" | "h2" => {
+                                self.close_p_element();
+                                self.insert_element(tag, attributes.clone());
+                            }
+                            _ => {
+                                if Self::element_kind(tag).is_some() {
+                                    self.insert_element(tag, attributes.clone());
+                                }
+                            }
+                        }
+                        token = self.t.next();
+                    }
+                    HtmlToken::EndTag { tag } => {
+                        match tag.as_str() {
+                            "body" => {
+                                if self.contain_in_stack(ElementKind::Body) {
+                                    self.mode = InsertionMode::AfterBody;
+                                }
+                            }
+                            "html" => {
+                                if self.contain_in_stack(ElementKind::Body) {
+                                    self.mode = InsertionMode::AfterBody;
+                                    continue;
+                                }
+                            }
+                            _ => {
+                                if let Some(kind) = Self::element_kind(tag) {
+                                    if self.contain_in_stack(kind) {
+                                        self.pop_until(kind);
+                                    }
+                                }
+                            }
+                        }
+                        token = self.t.next();
+                    }
+                    HtmlToken::Eof => return self.window.clone(),
+                },
+                InsertionMode::Text => match t {
+                    HtmlToken::Char(c) => {
+                        self.insert_char(*c);
+                        token = self.t.next();
+                    }
+                    HtmlToken::Eof => {
+                        self.stack_of_open_elements.pop();
+                        self.mode = self.original_insertion_mode;
+                    }
+                    _ => {
+                        self.stack_of_open_elements.pop();
+                        self.mode = self.original_insertion_mode;
+                        token = self.t.next();
+                    }
+                },
+                InsertionMode::AfterBody => {
+                    match t {
+                        HtmlToken::Char(c) if Self::is_whitespace(*c) => {
+                            self.insert_char(*c);
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Comment(data) => {
+                            // The comment becomes the last child of the html element.
+                            let html = match self.stack_of_open_elements.first() {
+                                Some(html) => html.clone(),
+                                None => self.document(),
+                            };
+                            self.insert_comment(data.clone(), &html);
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Doctype { .. } => {
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::EndTag { tag } if tag == "html" => {
+                            self.mode = InsertionMode::AfterAfterBody;
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Eof => return self.window.clone(),
+                        _ => {}
+                    }
+                    self.mode = InsertionMode::InBody;
+                }
+                InsertionMode::AfterAfterBody => {
+                    match t {
+                        HtmlToken::Comment(data) => {
+                            self.insert_comment(data.clone(), &self.document());
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Char(c) if Self::is_whitespace(*c) => {
+                            self.insert_char(*c);
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Doctype { .. } => {
+                            token = self.t.next();
+                            continue;
+                        }
+                        HtmlToken::Eof => return self.window.clone(),
+                        _ => {}
+                    }
+                    self.mode = InsertionMode::InBody;
+                }
+            }
+        }
+    }
+}
+
+/// Moves the `xlink:`, `xml:` and `xmlns` attributes of a foreign element
+/// into their namespaces.
+/// https://html.spec.whatwg.org/multipage/parsing.html#adjust-foreign-attributes
+fn adjust_foreign_attribute(attr: &Attribute) -> Attribute {
+    let name = attr.name();
+    let (prefix, local_name, namespace) = match name.as_str() {
+        "xlink:actuate" | "xlink:arcrole" | "xlink:href" | "xlink:role" | "xlink:show"
+        | "xlink:title" | "xlink:type" => (Some("xlink"), &name[6..], XLINK_NAMESPACE),
+        "xml:lang" | "xml:space" => (Some("xml"), &name[4..], XML_NAMESPACE),
+        "xmlns" => (None, "xmlns", XMLNS_NAMESPACE),
+        "xmlns:xlink" => (Some("xmlns"), "xlink", XMLNS_NAMESPACE),
+        _ => return attr.clone(),
+    };
+    Attribute::with_namespace(Some(namespace), prefix, local_name, &attr.value())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::alloc::string::ToString;
+    use alloc::vec;
+
+    /// Parses `html` and returns the window of the document.
+    pub(crate) fn parse(html: &str) -> Rc<RefCell<Window>> {
+        HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree()
+    }
+
+    /// Returns the body of `document`, the last child of its root element.
+    pub(crate) fn body(document: &Rc<RefCell<Node>>) -> Rc<RefCell<Node>> {
+        let html = document.borrow().first_child().unwrap();
+        let body = html.borrow().last_child().upgrade().unwrap();
+        body
+    }
+
+    fn element(name: &str) -> NodeKind {
+        NodeKind::Element(Element::new(name, Vec::new()))
+    }
+
+    fn children(node: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+        node.borrow().children().collect()
+    }
+
+    #[test]
+    fn test_empty() {
+        // The end of the input implies html, head and body.
+        for html in ["", "<html>", "<html><head>"] {
+            let window = parse(html);
+            let document = window.borrow().document();
+            let html = document.borrow().first_child().unwrap();
+            assert_eq!(element("html"), html.borrow().kind());
+            let html_children = children(&html);
+            assert_eq!(2, html_children.len());
+            assert_eq!(element("head"), html_children[0].borrow().kind());
+            assert_eq!(element("body"), html_children[1].borrow().kind());
+        }
+    }
+
+    #[test]
+    fn test_implied_elements() {
+        let window = parse("<p>Hello</p>");
+        let document = window.borrow().document();
+        let html = document.borrow().first_child().unwrap();
+        assert_eq!(element("html"), html.borrow().kind());
+        let html_children = children(&html);
+        assert_eq!(element("head"), html_children[0].borrow().kind());
+        assert_eq!(element("body"), html_children[1].borrow().kind());
+        let p = html_children[1].borrow().first_child().unwrap();
+        assert_eq!(element("p"), p.borrow().kind());
+        let text = p.borrow().first_child().unwrap();
+        let kind = text.borrow().kind();
+        match kind {
+            NodeKind::Text(s) => assert_eq!("Hello", s),
+            k => panic!("unexpected node {:?}", k),
+        }
+    }
+
+    #[test]
+    fn test_doctype_and_comments() {
+        let window = parse(
+            "<!DOCTYPE html><!--a--><html><head><!--b--></head><body><!--c--></body></html><!--d-->",
+        );
+        let document = window.borrow().document();
+        let nodes = children(&document);
+        assert_eq!(4, nodes.len());
+        match nodes[0].borrow().kind() {
+            NodeKind::DocumentType {
+                name,
+                public_id,
+                system_id,
+            } => {
+                assert_eq!("html", name);
+                assert!(public_id.is_empty());
+                assert!(system_id.is_empty());
+            }
+            k => panic!("unexpected node {:?}", k),
+        }
+        let comment = |node: &Rc<RefCell<Node>>| match node.borrow().kind() {
+            NodeKind::Comment(data) => data,
+            k => panic!("unexpected node {:?}", k),
+        };
+        assert_eq!("a", comment(&nodes[1]));
+        assert_eq!(element("html"), nodes[2].borrow().kind());
+        assert_eq!("d", comment(&nodes[3]));
+
+        let html_children = children(&nodes[2]);
+        assert_eq!(
+            "b",
+            comment(&html_children[0].borrow().first_child().unwrap())
+        );
+        assert_eq!(
+            "c",
+            comment(&html_children[1].borrow().first_child().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_comment_after_body() {
+        let window = parse("<body></body><!--x-->");
+        let document = window.borrow().document();
+        let html = document.borrow().first_child().unwrap();
+        let last = html.borrow().last_child().upgrade().unwrap();
+        assert_eq!(NodeKind::Comment(String::new()), last.borrow().kind());
+    }
+
+    #[test]
+    fn test_script_and_style() {
+        let window =
+            parse("<html><head><style>a > b {}</style><script>if (a<b) {}</script></head></html>");
+        let document = window.borrow().document();
+        let html = document.borrow().first_child().unwrap();
+        let head = html.borrow().first_child().unwrap();
+        let texts: Vec<String> = children(&head)
+            .iter()
+            .map(
+                |n| match n.borrow().first_child().unwrap().borrow().kind() {
+                    NodeKind::Text(s) => s,
+                    k => panic!("unexpected node {:?}", k),
+                },
+            )
+            .collect();
+        assert_eq!(vec!["a > b {}", "if (a<b) {}"], texts);
+    }
+
+    #[test]
+    fn test_body() {
+        let window = parse(
+            "<html>\n<body>\n  <h1>Test Page</h1>\n  <p>Hello <a href=\"/\">World</a>!<p>Second</body>\n</html>\n",
+        );
+        let document = window.borrow().document();
+        let html = document.borrow().first_child().unwrap();
+        let body = html.borrow().last_child().upgrade().unwrap();
+        let kinds: Vec<NodeKind> = children(&body).iter().map(|n| n.borrow().kind()).collect();
+        assert_eq!(
+            vec![
+                NodeKind::Text(String::new()),
+                element("h1"),
+                NodeKind::Text(String::new()),
+                element("p"),
+                element("p"),
+            ],
+            kinds
+        );
+        let p = children(&body)[3].clone();
+        let a = children(&p)[1].clone();
+        assert_eq!(
+            Some("/".to_string()),
+            a.borrow().get_element().unwrap().get_attribute("href")
+        );
+    }
+
+    #[test]
+    fn test_head_metadata_elements() {
+        let window = parse(
+            "<head><meta charset=utf-8><title>a &amp; <b></title><link rel=icon href=i.png><base href=/x/></head><body><meta name=x></body>",
+        );
+        let document = window.borrow().document();
+        let html = document.borrow().first_child().unwrap();
+        let head = html.borrow().first_child().unwrap();
+        let kinds: Vec<NodeKind> = children(&head).iter().map(|n| n.borrow().kind()).collect();
+        assert_eq!(
+            vec![
+                element("meta"),
+                element("title"),
+                element("link"),
+                element("base"),
+            ],
+            kinds
+        );
+        let title = children(&head)[1].clone();
+        let text = title.borrow().first_child().unwrap();
+        assert_eq!(NodeKind::Text("a & <b>".to_string()), text.borrow().kind());
+
+        let body = html.borrow().last_child().upgrade().unwrap();
+        let meta = body.borrow().first_child().unwrap();
+        assert_eq!(element("meta"), meta.borrow().kind());
+        assert!(meta.borrow().first_child().is_none());
+    }
+
+    #[test]
+    fn test_title_end_tag() {
+        for (html, expected) in [
+            ("<title>a</p>b</title><p>y</p>", "a</p>b"),
+            ("<title>a</title ><p>x</p>", "a"),
+            ("<title>a</TITLE/><p>x</p>", "a"),
+        ] {
+            let window = parse(html);
+            let document = window.borrow().document();
+            let html = document.borrow().first_child().unwrap();
+            let head = html.borrow().first_child().unwrap();
+            let title = head.borrow().first_child().unwrap();
+            assert_eq!(element("title"), title.borrow().kind());
+            let text = title.borrow().first_child().unwrap();
+            assert_eq!(NodeKind::Text(expected.to_string()), text.borrow().kind());
+
+            let body = html.borrow().last_child().upgrade().unwrap();
+            let kinds: Vec<NodeKind> = children(&body).iter().map(|n| n.borrow().kind()).collect();
+            assert_eq!(vec![element("p")], kinds);
+        }
+    }
+
+    #[test]
+    fn test_foreign_content() {
+        let window = parse(
+            "<body><svg xmlns:xlink=\"http://www.w3.org/1999/xlink\" class=c><a xlink:href=\"#x\" xml:lang=\"en\"/><title>T</title></svg><p>after</p><math></math></body>",
+        );
+        let document = window.borrow().document();
+        let html = document.borrow().first_child().unwrap();
+        let body = html.borrow().last_child().upgrade().unwrap();
+        let body_children = children(&body);
+        assert_eq!(
+            vec![element("svg"), element("p"), element("math")],
+            body_children
+                .iter()
+                .map(|n| n.borrow().kind())
+                .collect::<Vec<NodeKind>>()
+        );
+
+        let svg = body_children[0].borrow().get_element().unwrap();
+        assert_eq!(Some(SVG_NAMESPACE.to_string()), svg.namespace_uri());
+        assert_eq!(Some("c".to_string()), svg.get_attribute_ns(None, "class"));
+        let xmlns = svg
+            .get_attribute_node_ns(Some(XMLNS_NAMESPACE), "xlink")
+            .unwrap();
+        assert_eq!("xmlns:xlink", xmlns.name());
+        assert_eq!(XLINK_NAMESPACE, xmlns.value());
+
+        // The self-closing `a` has no children, so `title` is its sibling.
+        let svg_children = children(&body_children[0]);
+        assert_eq!(vec![element("a"), element("title")], {
+            svg_children
+                .iter()
+                .map(|n| n.borrow().kind())
+                .collect::<Vec<NodeKind>>()
+        });
+        let a = svg_children[0].borrow().get_element().unwrap();
+        assert_eq!(Some(SVG_NAMESPACE.to_string()), a.namespace_uri());
+        assert_eq!(
+            Some("#x".to_string()),
+            a.get_attribute_ns(Some(XLINK_NAMESPACE), "href")
+        );
+        assert_eq!(
+            Some("en".to_string()),
+            a.get_attribute_ns(Some(XML_NAMESPACE), "lang")
+        );
+        let title = svg_children[1].borrow().get_element().unwrap();
+        assert_eq!(Some(SVG_NAMESPACE.to_string()), title.namespace_uri());
+
+        // `p` breaks out of the foreign content.
+        let p = body_children[1].borrow().get_element().unwrap();
+        assert_eq!(Some(HTML_NAMESPACE.to_string()), p.namespace_uri());
+        let math = body_children[2].borrow().get_element().unwrap();
+        assert_eq!(Some(MATHML_NAMESPACE.to_string()), math.namespace_uri());
+    }
+
+    #[test]
+    fn test_unknown_elements_are_dropped() {
+        let window = parse("<body><p><span>x</span></p></body>");
+        let document = window.borrow().document();
+        let html = document.borrow().first_child().unwrap();
+        let body = html.borrow().last_child().upgrade().unwrap();
+        let p = body.borrow().first_child().unwrap();
+        let text = p.borrow().first_child().unwrap();
+        let kind = text.borrow().kind();
+        match kind {
+            NodeKind::Text(s) => assert_eq!("x", s),
+            k => panic!("unexpected node {:?}", k),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments
+
+use crate::renderer::dom::node::Element;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use core::cell::RefCell;
+
+/// https://html.spec.whatwg.org/multipage/syntax.html#void-elements
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input",
+    "keygen", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements whose text children are serialized without escaping.
+const RAW_TEXT_ELEMENTS: &[&str] = &[
+    "style",
+    "script",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "plaintext",
+];
+
+/// Serializes the children of `node`.
+/// https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#dom-element-innerhtml
+pub fn inner_html(node: &Rc<RefCell<Node>>) -> String {
+    let mut s = String::new();
+    serialize_children(node, &mut s);
+    s
+}
+
+/// Serializes `node` and its descendants.
+/// https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#dom-element-outerhtml
+pub fn outer_html(node: &Rc<RefCell<Node>>) -> String {
+    let mut s = String::new();
+    serialize_node(node, &mut s);
+    s
+}
+
+/// Serializes a whole document, including its doctype and comments outside
+/// the document element.
+pub fn serialize_document(document: &Rc<RefCell<Node>>) -> String {
+    inner_html(document)
+}
+
+fn serialize_children(node: &Rc<RefCell<Node>>, s: &mut String) {
+    let children = node.borrow().children();
+    for child in children {
+        serialize_node(&child, s);
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments
+fn serialize_node(node: &Rc<RefCell<Node>>, s: &mut String) {
+    let kind = node.borrow().kind();
+    match kind {
+        NodeKind::Document | NodeKind::DocumentFragment => serialize_children(node, s),
+        NodeKind::Element(ref e) => {
+            let tag_name = e.kind().to_string();
+            serialize_start_tag(&tag_name, e, s);
+            if VOID_ELEMENTS.contains(&tag_name.as_str()) {
+                return;
+            }
+            serialize_children(node, s);
+            s.push_str("</");
+            s.push_str(&tag_name);
+            s.push('>');
+        }
+        NodeKind::Text(ref text) => {
+            let parent_name = node
+                .borrow()
+                .parent()
+                .upgrade()
+                .and_then(|p| p.borrow().element_kind())
+                .map(|k| k.to_string());
+            match parent_name {
+                Some(name) if RAW_TEXT_ELEMENTS.contains(&name.as_str()) => s.push_str(text),
+                _ => escape(text, false, s),
+            }
+        }
+        NodeKind::Comment(ref data) => {
+            s.push_str("<!--");
+            s.push_str(data);
+            s.push_str("-->");
+        }
+        NodeKind::ProcessingInstruction {
+            ref target,
+            ref data,
+        } => {
+            s.push_str("<?");
+            s.push_str(target);
+            s.push(' ');
+            s.push_str(data);
+            s.push('>');
+        }
+        NodeKind::DocumentType { ref name, .. } => {
+            s.push_str("<!DOCTYPE ");
+            s.push_str(name);
+            s.push('>');
+        }
+    }
+}
+
+fn serialize_start_tag(tag_name: &str, element: &Element, s: &mut String) {
+    s.push('<');
+    s.push_str(tag_name);
+    for attr in element.attributes() {
+        s.push(' ');
+        s.push_str(&attr.name());
+        s.push_str("=\"");
+        escape(&attr.value(), true, s);
+        s.push('"');
+    }
+    s.push('>');
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#escapingString
+fn escape(text: &str, attribute_mode: bool, s: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => s.push_str("&amp;"),
+            '\u{a0}' => s.push_str("&nbsp;"),
+            '"' if attribute_mode => s.push_str("&quot;"),
+            '<' if !attribute_mode => s.push_str("&lt;"),
+            '>' if !attribute_mode => s.push_str("&gt;"),
+            _ => s.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::dom::api::append_child;
+    use crate::renderer::html::attribute::Attribute;
+    use crate::renderer::html::parser::tests::parse;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn node(kind: NodeKind) -> Rc<RefCell<Node>> {
+        Rc::new(RefCell::new(Node::new(kind)))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let html = "<!DOCTYPE html><!--top--><html><head><style>p > a { }</style></head><body>\n<h1 id=\"t\" class=\"a b\">Title</h1>\n<p>a &amp; b &lt;c&gt; <a href=\"/x?a=1&amp;b=&quot;2&quot;\">link</a></p>\n<script>if (a < b && c) {}</script></body></html>";
+        let window = parse(html);
+        assert_eq!(html, serialize_document(&window.borrow().document()));
+    }
+
+    #[test]
+    fn test_round_trip_test_page() {
+        let html = "<html><head></head><body>\n  <h1>Test Page</h1>\n  <p>Hello World!</p>\n\n\n</body></html>";
+        let window = parse(html);
+        assert_eq!(html, serialize_document(&window.borrow().document()));
+    }
+
+    #[test]
+    fn test_round_trip_end_tags_in_script_data() {
+        for html in [
+            "<html><head><script>x=\"</p>\";y=1</script></head><body><p>z</p></body></html>",
+            "<html><head><script>a=\"</p \";</script></head><body><p>z</p></body></html>",
+            "<html><head><style>p{}</p> q{}</style></head><body><p>z</p></body></html>",
+        ] {
+            let window = parse(html);
+            assert_eq!(html, serialize_document(&window.borrow().document()));
+        }
+    }
+
+    #[test]
+    fn test_inner_and_outer_html() {
+        let p = node(NodeKind::Element(Element::new(
+            "p",
+            vec![Attribute::with_value("title", "\"q\" & \u{a0}<>")],
+        )));
+        append_child(&p, node(NodeKind::Text("1 < 2 & 3 > \u{a0}\"".to_string()))).unwrap();
+        append_child(&p, node(NodeKind::Comment(" c ".to_string()))).unwrap();
+
+        assert_eq!("1 &lt; 2 &amp; 3 &gt; &nbsp;\"<!-- c -->", inner_html(&p));
+        assert_eq!(
+            "<p title=\"&quot;q&quot; &amp; &nbsp;<>\">1 &lt; 2 &amp; 3 &gt; &nbsp;\"<!-- c --></p>",
+            outer_html(&p)
+        );
+    }
+
+    #[test]
+    fn test_raw_text() {
+        let style = node(NodeKind::Element(Element::new("style", Vec::new())));
+        append_child(&style, node(NodeKind::Text("a > b & c".to_string()))).unwrap();
+        assert_eq!("<style>a > b & c</style>", outer_html(&style));
+    }
+
+    #[test]
+    fn test_fragment_and_processing_instruction() {
+        let fragment = node(NodeKind::DocumentFragment);
+        append_child(
+            &fragment,
+            node(NodeKind::ProcessingInstruction {
+                target: "xml-stylesheet".to_string(),
+                data: "href=\"a.css\"".to_string(),
+            }),
+        )
+        .unwrap();
+        append_child(&fragment, node(NodeKind::Text("x".to_string()))).unwrap();
+        assert_eq!("<?xml-stylesheet href=\"a.css\">x", outer_html(&fragment));
+    }
+}
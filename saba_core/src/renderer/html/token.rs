@@ -10,6 +10,11 @@ pub struct HtmlTokenizer {
     latest_token: Option<HtmlToken>,
     input: Vec<char>,
     buf: String,
+    /// The state to return to once the temporary buffer has been flushed.
+    return_state: State,
+    /// The tag name of the last start tag emitted before switching to the
+    /// RCDATA or script data state, used to find an appropriate end tag.
+    last_start_tag: String,
 }
 
 impl HtmlTokenizer {
@@ -21,6 +26,8 @@ impl HtmlTokenizer {
             latest_token: None,
             input: html.chars().collect(),
             buf: String::new(),
+            return_state: State::Data,
+            last_start_tag: String::new(),
         }
     }
 
@@ -44,7 +51,165 @@ impl HtmlTokenizer {
 
     fn reconsume_input(&mut self) -> char {
         self.reconsume = false;
-        self.input[self.pos - 1]
+        self.input.get(self.pos - 1).copied().unwrap_or('\0')
+    }
+
+    /// Switches to the script data state. The tree builder calls this after
+    /// inserting a `script` or `style` element so that its contents are not
+    /// tokenized as markup. `tag` is the name of the inserted element, which
+    /// ends the script data section.
+    pub fn switch_to_script_data(&mut self, tag: &str) {
+        self.state = State::ScriptData;
+        self.last_start_tag = tag.to_ascii_lowercase();
+    }
+
+    /// Switches to the RCDATA state. The tree builder calls this after
+    /// inserting a `title` element so that its contents are not tokenized as
+    /// markup, while character references are still decoded. `tag` is the
+    /// name of the inserted element, which ends the RCDATA section.
+    pub fn switch_to_rcdata(&mut self, tag: &str) {
+        self.state = State::Rcdata;
+        self.last_start_tag = tag.to_ascii_lowercase();
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-end-tag-token
+    fn is_appropriate_end_tag(&self) -> bool {
+        match &self.latest_token {
+            Some(HtmlToken::EndTag { tag }) => *tag == self.last_start_tag,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the input from the current input character onwards
+    /// starts with `s`, ignoring ASCII case. If it does, the characters after
+    /// the current input character are consumed.
+    fn consume_if_starts_with(&mut self, s: &str) -> bool {
+        let start = self.pos - 1;
+        let len = s.chars().count();
+        if start + len > self.input.len() {
+            return false;
+        }
+        let matched = self.input[start..start + len]
+            .iter()
+            .zip(s.chars())
+            .all(|(a, b)| a.eq_ignore_ascii_case(&b));
+        if matched {
+            self.pos = start + len;
+        }
+        matched
+    }
+
+    /// Decodes a character reference after an `&` that has just been
+    /// consumed, `in_attribute` being true in an attribute value. Numeric
+    /// references and a subset of the named references are supported.
+    /// Returns `None` without consuming anything if the input doesn't start
+    /// with a known reference, in which case the `&` is taken literally.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#character-reference-state
+    fn consume_character_reference(&mut self, in_attribute: bool) -> Option<char> {
+        let rest = &self.input[self.pos..];
+
+        if rest.first() == Some(&'#') {
+            let hex = matches!(rest.get(1), Some('x') | Some('X'));
+            let start = if hex { 2 } else { 1 };
+            let radix = if hex { 16 } else { 10 };
+            let len = rest[start..]
+                .iter()
+                .take_while(|c| c.is_digit(radix))
+                .count();
+            if len == 0 {
+                return None;
+            }
+            let digits: String = rest[start..start + len].iter().collect();
+            let mut end = start + len;
+            // missing-semicolon-after-character-reference parse error
+            if rest.get(end) == Some(&';') {
+                end += 1;
+            }
+            let c = match u32::from_str_radix(&digits, radix)
+                .ok()
+                .and_then(char::from_u32)
+            {
+                Some('\0') | None => char::REPLACEMENT_CHARACTER,
+                Some(c) => c,
+            };
+            self.pos += end;
+            return Some(c);
+        }
+
+        // The longest matching name wins, e.g. `&notin;` over `&not`.
+        let mut matched: Option<(usize, char, bool)> = None;
+        let starts_with = |name: &str| rest.iter().take(name.len()).copied().eq(name.chars());
+        for (name, c) in NAMED_CHARACTER_REFERENCES {
+            let len = name.len();
+            if starts_with(name) && rest.get(len) == Some(&';') {
+                matched = matched
+                    .filter(|m| m.0 > len + 1)
+                    .or(Some((len + 1, *c, true)));
+            }
+        }
+        for (name, c) in LEGACY_CHARACTER_REFERENCES {
+            let len = name.len();
+            if starts_with(name) {
+                let candidate = match rest.get(len) == Some(&';') {
+                    true => (len + 1, *c, true),
+                    // missing-semicolon-after-character-reference parse error
+                    false => (len, *c, false),
+                };
+                matched = matched.filter(|m| m.0 > candidate.0).or(Some(candidate));
+            }
+        }
+
+        let (len, c, has_semicolon) = matched?;
+        // For historical reasons, a reference without a semicolon in an
+        // attribute value that is followed by `=` or an alphanumeric is taken
+        // literally, e.g. in `href="?a=1&copy=2"`.
+        if !has_semicolon && in_attribute {
+            if let Some(next) = rest.get(len) {
+                if *next == '=' || next.is_ascii_alphanumeric() {
+                    return None;
+                }
+            }
+        }
+        self.pos += len;
+        Some(c)
+    }
+
+    fn create_comment(&mut self) {
+        self.latest_token = Some(HtmlToken::Comment(String::new()));
+    }
+
+    fn append_comment(&mut self, s: &str) {
+        assert!(self.latest_token.is_some());
+        match self.latest_token.as_mut().unwrap() {
+            HtmlToken::Comment(data) => data.push_str(s),
+            _ => panic!("`latest_token` should be a Comment"),
+        }
+    }
+
+    fn create_doctype(&mut self) {
+        self.latest_token = Some(HtmlToken::Doctype {
+            name: String::new(),
+            public_id: String::new(),
+            system_id: String::new(),
+        });
+    }
+
+    /// Appends `c` to the name of the latest DOCTYPE token, or to its public
+    /// or system identifier.
+    fn append_doctype(&mut self, c: char, field: DoctypeField) {
+        assert!(self.latest_token.is_some());
+        match self.latest_token.as_mut().unwrap() {
+            HtmlToken::Doctype {
+                name,
+                public_id,
+                system_id,
+            } => match field {
+                DoctypeField::Name => name.push(c),
+                DoctypeField::PublicId => public_id.push(c),
+                DoctypeField::SystemId => system_id.push(c),
+            },
+            _ => panic!("`latest_token` should be a Doctype"),
+        }
     }
 
     fn take_latest_token(&mut self) -> Option<HtmlToken> {
@@ -79,6 +244,9 @@ impl HtmlTokenizer {
             } => {
                 attributes.push(Attribute::default());
             }
+            // End tags with attributes or a trailing solidus are parse errors.
+            // The attributes and the flag are dropped.
+            HtmlToken::EndTag { .. } => {}
             _ => panic!("`latest_token` should be a StartTag"),
         }
     }
@@ -94,6 +262,9 @@ impl HtmlTokenizer {
                 Some(attr) => attr.add_char(c, is_name),
                 None => panic!("attribute must be exists"),
             },
+            // End tags with attributes or a trailing solidus are parse errors.
+            // The attributes and the flag are dropped.
+            HtmlToken::EndTag { .. } => {}
             _ => panic!("`latest_token` should be a StartTag"),
         }
     }
@@ -106,12 +277,146 @@ impl HtmlTokenizer {
                 ref mut self_closing,
                 attributes: _,
             } => *self_closing = true,
+            // End tags with attributes or a trailing solidus are parse errors.
+            // The attributes and the flag are dropped.
+            HtmlToken::EndTag { .. } => {}
             _ => panic!("`latest_token` should be a StartTag"),
         }
     }
 }
 
-#[derive(PartialEq, Debug)]
+/// A subset of the named character references that require the trailing
+/// semicolon.
+/// https://html.spec.whatwg.org/multipage/named-characters.html
+const NAMED_CHARACTER_REFERENCES: &[(&str, char)] = &[
+    ("apos", '\''),
+    ("hellip", '\u{2026}'),
+    ("mdash", '\u{2014}'),
+    ("ndash", '\u{2013}'),
+    ("notin", '\u{2209}'),
+];
+
+/// The named character references that may omit the trailing semicolon
+/// for compatibility with legacy content: the Latin-1 characters and a few
+/// uppercase names.
+/// https://html.spec.whatwg.org/multipage/named-characters.html
+const LEGACY_CHARACTER_REFERENCES: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("AMP", '&'),
+    ("lt", '<'),
+    ("LT", '<'),
+    ("gt", '>'),
+    ("GT", '>'),
+    ("quot", '"'),
+    ("QUOT", '"'),
+    ("COPY", '\u{a9}'),
+    ("REG", '\u{ae}'),
+    ("nbsp", '\u{a0}'),
+    ("iexcl", '\u{a1}'),
+    ("cent", '\u{a2}'),
+    ("pound", '\u{a3}'),
+    ("curren", '\u{a4}'),
+    ("yen", '\u{a5}'),
+    ("brvbar", '\u{a6}'),
+    ("sect", '\u{a7}'),
+    ("uml", '\u{a8}'),
+    ("copy", '\u{a9}'),
+    ("ordf", '\u{aa}'),
+    ("laquo", '\u{ab}'),
+    ("not", '\u{ac}'),
+    ("shy", '\u{ad}'),
+    ("reg", '\u{ae}'),
+    ("macr", '\u{af}'),
+    ("deg", '\u{b0}'),
+    ("plusmn", '\u{b1}'),
+    ("sup2", '\u{b2}'),
+    ("sup3", '\u{b3}'),
+    ("acute", '\u{b4}'),
+    ("micro", '\u{b5}'),
+    ("para", '\u{b6}'),
+    ("middot", '\u{b7}'),
+    ("cedil", '\u{b8}'),
+    ("sup1", '\u{b9}'),
+    ("ordm", '\u{ba}'),
+    ("raquo", '\u{bb}'),
+    ("frac14", '\u{bc}'),
+    ("frac12", '\u{bd}'),
+    ("frac34", '\u{be}'),
+    ("iquest", '\u{bf}'),
+    ("Agrave", '\u{c0}'),
+    ("Aacute", '\u{c1}'),
+    ("Acirc", '\u{c2}'),
+    ("Atilde", '\u{c3}'),
+    ("Auml", '\u{c4}'),
+    ("Aring", '\u{c5}'),
+    ("AElig", '\u{c6}'),
+    ("Ccedil", '\u{c7}'),
+    ("Egrave", '\u{c8}'),
+    ("Eacute", '\u{c9}'),
+    ("Ecirc", '\u{ca}'),
+    ("Euml", '\u{cb}'),
+    ("Igrave", '\u{cc}'),
+    ("Iacute", '\u{cd}'),
+    ("Icirc", '\u{ce}'),
+    ("Iuml", '\u{cf}'),
+    ("ETH", '\u{d0}'),
+    ("Ntilde", '\u{d1}'),
+    ("Ograve", '\u{d2}'),
+    ("Oacute", '\u{d3}'),
+    ("Ocirc", '\u{d4}'),
+    ("Otilde", '\u{d5}'),
+    ("Ouml", '\u{d6}'),
+    ("times", '\u{d7}'),
+    ("Oslash", '\u{d8}'),
+    ("Ugrave", '\u{d9}'),
+    ("Uacute", '\u{da}'),
+    ("Ucirc", '\u{db}'),
+    ("Uuml", '\u{dc}'),
+    ("Yacute", '\u{dd}'),
+    ("THORN", '\u{de}'),
+    ("szlig", '\u{df}'),
+    ("agrave", '\u{e0}'),
+    ("aacute", '\u{e1}'),
+    ("acirc", '\u{e2}'),
+    ("atilde", '\u{e3}'),
+    ("auml", '\u{e4}'),
+    ("aring", '\u{e5}'),
+    ("aelig", '\u{e6}'),
+    ("ccedil", '\u{e7}'),
+    ("egrave", '\u{e8}'),
+    ("eacute", '\u{e9}'),
+    ("ecirc", '\u{ea}'),
+    ("euml", '\u{eb}'),
+    ("igrave", '\u{ec}'),
+    ("iacute", '\u{ed}'),
+    ("icirc", '\u{ee}'),
+    ("iuml", '\u{ef}'),
+    ("eth", '\u{f0}'),
+    ("ntilde", '\u{f1}'),
+    ("ograve", '\u{f2}'),
+    ("oacute", '\u{f3}'),
+    ("ocirc", '\u{f4}'),
+    ("otilde", '\u{f5}'),
+    ("ouml", '\u{f6}'),
+    ("divide", '\u{f7}'),
+    ("oslash", '\u{f8}'),
+    ("ugrave", '\u{f9}'),
+    ("uacute", '\u{fa}'),
+    ("ucirc", '\u{fb}'),
+    ("uuml", '\u{fc}'),
+    ("yacute", '\u{fd}'),
+    ("thorn", '\u{fe}'),
+    ("yuml", '\u{ff}'),
+];
+
+#[derive(Copy, Clone)]
+enum DoctypeField {
+    Name,
+    PublicId,
+    SystemId,
+}
+
+#[derive(PartialEq, Debug, Clone)]
 pub enum HtmlToken {
     StartTag {
         tag: String,
@@ -125,9 +430,20 @@ pub enum HtmlToken {
 
     Char(char),
 
+    /// https://html.spec.whatwg.org/multipage/parsing.html#tokenization
+    Comment(String),
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#tokenization
+    Doctype {
+        name: String,
+        public_id: String,
+        system_id: String,
+    },
+
     Eof,
 }
 
+#[derive(Copy, Clone)]
 pub enum State {
     /// https://html.spec.whatwg.org/multipage/parsing.html#data-state
     Data,
@@ -155,6 +471,14 @@ pub enum State {
     AfterAttributeValueQuoted,
     /// https://html.spec.whatwg.org/multipage/parsing.html#self-closing-start-tag-state
     SelfClosingStartTag,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rcdata-state
+    Rcdata,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rcdata-less-than-sign-state
+    RcdataLessThanSign,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rcdata-end-tag-open-state
+    RcdataEndTagOpen,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rcdata-end-tag-name-state
+    RcdataEndTagName,
     /// https://html.spec.whatwg.org/multipage/parsing.html#script-data-state
     ScriptData,
     /// https://html.spec.whatwg.org/multipage/parsing.html#script-data-less-than-sign-state
@@ -165,11 +489,61 @@ pub enum State {
     ScriptDataEndTagName,
     /// https://html.spec.whatwg.org/multipage/parsing.html#temporary-buffer
     TemporaryBuffer,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#markup-declaration-open-state
+    MarkupDeclarationOpen,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#bogus-comment-state
+    BogusComment,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-start-state
+    CommentStart,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-start-dash-state
+    CommentStartDash,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-state
+    Comment,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-end-dash-state
+    CommentEndDash,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-end-state
+    CommentEnd,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-end-bang-state
+    CommentEndBang,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-state
+    Doctype,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#before-doctype-name-state
+    BeforeDoctypeName,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-name-state
+    DoctypeName,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-name-state
+    AfterDoctypeName,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-public-keyword-state
+    AfterDoctypePublicKeyword,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#before-doctype-public-identifier-state
+    BeforeDoctypePublicIdentifier,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-public-identifier-(double-quoted)-state
+    DoctypePublicIdentifierDoubleQuoted,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-public-identifier-(single-quoted)-state
+    DoctypePublicIdentifierSingleQuoted,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-public-identifier-state
+    AfterDoctypePublicIdentifier,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#between-doctype-public-and-system-identifiers-state
+    BetweenDoctypePublicAndSystemIdentifiers,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-system-keyword-state
+    AfterDoctypeSystemKeyword,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#before-doctype-system-identifier-state
+    BeforeDoctypeSystemIdentifier,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-system-identifier-(double-quoted)-state
+    DoctypeSystemIdentifierDoubleQuoted,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-system-identifier-(single-quoted)-state
+    DoctypeSystemIdentifierSingleQuoted,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#after-doctype-system-identifier-state
+    AfterDoctypeSystemIdentifier,
+    /// https://html.spec.whatwg.org/multipage/parsing.html#bogus-doctype-state
+    BogusDoctype,
 }
 
 impl HtmlTokenizer {
+    /// Returns the next input character. Past the end of the input, a
+    /// placeholder is returned and `is_eof` becomes true.
     fn consume_next_input(&mut self) -> char {
-        let c = self.input[self.pos];
+        let c = self.input.get(self.pos).copied().unwrap_or('\0');
         self.pos += 1;
         c
     }
@@ -194,12 +568,21 @@ impl Iterator for HtmlTokenizer {
                         self.state = State::TagOpen;
                         continue;
                     }
+                    if c == '&' {
+                        if let Some(c) = self.consume_character_reference(false) {
+                            return Some(HtmlToken::Char(c));
+                        }
+                    }
                     if self.is_eof() {
                         return Some(HtmlToken::Eof);
                     }
                     return Some(HtmlToken::Char(c));
                 }
                 State::TagOpen => {
+                    if c == '!' {
+                        self.state = State::MarkupDeclarationOpen;
+                        continue;
+                    }
                     if c == '/' {
                         self.state = State::EndTagOpen;
                         continue;
@@ -210,6 +593,13 @@ impl Iterator for HtmlTokenizer {
                         self.create_tag(true);
                         continue;
                     }
+                    // unexpected-question-mark-instead-of-tag-name parse error
+                    if c == '?' {
+                        self.reconsume = true;
+                        self.state = State::BogusComment;
+                        self.create_comment();
+                        continue;
+                    }
                     if self.is_eof() {
                         return Some(HtmlToken::Eof);
                     }
@@ -326,6 +716,12 @@ impl Iterator for HtmlTokenizer {
                     if self.is_eof() {
                         return Some(HtmlToken::Eof);
                     }
+                    if c == '&' {
+                        if let Some(c) = self.consume_character_reference(true) {
+                            self.append_attribute(c, false);
+                            continue;
+                        }
+                    }
                     self.append_attribute(c, false);
                 }
                 State::AttributeValueSingleQuoted => {
@@ -336,6 +732,12 @@ impl Iterator for HtmlTokenizer {
                     if self.is_eof() {
                         return Some(HtmlToken::Eof);
                     }
+                    if c == '&' {
+                        if let Some(c) = self.consume_character_reference(true) {
+                            self.append_attribute(c, false);
+                            continue;
+                        }
+                    }
                     self.append_attribute(c, false);
                 }
                 State::AttributeValueUnquoted => {
@@ -353,6 +755,12 @@ impl Iterator for HtmlTokenizer {
                     // unexpected-character-in-unquoted-attribute-value parse error
                     // Includes code points that the parser encounters
                     // such as U+0022 ("), U+0027 ('), U+003C (<), U+003D (=), or U+0060 (`)
+                    if c == '&' {
+                        if let Some(c) = self.consume_character_reference(true) {
+                            self.append_attribute(c, false);
+                            continue;
+                        }
+                    }
                     self.append_attribute(c, false);
                 }
                 State::AfterAttributeValueQuoted => {
@@ -392,6 +800,73 @@ impl Iterator for HtmlTokenizer {
                     self.reconsume = true;
                     self.state = State::BeforeAttributeName;
                 }
+                State::Rcdata => {
+                    if c == '<' {
+                        self.state = State::RcdataLessThanSign;
+                        continue;
+                    }
+                    if c == '&' {
+                        if let Some(c) = self.consume_character_reference(false) {
+                            return Some(HtmlToken::Char(c));
+                        }
+                    }
+                    if self.is_eof() {
+                        return Some(HtmlToken::Eof);
+                    }
+                    return Some(HtmlToken::Char(c));
+                }
+                State::RcdataLessThanSign => {
+                    if c == '/' {
+                        self.buf = String::new();
+                        self.state = State::RcdataEndTagOpen;
+                        continue;
+                    }
+                    self.reconsume = true;
+                    self.state = State::Rcdata;
+                    return Some(HtmlToken::Char('<'));
+                }
+                State::RcdataEndTagOpen => {
+                    if c.is_ascii_alphabetic() {
+                        self.reconsume = true;
+                        self.state = State::RcdataEndTagName;
+                        self.create_tag(false);
+                        continue;
+                    }
+                    self.reconsume = true;
+                    self.state = State::Rcdata;
+                    // The specification returns two tokens: '<' and '/'
+                    // However, here we can only return one token
+                    return Some(HtmlToken::Char('<'));
+                }
+                State::RcdataEndTagName => {
+                    if self.is_appropriate_end_tag() {
+                        if c.is_whitespace() {
+                            self.state = State::BeforeAttributeName;
+                            continue;
+                        }
+                        if c == '/' {
+                            self.state = State::SelfClosingStartTag;
+                            continue;
+                        }
+                        if c == '>' {
+                            self.state = State::Data;
+                            return self.take_latest_token();
+                        }
+                    }
+                    if c.is_ascii_alphabetic() {
+                        self.append_tag_name(c.to_ascii_lowercase());
+                        self.buf.push(c);
+                        continue;
+                    }
+                    // Not an appropriate end tag. Emit "</" and the buffer as
+                    // characters and reconsume `c` in the RCDATA state.
+                    self.latest_token = None;
+                    self.reconsume = true;
+                    self.return_state = State::Rcdata;
+                    self.state = State::TemporaryBuffer;
+                    self.buf = String::from("</") + &self.buf;
+                    continue;
+                }
                 State::ScriptData => {
                     if c == '<' {
                         self.state = State::ScriptDataLessThanSign;
@@ -417,6 +892,7 @@ impl Iterator for HtmlTokenizer {
                         self.reconsume = true;
                         self.state = State::ScriptDataEndTagName;
                         self.create_tag(false);
+                        continue;
                     }
                     self.reconsume = true;
                     self.state = State::ScriptData;
@@ -425,25 +901,39 @@ impl Iterator for HtmlTokenizer {
                     return Some(HtmlToken::Char('<'));
                 }
                 State::ScriptDataEndTagName => {
-                    if c == '>' {
-                        self.state = State::Data;
-                        return self.take_latest_token();
+                    if self.is_appropriate_end_tag() {
+                        if c.is_whitespace() {
+                            self.state = State::BeforeAttributeName;
+                            continue;
+                        }
+                        if c == '/' {
+                            self.state = State::SelfClosingStartTag;
+                            continue;
+                        }
+                        if c == '>' {
+                            self.state = State::Data;
+                            return self.take_latest_token();
+                        }
                     }
                     if c.is_ascii_alphabetic() {
                         self.append_tag_name(c.to_ascii_lowercase());
                         self.buf.push(c);
                         continue;
                     }
+                    // Not an appropriate end tag. Emit "</" and the buffer as
+                    // characters and reconsume `c` in the script data state.
+                    self.latest_token = None;
+                    self.reconsume = true;
+                    self.return_state = State::ScriptData;
                     self.state = State::TemporaryBuffer;
                     self.buf = String::from("</") + &self.buf;
-                    self.buf.push(c);
                     continue;
                 }
                 State::TemporaryBuffer => {
                     self.reconsume = true;
 
                     if self.buf.is_empty() {
-                        self.state = State::Data;
+                        self.state = self.return_state;
                         continue;
                     }
 
@@ -455,6 +945,327 @@ impl Iterator for HtmlTokenizer {
                     self.buf.remove(0);
                     return Some(HtmlToken::Char(c));
                 }
+
+                State::MarkupDeclarationOpen => {
+                    if c == '-' && self.input.get(self.pos) == Some(&'-') {
+                        self.pos += 1;
+                        self.state = State::CommentStart;
+                        self.create_comment();
+                        continue;
+                    }
+                    if self.consume_if_starts_with("DOCTYPE") {
+                        self.state = State::Doctype;
+                        continue;
+                    }
+                    // incorrectly-opened-comment parse error
+                    // CDATA sections are only allowed in foreign content,
+                    // which is not supported, so they become bogus comments too.
+                    self.reconsume = true;
+                    self.state = State::BogusComment;
+                    self.create_comment();
+                }
+                State::BogusComment => {
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    self.append_comment(c.encode_utf8(&mut [0; 4]));
+                }
+                State::CommentStart => {
+                    if c == '-' {
+                        self.state = State::CommentStartDash;
+                        continue;
+                    }
+                    // abrupt-closing-of-empty-comment parse error
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::CommentStartDash => {
+                    if c == '-' {
+                        self.state = State::CommentEnd;
+                        continue;
+                    }
+                    // abrupt-closing-of-empty-comment parse error
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    // eof-in-comment parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    self.append_comment("-");
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::Comment => {
+                    if c == '-' {
+                        self.state = State::CommentEndDash;
+                        continue;
+                    }
+                    // eof-in-comment parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    self.append_comment(c.encode_utf8(&mut [0; 4]));
+                }
+                State::CommentEndDash => {
+                    if c == '-' {
+                        self.state = State::CommentEnd;
+                        continue;
+                    }
+                    // eof-in-comment parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    self.append_comment("-");
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::CommentEnd => {
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    if c == '!' {
+                        self.state = State::CommentEndBang;
+                        continue;
+                    }
+                    if c == '-' {
+                        self.append_comment("-");
+                        continue;
+                    }
+                    // eof-in-comment parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    self.append_comment("--");
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::CommentEndBang => {
+                    if c == '-' {
+                        self.append_comment("--!");
+                        self.state = State::CommentEndDash;
+                        continue;
+                    }
+                    // incorrectly-closed-comment parse error
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    // eof-in-comment parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    self.append_comment("--!");
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::Doctype => {
+                    if c.is_whitespace() {
+                        self.state = State::BeforeDoctypeName;
+                        continue;
+                    }
+                    // eof-in-doctype parse error
+                    if self.is_eof() {
+                        self.create_doctype();
+                        return self.take_latest_token();
+                    }
+                    // missing-whitespace-before-doctype-name parse error
+                    self.reconsume = true;
+                    self.state = State::BeforeDoctypeName;
+                }
+                State::BeforeDoctypeName => {
+                    if c.is_whitespace() {
+                        continue;
+                    }
+                    self.create_doctype();
+                    // missing-doctype-name parse error
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    // eof-in-doctype parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    self.append_doctype(c.to_ascii_lowercase(), DoctypeField::Name);
+                    self.state = State::DoctypeName;
+                }
+                State::DoctypeName => {
+                    if c.is_whitespace() {
+                        self.state = State::AfterDoctypeName;
+                        continue;
+                    }
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    // eof-in-doctype parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    self.append_doctype(c.to_ascii_lowercase(), DoctypeField::Name);
+                }
+                State::AfterDoctypeName => {
+                    if c.is_whitespace() {
+                        continue;
+                    }
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    // eof-in-doctype parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    if self.consume_if_starts_with("PUBLIC") {
+                        self.state = State::AfterDoctypePublicKeyword;
+                        continue;
+                    }
+                    if self.consume_if_starts_with("SYSTEM") {
+                        self.state = State::AfterDoctypeSystemKeyword;
+                        continue;
+                    }
+                    // invalid-character-sequence-after-doctype-name parse error
+                    self.reconsume = true;
+                    self.state = State::BogusDoctype;
+                }
+                State::AfterDoctypePublicKeyword | State::BeforeDoctypePublicIdentifier => {
+                    if c.is_whitespace() {
+                        self.state = State::BeforeDoctypePublicIdentifier;
+                        continue;
+                    }
+                    if c == '"' {
+                        self.state = State::DoctypePublicIdentifierDoubleQuoted;
+                        continue;
+                    }
+                    if c == '\'' {
+                        self.state = State::DoctypePublicIdentifierSingleQuoted;
+                        continue;
+                    }
+                    // missing-doctype-public-identifier parse error
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    // eof-in-doctype parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    // missing-quote-before-doctype-public-identifier parse error
+                    self.reconsume = true;
+                    self.state = State::BogusDoctype;
+                }
+                State::DoctypePublicIdentifierDoubleQuoted
+                | State::DoctypePublicIdentifierSingleQuoted => {
+                    let quote = match self.state {
+                        State::DoctypePublicIdentifierDoubleQuoted => '"',
+                        _ => '\'',
+                    };
+                    if c == quote {
+                        self.state = State::AfterDoctypePublicIdentifier;
+                        continue;
+                    }
+                    // abrupt-doctype-public-identifier parse error
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    // eof-in-doctype parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    self.append_doctype(c, DoctypeField::PublicId);
+                }
+                State::AfterDoctypePublicIdentifier
+                | State::BetweenDoctypePublicAndSystemIdentifiers
+                | State::AfterDoctypeSystemKeyword
+                | State::BeforeDoctypeSystemIdentifier => {
+                    if c.is_whitespace() {
+                        self.state = match self.state {
+                            State::AfterDoctypePublicIdentifier
+                            | State::BetweenDoctypePublicAndSystemIdentifiers => {
+                                State::BetweenDoctypePublicAndSystemIdentifiers
+                            }
+                            _ => State::BeforeDoctypeSystemIdentifier,
+                        };
+                        continue;
+                    }
+                    if c == '"' {
+                        self.state = State::DoctypeSystemIdentifierDoubleQuoted;
+                        continue;
+                    }
+                    if c == '\'' {
+                        self.state = State::DoctypeSystemIdentifierSingleQuoted;
+                        continue;
+                    }
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    // eof-in-doctype parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    // missing-quote-before-doctype-system-identifier parse error
+                    self.reconsume = true;
+                    self.state = State::BogusDoctype;
+                }
+                State::DoctypeSystemIdentifierDoubleQuoted
+                | State::DoctypeSystemIdentifierSingleQuoted => {
+                    let quote = match self.state {
+                        State::DoctypeSystemIdentifierDoubleQuoted => '"',
+                        _ => '\'',
+                    };
+                    if c == quote {
+                        self.state = State::AfterDoctypeSystemIdentifier;
+                        continue;
+                    }
+                    // abrupt-doctype-system-identifier parse error
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    // eof-in-doctype parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    self.append_doctype(c, DoctypeField::SystemId);
+                }
+                State::AfterDoctypeSystemIdentifier => {
+                    if c.is_whitespace() {
+                        continue;
+                    }
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    // eof-in-doctype parse error
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                    // unexpected-character-after-doctype-system-identifier parse error
+                    self.reconsume = true;
+                    self.state = State::BogusDoctype;
+                }
+                State::BogusDoctype => {
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+                    if self.is_eof() {
+                        return self.take_latest_token();
+                    }
+                }
             }
         }
     }
@@ -572,4 +1383,177 @@ mod tests {
             assert_eq!(Some(e), tokenizer.next());
         }
     }
+
+    #[test]
+    fn test_comment() {
+        let html = "<!-- a -- b --><!----><!--->x".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let expected = [
+            HtmlToken::Comment(" a -- b ".to_string()),
+            HtmlToken::Comment("".to_string()),
+            HtmlToken::Comment("".to_string()),
+            HtmlToken::Char('x'),
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+        assert!(tokenizer.next().is_none());
+    }
+
+    #[test]
+    fn test_bogus_comment() {
+        let html = "<?xml version=\"1.0\"?><!x>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let expected = [
+            HtmlToken::Comment("?xml version=\"1.0\"?".to_string()),
+            HtmlToken::Comment("x".to_string()),
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+    }
+
+    #[test]
+    fn test_unterminated_comment() {
+        let html = "<!-- abc".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        assert_eq!(
+            Some(HtmlToken::Comment(" abc".to_string())),
+            tokenizer.next()
+        );
+        assert!(tokenizer.next().is_none());
+    }
+
+    #[test]
+    fn test_doctype() {
+        let html = "<!DOCTYPE html><!doctype HTML PUBLIC \"-//W3C//DTD HTML 4.01//EN\" 'http://www.w3.org/TR/html4/strict.dtd'>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let expected = [
+            HtmlToken::Doctype {
+                name: "html".to_string(),
+                public_id: "".to_string(),
+                system_id: "".to_string(),
+            },
+            HtmlToken::Doctype {
+                name: "html".to_string(),
+                public_id: "-//W3C//DTD HTML 4.01//EN".to_string(),
+                system_id: "http://www.w3.org/TR/html4/strict.dtd".to_string(),
+            },
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+    }
+
+    #[test]
+    fn test_doctype_system() {
+        let html = "<!DOCTYPE svg SYSTEM \"about:legacy-compat\" junk>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        assert_eq!(
+            Some(HtmlToken::Doctype {
+                name: "svg".to_string(),
+                public_id: "".to_string(),
+                system_id: "about:legacy-compat".to_string(),
+            }),
+            tokenizer.next()
+        );
+        assert!(tokenizer.next().is_none());
+    }
+
+    #[test]
+    fn test_eof_in_tag() {
+        let html = "<p class=".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        assert_eq!(Some(HtmlToken::Eof), tokenizer.next());
+        assert!(tokenizer.next().is_none());
+    }
+
+    #[test]
+    fn test_rcdata() {
+        let html = "<title>a &amp; <b></title>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        assert_eq!(
+            Some(HtmlToken::StartTag {
+                tag: "title".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            }),
+            tokenizer.next()
+        );
+        tokenizer.switch_to_rcdata("title");
+        let expected = [
+            HtmlToken::Char('a'),
+            HtmlToken::Char(' '),
+            HtmlToken::Char('&'),
+            HtmlToken::Char(' '),
+            HtmlToken::Char('<'),
+            HtmlToken::Char('b'),
+            HtmlToken::Char('>'),
+            HtmlToken::EndTag {
+                tag: "title".to_string(),
+            },
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+        assert!(tokenizer.next().is_none());
+    }
+
+    #[test]
+    fn test_script_data() {
+        let html = "<script>a<b</script>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        assert_eq!(
+            Some(HtmlToken::StartTag {
+                tag: "script".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            }),
+            tokenizer.next()
+        );
+        tokenizer.switch_to_script_data("script");
+        let expected = [
+            HtmlToken::Char('a'),
+            HtmlToken::Char('<'),
+            HtmlToken::Char('b'),
+            HtmlToken::EndTag {
+                tag: "script".to_string(),
+            },
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+        assert!(tokenizer.next().is_none());
+    }
+
+    #[test]
+    fn test_character_references() {
+        let html = "a&amp;b&lt;&#x41;&#66;&unknown;&&notit;&notin;&AMP<p title=\"&quot;x&quot;\" \
+                    href=\"?a&copy=1&not;&not\">"
+            .to_string();
+        let tokenizer = HtmlTokenizer::new(html);
+        let tokens: Vec<HtmlToken> = tokenizer.collect();
+        let chars: String = tokens
+            .iter()
+            .filter_map(|t| match t {
+                HtmlToken::Char(c) => Some(*c),
+                _ => None,
+            })
+            .collect();
+        // Legacy references may omit the semicolon.
+        assert_eq!("a&b<AB&unknown;&\u{ac}it;\u{2209}&", chars);
+        assert_eq!(
+            Some(&HtmlToken::StartTag {
+                tag: "p".to_string(),
+                self_closing: false,
+                attributes: vec![
+                    Attribute::with_value("title", "\"x\""),
+                    // ...except before `=` or an alphanumeric in an
+                    // attribute value.
+                    Attribute::with_value("href", "?a&copy=1\u{ac}\u{ac}"),
+                ],
+            }),
+            tokens.last()
+        );
+    }
 }
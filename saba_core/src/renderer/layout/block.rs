@@ -0,0 +1,495 @@
+//! https://www.w3.org/TR/CSS2/visuren.html#block-formatting
+//! https://www.w3.org/TR/CSS2/visudet.html
+
+use crate::renderer::css::computed_style::ComputedStyle;
+use crate::renderer::css::length::LengthPercentage;
+use crate::renderer::layout::box_model::BoxModel;
+use crate::renderer::layout::box_model::Edges;
+use crate::renderer::layout::box_model::Rect;
+use crate::renderer::layout::font::FontMetrics;
+use crate::renderer::layout::inline::intrinsic_widths;
+use crate::renderer::layout::inline::layout_inline_content;
+use crate::renderer::layout::layout_object::LayoutObject;
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+/// https://www.w3.org/TR/CSS2/visudet.html#containing-block-details
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct ContainingBlock {
+    pub(crate) width: f64,
+    /// `None` if the height depends on the content, in which case
+    /// percentage heights behave as `auto`.
+    pub(crate) height: Option<f64>,
+}
+
+/// Adjoining vertical margins, which collapse into a single margin.
+/// https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub(crate) struct CollapsedMargin {
+    /// The largest positive margin.
+    positive: f64,
+    /// The most negative margin.
+    negative: f64,
+}
+
+impl CollapsedMargin {
+    pub(crate) fn new(margin: f64) -> Self {
+        let mut collapsed = Self::default();
+        collapsed.adjoin_margin(margin);
+        collapsed
+    }
+
+    fn adjoin_margin(&mut self, margin: f64) {
+        self.positive = self.positive.max(margin);
+        self.negative = self.negative.min(margin);
+    }
+
+    pub(crate) fn adjoin(&mut self, other: CollapsedMargin) {
+        self.adjoin_margin(other.positive);
+        self.adjoin_margin(other.negative);
+    }
+
+    /// Returns the width of the collapsed margin: the largest positive
+    /// margin plus the most negative one.
+    pub(crate) fn value(&self) -> f64 {
+        self.positive + self.negative
+    }
+}
+
+/// The margins of a block-level box after it is laid out, which its parent
+/// collapses with its siblings' margins.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct BlockMargins {
+    /// The box's top margin, with the top margins of its descendants that
+    /// collapse with it.
+    pub(crate) top: CollapsedMargin,
+    /// The box's bottom margin, with the bottom margins of its descendants
+    /// that collapse with it.
+    pub(crate) bottom: CollapsedMargin,
+    /// Whether the top and bottom margins of the box are adjoining, which
+    /// happens when it is empty.
+    pub(crate) collapses_through: bool,
+}
+
+/// Lays out the block container `layout_object` and its descendants, and
+/// returns its margins. The content box of each box is positioned relative
+/// to the content box of its parent, and that of `layout_object` relative
+/// to its own top border edge. `establishes_bfc` is true if it establishes
+/// a block formatting context, so that its margins don't collapse with its
+/// children's. Text is measured with `metrics`.
+/// https://www.w3.org/TR/CSS2/visuren.html#block-formatting
+pub(crate) fn layout_block(
+    layout_object: &Rc<RefCell<LayoutObject>>,
+    containing_block: ContainingBlock,
+    establishes_bfc: bool,
+    metrics: &dyn FontMetrics,
+) -> BlockMargins {
+    let style = layout_object.borrow().style();
+    let is_inline_level = !layout_object.borrow().is_block_level();
+    let cb_width = containing_block.width;
+    let padding = Edges::new(
+        resolve(&style, "padding-top", cb_width).unwrap_or(0.0),
+        resolve(&style, "padding-right", cb_width).unwrap_or(0.0),
+        resolve(&style, "padding-bottom", cb_width).unwrap_or(0.0),
+        resolve(&style, "padding-left", cb_width).unwrap_or(0.0),
+    );
+    let border = Edges::new(
+        style.length("border-top-width").unwrap_or(0.0),
+        style.length("border-right-width").unwrap_or(0.0),
+        style.length("border-bottom-width").unwrap_or(0.0),
+        style.length("border-left-width").unwrap_or(0.0),
+    );
+    // https://www.w3.org/TR/CSS2/visudet.html#shrink-to-fit-float
+    // An inline-level box with an `auto` width shrinks to fit its content.
+    let fit_content = is_inline_level.then(|| intrinsic_widths(layout_object, metrics));
+    let (width, margin_left, margin_right) =
+        used_width(&style, cb_width, &padding, &border, fit_content);
+    // https://www.w3.org/TR/CSS2/visudet.html#normal-block
+    // `auto` vertical margins are 0.
+    let margin = Edges::new(
+        resolve(&style, "margin-top", cb_width).unwrap_or(0.0),
+        margin_right,
+        resolve(&style, "margin-bottom", cb_width).unwrap_or(0.0),
+        margin_left,
+    );
+
+    // https://www.w3.org/TR/CSS2/visudet.html#min-max-heights
+    let vertical_sizing = sizing(&style, &padding, &border, false);
+    let cb_height = containing_block.height;
+    let specified_height = size(&style, "height", cb_height).map(&vertical_sizing);
+    let min_height = size(&style, "min-height", cb_height)
+        .map(&vertical_sizing)
+        .unwrap_or(0.0);
+    let max_height = size(&style, "max-height", cb_height).map(&vertical_sizing);
+    let clamp_height = |height: f64| {
+        let height = max_height.map_or(height, |max| height.min(max));
+        height.max(min_height)
+    };
+    let content_containing_block = ContainingBlock {
+        width,
+        height: specified_height.map(clamp_height),
+    };
+
+    let can_collapse_top = !establishes_bfc && border.top == 0.0 && padding.top == 0.0;
+    let can_collapse_bottom = !establishes_bfc
+        && border.bottom == 0.0
+        && padding.bottom == 0.0
+        && specified_height.is_none();
+
+    // `top` is `None` while the top margin of the box still collapses with
+    // those of its children.
+    let mut top = None;
+    let mut pending = CollapsedMargin::new(margin.top);
+    if !can_collapse_top {
+        top = Some(core::mem::take(&mut pending));
+    }
+    // The bottom border edge of the last in-flow child that doesn't
+    // collapse through.
+    let mut y = 0.0;
+    let baseline;
+
+    let children = layout_object.borrow().children().to_vec();
+    if children.iter().any(|c| c.borrow().is_block_level()) {
+        for child in &children {
+            let child_margins = layout_block(child, content_containing_block, false, metrics);
+            pending.adjoin(child_margins.top);
+            if child_margins.collapses_through {
+                // https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+                // The top border edge of an empty box is where it would be
+                // if it had a bottom border.
+                let offset = if top.is_some() { pending.value() } else { 0.0 };
+                translate(child, y + offset);
+                pending.adjoin(child_margins.bottom);
+                continue;
+            }
+            let offset = match top {
+                Some(_) => core::mem::take(&mut pending).value(),
+                None => {
+                    top = Some(core::mem::take(&mut pending));
+                    0.0
+                }
+            };
+            translate(child, y + offset);
+            y += offset + child.borrow().box_model().border_box().height;
+            pending = child_margins.bottom;
+        }
+        baseline = children.iter().rev().find_map(|c| {
+            let child = c.borrow();
+            child.baseline().map(|b| child.box_model().content.y + b)
+        });
+    } else {
+        let height;
+        (height, baseline) =
+            layout_inline_content(&children, &style, content_containing_block, metrics);
+        if height > 0.0 {
+            top = top.or_else(|| Some(core::mem::take(&mut pending)));
+            y = height;
+        }
+    }
+
+    // https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+    // The top and bottom margins of an empty box collapse through it.
+    let collapses_through = top.is_none() && can_collapse_bottom && min_height == 0.0;
+    let (top, bottom, content_height) = if collapses_through {
+        (pending, CollapsedMargin::new(margin.bottom), 0.0)
+    } else {
+        let top = top.unwrap_or_else(|| core::mem::take(&mut pending));
+        // The bottom margin of the last child collapses with that of the
+        // box if its height is `auto`.
+        if can_collapse_bottom {
+            pending.adjoin(CollapsedMargin::new(margin.bottom));
+            (top, pending, clamp_height(y))
+        } else {
+            let height = specified_height.unwrap_or(y + pending.value());
+            (
+                top,
+                CollapsedMargin::new(margin.bottom),
+                clamp_height(height),
+            )
+        }
+    };
+
+    layout_object.borrow_mut().set_baseline(baseline);
+    *layout_object.borrow_mut().box_model_mut() = BoxModel {
+        content: Rect::new(
+            margin.left + border.left + padding.left,
+            border.top + padding.top,
+            width,
+            content_height,
+        ),
+        padding,
+        border,
+        margin,
+    };
+    BlockMargins {
+        top,
+        bottom,
+        collapses_through,
+    }
+}
+
+/// Moves `layout_object` down by `dy`. The boxes of its descendants, which
+/// are relative to it, don't move.
+pub(crate) fn translate(layout_object: &Rc<RefCell<LayoutObject>>, dy: f64) {
+    layout_object.borrow_mut().box_model_mut().content.y += dy;
+}
+
+/// Makes the content boxes and fragments of `layout_object` and its
+/// descendants, which are relative to their parent's, absolute. `x` and `y`
+/// are the absolute position of the content box of its parent. Inline boxes
+/// and text runs are relative to the block container of their inline
+/// formatting context instead.
+pub(crate) fn make_absolute(layout_object: &Rc<RefCell<LayoutObject>>, x: f64, y: f64) {
+    let (content, is_block_container) = {
+        let mut layout_object = layout_object.borrow_mut();
+        for fragment in layout_object.fragments_mut() {
+            fragment.rect = fragment.rect.translate(x, y);
+        }
+        let content = &mut layout_object.box_model_mut().content;
+        *content = content.translate(x, y);
+        let content = *content;
+        (content, layout_object.is_block_container())
+    };
+    let (x, y) = match is_block_container {
+        true => (content.x, content.y),
+        false => (x, y),
+    };
+    let children = layout_object.borrow().children().to_vec();
+    for child in &children {
+        make_absolute(child, x, y);
+    }
+}
+
+/// Returns the used value of the `<length-percentage>` property
+/// `property`, with percentages relative to `basis`, or `None` if it is a
+/// keyword, e.g. `auto`.
+fn resolve(style: &ComputedStyle, property: &str, basis: f64) -> Option<f64> {
+    style.length_percentage(property).map(|v| v.resolve(basis))
+}
+
+/// Returns the used value of the size property `property`, or `None` if it
+/// is `auto` or `none`, or a percentage of an unknown `basis`.
+/// https://www.w3.org/TR/CSS2/visudet.html#the-height-property
+fn size(style: &ComputedStyle, property: &str, basis: Option<f64>) -> Option<f64> {
+    match (style.length_percentage(property)?, basis) {
+        (LengthPercentage::Length(px), _) => Some(*px),
+        (value, Some(basis)) => Some(value.resolve(basis)),
+        (_, None) => None,
+    }
+}
+
+/// Returns a function that converts a size property to the size of the
+/// content box, according to `box-sizing`.
+/// https://www.w3.org/TR/css-sizing-3/#box-sizing
+fn sizing(
+    style: &ComputedStyle,
+    padding: &Edges,
+    border: &Edges,
+    horizontal: bool,
+) -> impl Fn(f64) -> f64 {
+    let inner = match (style.keyword("box-sizing"), horizontal) {
+        (Some("border-box"), true) => padding.horizontal() + border.horizontal(),
+        (Some("border-box"), false) => padding.vertical() + border.vertical(),
+        _ => 0.0,
+    };
+    move |size: f64| (size - inner).max(0.0)
+}
+
+/// Returns the used width and horizontal margins of a box in a containing
+/// block `cb_width` wide, subject to `min-width` and `max-width`.
+/// `fit_content` is the minimum and maximum content widths of an
+/// inline-level box, and `None` for block-level boxes.
+/// https://www.w3.org/TR/CSS2/visudet.html#min-max-widths
+fn used_width(
+    style: &ComputedStyle,
+    cb_width: f64,
+    padding: &Edges,
+    border: &Edges,
+    fit_content: Option<(f64, f64)>,
+) -> (f64, f64, f64) {
+    let horizontal_sizing = sizing(style, padding, border, true);
+    let width = size(style, "width", Some(cb_width)).map(&horizontal_sizing);
+    let min_width = size(style, "min-width", Some(cb_width))
+        .map(&horizontal_sizing)
+        .unwrap_or(0.0);
+    let max_width = size(style, "max-width", Some(cb_width)).map(&horizontal_sizing);
+
+    let solve = |width| solve_width(style, cb_width, padding, border, width, fit_content);
+    let mut used = solve(width);
+    if let Some(max_width) = max_width {
+        if used.0 > max_width {
+            used = solve(Some(max_width));
+        }
+    }
+    if used.0 < min_width {
+        used = solve(Some(min_width));
+    }
+    used
+}
+
+/// Solves the width constraint for `width`, which is `None` if it is
+/// `auto`. The direction is assumed to be left-to-right.
+/// https://www.w3.org/TR/CSS2/visudet.html#blockwidth
+fn solve_width(
+    style: &ComputedStyle,
+    cb_width: f64,
+    padding: &Edges,
+    border: &Edges,
+    width: Option<f64>,
+    fit_content: Option<(f64, f64)>,
+) -> (f64, f64, f64) {
+    let margin_left = resolve(style, "margin-left", cb_width);
+    let margin_right = resolve(style, "margin-right", cb_width);
+    let inner = padding.horizontal() + border.horizontal();
+
+    // https://www.w3.org/TR/CSS2/visudet.html#inlineblock-width
+    // `auto` margins of inline-level boxes are 0, and an `auto` width is
+    // the shrink-to-fit width.
+    if let Some((min_content, max_content)) = fit_content {
+        let margin_left = margin_left.unwrap_or(0.0);
+        let margin_right = margin_right.unwrap_or(0.0);
+        let width = width.unwrap_or_else(|| {
+            let available = (cb_width - margin_left - margin_right - inner).max(0.0);
+            min_content.max(available).min(max_content)
+        });
+        return (width, margin_left, margin_right);
+    }
+
+    match width {
+        // Other `auto` values become 0 and the width follows from the
+        // equality. If it would be negative, the margin-right is adjusted.
+        None => {
+            let margin_left = margin_left.unwrap_or(0.0);
+            let margin_right = margin_right.unwrap_or(0.0);
+            let width = (cb_width - margin_left - margin_right - inner).max(0.0);
+            (width, margin_left, cb_width - margin_left - inner - width)
+        }
+        Some(width) => {
+            let remaining = cb_width - width - inner;
+            match (margin_left, margin_right) {
+                // Both margins are `auto`: the box is centered.
+                (None, None) if remaining >= 0.0 => (width, remaining / 2.0, remaining / 2.0),
+                // `auto` margins are 0 if the box is too wide, and the
+                // over-constrained margin-right is adjusted.
+                (None, None) => (width, 0.0, remaining),
+                (None, Some(margin_right)) if remaining - margin_right >= 0.0 => {
+                    (width, remaining - margin_right, margin_right)
+                }
+                (None, Some(_)) => (width, 0.0, remaining),
+                (Some(margin_left), _) => (width, margin_left, remaining - margin_left),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::layout::tests::layout_tree;
+    use alloc::string::String;
+
+    /// Returns the layout tree of `html` in an 800x600 viewport, with the
+    /// border box of each box.
+    fn layout(html: &str) -> String {
+        layout_tree(html, true, false)
+    }
+
+    #[test]
+    fn test_widths() {
+        assert_eq!(
+            r#"Block <html> (0, 0) 800x86
+  Block <body> (8, 8) 784x70
+    Block <p> (350, 8) 100x10
+    Block <p> (8, 18) 392x30
+    Block <p> (8, 48) 1000x10
+    Block <p> (8, 58) 900x10
+    Block <p> (28, 68) 100x10
+"#,
+            layout(
+                "<style>
+  p { margin: 0; height: 10px }
+  #a { width: 100px; margin: 0 auto }
+  #b { width: 50%; padding: 10px; border: 5px solid; box-sizing: border-box }
+  #c { width: 1000px; margin-left: auto }
+  #d { min-width: 900px }
+  #e { max-width: 100px; margin-left: 20px; margin-right: auto }
+</style><p id=a></p><p id=b></p><p id=c></p><p id=d></p><p id=e></p>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_margin_collapsing() {
+        // #b: sibling margins. #c: parent and first child margins. #e: an
+        // empty box, whose margins collapse through it with a negative
+        // margin.
+        assert_eq!(
+            r#"Block <html> (0, 0) 800x147
+  Block <body> (0, 0) 800x147
+    Block <p> (0, 0) 800x20
+      Text "a"
+        | "a" (0, 2) 8x16
+    Block <p> (0, 50) 800x20
+      Text "b"
+        | "b" (0, 52) 8x16
+    Block <p> (0, 95) 800x20
+      Block <a> (0, 95) 800x20
+        Text "d"
+          | "d" (0, 97) 8x16
+    Block <p> (0, 130) 800x0
+    Block <p> (0, 126) 800x21
+      Text "f"
+        | "f" (0, 129) 8x16
+"#,
+            layout(
+                "<style>
+  body { margin: 0; line-height: 20px }
+  p { margin: 0 }
+  a { display: block }
+  #a { margin-bottom: 20px }
+  #b { margin-top: 30px; margin-bottom: 10px }
+  #c { margin-top: 10px; margin-bottom: 5px }
+  #d { margin-top: 25px; margin-bottom: 15px }
+  #e { margin-top: 12px; margin-bottom: -4px }
+  #f { margin-top: 3px; padding-top: 1px }
+</style><p id=a>a</p><p id=b>b</p><p id=c><a id=d>d</a></p><p id=e></p><p id=f>f</p>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_heights() {
+        // #a: padding keeps the bottom margin of its child inside it. #d: a
+        // percentage of a definite height. #e: `min-height` wins over
+        // `max-height`. #f: a percentage of an `auto` height.
+        assert_eq!(
+            r#"Block <html> (0, 0) 800x235
+  Block <body> (0, 0) 800x235
+    Block <p> (0, 0) 800x55
+      Block <a> (0, 0) 800x20
+        Text "b"
+          | "b" (0, 2) 8x16
+    Block <p> (0, 75) 800x100
+      Block <a> (0, 75) 800x50
+    Block <p> (0, 175) 800x40
+      Text "e"
+        | "e" (0, 177) 8x16
+    Block <p> (0, 215) 800x20
+      Text "f"
+        | "f" (0, 217) 8x16
+"#,
+            layout(
+                "<style>
+  body { margin: 0; line-height: 20px }
+  p { margin: 0 }
+  a { display: block }
+  #a { padding-bottom: 5px; margin-bottom: 10px }
+  #b { margin-bottom: 30px }
+  #c { height: 100px; margin-top: 10px }
+  #d { height: 50%; margin-top: 20px }
+  #e { min-height: 40px; max-height: 30px }
+  #f { height: 50% }
+</style><p id=a><a id=b>b</a></p><p id=c><a id=d></a></p><p id=e>e</p><p id=f>f</p>"
+            )
+        );
+    }
+}
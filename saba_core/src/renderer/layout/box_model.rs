@@ -0,0 +1,113 @@
+//! https://www.w3.org/TR/css-box-3/#box-model
+
+use alloc::string::String;
+
+/// A rectangle in px.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns this rectangle moved by `dx` and `dy`.
+    pub fn translate(&self, dx: f64, dy: f64) -> Self {
+        Self {
+            x: self.x + dx,
+            y: self.y + dy,
+            ..*self
+        }
+    }
+
+    /// Returns this rectangle grown by `edges` on each side.
+    pub fn expand(&self, edges: &Edges) -> Self {
+        Self {
+            x: self.x - edges.left,
+            y: self.y - edges.top,
+            width: self.width + edges.horizontal(),
+            height: self.height + edges.vertical(),
+        }
+    }
+}
+
+/// The sizes of the four sides of a box area, e.g. the widths of its
+/// border, in px.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Edges {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl Edges {
+    pub fn new(top: f64, right: f64, bottom: f64, left: f64) -> Self {
+        Self {
+            top,
+            right,
+            bottom,
+            left,
+        }
+    }
+
+    /// Returns the sum of the left and right sides.
+    pub fn horizontal(&self) -> f64 {
+        self.left + self.right
+    }
+
+    /// Returns the sum of the top and bottom sides.
+    pub fn vertical(&self) -> f64 {
+        self.top + self.bottom
+    }
+}
+
+/// The used sizes of the areas of a box.
+/// https://www.w3.org/TR/css-box-3/#box-model
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct BoxModel {
+    /// The content box, in absolute coordinates once layout is done.
+    pub content: Rect,
+    pub padding: Edges,
+    pub border: Edges,
+    /// The used margins. Vertical margins are those before collapsing.
+    pub margin: Edges,
+}
+
+impl BoxModel {
+    /// https://www.w3.org/TR/css-box-3/#padding-box
+    pub fn padding_box(&self) -> Rect {
+        self.content.expand(&self.padding)
+    }
+
+    /// https://www.w3.org/TR/css-box-3/#border-box
+    pub fn border_box(&self) -> Rect {
+        self.padding_box().expand(&self.border)
+    }
+
+    /// https://www.w3.org/TR/css-box-3/#margin-box
+    pub fn margin_box(&self) -> Rect {
+        self.border_box().expand(&self.margin)
+    }
+}
+
+/// The part of an inline box or of a text run in one line box.
+/// https://www.w3.org/TR/css-break-3/#fragment
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Fragment {
+    /// The border box of the part of an inline box, or the content area of
+    /// the part of a text run.
+    pub rect: Rect,
+    /// The text of the part of a text run, which is empty for inline boxes.
+    pub text: String,
+}
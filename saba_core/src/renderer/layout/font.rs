@@ -0,0 +1,58 @@
+//! https://www.w3.org/TR/css-fonts-4/
+
+use crate::renderer::css::computed_style::ComputedStyle;
+
+/// The metrics of the font used to render text with a style, which layout
+/// measures text with.
+pub trait FontMetrics {
+    /// Returns the advance width of `text` in px.
+    fn text_width(&self, text: &str, style: &ComputedStyle) -> f64;
+
+    /// Returns the distance from the top of the glyphs to the baseline in
+    /// px.
+    /// https://www.w3.org/TR/css-inline-3/#ascent-metric
+    fn ascent(&self, style: &ComputedStyle) -> f64;
+
+    /// Returns the distance from the baseline to the bottom of the glyphs
+    /// in px.
+    /// https://www.w3.org/TR/css-inline-3/#descent-metric
+    fn descent(&self, style: &ComputedStyle) -> f64;
+
+    /// https://www.w3.org/TR/css-values-4/#ex
+    fn x_height(&self, style: &ComputedStyle) -> f64 {
+        self.ascent(style) / 2.0
+    }
+}
+
+/// The width of a glyph of the bitmap font at `BITMAP_FONT_SIZE`.
+const GLYPH_WIDTH: f64 = 8.0;
+/// The height of a glyph of the bitmap font at `BITMAP_FONT_SIZE`.
+const GLYPH_HEIGHT: f64 = 16.0;
+/// The distance from the top of a glyph to the baseline.
+const GLYPH_ASCENT: f64 = 12.0;
+const BITMAP_FONT_SIZE: f64 = 16.0;
+
+/// The metrics of the 8x16 bitmap font of Wasabi OS, scaled to the font
+/// size. Every character has the same width.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct BitmapFontMetrics;
+
+impl BitmapFontMetrics {
+    fn scale(style: &ComputedStyle) -> f64 {
+        style.font_size() / BITMAP_FONT_SIZE
+    }
+}
+
+impl FontMetrics for BitmapFontMetrics {
+    fn text_width(&self, text: &str, style: &ComputedStyle) -> f64 {
+        text.chars().count() as f64 * GLYPH_WIDTH * Self::scale(style)
+    }
+
+    fn ascent(&self, style: &ComputedStyle) -> f64 {
+        GLYPH_ASCENT * Self::scale(style)
+    }
+
+    fn descent(&self, style: &ComputedStyle) -> f64 {
+        (GLYPH_HEIGHT - GLYPH_ASCENT) * Self::scale(style)
+    }
+}
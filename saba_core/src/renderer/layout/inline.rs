@@ -0,0 +1,931 @@
+//! https://www.w3.org/TR/CSS2/visuren.html#inline-formatting
+//! https://www.w3.org/TR/css-text-3/#line-breaking
+
+use crate::renderer::css::computed_style::ComputedStyle;
+use crate::renderer::css::computed_style::ComputedValue;
+use crate::renderer::css::computed_style::VerticalAlign;
+use crate::renderer::css::length::LengthPercentage;
+use crate::renderer::layout::block::layout_block;
+use crate::renderer::layout::block::ContainingBlock;
+use crate::renderer::layout::box_model::BoxModel;
+use crate::renderer::layout::box_model::Edges;
+use crate::renderer::layout::box_model::Fragment;
+use crate::renderer::layout::box_model::Rect;
+use crate::renderer::layout::font::FontMetrics;
+use crate::renderer::layout::layout_object::LayoutObject;
+use crate::renderer::layout::layout_object::LayoutObjectKind;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::ops::Range;
+
+/// What an item of an inline formatting context is.
+#[derive(Debug, Clone, PartialEq)]
+enum ItemKind {
+    /// The start of an inline box, with its left margin, border and padding.
+    Start,
+    /// The end of an inline box, with its right padding, border and margin.
+    End,
+    /// A part of a text run with no soft wrap opportunity inside it.
+    Text(String),
+    /// https://www.w3.org/TR/css-display-3/#atomic-inline
+    Atomic,
+    /// A marker outside of the line box, which takes no space in it.
+    /// https://www.w3.org/TR/css-lists-3/#valdef-list-style-position-outside
+    OutsideMarker,
+}
+
+/// A unit of an inline formatting context for line breaking.
+#[derive(Debug, Clone)]
+struct Item {
+    kind: ItemKind,
+    layout_object: Rc<RefCell<LayoutObject>>,
+    /// The advance width.
+    width: f64,
+    /// The width of the spaces at the end of a text part, which don't take
+    /// space at the end of a line.
+    /// https://www.w3.org/TR/css-text-3/#hang
+    trailing_space: f64,
+    /// Whether there is a soft wrap opportunity after this item.
+    /// https://www.w3.org/TR/css-text-3/#soft-wrap-opportunity
+    break_after: bool,
+    /// Whether a preserved segment break follows this item.
+    /// https://www.w3.org/TR/css-text-3/#forced-line-break
+    forced_break_after: bool,
+    /// The alignment of the item, or of the inline box it belongs to if it
+    /// is aligned to the baseline.
+    vertical_align: VerticalAlign,
+}
+
+impl Item {
+    fn new(
+        kind: ItemKind,
+        layout_object: &Rc<RefCell<LayoutObject>>,
+        width: f64,
+        vertical_align: VerticalAlign,
+    ) -> Self {
+        Self {
+            kind,
+            layout_object: layout_object.clone(),
+            width,
+            trailing_space: 0.0,
+            break_after: false,
+            forced_break_after: false,
+            vertical_align,
+        }
+    }
+
+    /// Returns true if the item makes a line box not empty.
+    /// https://www.w3.org/TR/CSS2/visuren.html#phantom-line-box
+    fn is_content(&self) -> bool {
+        match &self.kind {
+            ItemKind::Start | ItemKind::End => self.width != 0.0,
+            ItemKind::Text(text) => !text.is_empty() || self.forced_break_after,
+            ItemKind::Atomic | ItemKind::OutsideMarker => true,
+        }
+    }
+}
+
+/// Lays out `children`, the inline-level boxes of a block container whose
+/// style is `style`, in line boxes as wide as `containing_block`, and
+/// returns their total height and the position of the baseline of the last
+/// line box. Positions are relative to the content box of the block
+/// container.
+/// https://www.w3.org/TR/CSS2/visuren.html#inline-formatting
+pub(crate) fn layout_inline_content(
+    children: &[Rc<RefCell<LayoutObject>>],
+    style: &ComputedStyle,
+    containing_block: ContainingBlock,
+    metrics: &dyn FontMetrics,
+) -> (f64, Option<f64>) {
+    let atomic_width = |layout_object: &Rc<RefCell<LayoutObject>>| {
+        let kind = layout_object.borrow().kind();
+        match kind {
+            // Images of generated content have no intrinsic size.
+            LayoutObjectKind::Image(_) => {
+                *layout_object.borrow_mut().box_model_mut() = BoxModel::default();
+                0.0
+            }
+            // https://www.w3.org/TR/CSS2/visuren.html#inline-formatting
+            // An inline-block establishes a new block formatting context.
+            _ => {
+                layout_block(layout_object, containing_block, true, metrics);
+                let width = layout_object.borrow().box_model().margin_box().width;
+                width
+            }
+        }
+    };
+    let mut items = Vec::new();
+    collect_items(
+        children,
+        containing_block.width,
+        VerticalAlign::Baseline,
+        metrics,
+        &atomic_width,
+        &mut items,
+    );
+
+    let mut height = 0.0;
+    let mut baseline = None;
+    // The inline boxes that continue from the previous line.
+    let mut open = Vec::new();
+    for line in break_lines(&items, containing_block.width) {
+        let line = &mut items[line];
+        let (line_height, line_baseline) =
+            layout_line(line, &open, style, containing_block.width, height, metrics);
+        height += line_height;
+        baseline = line_baseline.or(baseline);
+        for item in line.iter() {
+            match item.kind {
+                ItemKind::Start => open.push((item.layout_object.clone(), item.vertical_align)),
+                ItemKind::End => {
+                    open.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    (height, baseline)
+}
+
+/// Returns the minimum and maximum content widths of the block container
+/// `layout_object`: the widths of its content box with all the soft wrap
+/// opportunities taken, and with none of them.
+/// https://www.w3.org/TR/css-sizing-3/#intrinsic-sizes
+pub(crate) fn intrinsic_widths(
+    layout_object: &Rc<RefCell<LayoutObject>>,
+    metrics: &dyn FontMetrics,
+) -> (f64, f64) {
+    let children = layout_object.borrow().children().to_vec();
+    if children.iter().any(|c| c.borrow().is_block_level()) {
+        return children
+            .iter()
+            .map(|c| intrinsic_contributions(c, metrics))
+            .fold((0.0, 0.0), |(min, max), (c_min, c_max)| {
+                (f64::max(min, c_min), f64::max(max, c_max))
+            });
+    }
+
+    let widest_line = |width: f64| {
+        let atomic_width = |atomic: &Rc<RefCell<LayoutObject>>| {
+            let contributions = intrinsic_contributions(atomic, metrics);
+            if width == 0.0 {
+                contributions.0
+            } else {
+                contributions.1
+            }
+        };
+        let mut items = Vec::new();
+        collect_items(
+            &children,
+            0.0,
+            VerticalAlign::Baseline,
+            metrics,
+            &atomic_width,
+            &mut items,
+        );
+        break_lines(&items, width)
+            .into_iter()
+            .map(|line| line_width(&items[line]))
+            .fold(0.0, f64::max)
+    };
+    (widest_line(0.0), widest_line(f64::INFINITY))
+}
+
+/// Returns the minimum and maximum content contributions of
+/// `layout_object`: the widths of its margin box for its intrinsic widths.
+/// Percentages and `auto` are treated as 0.
+/// https://www.w3.org/TR/css-sizing-3/#contributions
+fn intrinsic_contributions(
+    layout_object: &Rc<RefCell<LayoutObject>>,
+    metrics: &dyn FontMetrics,
+) -> (f64, f64) {
+    if let LayoutObjectKind::Image(_) = layout_object.borrow().kind() {
+        return (0.0, 0.0);
+    }
+    let style = layout_object.borrow().style();
+    let length = |property: &str| match style.length_percentage(property) {
+        Some(LengthPercentage::Length(px)) => *px,
+        _ => style.length(property).unwrap_or(0.0),
+    };
+    let inner = length("padding-left")
+        + length("padding-right")
+        + length("border-left-width")
+        + length("border-right-width");
+    let outer = inner + length("margin-left") + length("margin-right");
+    let (min, max) = match style.length_percentage("width") {
+        Some(LengthPercentage::Length(width)) => {
+            let width = match style.keyword("box-sizing") {
+                Some("border-box") => (width - inner).max(0.0),
+                _ => *width,
+            };
+            (width, width)
+        }
+        _ => intrinsic_widths(layout_object, metrics),
+    };
+    (min + outer, max + outer)
+}
+
+/// Appends the items of the inline-level boxes `children` to `items`.
+/// Percentages of margins and paddings are relative to `cb_width`.
+/// `atomic_width` returns the width of the margin box of an atomic inline,
+/// and `vertical_align` is the alignment of the parent inline box.
+fn collect_items(
+    children: &[Rc<RefCell<LayoutObject>>],
+    cb_width: f64,
+    vertical_align: VerticalAlign,
+    metrics: &dyn FontMetrics,
+    atomic_width: &dyn Fn(&Rc<RefCell<LayoutObject>>) -> f64,
+    items: &mut Vec<Item>,
+) {
+    for child in children {
+        let kind = child.borrow().kind();
+        let style = child.borrow().style();
+        match kind {
+            LayoutObjectKind::Inline => {
+                let box_model = inline_box_model(&style, cb_width);
+                *child.borrow_mut().box_model_mut() = box_model;
+                child.borrow_mut().fragments_mut().clear();
+                // A box aligned to the baseline moves with its parent.
+                let vertical_align = match style.vertical_align() {
+                    VerticalAlign::Baseline => vertical_align,
+                    other => other,
+                };
+                let start = box_model.margin.left + box_model.border.left + box_model.padding.left;
+                items.push(Item::new(ItemKind::Start, child, start, vertical_align));
+                let grandchildren = child.borrow().children().to_vec();
+                collect_items(
+                    &grandchildren,
+                    cb_width,
+                    vertical_align,
+                    metrics,
+                    atomic_width,
+                    items,
+                );
+                let end = box_model.padding.right + box_model.border.right + box_model.margin.right;
+                items.push(Item::new(ItemKind::End, child, end, vertical_align));
+            }
+            LayoutObjectKind::Text(text) => {
+                child.borrow_mut().fragments_mut().clear();
+                collect_text(child, &text, &style, vertical_align, metrics, items);
+            }
+            LayoutObjectKind::Marker(text) => {
+                let width = metrics.text_width(&text, &style);
+                let item = match style.keyword("list-style-position") {
+                    Some("inside") => Item::new(ItemKind::Atomic, child, width, vertical_align),
+                    _ => Item::new(ItemKind::OutsideMarker, child, 0.0, vertical_align),
+                };
+                items.push(item);
+            }
+            LayoutObjectKind::InlineBlock | LayoutObjectKind::Image(_) => {
+                // https://www.w3.org/TR/css-text-3/#line-break-details
+                // There are soft wrap opportunities before and after atomic
+                // inlines.
+                let wraps = style.white_space().wraps();
+                if let Some(last) = items.last_mut() {
+                    last.break_after |= wraps;
+                }
+                let vertical_align = style.vertical_align();
+                let mut item =
+                    Item::new(ItemKind::Atomic, child, atomic_width(child), vertical_align);
+                item.break_after = wraps;
+                items.push(item);
+            }
+            LayoutObjectKind::Block | LayoutObjectKind::ListItem => {}
+        }
+    }
+}
+
+/// Appends the items of the text run `layout_object` whose text is `text`.
+fn collect_text(
+    layout_object: &Rc<RefCell<LayoutObject>>,
+    text: &str,
+    style: &ComputedStyle,
+    vertical_align: VerticalAlign,
+    metrics: &dyn FontMetrics,
+    items: &mut Vec<Item>,
+) {
+    let white_space = style.white_space();
+    let segments: Vec<&str> = match white_space.preserves_segment_breaks() {
+        true => text.split('\n').collect(),
+        false => vec![text],
+    };
+    for (i, segment) in segments.iter().enumerate() {
+        let start = items.len();
+        for part in split_at_soft_wraps(segment, white_space.wraps()) {
+            let width = metrics.text_width(part, style);
+            let mut item = Item::new(
+                ItemKind::Text(part.to_string()),
+                layout_object,
+                width,
+                vertical_align,
+            );
+            item.trailing_space = width - metrics.text_width(part.trim_end_matches(' '), style);
+            item.break_after = white_space.wraps() && part.ends_with(' ');
+            items.push(item);
+        }
+        if i + 1 < segments.len() {
+            if items.len() == start {
+                items.push(Item::new(
+                    ItemKind::Text(String::new()),
+                    layout_object,
+                    0.0,
+                    vertical_align,
+                ));
+            }
+            if let Some(last) = items.last_mut() {
+                last.forced_break_after = true;
+            }
+        }
+    }
+}
+
+/// Splits `text` after each sequence of spaces, which are soft wrap
+/// opportunities if `wraps` is true.
+/// https://www.w3.org/TR/css-text-3/#soft-wrap-opportunity
+fn split_at_soft_wraps(text: &str, wraps: bool) -> Vec<&str> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+    if !wraps {
+        return vec![text];
+    }
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut is_after_space = false;
+    for (i, c) in text.char_indices() {
+        if is_after_space && c != ' ' {
+            parts.push(&text[start..i]);
+            start = i;
+        }
+        is_after_space = c == ' ';
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Returns the ranges of `items` in each line box `width` wide. A line is
+/// broken at the last soft wrap opportunity before the item that overflows
+/// it, or after the item if there is none.
+/// https://www.w3.org/TR/css-text-3/#line-breaking
+fn break_lines(items: &[Item], width: f64) -> Vec<Range<usize>> {
+    // The end of a line, after the item at `i` and the ends of the inline
+    // boxes that follow it.
+    let line_end = |i: usize| {
+        let mut end = i + 1;
+        while items
+            .get(end)
+            .map_or(false, |item| item.kind == ItemKind::End)
+        {
+            end += 1;
+        }
+        end
+    };
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut x = 0.0;
+    let mut last_break = None;
+    let mut has_content = false;
+    let mut i = 0;
+    while i < items.len() {
+        let item = &items[i];
+        let end = match last_break {
+            Some(b) if has_content && x + item.width - item.trailing_space > width => Some(b),
+            _ => {
+                x += item.width;
+                has_content |= item.is_content();
+                if item.break_after {
+                    last_break = Some(i);
+                }
+                match item.forced_break_after {
+                    true => Some(i),
+                    false => None,
+                }
+            }
+        };
+        match end {
+            Some(end) => {
+                let end = line_end(end);
+                lines.push(start..end);
+                start = end;
+                i = end;
+                x = 0.0;
+                last_break = None;
+                has_content = false;
+            }
+            None => i += 1,
+        }
+    }
+    if start < items.len() {
+        lines.push(start..items.len());
+    }
+    lines
+}
+
+/// Returns the width of the spaces at the end of the line of `items`, which
+/// hang or are removed.
+/// https://www.w3.org/TR/css-text-3/#white-space-phase-2
+fn trailing_space(items: &[Item]) -> f64 {
+    items
+        .iter()
+        .rev()
+        .find(|item| item.kind != ItemKind::End)
+        .map_or(0.0, |item| item.trailing_space)
+}
+
+/// Returns the width of the content of the line of `items`.
+fn line_width(items: &[Item]) -> f64 {
+    items.iter().map(|item| item.width).sum::<f64>() - trailing_space(items)
+}
+
+/// Lays out the line of `items`, whose top is at `y`, in a block container
+/// whose style is `style` and whose content box is `width` wide, and
+/// returns the height of the line box and the position of its baseline, or
+/// `None` if the line box is empty. `open` are the inline boxes that
+/// continue from the previous line, with their alignment.
+/// https://www.w3.org/TR/CSS2/visudet.html#line-height
+fn layout_line(
+    items: &mut [Item],
+    open: &[(Rc<RefCell<LayoutObject>>, VerticalAlign)],
+    style: &ComputedStyle,
+    width: f64,
+    y: f64,
+    metrics: &dyn FontMetrics,
+) -> (f64, Option<f64>) {
+    if !items.iter().any(|item| item.is_content()) {
+        return (0.0, None);
+    }
+
+    // https://www.w3.org/TR/css-text-3/#white-space-phase-2
+    // Collapsible spaces at the end of a line are removed.
+    if let Some(last) = items
+        .iter_mut()
+        .rev()
+        .find(|item| item.kind != ItemKind::End)
+    {
+        let white_space = last.layout_object.borrow().style().white_space();
+        if let (ItemKind::Text(text), true) = (&mut last.kind, white_space.collapses_spaces()) {
+            text.truncate(text.trim_end_matches(' ').len());
+            last.width -= last.trailing_space;
+            last.trailing_space = 0.0;
+        }
+    }
+
+    // https://www.w3.org/TR/css-text-3/#text-align-property
+    let free_space = (width - line_width(items)).max(0.0);
+    let mut x = match style.keyword("text-align") {
+        Some("right") | Some("end") => free_space,
+        Some("center") => free_space / 2.0,
+        _ => 0.0,
+    };
+
+    // The horizontal extent of each part of an inline box, text run or
+    // atomic inline in this line, with its alignment.
+    let mut inline_boxes: Vec<(Rc<RefCell<LayoutObject>>, VerticalAlign, f64, f64)> = open
+        .iter()
+        .map(|(b, vertical_align)| (b.clone(), *vertical_align, x, x))
+        .collect();
+    // The inline boxes that aren't closed yet, as indices in
+    // `inline_boxes`.
+    let mut stack: Vec<usize> = (0..inline_boxes.len()).collect();
+    let mut placed = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        match item.kind {
+            ItemKind::Start => {
+                let margin_left = item.layout_object.borrow().box_model().margin.left;
+                stack.push(inline_boxes.len());
+                inline_boxes.push((
+                    item.layout_object.clone(),
+                    item.vertical_align,
+                    x + margin_left,
+                    x + margin_left,
+                ));
+            }
+            ItemKind::End => {
+                let margin_right = item.layout_object.borrow().box_model().margin.right;
+                if let Some(j) = stack.pop() {
+                    inline_boxes[j].3 = x + item.width - margin_right;
+                }
+            }
+            ItemKind::OutsideMarker => {
+                let kind = item.layout_object.borrow().kind();
+                let text = match kind {
+                    LayoutObjectKind::Marker(text) => text,
+                    _ => String::new(),
+                };
+                let marker_width = metrics.text_width(&text, &item.layout_object.borrow().style());
+                placed.push((i, -marker_width));
+            }
+            ItemKind::Text(_) | ItemKind::Atomic => placed.push((i, x)),
+        }
+        x += item.width;
+    }
+    for j in stack {
+        inline_boxes[j].3 = x;
+    }
+
+    // https://www.w3.org/TR/CSS2/visudet.html#inline-box-height
+    // Each box is aligned relative to the baseline. A zero width strut with
+    // the font and line height of the block container starts the line.
+    let (strut_height, strut_ascent) = layout_bounds(style, metrics);
+    let mut above = strut_ascent;
+    let mut below = strut_height - strut_ascent;
+    let boxes: Vec<VerticalBox> = inline_boxes
+        .iter()
+        .map(|(b, vertical_align, ..)| VerticalBox::new(b, None, *vertical_align, style, metrics))
+        .chain(placed.iter().map(|(i, _)| {
+            let item = &items[*i];
+            VerticalBox::new(
+                &item.layout_object,
+                Some(&item.kind),
+                item.vertical_align,
+                style,
+                metrics,
+            )
+        }))
+        .collect();
+    for b in &boxes {
+        if let Some(top) = b.top_relative_to_baseline() {
+            above = f64::max(above, -top);
+            below = f64::max(below, top + b.height);
+        }
+    }
+    // Boxes aligned to the top or bottom of the line box grow it as little
+    // as possible.
+    for b in &boxes {
+        match b.vertical_align {
+            VerticalAlign::Top if b.height > above + below => below = b.height - above,
+            VerticalAlign::Bottom if b.height > above + below => above = b.height - below,
+            _ => {}
+        }
+    }
+    let line_height = above + below;
+    let baseline = y + above;
+    // Returns the top of the layout bounds of a box.
+    let box_top = |b: &VerticalBox| match b.top_relative_to_baseline() {
+        Some(top) => baseline + top,
+        None if b.vertical_align == VerticalAlign::Top => y,
+        None => y + line_height - b.height,
+    };
+    // Returns the top and the height of the content area of a box, which
+    // is centered in its layout bounds.
+    let content_area = |b: &VerticalBox| {
+        let height = metrics.ascent(&b.style) + metrics.descent(&b.style);
+        (box_top(b) + (b.height - height) / 2.0, height)
+    };
+
+    for ((layout_object, _, start, end), b) in inline_boxes.iter().zip(&boxes) {
+        let (area_top, area_height) = content_area(b);
+        let box_model = layout_object.borrow().box_model();
+        let rect = Rect::new(
+            *start,
+            area_top - box_model.padding.top - box_model.border.top,
+            end - start,
+            area_height + box_model.padding.vertical() + box_model.border.vertical(),
+        );
+        add_fragment(layout_object, rect, String::new(), false);
+    }
+    let placed_boxes = &boxes[inline_boxes.len()..];
+    for (k, ((i, x), b)) in placed.iter().zip(placed_boxes).enumerate() {
+        let item = &items[*i];
+        let kind = item.layout_object.borrow().kind();
+        match (&item.kind, kind) {
+            (ItemKind::Text(text), _) => {
+                let (area_top, area_height) = content_area(b);
+                let rect = Rect::new(*x, area_top, item.width, area_height);
+                // Adjacent parts of a text run are in the same fragment.
+                let continues = k > 0
+                    && placed[k - 1].0 + 1 == *i
+                    && Rc::ptr_eq(&items[*i - 1].layout_object, &item.layout_object);
+                add_fragment(&item.layout_object, rect, text.clone(), continues);
+            }
+            (_, LayoutObjectKind::Marker(text)) => {
+                let (area_top, area_height) = content_area(b);
+                let width = metrics.text_width(&text, &b.style);
+                item.layout_object.borrow_mut().box_model_mut().content =
+                    Rect::new(*x, area_top, width, area_height);
+            }
+            _ => {
+                let top = box_top(b);
+                let mut layout_object = item.layout_object.borrow_mut();
+                let box_model = layout_object.box_model_mut();
+                let margin_top = box_model.margin.top;
+                box_model.content = box_model.content.translate(*x, top + margin_top);
+            }
+        }
+    }
+    (line_height, Some(baseline))
+}
+
+/// The vertical metrics of a box in a line box.
+/// https://www.w3.org/TR/CSS2/visudet.html#line-height
+struct VerticalBox {
+    style: Rc<ComputedStyle>,
+    /// The height of the layout bounds of the box: its line height, or the
+    /// height of the margin box of an atomic inline.
+    height: f64,
+    /// The distance from the top of its layout bounds to its baseline.
+    ascent: f64,
+    vertical_align: VerticalAlign,
+    /// The x-height of the parent box.
+    parent_x_height: f64,
+}
+
+impl VerticalBox {
+    /// Returns the metrics of `layout_object`, which is an inline box if
+    /// `kind` is `None`, in the line box of a block container whose style
+    /// is `style`.
+    fn new(
+        layout_object: &Rc<RefCell<LayoutObject>>,
+        kind: Option<&ItemKind>,
+        vertical_align: VerticalAlign,
+        style: &ComputedStyle,
+        metrics: &dyn FontMetrics,
+    ) -> Self {
+        let layout_object = layout_object.borrow();
+        let box_style = layout_object.style();
+        let (height, ascent) = match (kind, layout_object.kind()) {
+            (Some(ItemKind::Atomic), LayoutObjectKind::Image(_)) => (0.0, 0.0),
+            (Some(ItemKind::Atomic), LayoutObjectKind::InlineBlock) => {
+                // https://www.w3.org/TR/CSS2/visudet.html#propdef-vertical-align
+                // The baseline of an inline-block is the baseline of its
+                // last line box, or its bottom margin edge if it has none.
+                let box_model = layout_object.box_model();
+                let height = box_model.margin_box().height;
+                let ascent = layout_object.baseline().map_or(height, |baseline| {
+                    box_model.margin.top + box_model.content.y + baseline
+                });
+                (height, ascent)
+            }
+            _ => layout_bounds(&box_style, metrics),
+        };
+        let parent_x_height = match layout_object.parent().upgrade() {
+            Some(parent) => metrics.x_height(&parent.borrow().style()),
+            None => metrics.x_height(style),
+        };
+        Self {
+            style: box_style,
+            height,
+            ascent,
+            vertical_align,
+            parent_x_height,
+        }
+    }
+
+    /// Returns the position of the top of the layout bounds relative to the
+    /// baseline of the line box, or `None` if the box is aligned to the top
+    /// or bottom of the line box.
+    fn top_relative_to_baseline(&self) -> Option<f64> {
+        match self.vertical_align {
+            VerticalAlign::Baseline => Some(-self.ascent),
+            // The middle of the box is aligned with the baseline plus half
+            // the x-height of the parent.
+            VerticalAlign::Middle => Some(-self.parent_x_height / 2.0 - self.height / 2.0),
+            VerticalAlign::Top | VerticalAlign::Bottom => None,
+        }
+    }
+}
+
+/// Returns the height of the layout bounds of an inline box or a text run
+/// whose style is `style`, which is its line height, and the distance from
+/// their top to the baseline. The leading is split equally above and below
+/// the content area.
+/// https://www.w3.org/TR/CSS2/visudet.html#leading
+fn layout_bounds(style: &ComputedStyle, metrics: &dyn FontMetrics) -> (f64, f64) {
+    let ascent = metrics.ascent(style);
+    let descent = metrics.descent(style);
+    let height = line_height(style, metrics);
+    let leading = height - (ascent + descent);
+    (height, ascent + leading / 2.0)
+}
+
+/// Returns the used line height in px.
+/// https://www.w3.org/TR/CSS2/visudet.html#propdef-line-height
+fn line_height(style: &ComputedStyle, metrics: &dyn FontMetrics) -> f64 {
+    match style.get("line-height") {
+        Some(ComputedValue::Number(n)) => n * style.font_size(),
+        Some(ComputedValue::Length(px)) => *px,
+        // `normal` is the height of the content area.
+        _ => metrics.ascent(style) + metrics.descent(style),
+    }
+}
+
+/// Returns the used padding, border and margin of an inline box. Vertical
+/// margins of inline boxes have no effect.
+/// https://www.w3.org/TR/CSS2/visudet.html#inline-non-replaced
+fn inline_box_model(style: &ComputedStyle, cb_width: f64) -> BoxModel {
+    let resolve = |property: &str| {
+        style
+            .length_percentage(property)
+            .map_or(0.0, |v| v.resolve(cb_width))
+    };
+    BoxModel {
+        content: Rect::default(),
+        padding: Edges::new(
+            resolve("padding-top"),
+            resolve("padding-right"),
+            resolve("padding-bottom"),
+            resolve("padding-left"),
+        ),
+        border: Edges::new(
+            style.length("border-top-width").unwrap_or(0.0),
+            style.length("border-right-width").unwrap_or(0.0),
+            style.length("border-bottom-width").unwrap_or(0.0),
+            style.length("border-left-width").unwrap_or(0.0),
+        ),
+        margin: Edges::new(0.0, resolve("margin-right"), 0.0, resolve("margin-left")),
+    }
+}
+
+/// Adds a fragment to the inline box or text run `layout_object`, or
+/// extends its last fragment if `continues` is true. Its content box
+/// becomes the bounding box of its fragments.
+fn add_fragment(
+    layout_object: &Rc<RefCell<LayoutObject>>,
+    rect: Rect,
+    text: String,
+    continues: bool,
+) {
+    let mut layout_object = layout_object.borrow_mut();
+    let fragment = match layout_object.fragments_mut().pop() {
+        Some(mut last) if continues => {
+            last.rect.width = rect.x + rect.width - last.rect.x;
+            last.text.push_str(&text);
+            last
+        }
+        last => {
+            layout_object.fragments_mut().extend(last);
+            Fragment { rect, text }
+        }
+    };
+    let content = match layout_object.fragments().first() {
+        None => fragment.rect,
+        Some(_) => union(&layout_object.box_model().content, &fragment.rect),
+    };
+    layout_object.box_model_mut().content = content;
+    layout_object.fragments_mut().push(fragment);
+}
+
+/// Returns the bounding box of `a` and `b`.
+fn union(a: &Rect, b: &Rect) -> Rect {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    Rect::new(
+        x,
+        y,
+        (a.x + a.width).max(b.x + b.width) - x,
+        (a.y + a.height).max(b.y + b.height) - y,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::layout::tests::layout_tree;
+    use alloc::string::String;
+
+    /// Returns the layout tree of the body of `html` in an 800x600
+    /// viewport, with the fragments of inline boxes and text.
+    fn layout(html: &str) -> String {
+        layout_tree(html, true, true)
+    }
+
+    #[test]
+    fn test_line_breaking() {
+        // #a: a word wider than the line overflows it. #b and #c: the
+        // spaces at the end of a line don't count when aligning it.
+        assert_eq!(
+            r#"Block <p> (0, 0) 80x80
+  Text "aaa bbb ccc ddddddddddddd e"
+    | "aaa bbb" (0, 2) 56x16
+    | "ccc" (0, 22) 24x16
+    | "ddddddddddddd" (0, 42) 104x16
+    | "e" (0, 62) 8x16
+Block <p> (0, 80) 80x40
+  Text "aaa bbb ccc"
+    | "aaa bbb" (24, 82) 56x16
+    | "ccc" (56, 102) 24x16
+Block <p> (0, 120) 80x40
+  Text "aaa bbb ccc"
+    | "aaa bbb" (12, 122) 56x16
+    | "ccc" (28, 142) 24x16
+"#,
+            layout(
+                "<style>
+  body { margin: 0; width: 80px; line-height: 20px }
+  p { margin: 0 }
+  #b { text-align: right }
+  #c { text-align: center }
+</style><p id=a>aaa bbb ccc ddddddddddddd e</p><p id=b>aaa bbb ccc</p><p id=c>aaa bbb ccc</p>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_white_space() {
+        // #a: preserved spaces and segment breaks. #b: no wrapping. #c:
+        // preserved spaces hang at the end of a line.
+        assert_eq!(
+            r#"Block <p> (0, 0) 80x60
+  Text "a  b\n\nc"
+    | "a  b" (0, 2) 32x16
+    | (0, 22) 0x16
+    | "c" (0, 42) 8x16
+Block <p> (0, 60) 80x20
+  Text "aaa bbb ccc ddd"
+    | "aaa bbb ccc ddd" (0, 62) 120x16
+Block <p> (0, 80) 80x40
+  Text "aaa bbb    ccc"
+    | "aaa bbb    " (0, 82) 88x16
+    | "ccc" (0, 102) 24x16
+"#,
+            layout(
+                "<style>
+  body { margin: 0; width: 80px; line-height: 20px }
+  p { margin: 0 }
+  #a { white-space: pre }
+  #b { white-space: nowrap }
+  #c { white-space: pre-wrap }
+</style><p id=a>a  b
+
+c</p><p id=b>aaa bbb ccc ddd</p><p id=c>aaa bbb    ccc</p>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_inline_boxes() {
+        // An inline box split across lines has its start edge in the first
+        // fragment and its end edge in the last one.
+        assert_eq!(
+            r#"Block <p> (0, 0) 80x40
+  Text "aa "
+    | "aa " (0, 2) 24x16
+  Inline <a>
+    | (27, -1) 53x22
+    | (0, 19) 21x22
+    Text "bb ccc dd"
+      | "bb ccc" (32, 2) 48x16
+      | "dd" (0, 22) 16x16
+  Text " e"
+    | " e" (24, 22) 16x16
+"#,
+            layout(
+                "<style>
+  body { margin: 0; width: 80px; line-height: 20px }
+  p { margin: 0 }
+  a { padding: 2px 4px; border: 1px solid; margin: 0 3px }
+</style><p>aa <a>bb ccc dd</a> e</p>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_vertical_align() {
+        // #a: a taller inline box. #b: a box aligned to the top of the line
+        // box grows it downwards. #d: an inline-block that shrinks to fit,
+        // aligned by the baseline of its last line box.
+        assert_eq!(
+            r#"Block <p> (0, 0) 800x40
+  Text "a"
+    | "a" (0, 16) 8x16
+  Inline <a>
+    | (8, 4) 16x32
+    Text "b"
+      | "b" (8, 4) 16x32
+  Text "c"
+    | "c" (24, 16) 8x16
+Block <p> (0, 40) 800x60
+  Text "a"
+    | "a" (0, 42) 8x16
+  Inline <a>
+    | (8, 62) 8x16
+    Text "b"
+      | "b" (8, 62) 8x16
+  Inline <a>
+    | (16, 43) 8x16
+    Text "c"
+      | "c" (16, 43) 8x16
+Block <p> (0, 100) 800x30
+  Text "a"
+    | "a" (0, 102) 8x16
+  InlineBlock <a> (8, 100) 24x30
+    Text "d d"
+      | "d d" (8, 102) 24x16
+"#,
+            layout(
+                "<style>
+  body { margin: 0; line-height: 20px }
+  p { margin: 0 }
+  #a { font-size: 32px; line-height: 40px }
+  #b { vertical-align: top; line-height: 60px }
+  #c { vertical-align: middle }
+  #d { display: inline-block; height: 30px }
+</style><p>a<a id=a>b</a>c</p><p>a<a id=b>b</a><a id=c>c</a></p><p>a<a id=d>d d</a></p>"
+            )
+        );
+    }
+}
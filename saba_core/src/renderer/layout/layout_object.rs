@@ -0,0 +1,181 @@
+//! https://www.w3.org/TR/css-display-3/#box-tree
+
+use crate::renderer::css::computed_style::ComputedStyle;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::selector::PseudoElement;
+use crate::renderer::layout::box_model::BoxModel;
+use crate::renderer::layout::box_model::Fragment;
+use alloc::rc::Rc;
+use alloc::rc::Weak;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// https://www.w3.org/TR/css-display-3/#box
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutObjectKind {
+    /// A block-level block container, e.g. of `display: block`.
+    /// https://www.w3.org/TR/css-display-3/#block-container
+    Block,
+    /// A block with a marker.
+    /// https://www.w3.org/TR/css-display-3/#list-items
+    ListItem,
+    /// https://www.w3.org/TR/css-display-3/#inline-box
+    Inline,
+    /// An inline-level block container.
+    /// https://www.w3.org/TR/css-display-3/#valdef-display-inline-block
+    InlineBlock,
+    /// A run of text after white space processing.
+    /// https://www.w3.org/TR/css-display-3/#text-run
+    Text(String),
+    /// The marker of a list item.
+    /// https://www.w3.org/TR/css-lists-3/#marker-pseudo
+    Marker(String),
+    /// An image of generated content, with its URL.
+    Image(String),
+}
+
+/// A box of the layout tree.
+#[derive(Debug, Clone)]
+pub struct LayoutObject {
+    kind: LayoutObjectKind,
+    /// The node that generates this box, or the originating element of a
+    /// pseudo-element. It is `None` for anonymous boxes.
+    /// https://www.w3.org/TR/css-display-3/#anonymous
+    node: Option<Rc<RefCell<Node>>>,
+    pseudo_element: Option<PseudoElement>,
+    style: Rc<ComputedStyle>,
+    parent: Weak<RefCell<LayoutObject>>,
+    children: Vec<Rc<RefCell<LayoutObject>>>,
+    box_model: BoxModel,
+    fragments: Vec<Fragment>,
+    /// The position of the baseline of the last line box in this block
+    /// container, relative to the top of its content box.
+    baseline: Option<f64>,
+}
+
+impl LayoutObject {
+    pub fn new(
+        kind: LayoutObjectKind,
+        node: Option<Rc<RefCell<Node>>>,
+        pseudo_element: Option<PseudoElement>,
+        style: Rc<ComputedStyle>,
+    ) -> Self {
+        Self {
+            kind,
+            node,
+            pseudo_element,
+            style,
+            parent: Weak::new(),
+            children: Vec::new(),
+            box_model: BoxModel::default(),
+            fragments: Vec::new(),
+            baseline: None,
+        }
+    }
+
+    pub fn kind(&self) -> LayoutObjectKind {
+        self.kind.clone()
+    }
+
+    pub(crate) fn kind_mut(&mut self) -> &mut LayoutObjectKind {
+        &mut self.kind
+    }
+
+    pub fn node(&self) -> Option<Rc<RefCell<Node>>> {
+        self.node.clone()
+    }
+
+    /// Returns the pseudo-element that generates this box, if any.
+    pub fn pseudo_element(&self) -> Option<PseudoElement> {
+        self.pseudo_element
+    }
+
+    pub fn style(&self) -> Rc<ComputedStyle> {
+        self.style.clone()
+    }
+
+    pub fn parent(&self) -> Weak<RefCell<LayoutObject>> {
+        self.parent.clone()
+    }
+
+    pub fn children(&self) -> &[Rc<RefCell<LayoutObject>>] {
+        &self.children
+    }
+
+    /// Returns the used sizes and the position of this box, which are set by
+    /// `LayoutView::layout`.
+    pub fn box_model(&self) -> BoxModel {
+        self.box_model
+    }
+
+    pub(crate) fn box_model_mut(&mut self) -> &mut BoxModel {
+        &mut self.box_model
+    }
+
+    /// Returns the parts of this inline box or text run in each line box it
+    /// spans, in order, which are set by `LayoutView::layout`.
+    pub fn fragments(&self) -> &[Fragment] {
+        &self.fragments
+    }
+
+    pub(crate) fn fragments_mut(&mut self) -> &mut Vec<Fragment> {
+        &mut self.fragments
+    }
+
+    /// Returns the position of the baseline of the last line box in this
+    /// block container relative to the top of its content box, or `None`
+    /// if it has no line boxes.
+    /// https://www.w3.org/TR/css-inline-3/#last-baseline
+    pub(crate) fn baseline(&self) -> Option<f64> {
+        self.baseline
+    }
+
+    pub(crate) fn set_baseline(&mut self, baseline: Option<f64>) {
+        self.baseline = baseline;
+    }
+
+    /// Returns true if this box isn't generated by a node.
+    pub fn is_anonymous(&self) -> bool {
+        self.node.is_none()
+    }
+
+    /// https://www.w3.org/TR/css-display-3/#block-level
+    pub fn is_block_level(&self) -> bool {
+        matches!(
+            self.kind,
+            LayoutObjectKind::Block | LayoutObjectKind::ListItem
+        )
+    }
+
+    /// https://www.w3.org/TR/css-display-3/#block-container
+    pub fn is_block_container(&self) -> bool {
+        matches!(
+            self.kind,
+            LayoutObjectKind::Block | LayoutObjectKind::ListItem | LayoutObjectKind::InlineBlock
+        )
+    }
+
+    /// Returns true if this box is inline-level and is laid out as a single
+    /// unit in a line.
+    /// https://www.w3.org/TR/css-display-3/#atomic-inline
+    pub fn is_atomic_inline(&self) -> bool {
+        matches!(
+            self.kind,
+            LayoutObjectKind::InlineBlock
+                | LayoutObjectKind::Marker(_)
+                | LayoutObjectKind::Image(_)
+        )
+    }
+}
+
+/// Replaces the children of `parent` with `children`.
+pub(crate) fn set_children(
+    parent: &Rc<RefCell<LayoutObject>>,
+    children: Vec<Rc<RefCell<LayoutObject>>>,
+) {
+    for child in &children {
+        child.borrow_mut().parent = Rc::downgrade(parent);
+    }
+    parent.borrow_mut().children = children;
+}
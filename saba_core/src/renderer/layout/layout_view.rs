@@ -0,0 +1,576 @@
+//! https://www.w3.org/TR/css-display-3/#box-generation
+
+use crate::renderer::css::cascade::Cascade;
+use crate::renderer::css::computed_style::ComputedStyle;
+use crate::renderer::css::computed_style::Display;
+use crate::renderer::css::content::ContentGenerator;
+use crate::renderer::css::content::CounterStyle;
+use crate::renderer::css::content::GeneratedContent;
+use crate::renderer::dom::node::Node;
+use crate::renderer::dom::node::NodeKind;
+use crate::renderer::dom::selector::PseudoElement;
+use crate::renderer::layout::block::layout_block;
+use crate::renderer::layout::block::make_absolute;
+use crate::renderer::layout::block::translate;
+use crate::renderer::layout::block::ContainingBlock;
+use crate::renderer::layout::box_model::Rect;
+use crate::renderer::layout::font::FontMetrics;
+use crate::renderer::layout::layout_object::set_children;
+use crate::renderer::layout::layout_object::LayoutObject;
+use crate::renderer::layout::layout_object::LayoutObjectKind;
+use crate::renderer::layout::white_space::is_collapsible_white_space;
+use crate::renderer::layout::white_space::WhiteSpaceCollapser;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+
+/// The layout tree of a document: the boxes generated by its elements and
+/// text according to their computed style.
+/// https://www.w3.org/TR/css-display-3/#box-tree
+#[derive(Debug, Clone)]
+pub struct LayoutView {
+    /// The box of the root element, or `None` if it generates no box.
+    root: Option<Rc<RefCell<LayoutObject>>>,
+}
+
+impl LayoutView {
+    /// Builds the layout tree of `document`, whose styles must have been
+    /// computed by `cascade`.
+    pub fn new(document: &Rc<RefCell<Node>>, cascade: &Cascade) -> Self {
+        let root_element = document
+            .borrow()
+            .children()
+            .find(|c| c.borrow().get_element().is_some());
+        let mut builder = LayoutTreeBuilder {
+            cascade,
+            generator: ContentGenerator::new(),
+        };
+        let root = root_element.and_then(|e| builder.build_element(&e, true).into_iter().next());
+        Self { root }
+    }
+
+    pub fn root(&self) -> Option<Rc<RefCell<LayoutObject>>> {
+        self.root.clone()
+    }
+
+    /// Computes the box model of every box for a viewport `width` by
+    /// `height` px, which is the size of the initial containing block, and
+    /// breaks text into lines measured with `metrics`.
+    /// https://www.w3.org/TR/CSS2/visudet.html#containing-block-details
+    pub fn layout(&self, width: f64, height: f64, metrics: &dyn FontMetrics) {
+        let root = match &self.root {
+            Some(root) => root,
+            None => return,
+        };
+        // https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+        // The root element establishes a block formatting context, and its
+        // margins don't collapse.
+        let containing_block = ContainingBlock {
+            width,
+            height: Some(height),
+        };
+        let margins = layout_block(root, containing_block, true, metrics);
+        translate(root, margins.top.value());
+        make_absolute(root, 0.0, 0.0);
+    }
+}
+
+/// Prints the layout tree with one box per line, indented by depth. The
+/// alternate form `{:#}` also prints the border box of each box, or the
+/// content box of a marker, and the fragments of inline boxes and text
+/// below them, one per line.
+impl fmt::Display for LayoutView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.root {
+            Some(root) => write_tree(f, root, 0),
+            None => Ok(()),
+        }
+    }
+}
+
+fn write_tree(
+    f: &mut fmt::Formatter,
+    layout_object: &Rc<RefCell<LayoutObject>>,
+    depth: usize,
+) -> fmt::Result {
+    let layout_object = layout_object.borrow();
+    write!(f, "{:indent$}", "", indent = depth * 2)?;
+    match layout_object.kind() {
+        LayoutObjectKind::Text(text) => write!(f, "Text {:?}", text)?,
+        LayoutObjectKind::Marker(text) => write!(f, "Marker {:?}", text)?,
+        LayoutObjectKind::Image(url) => write!(f, "Image {:?}", url)?,
+        kind => {
+            write!(f, "{:?}", kind)?;
+            match layout_object.node().and_then(|n| n.borrow().get_element()) {
+                Some(element) => write!(f, " <{}>", element.local_name())?,
+                None => write!(f, " (anonymous)")?,
+            }
+            match layout_object.pseudo_element() {
+                Some(PseudoElement::Before) => write!(f, "::before")?,
+                Some(PseudoElement::After) => write!(f, "::after")?,
+                None => {}
+            }
+        }
+    }
+    let fragments = match layout_object.kind() {
+        LayoutObjectKind::Text(_) | LayoutObjectKind::Inline => layout_object.fragments(),
+        _ => &[],
+    };
+    if !f.alternate() {
+        writeln!(f)?;
+    } else if fragments.is_empty() {
+        let rect = match layout_object.kind() {
+            LayoutObjectKind::Marker(_) => layout_object.box_model().content,
+            _ => layout_object.box_model().border_box(),
+        };
+        write_rect(f, &rect)?;
+        writeln!(f)?;
+    } else {
+        writeln!(f)?;
+        for fragment in fragments {
+            write!(f, "{:indent$}|", "", indent = depth * 2 + 2)?;
+            if !fragment.text.is_empty() {
+                write!(f, " {:?}", fragment.text)?;
+            }
+            write_rect(f, &fragment.rect)?;
+            writeln!(f)?;
+        }
+    }
+    for child in layout_object.children() {
+        write_tree(f, child, depth + 1)?;
+    }
+    Ok(())
+}
+
+fn write_rect(f: &mut fmt::Formatter, rect: &Rect) -> fmt::Result {
+    write!(
+        f,
+        " ({}, {}) {}x{}",
+        rect.x, rect.y, rect.width, rect.height
+    )
+}
+
+struct LayoutTreeBuilder<'a> {
+    cascade: &'a Cascade,
+    generator: ContentGenerator,
+}
+
+impl<'a> LayoutTreeBuilder<'a> {
+    /// Returns the boxes that `node`, a child of an element whose style is
+    /// `parent_style`, generates.
+    fn build_node(
+        &mut self,
+        node: &Rc<RefCell<Node>>,
+        parent_style: &Rc<ComputedStyle>,
+    ) -> Vec<Rc<RefCell<LayoutObject>>> {
+        let kind = node.borrow().kind();
+        match kind {
+            NodeKind::Element(_) => self.build_element(node, false),
+            // https://www.w3.org/TR/css-display-3/#text-nodes
+            // Text inherits the style of its parent element.
+            NodeKind::Text(text) => vec![new_box(
+                LayoutObjectKind::Text(text),
+                Some(node.clone()),
+                None,
+                parent_style.clone(),
+            )],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the boxes that `element` and its descendants generate. It is
+    /// usually one box, or none if its `display` is `none`.
+    fn build_element(
+        &mut self,
+        element: &Rc<RefCell<Node>>,
+        is_root: bool,
+    ) -> Vec<Rc<RefCell<LayoutObject>>> {
+        let style = match element.borrow().computed_style() {
+            Some(style) => style,
+            None => return Vec::new(),
+        };
+        let display = match style.display() {
+            Display::None => return Vec::new(),
+            // https://www.w3.org/TR/css-display-3/#root
+            // The root element's display type is blockified.
+            Display::Inline | Display::InlineBlock if is_root => Display::Block,
+            display => display,
+        };
+
+        self.generator.enter(element);
+        let mut items = Vec::new();
+        if display == Display::ListItem {
+            items.extend(self.build_marker(element, &style));
+        }
+        items.extend(self.build_pseudo_element(element, PseudoElement::Before));
+        let children: Vec<Rc<RefCell<Node>>> = element.borrow().children().collect();
+        for child in &children {
+            items.extend(self.build_node(child, &style));
+        }
+        items.extend(self.build_pseudo_element(element, PseudoElement::After));
+
+        self.build_box(display, Some(element.clone()), None, style, items)
+    }
+
+    /// Returns the box of the `::before` or `::after` pseudo-element of
+    /// `element`, if it has one, with its generated content.
+    fn build_pseudo_element(
+        &mut self,
+        element: &Rc<RefCell<Node>>,
+        pseudo_element: PseudoElement,
+    ) -> Vec<Rc<RefCell<LayoutObject>>> {
+        let content = match self.generator.generate(element, pseudo_element) {
+            Some(content) => content,
+            None => return Vec::new(),
+        };
+        let style = match element.borrow().pseudo_element_style(pseudo_element) {
+            Some(style) => style,
+            None => return Vec::new(),
+        };
+        let items = content
+            .into_iter()
+            .map(|c| {
+                let kind = match c {
+                    GeneratedContent::Text(text) => LayoutObjectKind::Text(text),
+                    GeneratedContent::Image(url) => LayoutObjectKind::Image(url),
+                };
+                new_box(
+                    kind,
+                    Some(element.clone()),
+                    Some(pseudo_element),
+                    style.clone(),
+                )
+            })
+            .collect();
+        self.build_box(
+            style.display(),
+            Some(element.clone()),
+            Some(pseudo_element),
+            style,
+            items,
+        )
+    }
+
+    /// Returns the marker box of the list item `element`, whose style is
+    /// `style`, or `None` if its `list-style-type` is `none`.
+    /// https://www.w3.org/TR/css-lists-3/#marker-pseudo
+    fn build_marker(
+        &self,
+        element: &Rc<RefCell<Node>>,
+        style: &Rc<ComputedStyle>,
+    ) -> Option<Rc<RefCell<LayoutObject>>> {
+        let counter_style = style
+            .keyword("list-style-type")
+            .and_then(CounterStyle::from_name)?;
+        // https://www.w3.org/TR/css-counter-styles-3/#simple-symbolic
+        // Symbols are followed by a space, and numbers by a period and a
+        // space.
+        let value = counter_style.format(self.generator.counters().value("list-item"));
+        let text = match counter_style {
+            CounterStyle::None => return None,
+            CounterStyle::Disc | CounterStyle::Circle | CounterStyle::Square => {
+                format!("{} ", value)
+            }
+            _ => format!("{}. ", value),
+        };
+        Some(new_box(
+            LayoutObjectKind::Marker(text),
+            Some(element.clone()),
+            None,
+            style.clone(),
+        ))
+    }
+
+    /// Returns the boxes of an element or a pseudo-element whose display is
+    /// `display`, containing `items`, the boxes of its children.
+    fn build_box(
+        &self,
+        display: Display,
+        node: Option<Rc<RefCell<Node>>>,
+        pseudo_element: Option<PseudoElement>,
+        style: Rc<ComputedStyle>,
+        items: Vec<Rc<RefCell<LayoutObject>>>,
+    ) -> Vec<Rc<RefCell<LayoutObject>>> {
+        let kind = match display {
+            Display::None => return Vec::new(),
+            Display::Inline => return split_inline_box(node, pseudo_element, style, items),
+            Display::Block => LayoutObjectKind::Block,
+            Display::ListItem => LayoutObjectKind::ListItem,
+            Display::InlineBlock => LayoutObjectKind::InlineBlock,
+        };
+        let children = self.block_container_children(&style, items);
+        let layout_object = new_box(kind, node, pseudo_element, style);
+        set_children(&layout_object, children);
+        vec![layout_object]
+    }
+
+    /// Returns the children of a block container whose style is `style` and
+    /// whose child boxes are `items`. If `items` mixes block-level and
+    /// inline-level boxes, each run of inline-level boxes is wrapped in an
+    /// anonymous block box, unless it is only collapsible white space.
+    /// https://www.w3.org/TR/CSS2/visuren.html#anonymous-block-level
+    fn block_container_children(
+        &self,
+        style: &ComputedStyle,
+        items: Vec<Rc<RefCell<LayoutObject>>>,
+    ) -> Vec<Rc<RefCell<LayoutObject>>> {
+        if !items.iter().any(|i| i.borrow().is_block_level()) {
+            return inline_formatting_context(items);
+        }
+
+        let mut anonymous_style = None;
+        let mut children = Vec::new();
+        let mut run = Vec::new();
+        for item in items.into_iter().map(Some).chain(core::iter::once(None)) {
+            match item {
+                Some(item) if !item.borrow().is_block_level() => {
+                    run.push(item);
+                    continue;
+                }
+                _ => {}
+            }
+            if !run.iter().all(is_white_space_only) {
+                let style = anonymous_style
+                    .get_or_insert_with(|| Rc::new(self.cascade.compute_anonymous_style(style)))
+                    .clone();
+                let block = new_box(LayoutObjectKind::Block, None, None, style);
+                set_children(&block, inline_formatting_context(core::mem::take(&mut run)));
+                children.push(block);
+            }
+            run.clear();
+            children.extend(item);
+        }
+        children
+    }
+}
+
+fn new_box(
+    kind: LayoutObjectKind,
+    node: Option<Rc<RefCell<Node>>>,
+    pseudo_element: Option<PseudoElement>,
+    style: Rc<ComputedStyle>,
+) -> Rc<RefCell<LayoutObject>> {
+    Rc::new(RefCell::new(LayoutObject::new(
+        kind,
+        node,
+        pseudo_element,
+        style,
+    )))
+}
+
+/// Returns the inline boxes of an inline element or pseudo-element whose
+/// child boxes are `items`. If it contains block-level boxes, its inline box
+/// is broken around them, and they are returned between the pieces.
+/// https://www.w3.org/TR/CSS2/visuren.html#anonymous-block-level
+fn split_inline_box(
+    node: Option<Rc<RefCell<Node>>>,
+    pseudo_element: Option<PseudoElement>,
+    style: Rc<ComputedStyle>,
+    items: Vec<Rc<RefCell<LayoutObject>>>,
+) -> Vec<Rc<RefCell<LayoutObject>>> {
+    let mut boxes = Vec::new();
+    let mut run = Vec::new();
+    for item in items.into_iter().map(Some).chain(core::iter::once(None)) {
+        match item {
+            Some(item) if !item.borrow().is_block_level() => run.push(item),
+            _ => {
+                let inline = new_box(
+                    LayoutObjectKind::Inline,
+                    node.clone(),
+                    pseudo_element,
+                    style.clone(),
+                );
+                set_children(&inline, core::mem::take(&mut run));
+                boxes.push(inline);
+                boxes.extend(item);
+            }
+        }
+    }
+    boxes
+}
+
+/// Returns true if `layout_object` is a text run that consists only of
+/// collapsible white space.
+fn is_white_space_only(layout_object: &Rc<RefCell<LayoutObject>>) -> bool {
+    let layout_object = layout_object.borrow();
+    match layout_object.kind() {
+        LayoutObjectKind::Text(text) => {
+            is_collapsible_white_space(&text, layout_object.style().white_space())
+        }
+        _ => false,
+    }
+}
+
+/// Processes the white space of `items`, the inline-level boxes of an
+/// inline formatting context, and returns them without the text runs that
+/// became empty.
+/// https://www.w3.org/TR/css-text-3/#white-space-processing
+fn inline_formatting_context(
+    items: Vec<Rc<RefCell<LayoutObject>>>,
+) -> Vec<Rc<RefCell<LayoutObject>>> {
+    let mut collapser = WhiteSpaceCollapser::new();
+    let mut last_text = None;
+    for item in &items {
+        collapse_white_space(item, &mut collapser, &mut last_text);
+    }
+    // https://www.w3.org/TR/css-text-3/#white-space-phase-2
+    // A collapsible space at the end of the last line is removed.
+    if let (true, Some(text)) = (collapser.ends_with_space(), last_text) {
+        if let LayoutObjectKind::Text(text) = text.borrow_mut().kind_mut() {
+            text.pop();
+        }
+    }
+    remove_empty_text(items)
+}
+
+/// Collapses the white space of the text runs in `layout_object`, and sets
+/// `last_text` to the last one that isn't empty.
+fn collapse_white_space(
+    layout_object: &Rc<RefCell<LayoutObject>>,
+    collapser: &mut WhiteSpaceCollapser,
+    last_text: &mut Option<Rc<RefCell<LayoutObject>>>,
+) {
+    let kind = layout_object.borrow().kind();
+    match kind {
+        LayoutObjectKind::Text(text) => {
+            let white_space = layout_object.borrow().style().white_space();
+            let text = collapser.collapse(&text, white_space);
+            if !text.is_empty() {
+                *last_text = Some(layout_object.clone());
+            }
+            *layout_object.borrow_mut().kind_mut() = LayoutObjectKind::Text(text);
+        }
+        LayoutObjectKind::Inline => {
+            let children = layout_object.borrow().children().to_vec();
+            for child in &children {
+                collapse_white_space(child, collapser, last_text);
+            }
+        }
+        // A marker is outside of the line, or ends with a space.
+        LayoutObjectKind::Marker(_) => {}
+        _ => collapser.atomic_inline(),
+    }
+}
+
+fn remove_empty_text(items: Vec<Rc<RefCell<LayoutObject>>>) -> Vec<Rc<RefCell<LayoutObject>>> {
+    items
+        .into_iter()
+        .filter(|item| {
+            let kind = item.borrow().kind();
+            match kind {
+                LayoutObjectKind::Text(text) => !text.is_empty(),
+                LayoutObjectKind::Inline => {
+                    let children = remove_empty_text(item.borrow().children().to_vec());
+                    set_children(item, children);
+                    true
+                }
+                _ => true,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::layout::tests::layout_tree;
+    use alloc::string::String;
+
+    /// Returns the layout tree of `html` without the boxes of `<html>` and
+    /// `<body>`.
+    fn layout(html: &str) -> String {
+        layout_tree(html, false, true)
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            "Block <p>\n  Text \"a\"\n  Inline <a>\n    Text \"b\"\n",
+            layout("<style>h1 { display: none }</style><p>a<a>b</a></p><h1>c</h1>")
+        );
+        assert_eq!(
+            r#"ListItem <p>
+  Marker "• "
+  Text "a"
+ListItem <p>
+  Marker "II. "
+  InlineBlock <a>
+    Text "b"
+  Text " c"
+ListItem <p>
+"#,
+            layout(
+                "<style>p { display: list-item } #b { list-style-type: upper-roman } #c { list-style-type: none } a { display: inline-block }</style>
+<p>a</p><p id=b> <a> b </a> c</p><p id=c></p>"
+            )
+        );
+        assert_eq!(
+            r#"Block <p>
+  Block (anonymous)
+    Inline <a>
+      Inline <a>::before
+        Text "[x] "
+      Text "y"
+  Block <a>::after
+    Image "i.png"
+  Block (anonymous)
+    Inline <a>
+"#,
+            layout(
+                r#"<style>a::before { content: "[" attr(href) "] " } a::after { content: url(i.png); display: block }</style>
+<p><a href=x>y</a></p>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_anonymous_boxes() {
+        assert_eq!(
+            r#"Block <p>
+  Text "a "
+  Inline <a>
+    Text "b"
+Block (anonymous)
+  Text "c"
+Block <p>
+  Text "d"
+"#,
+            layout("<p>a <a>b</a> </p>\n c <p> d </p>\n")
+        );
+        // An inline box that contains a block is broken around it.
+        assert_eq!(
+            r#"Block (anonymous)
+  Inline <a>
+    Text "x"
+Block <p>
+  Text "y"
+Block (anonymous)
+  Inline <a>
+    Text "z"
+"#,
+            layout("<a>x<p>y</p>z</a>")
+        );
+    }
+
+    #[test]
+    fn test_white_space() {
+        assert_eq!(
+            r#"Block <p>
+  Text "a "
+  Inline <a>
+    Text "b c "
+  Text "d"
+Block <h1>
+  Text " e \n f "
+Block <h2>
+  Text "g\nh"
+"#,
+            layout(
+                "<style>h1 { white-space: pre } h2 { white-space: pre-line }</style>
+<p> a <a> b\n\tc </a> d </p><h1> e \n f </h1><h2> g \n h </h2>"
+            )
+        );
+    }
+}
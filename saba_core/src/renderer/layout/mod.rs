@@ -0,0 +1,46 @@
+pub mod block;
+pub mod box_model;
+pub mod font;
+pub mod inline;
+pub mod layout_object;
+pub mod layout_view;
+pub mod white_space;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::renderer::css::cascade::Cascade;
+    use crate::renderer::css::stylesheet::StyleSheet;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+    use crate::renderer::layout::font::BitmapFontMetrics;
+    use crate::renderer::layout::layout_view::LayoutView;
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::string::ToString;
+
+    /// Lays out `html` in an 800x600 viewport and returns the layout tree.
+    /// `alternate` adds the geometry of each box, and `body_only` leaves out
+    /// the boxes of `<html>` and `<body>`.
+    pub(crate) fn layout_tree(html: &str, alternate: bool, body_only: bool) -> String {
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let document = window.borrow().document();
+        let cascade = Cascade::new(&StyleSheet::from_style_elements(&document));
+        cascade.apply(&document);
+        let view = LayoutView::new(&document, &cascade);
+        view.layout(800.0, 600.0, &BitmapFontMetrics);
+        let tree = if alternate {
+            format!("{:#}", view)
+        } else {
+            view.to_string()
+        };
+        if !body_only {
+            return tree;
+        }
+        let mut body = String::new();
+        for line in tree.lines().skip(2) {
+            body.push_str(&line[4..]);
+            body.push('\n');
+        }
+        body
+    }
+}
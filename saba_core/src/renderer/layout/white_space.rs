@@ -0,0 +1,136 @@
+//! https://www.w3.org/TR/css-text-3/#white-space-processing
+
+use crate::renderer::css::computed_style::WhiteSpace;
+use alloc::string::String;
+
+/// What the text processed so far in an inline formatting context ends
+/// with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum End {
+    /// The start of a line, where collapsible spaces are removed.
+    /// https://www.w3.org/TR/css-text-3/#white-space-phase-2
+    LineStart,
+    /// A collapsible space.
+    Space,
+    Other,
+}
+
+/// Collapses the white space of the text runs of an inline formatting
+/// context, which must be processed in order. A collapsible space that
+/// follows another one is removed even if they are in different inline
+/// boxes.
+/// https://www.w3.org/TR/css-text-3/#white-space-phase-1
+#[derive(Debug, Clone)]
+pub struct WhiteSpaceCollapser {
+    end: End,
+}
+
+impl WhiteSpaceCollapser {
+    pub fn new() -> Self {
+        Self {
+            end: End::LineStart,
+        }
+    }
+
+    /// Returns `text` after white space processing according to
+    /// `white_space`.
+    pub fn collapse(&mut self, text: &str, white_space: WhiteSpace) -> String {
+        let mut result = String::new();
+        for c in text.chars() {
+            if !white_space.collapses_spaces() {
+                result.push(c);
+                self.end = match c {
+                    '\n' => End::LineStart,
+                    _ => End::Other,
+                };
+                continue;
+            }
+            match c {
+                // https://www.w3.org/TR/css-text-3/#line-break-transform
+                '\n' if white_space.preserves_segment_breaks() => {
+                    // Spaces before and after a preserved segment break
+                    // are removed.
+                    if self.end == End::Space && result.ends_with(' ') {
+                        result.pop();
+                    }
+                    result.push('\n');
+                    self.end = End::LineStart;
+                }
+                ' ' | '\t' | '\n' | '\r' | '\x0c' => {
+                    if self.end == End::Other {
+                        result.push(' ');
+                        self.end = End::Space;
+                    }
+                }
+                _ => {
+                    result.push(c);
+                    self.end = End::Other;
+                }
+            }
+        }
+        result
+    }
+
+    /// Processes an atomic inline, which ends a sequence of collapsible
+    /// spaces.
+    pub fn atomic_inline(&mut self) {
+        self.end = End::Other;
+    }
+
+    /// Returns true if the text processed so far ends with a collapsible
+    /// space, which is removed at the end of a line.
+    pub fn ends_with_space(&self) -> bool {
+        self.end == End::Space
+    }
+}
+
+impl Default for WhiteSpaceCollapser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns true if `text` consists only of collapsible white space, which
+/// doesn't generate a box between block-level boxes.
+/// https://www.w3.org/TR/CSS2/visuren.html#anonymous-block-level
+pub fn is_collapsible_white_space(text: &str, white_space: WhiteSpace) -> bool {
+    white_space.collapses_spaces()
+        && text.chars().all(|c| match c {
+            '\n' => !white_space.preserves_segment_breaks(),
+            ' ' | '\t' | '\r' | '\x0c' => true,
+            _ => false,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collapse() {
+        let mut collapser = WhiteSpaceCollapser::new();
+        assert_eq!(
+            "a b ",
+            collapser.collapse(" \n a \t\n b  ", WhiteSpace::Normal)
+        );
+        assert_eq!("c", collapser.collapse("  c", WhiteSpace::Nowrap));
+        assert_eq!(" d\n e ", collapser.collapse(" d\n e ", WhiteSpace::Pre));
+        assert!(!collapser.ends_with_space());
+        assert_eq!(" f ", collapser.collapse(" f ", WhiteSpace::PreWrap));
+        // Only collapsible spaces after a collapsible space are removed.
+        assert_eq!(" ", collapser.collapse("  ", WhiteSpace::Normal));
+        assert_eq!("", collapser.collapse("\t", WhiteSpace::Normal));
+        assert!(collapser.ends_with_space());
+        assert_eq!(
+            "g\nh\n\ni",
+            collapser.collapse("g  \n  h\n\n i", WhiteSpace::PreLine)
+        );
+        collapser.atomic_inline();
+        assert_eq!(" j", collapser.collapse("\n j", WhiteSpace::Normal));
+
+        assert!(is_collapsible_white_space(" \n\t", WhiteSpace::Normal));
+        assert!(!is_collapsible_white_space(" \n", WhiteSpace::PreLine));
+        assert!(!is_collapsible_white_space(" ", WhiteSpace::Pre));
+        assert!(!is_collapsible_white_space(" a", WhiteSpace::Normal));
+    }
+}
@@ -1,3 +1,4 @@
+use alloc::format;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec::Vec;
@@ -35,6 +36,82 @@ impl Url {
         Ok(self.clone())
     }
 
+    pub fn host(&self) -> String {
+        self.host.clone()
+    }
+
+    pub fn port(&self) -> String {
+        self.port.clone()
+    }
+
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    pub fn searchpart(&self) -> String {
+        self.searchpart.clone()
+    }
+
+    /// Resolves `input` against this URL, which is used as the base URL.
+    /// Returns `input` unchanged if it is already an absolute URL.
+    /// https://www.rfc-editor.org/rfc/rfc3986#section-5.2
+    pub fn join(&self, input: &str) -> String {
+        let input = input.trim();
+        if has_scheme(input) {
+            return input.to_string();
+        }
+
+        let base = match self.url.find('#') {
+            Some(index) => &self.url[..index],
+            None => &self.url,
+        };
+        let (scheme, rest) = match base.find("://") {
+            Some(index) => (&base[..index], &base[index + 3..]),
+            None => return input.to_string(),
+        };
+        let (authority, path_and_query) = match rest.find(['/', '?']) {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, ""),
+        };
+        let path = match path_and_query.find('?') {
+            Some(index) => &path_and_query[..index],
+            None => path_and_query,
+        };
+
+        if input.is_empty() {
+            return base.to_string();
+        }
+        if input.starts_with('#') {
+            return format!("{}{}", base, input);
+        }
+        if let Some(input) = input.strip_prefix("//") {
+            return format!("{}://{}", scheme, input);
+        }
+        if input.starts_with('?') {
+            return format!("{}://{}{}{}", scheme, authority, path, input);
+        }
+
+        let (input_path, suffix) = match input.find(['?', '#']) {
+            Some(index) => (&input[..index], &input[index..]),
+            None => (input, ""),
+        };
+        let merged = if input_path.starts_with('/') {
+            input_path.to_string()
+        } else {
+            match path.rfind('/') {
+                Some(index) => format!("{}{}", &path[..=index], input_path),
+                None => format!("/{}", input_path),
+            }
+        };
+        format!(
+            "{}://{}{}{}",
+            scheme,
+            authority,
+            remove_dot_segments(&merged),
+            suffix
+        )
+    }
+
     fn is_http(&mut self) -> bool {
         if self.url.contains("http://") {
             return true;
@@ -105,6 +182,44 @@ impl Url {
     }
 }
 
+/// https://www.rfc-editor.org/rfc/rfc3986#section-3.1
+fn has_scheme(input: &str) -> bool {
+    match input.find(':') {
+        Some(index) => {
+            let scheme = &input[..index];
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc3986#section-5.2.4
+fn remove_dot_segments(path: &str) -> String {
+    let mut output: Vec<&str> = Vec::new();
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+    for (i, segment) in segments.iter().enumerate() {
+        let is_last = i + 1 == segments.len();
+        match *segment {
+            "." => {
+                if is_last {
+                    output.push("");
+                }
+            }
+            ".." => {
+                output.pop();
+                if is_last {
+                    output.push("");
+                }
+            }
+            s => output.push(s),
+        }
+    }
+    format!("/{}", output.join("/"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -174,6 +289,31 @@ mod tests {
         assert_eq!(expected, Url::new(url).parse());
     }
 
+    #[test]
+    fn test_join() {
+        let base = Url::new("http://example.com:8888/a/b/c.html?q=1#top".to_string());
+        let cases = [
+            ("https://other.com/x", "https://other.com/x"),
+            ("mailto:a@example.com", "mailto:a@example.com"),
+            ("//other.com/x", "http://other.com/x"),
+            ("/x/y", "http://example.com:8888/x/y"),
+            ("d.html", "http://example.com:8888/a/b/d.html"),
+            ("./d.html?x#y", "http://example.com:8888/a/b/d.html?x#y"),
+            ("../d.html", "http://example.com:8888/a/d.html"),
+            ("../../../d.html", "http://example.com:8888/d.html"),
+            ("..", "http://example.com:8888/a/"),
+            ("?x", "http://example.com:8888/a/b/c.html?x"),
+            ("#y", "http://example.com:8888/a/b/c.html?q=1#y"),
+            ("", "http://example.com:8888/a/b/c.html?q=1"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(expected, base.join(input), "input: {:?}", input);
+        }
+
+        let base = Url::new("http://example.com".to_string());
+        assert_eq!("http://example.com/x", base.join("x"));
+    }
+
     #[test]
     fn test_no_scheme() {
         let url = "example.com".to_string();
@@ -6,12 +6,37 @@ extern crate alloc;
 use crate::alloc::string::ToString;
 use net_wasabi::http::HttpClient;
 use noli::prelude::*;
+use saba_core::renderer::css::cascade::Cascade;
+use saba_core::renderer::css::media::Viewport;
+use saba_core::renderer::css::stylesheet::StyleSheet;
+use saba_core::renderer::html::parser::HtmlParser;
+use saba_core::renderer::html::token::HtmlTokenizer;
+use saba_core::renderer::layout::font::BitmapFontMetrics;
+use saba_core::renderer::layout::layout_view::LayoutView;
+
+const WINDOW_WIDTH: f64 = 600.0;
+const WINDOW_HEIGHT: f64 = 400.0;
 
 fn main() -> u64 {
-    let client = HttpClient::new();
+    let mut client = HttpClient::new();
     match client.get("host.test".to_string(), 8000, "/test.html".to_string()) {
         Ok(res) => {
-            print!("response:\n{:#?}", res);
+            let window = HtmlParser::new(HtmlTokenizer::new(res.body())).construct_tree();
+            let document = window.borrow().document();
+            let mut cascade = Cascade::new(&StyleSheet::from_document(
+                &document,
+                "http://host.test:8000/test.html",
+                &mut client,
+            ));
+            cascade.set_viewport(Viewport {
+                width: WINDOW_WIDTH,
+                height: WINDOW_HEIGHT,
+                ..Viewport::default()
+            });
+            cascade.apply(&document);
+            let view = LayoutView::new(&document, &cascade);
+            view.layout(WINDOW_WIDTH, WINDOW_HEIGHT, &BitmapFontMetrics);
+            print!("layout tree:\n{:#}", view);
         }
         Err(e) => {
             print!("error:\n{:#?}", e);